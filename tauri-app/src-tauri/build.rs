@@ -1,3 +1,24 @@
+use std::path::PathBuf;
+
+#[allow(dead_code)]
+#[path = "src/llm_profile_types.rs"]
+mod llm_profile_types;
+
+/// Writes the JSON Schema of `LLMProfile` to `target/profile-schema.json`, bundled with
+/// releases for validation of profile files in editors
+fn write_profile_schema() {
+    println!("cargo:rerun-if-changed=src/llm_profile_types.rs");
+    let target = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"));
+    let content = serde_json::to_string_pretty(&llm_profile_types::profile_schema())
+        .expect("profile schema serializes");
+    std::fs::create_dir_all(&target).expect("target dir");
+    std::fs::write(target.join("profile-schema.json"), content)
+        .expect("write target/profile-schema.json");
+}
+
 fn main() {
+    write_profile_schema();
     tauri_build::build()
 }
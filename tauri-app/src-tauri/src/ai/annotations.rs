@@ -0,0 +1,183 @@
+//! Line references in an AI answer ("строка 12", "line 12, column 5") turned into
+//! annotations the editor can highlight.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationSeverity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InlineAnnotation {
+    /// 1-based line of the analysed code
+    pub line: u32,
+    /// 1-based column, when the answer names one inside the line
+    pub column: Option<u32>,
+    pub severity: AnnotationSeverity,
+    /// Answer line that mentions the code line
+    pub message: String,
+}
+
+lazy_static! {
+    static ref LINE_REF: Regex = Regex::new(
+        r"(?i)(?:\bat\s+line|\bline|\bстрок[аеиуой]|\bстр\.)\s*№?\s*(\d+)(?:\s*[,:]\s*(?:(?:column|col\.?|столб(?:ец|це)|колонк[аеи]|позиция)\s*)?(\d+))?"
+    )
+    .unwrap();
+    static ref INLINE_CODE: Regex = Regex::new(r"`([^`\n]+)`").unwrap();
+}
+
+/// Most severe category whose keyword occurs in the message
+fn classify(message: &str) -> AnnotationSeverity {
+    let lower = message.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+    if has(&[
+        "error",
+        "ошибк",
+        "ошибоч",
+        "critical",
+        "критич",
+        "исключени",
+    ]) {
+        AnnotationSeverity::Error
+    } else if has(&[
+        "warning",
+        "предупрежд",
+        "potential",
+        "потенциальн",
+        "возможн",
+        "риск",
+        "null",
+        "неопределено",
+    ]) {
+        AnnotationSeverity::Warning
+    } else if has(&["hint", "совет", "рекоменд", "лучше", "consider", "можно"])
+    {
+        AnnotationSeverity::Hint
+    } else {
+        AnnotationSeverity::Info
+    }
+}
+
+/// Answer line without list markers and emphasis
+fn clean_message(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(['-', '*', '•', '>'])
+        .trim()
+        .replace("**", "")
+}
+
+/// The referenced line when it is in the code. If the answer quotes code in
+/// backticks that is not on that line, the nearest line containing it is used
+/// (models often miscount by a few lines).
+fn anchor(line: usize, message: &str, code_lines: &[&str]) -> Option<usize> {
+    if line == 0 || line > code_lines.len() {
+        return None;
+    }
+    let snippets: Vec<&str> = INLINE_CODE
+        .captures_iter(message)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str().trim())
+        .filter(|s| s.len() >= 3)
+        .collect();
+    if snippets.is_empty() || snippets.iter().any(|s| code_lines[line - 1].contains(s)) {
+        return Some(line);
+    }
+    let nearest = code_lines
+        .iter()
+        .enumerate()
+        .filter(|(_, text)| snippets.iter().any(|s| text.contains(s)))
+        .map(|(i, _)| i + 1)
+        .min_by_key(|candidate| candidate.abs_diff(line));
+    Some(nearest.unwrap_or(line))
+}
+
+/// Annotations for every line reference in `response` that falls inside `code`;
+/// an answer without references gives an empty list.
+pub fn parse_annotations_from_response(response: &str, code: &str) -> Vec<InlineAnnotation> {
+    let code_lines: Vec<&str> = code.lines().collect();
+    let mut annotations: Vec<InlineAnnotation> = Vec::new();
+
+    for response_line in response.lines() {
+        let message = clean_message(response_line);
+        if message.is_empty() {
+            continue;
+        }
+        for caps in LINE_REF.captures_iter(response_line) {
+            let Some(line) = caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| anchor(n, &message, &code_lines))
+            else {
+                continue;
+            };
+            let line_len = code_lines[line - 1].chars().count();
+            let column = caps
+                .get(2)
+                .and_then(|m| m.as_str().parse::<u32>().ok())
+                .filter(|&c| c >= 1 && c as usize <= line_len.max(1));
+            let annotation = InlineAnnotation {
+                line: line as u32,
+                column,
+                severity: classify(&message),
+                message: message.clone(),
+            };
+            if !annotations.contains(&annotation) {
+                annotations.push(annotation);
+            }
+        }
+    }
+    annotations.sort_by_key(|a| a.line);
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "Процедура Тест()\n\tЗапрос = Новый Запрос;\n\tРезультат = Запрос.Выполнить();\n\tВыборка = Результат.Выбрать();\nКонецПроцедуры";
+
+    #[test]
+    fn finds_russian_and_english_references() {
+        let response = "Замечания:\n\
+            - **Строка 3**: возможна ошибка, если запрос вернёт пустой результат.\n\
+            - At line 4, column 2 the selection is never used.\n\
+            - Рекомендуется вынести запрос в функцию (стр. 2).";
+        let annotations = parse_annotations_from_response(response, CODE);
+
+        assert_eq!(annotations.len(), 3);
+        assert_eq!(annotations[0].line, 2);
+        assert_eq!(annotations[0].severity, AnnotationSeverity::Hint);
+        assert_eq!(annotations[1].line, 3);
+        assert_eq!(annotations[1].severity, AnnotationSeverity::Error);
+        assert_eq!(
+            annotations[1].message,
+            "Строка 3: возможна ошибка, если запрос вернёт пустой результат."
+        );
+        assert_eq!(annotations[2].line, 4);
+        assert_eq!(annotations[2].column, Some(2));
+        assert_eq!(annotations[2].severity, AnnotationSeverity::Info);
+    }
+
+    #[test]
+    fn anchors_quoted_code_and_drops_lines_outside_the_source() {
+        let response = "line 2: `Результат.Выбрать()` may dereference null.\nline 40 has an error.";
+        let annotations = parse_annotations_from_response(response, CODE);
+
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].line, 4);
+        assert_eq!(annotations[0].severity, AnnotationSeverity::Warning);
+    }
+
+    #[test]
+    fn answer_without_references_gives_empty_list() {
+        assert!(parse_annotations_from_response("Код выглядит корректно.", CODE).is_empty());
+        assert!(parse_annotations_from_response("", "").is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Message attachments: images for vision models (downscaled `data:` URLs for
+//! `image_url` content parts) and source files sent as fenced text before the question.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use base64::Engine;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Longest image side sent to the model. Larger screenshots are downscaled:
+/// providers resize them anyway, and full-size PNGs blow up the request.
+pub const MAX_IMAGE_SIDE: u32 = 1568;
+
+/// Upper bound for the source file, checked before decoding.
+const MAX_SOURCE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Encoded image ready to be attached to the next user message.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageAttachment {
+    /// `data:image/png;base64,...`
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Size that fits into `max_side` x `max_side`, keeping the aspect ratio.
+pub fn fit_within(width: u32, height: u32, max_side: u32) -> (u32, u32) {
+    if width <= max_side && height <= max_side {
+        return (width, height);
+    }
+    let scale = max_side as f64 / width.max(height) as f64;
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// Downscales if needed and encodes the image as a PNG data URL.
+pub fn encode_image(image: DynamicImage) -> Result<ImageAttachment, String> {
+    let (width, height) = fit_within(image.width(), image.height(), MAX_IMAGE_SIDE);
+    let image = if (width, height) != (image.width(), image.height()) {
+        image.resize_exact(width, height, image::imageops::FilterType::Lanczos3)
+    } else {
+        image
+    };
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Не удалось закодировать изображение: {}", e))?;
+
+    Ok(ImageAttachment {
+        data_url: format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&png)
+        ),
+        width,
+        height,
+    })
+}
+
+/// Loads an image file (PNG, JPEG, WebP, GIF, BMP).
+pub fn load_image_file(path: &Path) -> Result<ImageAttachment, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Файл недоступен {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_SOURCE_BYTES {
+        return Err(format!(
+            "Файл слишком большой ({} МБ), максимум {} МБ",
+            size / (1024 * 1024),
+            MAX_SOURCE_BYTES / (1024 * 1024)
+        ));
+    }
+    let image = image::open(path)
+        .map_err(|e| format!("Не удалось открыть изображение {}: {}", path.display(), e))?;
+    encode_image(image)
+}
+
+/// Reads the current clipboard image (e.g. a screenshot taken with Win+Shift+S).
+pub fn load_clipboard_image() -> Result<ImageAttachment, String> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Буфер обмена недоступен: {}", e))?;
+    let data = clipboard
+        .get_image()
+        .map_err(|_| "В буфере обмена нет изображения".to_string())?;
+    let rgba = RgbaImage::from_raw(
+        u32::try_from(data.width).unwrap_or(u32::MAX),
+        u32::try_from(data.height).unwrap_or(u32::MAX),
+        data.bytes.into_owned(),
+    )
+    .ok_or("Некорректные данные изображения в буфере обмена")?;
+    encode_image(DynamicImage::ImageRgba8(rgba))
+}
+
+/// Source file sent with the request in the synthetic "relevant files" message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileAttachment {
+    pub path: String,
+    pub content: String,
+    /// Code fence language (`bsl`, `xml`, `json` or empty)
+    #[serde(default)]
+    pub language: String,
+}
+
+impl FileAttachment {
+    /// File name for messages and the UI
+    pub fn name(&self) -> &str {
+        Path::new(&self.path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&self.path)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.content.len() as u64
+    }
+}
+
+/// Decodes a 1C source file: UTF-8/UTF-16 with BOM, plain UTF-8, otherwise windows-1251.
+pub fn decode_source(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_len)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return text.into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => {
+            let (text, _) = encoding_rs::WINDOWS_1251.decode_without_bom_handling(bytes);
+            text.into_owned()
+        }
+    }
+}
+
+/// Code fence language by file extension; 1C exports without one are BSL.
+pub fn language_for_path(path: &str) -> &'static str {
+    match Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("bsl") | Some("os") | Some("txt") | None => "bsl",
+        Some("xml") => "xml",
+        Some("json") => "json",
+        Some(_) => "",
+    }
+}
+
+/// `<path> (<module type>):` followed by the content in a code fence that is longer than
+/// any backtick run inside the file, so embedded fences cannot close it early.
+pub fn format_file_block(file: &FileAttachment) -> String {
+    let header = match super::prompts::detect_bsl_module_type(&file.path).describe() {
+        Some(module_type) => format!("{} ({})", file.path, module_type),
+        None => file.path.clone(),
+    };
+    let longest_run = file
+        .content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!(
+        "{}:\n{}{}\n{}\n{}",
+        header,
+        fence,
+        file.language,
+        file.content.trim_end_matches(['\r', '\n']),
+        fence
+    )
+}
+
+/// Text of the synthetic user message sent before the question; `None` without files.
+pub fn attachments_message_text(files: &[FileAttachment]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    let blocks: Vec<String> = files.iter().map(format_file_block).collect();
+    Some(format!(
+        "Here are the relevant files:\n\n{}",
+        blocks.join("\n\n")
+    ))
+}
+
+/// Checks the combined size of the files against `max_total_bytes`, naming the file that overflows.
+pub fn check_attachments_size(
+    files: &[FileAttachment],
+    max_total_bytes: u64,
+) -> Result<(), String> {
+    let mut total = 0u64;
+    for file in files {
+        total += file.bytes();
+        if total > max_total_bytes {
+            return Err(format!(
+                "Файл «{}» не помещается в лимит вложений: {} КБ при лимите {} КБ",
+                file.name(),
+                total.div_ceil(1024),
+                max_total_bytes / 1024
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Reads a file for attaching, enforcing the per-file and the running total limits (bytes).
+pub fn load_source_file(
+    path: &Path,
+    max_file_bytes: u64,
+    attached_bytes: u64,
+    max_total_bytes: u64,
+) -> Result<FileAttachment, String> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    let bytes = std::fs::metadata(path)
+        .map_err(|e| format!("Файл недоступен {}: {}", path.display(), e))?
+        .len();
+    if bytes > max_file_bytes {
+        return Err(format!(
+            "Файл «{}» слишком большой: {} КБ при лимите {} КБ на файл",
+            name,
+            bytes.div_ceil(1024),
+            max_file_bytes / 1024
+        ));
+    }
+    if attached_bytes + bytes > max_total_bytes {
+        return Err(format!(
+            "Файл «{}» не помещается в лимит вложений: {} КБ уже прикреплено, лимит {} КБ",
+            name,
+            attached_bytes.div_ceil(1024),
+            max_total_bytes / 1024
+        ));
+    }
+
+    let raw = std::fs::read(path).map_err(|e| format!("Не удалось прочитать {}: {}", name, e))?;
+    let path = path.display().to_string();
+    Ok(FileAttachment {
+        content: decode_source(&raw),
+        language: language_for_path(&path).to_string(),
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_within_keeps_small_images_and_aspect_ratio() {
+        assert_eq!(fit_within(800, 600, MAX_IMAGE_SIDE), (800, 600));
+        assert_eq!(fit_within(3136, 1568, MAX_IMAGE_SIDE), (1568, 784));
+        assert_eq!(fit_within(1000, 4000, 1000), (250, 1000));
+        assert_eq!(fit_within(10_000, 1, 100), (100, 1));
+    }
+
+    #[test]
+    fn encodes_downscaled_png_data_url() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(2000, 1000));
+        let attachment = encode_image(image).unwrap();
+
+        assert_eq!((attachment.width, attachment.height), (1568, 784));
+        assert!(attachment.data_url.starts_with("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn decodes_bom_utf8_and_cp1251() {
+        assert_eq!(decode_source("\u{FEFF}Процедура".as_bytes()), "Процедура");
+        assert_eq!(decode_source("Функция".as_bytes()), "Функция");
+        // "Мир" in windows-1251
+        assert_eq!(decode_source(&[0xCC, 0xE8, 0xF0]), "Мир");
+    }
+
+    fn file(path: &str, content: &str) -> FileAttachment {
+        FileAttachment {
+            path: path.to_string(),
+            content: content.to_string(),
+            language: language_for_path(path).to_string(),
+        }
+    }
+
+    #[test]
+    fn file_block_fence_outlasts_embedded_backticks() {
+        assert_eq!(
+            format_file_block(&file("src/Модуль.bsl", "А = 1;\r\n")),
+            "src/Модуль.bsl:\n```bsl\nА = 1;\n```"
+        );
+        let block = format_file_block(&file("readme.md", "```bsl\nx\n```"));
+        assert!(block.starts_with("readme.md:\n````\n"));
+        assert!(block.ends_with("\n````"));
+    }
+
+    #[test]
+    fn composite_message_lists_every_file_and_checks_total_size() {
+        let files = vec![
+            file(
+                "CommonModules/Общий/Module.bsl",
+                "Процедура Общая() Экспорт\nКонецПроцедуры",
+            ),
+            file("Documents/Заказ/ObjectModule.bsl", "Общий.Общая();"),
+        ];
+        assert_eq!(
+            attachments_message_text(&files).unwrap(),
+            "Here are the relevant files:\n\n\
+             CommonModules/Общий/Module.bsl (ОбщийМодуль «Общий»):\n```bsl\nПроцедура Общая() Экспорт\nКонецПроцедуры\n```\n\n\
+             Documents/Заказ/ObjectModule.bsl (МодульОбъекта):\n```bsl\nОбщий.Общая();\n```"
+        );
+        assert!(attachments_message_text(&[]).is_none());
+
+        let total = files.iter().map(FileAttachment::bytes).sum::<u64>();
+        assert!(check_attachments_size(&files, total).is_ok());
+        let err = check_attachments_size(&files, total - 1).unwrap_err();
+        assert!(err.contains("ObjectModule.bsl"), "{}", err);
+    }
+
+    #[test]
+    fn source_file_limits_name_the_file() {
+        let dir = std::env::temp_dir().join(format!("attach-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Большой.bsl");
+        std::fs::write(&path, vec![b'a'; 2048]).unwrap();
+
+        let err = load_source_file(&path, 1024, 0, 4096).unwrap_err();
+        assert!(err.contains("Большой.bsl"), "{}", err);
+        let err = load_source_file(&path, 4096, 3072, 4096).unwrap_err();
+        assert!(err.contains("Большой.bsl"), "{}", err);
+        let ok = load_source_file(&path, 4096, 0, 4096).unwrap();
+        assert_eq!(ok.bytes(), 2048);
+        assert_eq!(ok.name(), "Большой.bsl");
+        assert_eq!(ok.language, "bsl");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
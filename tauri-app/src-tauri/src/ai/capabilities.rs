@@ -0,0 +1,338 @@
+//! Model capabilities resolved from the model id (context window etc.)
+
+use serde::Serialize;
+
+use super::models::{ChatRequest, ThinkingConfig};
+use crate::llm_profiles::{LLMProfile, LLMProvider};
+
+/// Fallback context window when the model is unknown (same as the chat UI indicator).
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 128_000;
+
+/// Static facts about a model that affect how requests are built.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelCapabilities {
+    pub context_window: u32,
+    /// Reasoning model: takes `max_completion_tokens` and rejects custom `temperature`/`top_p`
+    pub reasoning: bool,
+    /// Accepts `image_url` content parts
+    pub vision: bool,
+    /// Anthropic extended thinking (`thinking.budget_tokens`)
+    pub extended_thinking: bool,
+    /// USD per 1k prompt tokens, 0.0 when the price is unknown (see `pricing.rs`)
+    pub input_price_per_1k_tokens: f64,
+    /// USD per 1k completion tokens, 0.0 when the price is unknown
+    pub output_price_per_1k_tokens: f64,
+}
+
+/// Context windows by model-id prefix. First match wins, so more specific prefixes go first.
+const CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("gpt-5", 272_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+    ("gemini", 1_048_576),
+    ("deepseek", 64_000),
+    ("qwen3-coder", 262_144),
+    ("minimax-m2", 204_800),
+    ("grok", 131_072),
+    ("mistral-large", 128_000),
+    ("llama-3", 128_000),
+];
+
+/// Reasoning model id prefixes, with exceptions that behave like regular chat models.
+const REASONING_PREFIXES: &[&str] = &["o1", "o3", "o4", "gpt-5"];
+const NON_REASONING_PREFIXES: &[&str] = &["gpt-5-chat"];
+
+/// Claude models with extended thinking.
+const EXTENDED_THINKING_PREFIXES: &[&str] = &[
+    "claude-3-7",
+    "claude-3.7",
+    "claude-sonnet-4",
+    "claude-opus-4",
+    "claude-haiku-4",
+    "claude-4",
+];
+
+/// Thinking budget per reasoning effort; Anthropic requires at least 1024.
+fn thinking_budget_for_effort(effort: &str) -> u32 {
+    match effort {
+        "low" => 2_048,
+        "high" | "xhigh" => 24_576,
+        _ => 8_192,
+    }
+}
+
+/// Vision-capable model id prefixes.
+const VISION_PREFIXES: &[&str] = &[
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4-turbo",
+    "gpt-5",
+    "o1",
+    "o3",
+    "o4",
+    "claude",
+    "gemini",
+    "grok-4",
+    "grok-2-vision",
+    "qwen-vl",
+    "qwen2.5-vl",
+    "qwen3-vl",
+    "glm-4.5v",
+    "glm-4v",
+    "pixtral",
+    "mistral-medium",
+    "mistral-small",
+    "llava",
+    "llama-4",
+    "gemma3",
+    "minicpm-v",
+];
+/// Matches that are text-only despite a vision prefix.
+const NON_VISION_PREFIXES: &[&str] = &["o1-mini", "o3-mini"];
+
+/// Strips the vendor prefix used by aggregators ("openai/gpt-4o" → "gpt-4o").
+pub(crate) fn bare_model_id(model: &str) -> String {
+    model
+        .rsplit('/')
+        .next()
+        .unwrap_or(model)
+        .trim()
+        .to_lowercase()
+}
+
+/// Capabilities of a model by id, without profile overrides.
+pub fn capabilities_for_model(model: &str) -> ModelCapabilities {
+    let id = bare_model_id(model);
+    let context_window = CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| id.starts_with(prefix))
+        .map(|(_, cw)| *cw)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW);
+
+    let reasoning = REASONING_PREFIXES.iter().any(|p| id.starts_with(p))
+        && !NON_REASONING_PREFIXES.iter().any(|p| id.starts_with(p));
+
+    let vision = VISION_PREFIXES.iter().any(|p| id.starts_with(p))
+        && !NON_VISION_PREFIXES.iter().any(|p| id.starts_with(p));
+
+    let extended_thinking = EXTENDED_THINKING_PREFIXES.iter().any(|p| id.starts_with(p));
+
+    let price = super::pricing::price_for_model(model);
+
+    ModelCapabilities {
+        context_window,
+        reasoning,
+        vision,
+        extended_thinking,
+        input_price_per_1k_tokens: price.map(|p| p.input_per_1k).unwrap_or(0.0),
+        output_price_per_1k_tokens: price.map(|p| p.output_per_1k).unwrap_or(0.0),
+    }
+}
+
+/// Capabilities of the profile's model with the profile overrides applied.
+pub fn get_model_capabilities(profile: &LLMProfile) -> ModelCapabilities {
+    let mut caps = capabilities_for_model(&profile.model);
+    if let Some(cw) = profile.context_window_override {
+        caps.context_window = cw;
+    }
+    if let Some(reasoning) = profile.reasoning_model {
+        caps.reasoning = reasoning;
+    }
+    // CLI-backed providers and Naparnik accept text only
+    if matches!(
+        profile.provider,
+        LLMProvider::QwenCli | LLMProvider::CodexCli | LLMProvider::OneCNaparnik
+    ) {
+        caps.vision = false;
+    }
+    caps
+}
+
+/// Error for image attachments sent to a model that cannot read them.
+pub fn ensure_vision_supported(profile: &LLMProfile) -> Result<(), String> {
+    if get_model_capabilities(profile).vision {
+        return Ok(());
+    }
+    Err(format!(
+        "Модель «{}» не поддерживает изображения. Выберите модель с поддержкой vision (например, gpt-4o, Claude, Gemini) или уберите вложение.",
+        profile.model
+    ))
+}
+
+/// Adjusts a request body to what the model accepts: reasoning models get
+/// `max_completion_tokens` instead of `max_tokens` and no sampling parameters.
+/// `reasoning_effort` stays for reasoning models, becomes `thinking.budget_tokens`
+/// for Claude extended thinking and is dropped for everyone else (they answer 400).
+pub fn apply_model_rules(request: &mut ChatRequest, caps: &ModelCapabilities) {
+    if caps.reasoning {
+        if let Some(limit) = request.max_tokens.take() {
+            request.max_completion_tokens = Some(limit);
+        }
+        request.temperature = None;
+        request.top_p = None;
+        request.logit_bias = None;
+    }
+
+    if let Some(effort) = request.reasoning_effort.take() {
+        if caps.reasoning {
+            request.reasoning_effort = Some(effort);
+        } else if caps.extended_thinking {
+            let budget = thinking_budget_for_effort(&effort);
+            request.thinking = Some(ThinkingConfig::enabled(budget));
+            // max_tokens must exceed the budget; thinking rejects custom sampling
+            request.max_tokens = Some(request.max_tokens.unwrap_or(0).max(budget + 4_096));
+            request.temperature = None;
+            request.top_p = None;
+        } else {
+            crate::app_log!(
+                "[AI] reasoning_effort '{}' ignored: model '{}' does not support it",
+                effort,
+                request.model
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_context_window_by_prefix() {
+        assert_eq!(
+            capabilities_for_model("gpt-4o-mini").context_window,
+            128_000
+        );
+        assert_eq!(capabilities_for_model("gpt-4").context_window, 8_192);
+        assert_eq!(
+            capabilities_for_model("anthropic/claude-3.5-sonnet").context_window,
+            200_000
+        );
+        assert_eq!(
+            capabilities_for_model("my-local-model").context_window,
+            DEFAULT_CONTEXT_WINDOW
+        );
+    }
+
+    fn request_for(model: &str) -> ChatRequest {
+        ChatRequest {
+            model: model.to_string(),
+            messages: Vec::new(),
+            stream: true,
+            temperature: Some(0.7),
+            max_tokens: Some(4096),
+            max_completion_tokens: None,
+            top_p: Some(0.9),
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            seed: None,
+            logit_bias: None,
+            n: None,
+            user: None,
+            system: None,
+            stream_options: None,
+            response_format: None,
+            tools: None,
+            enable_thinking: None,
+            thinking_budget_tokens: None,
+            reasoning_effort: None,
+            thinking: None,
+        }
+    }
+
+    #[test]
+    fn model_rules_switch_token_field_for_reasoning_models() {
+        // (model, max_tokens, max_completion_tokens, temperature)
+        let cases = [
+            ("gpt-4o", Some(4096), None, Some(0.7)),
+            ("o4-mini", None, Some(4096), None),
+            ("o1", None, Some(4096), None),
+            ("openai/gpt-5-mini", None, Some(4096), None),
+            ("gpt-5-chat-latest", Some(4096), None, Some(0.7)),
+            ("some-unknown-model", Some(4096), None, Some(0.7)),
+        ];
+
+        for (model, max_tokens, max_completion_tokens, temperature) in cases {
+            let mut request = request_for(model);
+            apply_model_rules(&mut request, &capabilities_for_model(model));
+
+            assert_eq!(request.max_tokens, max_tokens, "{}", model);
+            assert_eq!(
+                request.max_completion_tokens, max_completion_tokens,
+                "{}",
+                model
+            );
+            assert_eq!(request.temperature, temperature, "{}", model);
+        }
+    }
+
+    #[test]
+    fn reasoning_effort_is_mapped_or_suppressed_per_model() {
+        let with_effort = |model: &str| {
+            let mut request = request_for(model);
+            request.reasoning_effort = Some("high".to_string());
+            apply_model_rules(&mut request, &capabilities_for_model(model));
+            request
+        };
+
+        let o3 = with_effort("o3-mini");
+        assert_eq!(o3.reasoning_effort.as_deref(), Some("high"));
+        assert_eq!(o3.thinking, None);
+
+        let claude = with_effort("anthropic/claude-sonnet-4");
+        assert_eq!(claude.reasoning_effort, None);
+        assert_eq!(claude.thinking, Some(ThinkingConfig::enabled(24_576)));
+        assert_eq!(claude.max_tokens, Some(24_576 + 4_096));
+        assert_eq!(claude.temperature, None);
+
+        let gpt4o = with_effort("gpt-4o");
+        assert_eq!(gpt4o.reasoning_effort, None);
+        assert_eq!(gpt4o.thinking, None);
+        assert_eq!(gpt4o.temperature, Some(0.7));
+
+        let json = serde_json::to_value(&claude).unwrap();
+        assert_eq!(json["thinking"]["type"], "enabled");
+        assert_eq!(json["thinking"]["budget_tokens"], 24_576);
+        assert!(serde_json::to_value(&gpt4o)
+            .unwrap()
+            .get("reasoning_effort")
+            .is_none());
+    }
+
+    #[test]
+    fn profile_reasoning_override_wins() {
+        let mut profile = LLMProfile::default_profile();
+        profile.model = "my-reasoner".to_string();
+        profile.reasoning_model = Some(true);
+        assert!(get_model_capabilities(&profile).reasoning);
+    }
+
+    #[test]
+    fn vision_support_by_model_and_provider() {
+        assert!(capabilities_for_model("openai/gpt-4o-mini").vision);
+        assert!(capabilities_for_model("claude-sonnet-4").vision);
+        assert!(!capabilities_for_model("o3-mini").vision);
+        assert!(!capabilities_for_model("deepseek-chat").vision);
+
+        let mut profile = LLMProfile::default_profile();
+        profile.model = "gpt-4o".to_string();
+        assert!(get_model_capabilities(&profile).vision);
+        profile.provider = LLMProvider::CodexCli;
+        assert!(!get_model_capabilities(&profile).vision);
+    }
+
+    #[test]
+    fn profile_override_wins() {
+        let mut profile = LLMProfile::default_profile();
+        profile.context_window_override = Some(32_000);
+        assert_eq!(get_model_capabilities(&profile).context_window, 32_000);
+    }
+}
@@ -1,5 +1,5 @@
 use futures::StreamExt;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
 use std::{
     collections::HashMap,
     sync::{Mutex, OnceLock},
@@ -7,16 +7,28 @@ use std::{
 };
 use tauri::Emitter;
 
+use super::attachments::FileAttachment;
+use super::error::AiClientError;
+use super::git_diff::GitDiffAttachment;
 use super::models::*;
 use super::prompts::*;
+use super::sentence_smoother::SentenceSmoother;
+use super::tokenizer::estimate_messages_tokens;
 use super::tools::*;
-use crate::llm_profiles::{get_active_profile, LLMProvider};
+use super::word_smoother::WordSmoother;
+use crate::llm_profiles::{
+    normalize_codex_reasoning_effort, resolve_profile, LLMProvider, SmoothingMode,
+    SystemPromptPlacement, DEFAULT_MAX_RECONNECT_ATTEMPTS, DEFAULT_MAX_RESPONSE_BYTES,
+    DEFAULT_MIN_RECONNECT_DELAY_MS,
+};
 
 const QWEN_MIN_REQUEST_GAP_MS: u64 = 1_100;
 const QWEN_MAX_RETRY_DELAY_SECS: u64 = 10;
 const QWEN_MAX_429_ATTEMPTS: u32 = 3;
 
 static QWEN_REQUEST_SLOTS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+/// Last `X-Request-ID` per profile id, for support tickets
+static LAST_REQUEST_IDS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct QwenRateLimitContext {
@@ -31,8 +43,21 @@ fn qwen_request_slots() -> &'static Mutex<HashMap<String, Instant>> {
     QWEN_REQUEST_SLOTS.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-async fn wait_with_chat_status<F>(
-    app_handle: &tauri::AppHandle,
+fn last_request_ids() -> &'static Mutex<HashMap<String, String>> {
+    LAST_REQUEST_IDS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Most recent `X-Request-ID` sent for the profile
+pub fn last_request_id(profile_id: &str) -> Option<String> {
+    last_request_ids()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(profile_id)
+        .cloned()
+}
+
+async fn wait_with_chat_status<R: tauri::Runtime, F>(
+    app_handle: &tauri::AppHandle<R>,
     duration: Duration,
     mut format_status: F,
 ) where
@@ -52,7 +77,10 @@ async fn wait_with_chat_status<F>(
     }
 }
 
-async fn wait_for_qwen_request_slot(profile_id: &str, app_handle: &tauri::AppHandle) {
+async fn wait_for_qwen_request_slot<R: tauri::Runtime>(
+    profile_id: &str,
+    app_handle: &tauri::AppHandle<R>,
+) {
     let wait_for = {
         let mut slots = qwen_request_slots()
             .lock()
@@ -88,14 +116,6 @@ fn extend_qwen_cooldown(profile_id: &str, cooldown: Duration) {
     }
 }
 
-fn extract_retry_after_secs(headers: &HeaderMap) -> Option<u64> {
-    headers
-        .get("retry-after")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.trim().parse::<u64>().ok())
-        .map(|secs| secs.clamp(1, QWEN_MAX_RETRY_DELAY_SECS))
-}
-
 fn parse_qwen_rate_limit_context(headers: &HeaderMap, error_body: &str) -> QwenRateLimitContext {
     let body_lower = error_body.to_lowercase();
     let message = serde_json::from_str::<serde_json::Value>(error_body)
@@ -112,7 +132,8 @@ fn parse_qwen_rate_limit_context(headers: &HeaderMap, error_body: &str) -> QwenR
         || body_lower.contains("postpaid bill is overdue");
 
     QwenRateLimitContext {
-        retry_after_secs: extract_retry_after_secs(headers),
+        retry_after_secs: super::retry::parse_retry_after(headers)
+            .map(|delay| delay.as_secs().clamp(1, QWEN_MAX_RETRY_DELAY_SECS)),
         is_quota_exceeded: body_lower.contains("insufficient_quota")
             || body_lower.contains("allocated quota exceeded")
             || body_lower.contains("current quota"),
@@ -191,8 +212,11 @@ fn reduce_qwen_request_pressure(
     } else {
         16_384
     };
-    if request_body.max_tokens > max_tokens_cap {
-        request_body.max_tokens = max_tokens_cap;
+    if request_body
+        .max_tokens
+        .is_some_and(|max| max > max_tokens_cap)
+    {
+        request_body.max_tokens = Some(max_tokens_cap);
         changed = true;
     }
 
@@ -212,7 +236,7 @@ fn sanitize_messages_for_ollama_cloud(messages: Vec<ApiMessage>) -> Vec<ApiMessa
         .into_iter()
         .map(|mut message| {
             if message.content.is_none() {
-                message.content = Some(String::new());
+                message.content = Some(String::new().into());
             }
             message
         })
@@ -245,6 +269,471 @@ fn build_qwen_rate_limit_message(ctx: &QwenRateLimitContext) -> String {
     }
 }
 
+/// SSE control fields that matter for resuming a broken stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SseControlField {
+    /// `id:` — an empty value resets the last event id (per the SSE spec).
+    Id(Option<String>),
+    /// `retry:` — reconnection delay in milliseconds.
+    Retry(u64),
+}
+
+fn parse_sse_control_field(line: &str) -> Option<SseControlField> {
+    if let Some(id) = line.strip_prefix("id:") {
+        let id = id.trim();
+        return Some(SseControlField::Id(if id.is_empty() {
+            None
+        } else {
+            Some(id.to_string())
+        }));
+    }
+    line.strip_prefix("retry:")
+        .and_then(|ms| ms.trim().parse::<u64>().ok())
+        .map(SseControlField::Retry)
+}
+
+/// Resume state of a broken SSE stream: the last event id, the pause before an attempt
+/// and the attempts used so far (for the whole response, not per break)
+struct SseReconnect {
+    last_event_id: Option<String>,
+    min_delay: Duration,
+    delay: Duration,
+    attempts: u32,
+    max_attempts: u32,
+}
+
+impl SseReconnect {
+    fn new(profile: &crate::llm_profiles::LLMProfile) -> Self {
+        let min_delay = Duration::from_millis(
+            profile
+                .min_reconnect_delay_ms
+                .unwrap_or(DEFAULT_MIN_RECONNECT_DELAY_MS),
+        );
+        Self {
+            last_event_id: None,
+            min_delay,
+            delay: min_delay,
+            attempts: 0,
+            max_attempts: profile
+                .max_reconnect_attempts
+                .unwrap_or(DEFAULT_MAX_RECONNECT_ATTEMPTS),
+        }
+    }
+
+    /// `retry:` below the profile's minimum is ignored: some providers suggest intervals
+    /// too short for their own rate limits
+    fn observe(&mut self, field: SseControlField) {
+        match field {
+            SseControlField::Id(id) => self.last_event_id = id,
+            SseControlField::Retry(ms) => {
+                let suggested = Duration::from_millis(ms);
+                if suggested > self.min_delay {
+                    self.delay = suggested;
+                }
+            }
+        }
+    }
+
+    /// Re-sends the request with `Last-Event-ID` until the server accepts it or the
+    /// attempts run out; streams without event ids cannot be resumed
+    async fn resume(
+        &mut self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Option<reqwest::Response> {
+        let event_id = self.last_event_id.clone()?;
+        while self.attempts < self.max_attempts {
+            self.attempts += 1;
+            crate::app_log!(
+                force: true,
+                "[AI][RECONNECT] attempt {}/{} in {}ms, Last-Event-ID={}",
+                self.attempts,
+                self.max_attempts,
+                self.delay.as_millis(),
+                event_id
+            );
+            tokio::time::sleep(self.delay).await;
+            match request()
+                .header("Last-Event-ID", event_id.as_str())
+                .send()
+                .await
+            {
+                Ok(r) if r.status().is_success() => return Some(r),
+                Ok(r) => crate::app_log!(
+                    force: true,
+                    "[AI][RECONNECT] attempt {} rejected: {}",
+                    self.attempts,
+                    r.status()
+                ),
+                Err(err) => crate::app_log!(
+                    force: true,
+                    "[AI][RECONNECT] attempt {} failed: {}",
+                    self.attempts,
+                    err
+                ),
+            }
+        }
+        None
+    }
+
+    /// Error once a resumable stream has used every attempt; `None` for streams that
+    /// could not be resumed at all
+    fn exhausted_error(&self, details: &str, partial_response: &str) -> Option<AiClientError> {
+        (self.last_event_id.is_some()
+            && self.max_attempts > 0
+            && self.attempts >= self.max_attempts)
+            .then(|| AiClientError::MaxReconnectsExceeded {
+                attempts: self.attempts,
+                message: format!(
+                    "Поток ответа оборвался, все попытки переподключения ({}) исчерпаны: {}",
+                    self.attempts, details
+                ),
+                partial_response: partial_response.to_string(),
+            })
+    }
+}
+
+fn describe_stream_error(e: &reqwest::Error) -> String {
+    // Log full error chain for diagnostics (decode errors often hide in source())
+    use std::error::Error as _;
+    let mut details = format!("{}", e);
+    let mut src: Option<&(dyn std::error::Error + 'static)> = e.source();
+    while let Some(s) = src {
+        details.push_str(" → ");
+        details.push_str(&s.to_string());
+        src = s.source();
+    }
+    details
+}
+
+/// Providers known to accept `stream_options.include_usage`.
+fn provider_supports_stream_usage(provider: &LLMProvider) -> bool {
+    matches!(
+        provider,
+        LLMProvider::OpenAI
+            | LLMProvider::OpenRouter
+            | LLMProvider::DeepSeek
+            | LLMProvider::Groq
+            | LLMProvider::XAI
+    )
+}
+
+/// Providers whose OpenAI-compatible endpoints answer 400 to an unknown `seed` field.
+fn provider_rejects_seed(provider: &LLMProvider) -> bool {
+    matches!(
+        provider,
+        LLMProvider::Anthropic | LLMProvider::Perplexity | LLMProvider::MiniMax
+    )
+}
+
+/// Providers that reject `logit_bias` (no token-level bias or a different tokenizer API).
+fn provider_rejects_logit_bias(provider: &LLMProvider) -> bool {
+    matches!(
+        provider,
+        LLMProvider::Anthropic
+            | LLMProvider::Google
+            | LLMProvider::Perplexity
+            | LLMProvider::MiniMax
+            | LLMProvider::QwenCli
+            | LLMProvider::CodexCli
+            | LLMProvider::OneCNaparnik
+    )
+}
+
+/// Providers that return several `choices` for `n > 1`; others get sequential requests.
+fn provider_supports_n(provider: &LLMProvider) -> bool {
+    matches!(
+        provider,
+        LLMProvider::OpenAI | LLMProvider::XAI | LLMProvider::Mistral
+    )
+}
+
+/// Appends content deltas of choices other than 0 to `alternatives` (choice `i` at `i - 1`)
+/// and returns them as `(index, delta)` for `chat-alternative-chunk`.
+fn collect_alternative_deltas<'a>(
+    chunk: &'a StreamChunk,
+    alternatives: &mut Vec<String>,
+) -> Vec<(usize, &'a str)> {
+    let mut deltas = Vec::new();
+    for choice in chunk.choices.iter().filter(|c| c.index > 0) {
+        let Some(content) = choice.delta.content.as_deref().filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        if alternatives.len() < choice.index {
+            alternatives.resize(choice.index, String::new());
+        }
+        alternatives[choice.index - 1].push_str(content);
+        deltas.push((choice.index, content));
+    }
+    deltas
+}
+
+/// Cuts `alternatives` to `budget` bytes in total, dropping the trailing ones left empty
+/// (positions of the others are kept, they match choice indices).
+/// Returns true when they exceeded it.
+fn enforce_alternatives_limit(alternatives: &mut Vec<String>, budget: usize) -> bool {
+    let mut remaining = budget;
+    let mut truncated = false;
+    for alternative in alternatives.iter_mut() {
+        truncated |= enforce_response_limit(alternative, remaining);
+        remaining -= alternative.len();
+    }
+    if truncated {
+        while alternatives.last().is_some_and(String::is_empty) {
+            alternatives.pop();
+        }
+    }
+    truncated
+}
+
+/// Alternatives `1..=count` for providers without `n`: the same request repeated without
+/// streaming. Only a final text answer gets them, not a turn that calls tools. Stops at the
+/// first failure or when the answer and the alternatives reach `max_bytes`, and returns
+/// what was received.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_sequential_alternatives<R: tauri::Runtime>(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    request: &ChatRequest,
+    count: u8,
+    message: &ApiMessage,
+    max_bytes: usize,
+    app_handle: &tauri::AppHandle<R>,
+) -> Vec<String> {
+    let has_tool_calls = message
+        .tool_calls
+        .as_ref()
+        .is_some_and(|calls| !calls.is_empty());
+    if count == 0 || has_tool_calls {
+        return Vec::new();
+    }
+    let budget = max_bytes.saturating_sub(message.text().unwrap_or_default().len());
+    let mut body = request.clone();
+    body.stream = false;
+    body.stream_options = None;
+    body.n = None;
+    // Alternatives are text only, and a fixed seed would repeat the primary answer
+    body.tools = None;
+    body.seed = None;
+
+    let mut alternatives = Vec::new();
+    for index in 1..=count as usize {
+        let _ = app_handle.emit(
+            "chat-status",
+            format!(
+                "Генерирую вариант {} из {}...",
+                index + 1,
+                count as usize + 1
+            ),
+        );
+        let result = async {
+            let response = client
+                .post(url)
+                .headers(headers.clone())
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("API error {}", response.status()));
+            }
+            let resp: NonStreamResponse = response.json().await.map_err(|e| e.to_string())?;
+            Ok(resp
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|c| c.message.content)
+                .unwrap_or_default())
+        }
+        .await;
+        match result {
+            Ok(text) => {
+                alternatives.push(text);
+                let truncated = enforce_alternatives_limit(&mut alternatives, budget);
+                if let Some(text) = alternatives.get(index - 1) {
+                    let _ = app_handle.emit(
+                        "chat-alternative-chunk",
+                        serde_json::json!({ "index": index, "chunk": text }),
+                    );
+                }
+                if truncated {
+                    crate::app_log!(
+                        "[AI] Alternatives reached {} bytes, skipping the rest",
+                        max_bytes
+                    );
+                    break;
+                }
+            }
+            Err(e) => {
+                crate::app_log!("[AI] Alternative {} failed: {}", index, e);
+                break;
+            }
+        }
+    }
+    alternatives
+}
+
+fn parse_completion_meta_headers(headers: &HeaderMap) -> CompletionMeta {
+    let header_str = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let header_num = |name: &str| header_str(name).and_then(|s| s.parse::<u64>().ok());
+    CompletionMeta {
+        processing_ms: header_num("openai-processing-ms"),
+        remaining_tokens: header_num("x-ratelimit-remaining-tokens")
+            .map(|v| u32::try_from(v).unwrap_or(u32::MAX)),
+        prompt_tokens: None,
+        completion_tokens: None,
+        cached_tokens: None,
+        system_fingerprint: None,
+        estimated_cost_usd: None,
+        request_id: None,
+        server_request_id: header_str("x-request-id").or_else(|| header_str("cf-ray")),
+        time_to_first_token_ms: None,
+        usage_estimated: false,
+    }
+}
+
+/// Cuts `content` down to `limit` bytes (on a char boundary).
+/// Returns true when the content exceeded the limit.
+fn enforce_response_limit(content: &mut String, limit: usize) -> bool {
+    if content.len() <= limit {
+        return false;
+    }
+    let mut cut = limit;
+    while !content.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    content.truncate(cut);
+    true
+}
+
+/// Assistant message and alternatives of a `stream: false` response. Usage is recorded in
+/// `meta` exactly as the streaming path does, so history and accounting see the same data.
+fn parse_non_stream_response(
+    body: &str,
+    meta: &mut CompletionMeta,
+) -> Result<(ApiMessage, Vec<String>), AiClientError> {
+    let resp: NonStreamResponse = serde_json::from_str(body).map_err(|e| {
+        AiClientError::parse(format!(
+            "Failed to parse non-stream response: {} body={}",
+            e,
+            &body[..body.len().min(200)]
+        ))
+    })?;
+    if let Some(usage) = &resp.usage {
+        meta.apply_usage(usage);
+    }
+    if resp.system_fingerprint.is_some() {
+        meta.system_fingerprint = resp.system_fingerprint.clone();
+    }
+    let mut choices = resp.choices;
+    choices.sort_by_key(|c| c.index);
+    let mut choices = choices.into_iter();
+    let choice = choices
+        .next()
+        .ok_or_else(|| AiClientError::parse("Empty response from API"))?;
+    let alternatives: Vec<String> = choices
+        .map(|c| c.message.content.unwrap_or_default())
+        .collect();
+    let content = choice.message.content.unwrap_or_default();
+    // Convert NonStreamToolCall → ToolCall, normalising arguments to valid JSON string.
+    let tool_calls: Vec<ToolCall> = choice
+        .message
+        .tool_calls
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tc| ToolCall {
+            id: tc.id,
+            r#type: tc.r#type,
+            function: ToolCallFunction {
+                name: tc.function.name,
+                arguments: tc
+                    .function
+                    .arguments
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| "{}".to_string()),
+            },
+        })
+        .collect();
+    let message = ApiMessage {
+        role: "assistant".to_string(),
+        content: if content.is_empty() {
+            None
+        } else {
+            Some(content.into())
+        },
+        tool_calls: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(tool_calls)
+        },
+        tool_call_id: None,
+        name: None,
+    };
+    Ok((message, alternatives))
+}
+
+/// Records usage, emits `chat-end` with the response metadata and pairs it with the message.
+/// `extra_alternatives` are choices 1.. of an `n > 1` request. A final answer (no tool calls)
+/// goes through `pipeline` before anything is emitted; `chat-answer-replaced` carries the new
+/// text when it differs from the streamed one.
+fn finish_completion<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    model: &str,
+    mut message: ApiMessage,
+    mut meta: CompletionMeta,
+    mut extra_alternatives: Vec<String>,
+    pipeline: Option<&super::transforms::TransformPipeline>,
+) -> ChatCompletion {
+    if let (Some(prompt), Some(completion)) = (meta.prompt_tokens, meta.completion_tokens) {
+        meta.estimated_cost_usd = super::pricing::estimate_cost(prompt, completion, model);
+    }
+    super::usage::record_usage(&meta);
+    let _ = app_handle.emit(
+        "chat-usage",
+        serde_json::json!({
+            "prompt_tokens": meta.prompt_tokens,
+            "completion_tokens": meta.completion_tokens,
+            "cached_tokens": meta.cached_tokens,
+            "estimated": meta.usage_estimated,
+        }),
+    );
+    // Alternatives of a turn that calls tools are not shown: the loop continues from the calls
+    let has_tool_calls = message
+        .tool_calls
+        .as_ref()
+        .is_some_and(|calls| !calls.is_empty());
+    if let Some(pipeline) = pipeline.filter(|_| !has_tool_calls) {
+        if let Some(text) = message.text() {
+            let transformed = pipeline.apply(text);
+            if transformed != text {
+                let _ = app_handle.emit("chat-answer-replaced", &transformed);
+                message.content = Some(transformed.into());
+            }
+        }
+        for alternative in &mut extra_alternatives {
+            *alternative = pipeline.apply(alternative);
+        }
+    }
+    let alternatives: Vec<String> = std::iter::once(message.text().unwrap_or_default().to_string())
+        .chain(extra_alternatives.into_iter().filter(|_| !has_tool_calls))
+        .collect();
+    if alternatives.len() > 1 {
+        let _ = app_handle.emit("chat-alternatives", &alternatives);
+    }
+    let _ = app_handle.emit("chat-end", &meta);
+    ChatCompletion {
+        message,
+        meta,
+        alternatives,
+    }
+}
+
 fn provider_requires_api_key(provider: &LLMProvider) -> bool {
     matches!(
         provider,
@@ -263,151 +752,874 @@ fn provider_requires_api_key(provider: &LLMProvider) -> bool {
     )
 }
 
+/// `/chat/completions` endpoint of an OpenAI-compatible profile.
+pub(crate) fn chat_completions_url(profile: &crate::llm_profiles::LLMProfile) -> String {
+    format!("{}/chat/completions", profile.get_base_url())
+}
+
+/// Content type, bearer auth and provider attribution headers for OpenAI-compatible APIs.
+pub(crate) fn openai_compatible_headers(
+    provider: &LLMProvider,
+    api_key: &str,
+) -> Result<HeaderMap, AiClientError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    if !api_key.is_empty() {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|e| AiClientError::invalid_profile(e.to_string()))?,
+        );
+    }
+
+    if matches!(provider, LLMProvider::OpenRouter) {
+        headers.insert(
+            "HTTP-Referer",
+            HeaderValue::from_static("https://mini-ai-1c.local"),
+        );
+        headers.insert("X-Title", HeaderValue::from_static("Mini AI 1C Agent"));
+    }
+
+    Ok(headers)
+}
+
 pub fn resolve_profile_api_key(
     profile: &crate::llm_profiles::LLMProfile,
-) -> Result<String, String> {
-    let api_key = profile.try_get_api_key()?;
+) -> Result<String, AiClientError> {
+    let api_key = profile
+        .try_get_api_key()
+        .map_err(AiClientError::invalid_profile)?;
     if provider_requires_api_key(&profile.provider) && api_key.trim().is_empty() {
-        return Err(format!(
+        return Err(AiClientError::invalid_profile(format!(
             "Для провайдера {} не найден API key. Откройте профиль и сохраните ключ заново.",
             profile.provider
-        ));
+        )));
     }
     Ok(api_key)
 }
 
-/// Stream chat completion from OpenAI-compatible API
-/// Returns the full accumulated response text
-pub async fn stream_chat_completion(
-    messages: Vec<ApiMessage>,
-    app_handle: tauri::AppHandle,
-) -> Result<ApiMessage, String> {
-    // Route 1С:Напарник to its dedicated client (non-OpenAI API)
-    {
-        let p = get_active_profile().ok_or("No active LLM profile")?;
-        if matches!(p.provider, LLMProvider::OneCNaparnik) {
-            return super::naparnik_client::stream_naparnik_completion(messages, app_handle).await;
-        }
+/// System message (with the task addendum and current file), few-shot examples, then the conversation.
+fn build_api_messages(
+    mut system_prompt: String,
+    task_type: TaskType,
+    verbosity: Option<Verbosity>,
+    file_context: Option<&FileContext>,
+    attachments: &[FileAttachment],
+    examples: Vec<ApiMessage>,
+    mut messages: Vec<ApiMessage>,
+) -> Vec<ApiMessage> {
+    apply_task_addendum(&mut system_prompt, task_type, verbosity);
+    if let Some(context) = file_context {
+        apply_file_context(&mut system_prompt, context);
     }
-
-    let profile = get_active_profile().ok_or("No active LLM profile")?;
-    let has_tool_heavy_context = qwen_has_tool_heavy_context(&messages);
-    // Build system prompt: use lightweight variant for local providers (Ollama/LMStudio)
-    // to avoid smaller models rephrasing instead of responding.
-    let tools_info = get_available_tools().await;
-    let tools: Vec<Tool> = tools_info.iter().map(|i| i.tool.clone()).collect();
-    let tools_opt = if tools.is_empty() { None } else { Some(tools) };
-
-    let system_prompt = if is_local_provider(Some(&profile.provider)) {
-        get_lightweight_system_prompt(&tools_info, &messages)
-    } else {
-        get_system_prompt(&tools_info, &messages)
-    };
-
     let mut api_messages = vec![ApiMessage {
         role: "system".to_string(),
-        content: Some(system_prompt),
+        content: Some(system_prompt.into()),
         tool_calls: None,
         tool_call_id: None,
         name: None,
     }];
-    api_messages.extend(messages);
-    if matches!(profile.provider, LLMProvider::OllamaCloud) {
-        api_messages = sanitize_messages_for_ollama_cloud(api_messages);
+    // Examples follow the pinned context and system blocks, keeping the cached prefix stable
+    let stable_prefix = messages
+        .iter()
+        .take_while(|m| {
+            m.role == "system" || m.content.as_ref().is_some_and(MessageContent::is_cacheable)
+        })
+        .count();
+    messages.splice(stable_prefix..stable_prefix, examples);
+    // Attached files go right before the question they belong to
+    if let Some(text) = super::attachments::attachments_message_text(attachments) {
+        let at = messages
+            .iter()
+            .rposition(|m| m.role == "user")
+            .unwrap_or(messages.len());
+        messages.insert(
+            at,
+            ApiMessage {
+                role: "user".to_string(),
+                content: Some(text.into()),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+        );
     }
+    api_messages.extend(messages);
+    api_messages
+}
 
-    if matches!(profile.provider, LLMProvider::CodexCli) {
-        return super::codex_client::stream_codex_completion(api_messages, app_handle).await;
+/// Fails when the estimated request does not fit into the model context window.
+fn check_context_budget(messages: &[ApiMessage], context_window: u32) -> Result<(), AiClientError> {
+    let estimated_tokens = estimate_messages_tokens(messages);
+    if estimated_tokens > context_window {
+        return Err(AiClientError::Other {
+            message: format!(
+                "Запрос с вложениями не помещается в контекст модели: ~{} токенов при окне {}. \
+                Прикрепите меньше файлов.",
+                estimated_tokens, context_window
+            ),
+        });
     }
+    Ok(())
+}
 
-    let (api_key, url) = if matches!(profile.provider, LLMProvider::QwenCli) {
-        let token_info = crate::llm::cli_providers::qwen::QwenCliProvider::get_token(&profile.id)?;
-        let (access_token, refresh_token, expires_at, resource_url) =
-            token_info.ok_or("Qwen CLI: Требуется авторизация")?;
+/// Newer OpenAI models take instructions in the `developer` role.
+const DEVELOPER_ROLE_PREFIXES: &[&str] = &["o1", "o3", "o4", "gpt-5"];
 
-        // Auto-refresh if token expired (or expires within next 60 seconds)
-        let (access_token, resource_url) = if chrono::Utc::now().timestamp() as u64 + 60
-            > expires_at
-        {
-            if let Some(rt) = refresh_token.as_deref() {
-                crate::app_log!(force: true, "[Qwen] Token expired/near-expiry, attempting refresh...");
-                let _ =
-                    app_handle.emit("chat-status", "Qwen: обновляю токен доступа...".to_string());
-                match crate::llm::cli_providers::qwen::QwenCliProvider::refresh_access_token(
-                    &profile.id,
-                    rt,
-                )
-                .await
-                {
-                    Ok(()) => {
-                        let new_info = crate::llm::cli_providers::qwen::QwenCliProvider::get_token(
-                            &profile.id,
-                        )?
-                        .ok_or("Qwen CLI: Токен не найден после обновления")?;
-                        crate::app_log!(force: true, "[Qwen] Token refreshed successfully");
-                        (new_info.0, new_info.3)
-                    }
-                    Err(e) => {
-                        crate::app_log!(force: true, "[Qwen] Token refresh failed: {}", e);
-                        return Err("Qwen CLI: Токен истек и не удалось обновить. Требуется повторная авторизация".to_string());
-                    }
-                }
-            } else {
-                return Err("Qwen CLI: Токен истек. Требуется повторная авторизация".to_string());
-            }
-        } else {
-            (access_token, resource_url)
-        };
+/// Model families without a system role in their chat template.
+const NO_SYSTEM_ROLE_MARKERS: &[&str] = &["gemma"];
 
-        let base = if let Some(ru) = resource_url
-            .as_deref()
-            .filter(|s| !s.is_empty() && !s.contains("dashscope") && !s.contains("aliyun"))
-        {
-            format!("https://{}/v1", ru)
-        } else {
-            "https://portal.qwen.ai/v1".to_string()
-        };
-        (access_token, format!("{}/chat/completions", base))
-    } else {
-        let api_key = resolve_profile_api_key(&profile)?;
-        let raw_url = profile.get_base_url();
-        // Normalize: Ollama and similar OpenAI-compatible servers require /v1/chat/completions.
-        // If the user entered a bare URL without /v1 (e.g. http://host:11434), add it automatically.
-        let base_url = {
-            let trimmed = raw_url.trim_end_matches('/');
-            if matches!(
-                profile.provider,
-                LLMProvider::Ollama | LLMProvider::OllamaCloud | LLMProvider::LMStudio
-            ) && !trimmed.ends_with("/v1")
-            {
-                format!("{}/v1", trimmed)
-            } else {
-                trimmed.to_string()
-            }
-        };
-        (api_key, format!("{}/chat/completions", base_url))
-    };
+/// Profile override, otherwise by provider and model. Anthropic keeps `system`: its
+/// OpenAI-compatible endpoint hoists system messages into the top-level field itself.
+fn resolve_system_prompt_placement(
+    profile: &crate::llm_profiles::LLMProfile,
+) -> SystemPromptPlacement {
+    if let Some(placement) = profile.system_prompt_placement {
+        return placement;
+    }
+    let model = profile.model.to_lowercase();
+    let model_id = model.rsplit('/').next().unwrap_or(&model);
+    if NO_SYSTEM_ROLE_MARKERS.iter().any(|m| model_id.contains(m)) {
+        return SystemPromptPlacement::PrependToUser;
+    }
+    if matches!(profile.provider, LLMProvider::OpenAI)
+        && DEVELOPER_ROLE_PREFIXES
+            .iter()
+            .any(|p| model_id.starts_with(p))
+    {
+        return SystemPromptPlacement::Developer;
+    }
+    SystemPromptPlacement::System
+}
 
-    let api_max_tokens = if matches!(profile.provider, LLMProvider::QwenCli) {
-        qwen_max_tokens(profile.max_tokens, has_tool_heavy_context)
-    } else if matches!(profile.provider, LLMProvider::LMStudio) {
-        // Qwen3 thinking models need large token budget to finish thinking before producing content
-        profile.max_tokens.max(8192)
-    } else if matches!(profile.provider, LLMProvider::MiniMax) {
+/// Moves the `system` messages built by `build_api_messages` where the provider expects
+/// them; returns the prompt for the top-level `system` field.
+fn apply_system_prompt_placement(
+    messages: &mut Vec<ApiMessage>,
+    placement: SystemPromptPlacement,
+) -> Option<String> {
+    match placement {
+        SystemPromptPlacement::System => None,
+        SystemPromptPlacement::Developer => {
+            for m in messages.iter_mut().filter(|m| m.role == "system") {
+                m.role = "developer".to_string();
+            }
+            None
+        }
+        SystemPromptPlacement::PrependToUser | SystemPromptPlacement::TopLevel => {
+            let system_text = messages
+                .iter()
+                .filter(|m| m.role == "system")
+                .filter_map(|m| m.text())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            messages.retain(|m| m.role != "system");
+            if system_text.is_empty() {
+                return None;
+            }
+            if placement == SystemPromptPlacement::TopLevel {
+                return Some(system_text);
+            }
+            match messages.iter_mut().find(|m| m.role == "user") {
+                Some(first_user) => match first_user.content.as_mut() {
+                    Some(content) => content.prepend_text(&format!("{}\n\n---\n\n", system_text)),
+                    None => first_user.content = Some(system_text.into()),
+                },
+                None => messages.insert(
+                    0,
+                    ApiMessage {
+                        role: "user".to_string(),
+                        content: Some(system_text.into()),
+                        tool_calls: None,
+                        tool_call_id: None,
+                        name: None,
+                    },
+                ),
+            }
+            None
+        }
+    }
+}
+
+/// Providers that take Anthropic `cache_control` breakpoints in content parts.
+fn uses_cache_control(profile: &crate::llm_profiles::LLMProfile) -> bool {
+    match profile.provider {
+        LLMProvider::Anthropic => true,
+        LLMProvider::OpenRouter => {
+            let model = profile.model.to_lowercase();
+            model.starts_with("anthropic/") || model.contains("claude")
+        }
+        _ => false,
+    }
+}
+
+/// Prompt caching. Anthropic-style providers get breakpoints on the system message and
+/// the last pinned message (at most 4 are allowed). Everyone else gets plain content, so the
+/// stable prefix stays byte-identical and automatic prefix caching (OpenAI) applies.
+fn apply_prompt_caching(messages: &mut [ApiMessage], profile: &crate::llm_profiles::LLMProfile) {
+    let last_pinned = messages
+        .iter()
+        .rposition(|m| m.content.as_ref().is_some_and(MessageContent::is_cacheable));
+    let cache_control = profile.prompt_caching && uses_cache_control(profile);
+
+    for (i, message) in messages.iter_mut().enumerate() {
+        let Some(content) = message.content.as_mut() else {
+            continue;
+        };
+        let keep = cache_control && (message.role == "system" && i == 0 || Some(i) == last_pinned);
+        if keep {
+            content.mark_cacheable();
+        } else {
+            content.strip_cache_control();
+        }
+    }
+}
+
+/// Stream chat completion from OpenAI-compatible API
+/// Returns the full accumulated response text. Ends with exactly one of `chat-done`,
+/// `chat-error` or `chat-cancelled` for the request id of `chat-start`.
+#[tracing::instrument(
+    name = "completion",
+    skip_all,
+    fields(
+        messages = messages.len(),
+        profile = tracing::field::Empty,
+        model = tracing::field::Empty,
+        request_bytes = tracing::field::Empty,
+        response_bytes = tracing::field::Empty,
+    )
+)]
+pub async fn stream_chat_completion<R: tauri::Runtime>(
+    messages: Vec<ApiMessage>,
+    overrides: &RequestOverrides,
+    task_type: TaskType,
+    file_context: Option<&FileContext>,
+    attachments: &[FileAttachment],
+    git_diff: Option<&GitDiffAttachment>,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<ChatCompletion, AiClientError> {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    super::terminal_event::with_terminal_event(
+        app_handle.clone(),
+        request_id.clone(),
+        run_chat_completion(
+            messages,
+            overrides,
+            task_type,
+            file_context,
+            attachments,
+            git_diff,
+            app_handle,
+            &request_id,
+        ),
+    )
+    .await
+}
+
+/// Profile fallback chain and post-processing around `stream_chat_completion_inner`;
+/// every attempt uses the same `request_id`
+#[allow(clippy::too_many_arguments)]
+async fn run_chat_completion<R: tauri::Runtime>(
+    messages: Vec<ApiMessage>,
+    overrides: &RequestOverrides,
+    task_type: TaskType,
+    file_context: Option<&FileContext>,
+    attachments: &[FileAttachment],
+    git_diff: Option<&GitDiffAttachment>,
+    app_handle: tauri::AppHandle<R>,
+    request_id: &str,
+) -> Result<ChatCompletion, AiClientError> {
+    let started = std::time::Instant::now();
+    let store = crate::llm_profiles::load_profiles();
+    if overrides.profile_id.is_none()
+        && !store
+            .profiles
+            .iter()
+            .any(|p| p.id == store.active_profile_id)
+    {
+        return Err(AiClientError::NoActiveProfile);
+    }
+    let primary = store
+        .resolve(overrides.profile_id.as_deref())
+        .map_err(AiClientError::invalid_profile)?;
+    if !primary.enabled {
+        return Err(AiClientError::invalid_profile(format!(
+            "Профиль «{}» отключён — укажите ключ API и включите его в настройках",
+            primary.name
+        )));
+    }
+    let mut tried = vec![primary.id.clone()];
+    let mut overrides = overrides.clone();
+    let span = tracing::Span::current();
+
+    let result = loop {
+        let mut retryable = false;
+        let profile_id = tried.last().cloned().unwrap_or_default();
+        let profile_name = store
+            .profiles
+            .iter()
+            .find(|p| p.id == profile_id)
+            .map_or(profile_id.as_str(), |p| p.name.as_str());
+        span.record("profile", profile_name);
+        let result = match crate::llm::circuit_breaker::check(&profile_id, profile_name) {
+            // Fails fast, straight to the fallback chain
+            Err(error) => {
+                retryable = true;
+                Err(error)
+            }
+            Ok(()) => {
+                let result = stream_chat_completion_inner(
+                    messages.clone(),
+                    &overrides,
+                    task_type,
+                    file_context,
+                    attachments,
+                    git_diff,
+                    app_handle.clone(),
+                    request_id,
+                    &mut retryable,
+                )
+                .await;
+                crate::llm::circuit_breaker::record(&profile_id, &result);
+                // Keeps the settings health table fresh without explicit checks; a
+                // cancelled request says nothing about the profile
+                if !matches!(result, Err(AiClientError::Cancelled)) {
+                    let error_text = result.as_ref().err().map(ToString::to_string);
+                    crate::llm::profile_health::record_chat(
+                        &profile_id,
+                        error_text.as_deref().map_or(Ok(()), Err),
+                        result
+                            .as_ref()
+                            .ok()
+                            .and_then(|c| c.meta.time_to_first_token_ms),
+                    );
+                }
+                super::usage::record_profile_usage(
+                    &profile_id,
+                    result.as_ref().ok().map(|c| &c.meta),
+                );
+                if let Err(error) = &result {
+                    if error.is_auth_failure() {
+                        crate::llm::profile_health::mark_auth_failed(&profile_id);
+                        error.emit_auth_error(&app_handle);
+                    }
+                }
+                result
+            }
+        };
+        let error = match &result {
+            Ok(completion) => {
+                let bytes: usize = completion.alternatives.iter().map(String::len).sum();
+                span.record("response_bytes", bytes);
+                break result;
+            }
+            Err(error) => error,
+        };
+        if !retryable {
+            break result;
+        }
+        // Only the primary profile's chain is followed, each profile at most once
+        let Some(next) = next_fallback_profile(&primary.fallback_profiles, &tried, |id| {
+            store.profiles.iter().any(|p| p.id == id && p.enabled)
+        }) else {
+            break result;
+        };
+        let next_name = store
+            .resolve(Some(&next))
+            .map_err(AiClientError::invalid_profile)?
+            .name;
+        crate::app_log!(
+            force: true,
+            "[AI][FALLBACK] {} failed ({}), switching to {}",
+            tried.last().map(String::as_str).unwrap_or_default(),
+            error,
+            next_name
+        );
+        let _ = app_handle.emit(
+            "chat-fallback",
+            serde_json::json!({
+                "from_profile_id": tried.last(),
+                "profile_id": next,
+                "profile_name": next_name,
+                "error": error.to_string(),
+            }),
+        );
+        // Model names are provider-specific, so a model override stays with the primary
+        overrides.model = None;
+        overrides.profile_id = Some(next.clone());
+        tried.push(next);
+    };
+
+    let profile_id = tried.pop().unwrap_or_default();
+    if let (Ok(completion), Ok(profile)) = (&result, store.resolve(Some(&profile_id))) {
+        emit_compatibility_issues(&app_handle, completion, &profile);
+    }
+    let meta = result.as_ref().ok().map(|c| &c.meta);
+    crate::metrics::record_chat(
+        &profile_id,
+        result.is_ok(),
+        started.elapsed(),
+        meta.and_then(|m| m.prompt_tokens),
+        meta.and_then(|m| m.completion_tokens),
+    );
+    result
+}
+
+/// Single question without the conversation: the configured system prompt plus `prompt`,
+/// answered without chat events and not added to the history.
+pub async fn one_shot_completion(prompt: String) -> Result<String, AiClientError> {
+    prompt_completion(prompt, TaskType::FreeForm).await
+}
+
+/// `prompt` as the only user message after the configured system prompt with the
+/// `task_type` addendum, sent to the active profile through `silent_completion`
+pub async fn prompt_completion(
+    prompt: String,
+    task_type: TaskType,
+) -> Result<String, AiClientError> {
+    let profile = resolve_profile(None).map_err(AiClientError::invalid_profile)?;
+    let messages = vec![ApiMessage {
+        role: "user".to_string(),
+        content: Some(prompt.into()),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }];
+    let (system_prompt, _) = resolve_system_prompt(
+        None,
+        &profile,
+        &super::prompt_template::PromptContext::default(),
+        &[],
+        &messages,
+    );
+    let api_messages = build_api_messages(
+        system_prompt,
+        task_type,
+        None,
+        None,
+        &[],
+        Vec::new(),
+        messages,
+    );
+    silent_completion(api_messages, &profile).await
+}
+
+/// Non-streaming completion for background work (summaries, probes): `messages` go to
+/// `profile` as-is, no Tauri events are emitted and no metrics are recorded.
+pub async fn silent_completion(
+    messages: Vec<ApiMessage>,
+    profile: &crate::llm_profiles::LLMProfile,
+) -> Result<String, AiClientError> {
+    silent_completion_with_meta(messages, profile)
+        .await
+        .map(|(text, _)| text)
+}
+
+/// `silent_completion` together with the token usage of the response.
+pub async fn silent_completion_with_meta(
+    messages: Vec<ApiMessage>,
+    profile: &crate::llm_profiles::LLMProfile,
+) -> Result<(String, CompletionMeta), AiClientError> {
+    if matches!(
+        profile.provider,
+        LLMProvider::CodexCli | LLMProvider::QwenCli | LLMProvider::OneCNaparnik
+    ) {
+        return Err(AiClientError::invalid_profile(format!(
+            "Фоновый запрос не поддерживается для провайдера {:?}",
+            profile.provider
+        )));
+    }
+
+    let api_key = resolve_profile_api_key(profile)?;
+    let url = chat_completions_url(profile);
+    let headers = openai_compatible_headers(&profile.provider, &api_key)?;
+    let client = crate::http_client::build_profile_client(profile)
+        .map_err(AiClientError::invalid_profile)?;
+
+    let mut request_body = ChatRequest {
+        model: profile.model.clone(),
+        messages,
+        stream: false,
+        temperature: profile.temperature,
+        max_tokens: profile.max_tokens,
+        max_completion_tokens: None,
+        top_p: profile.top_p,
+        frequency_penalty: profile.frequency_penalty,
+        presence_penalty: profile.presence_penalty,
+        stop: None,
+        seed: profile.seed,
+        logit_bias: None,
+        n: None,
+        user: None,
+        system: None,
+        stream_options: None,
+        response_format: None,
+        tools: None,
+        enable_thinking: None,
+        thinking_budget_tokens: None,
+        reasoning_effort: None,
+        thinking: None,
+    };
+    super::capabilities::apply_model_rules(
+        &mut request_body,
+        &super::capabilities::get_model_capabilities(profile),
+    );
+
+    let response = client
+        .post(&url)
+        .headers(headers)
+        .json(&request_body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if super::error::is_auth_status(status.as_u16()) {
+            return Err(AiClientError::auth_failed(
+                status.as_u16(),
+                &body,
+                profile,
+                &api_key,
+            ));
+        }
+        let message = super::error::describe_http_error(status.as_u16(), &body, &profile.model);
+        return Err(AiClientError::http(
+            status.as_u16(),
+            &body,
+            &profile.provider,
+            message,
+        ));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| AiClientError::stream(format!("Failed to read response: {}", e)))?;
+
+    let mut meta = CompletionMeta::default();
+    let (message, _) = parse_non_stream_response(&body, &mut meta)?;
+    let text = message
+        .text()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| AiClientError::parse("Пустой ответ от LLM"))?;
+    Ok((text, meta))
+}
+
+/// Buffering of the streamed answer by `LLMProfile::smoothing_mode`
+enum AnswerSmoother {
+    Word(WordSmoother),
+    Sentence(SentenceSmoother),
+}
+
+impl AnswerSmoother {
+    fn for_mode(mode: SmoothingMode) -> Option<Self> {
+        match mode {
+            SmoothingMode::None => None,
+            SmoothingMode::Word => Some(Self::Word(WordSmoother::default())),
+            SmoothingMode::Sentence => Some(Self::Sentence(SentenceSmoother::default())),
+        }
+    }
+}
+
+/// Sends answer text as `chat-chunk` (and `chat-sentence`) paced by the profile's smoothing mode
+fn emit_answer_chunk<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    smoother: &mut Option<AnswerSmoother>,
+    text: String,
+) {
+    match smoother {
+        Some(AnswerSmoother::Word(smoother)) => {
+            for piece in smoother.push_chunk(&text) {
+                let _ = app_handle.emit("chat-chunk", piece);
+            }
+        }
+        Some(AnswerSmoother::Sentence(smoother)) => {
+            for sentence in smoother.push_chunk(&text) {
+                let _ = app_handle.emit("chat-sentence", sentence);
+            }
+            let _ = app_handle.emit("chat-chunk", text);
+        }
+        None => {
+            let _ = app_handle.emit("chat-chunk", text);
+        }
+    }
+}
+
+fn flush_answer_chunks<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    smoother: &mut Option<AnswerSmoother>,
+) {
+    match smoother {
+        Some(AnswerSmoother::Word(smoother)) => {
+            if let Some(rest) = smoother.flush() {
+                let _ = app_handle.emit("chat-chunk", rest);
+            }
+        }
+        Some(AnswerSmoother::Sentence(smoother)) => {
+            if let Some(rest) = smoother.flush() {
+                let _ = app_handle.emit("chat-sentence", rest);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Checks BSL blocks of the answer against the profile's target platform version
+fn emit_compatibility_issues<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    completion: &ChatCompletion,
+    profile: &crate::llm_profiles::LLMProfile,
+) {
+    let Some(version) = profile.target_platform_version.as_deref() else {
+        return;
+    };
+    let code =
+        super::code_blocks::extract_code_blocks(completion.message.text().unwrap_or_default())
+            .into_iter()
+            .filter(|b| matches!(b.language.to_lowercase().as_str(), "" | "bsl" | "1c" | "1с"))
+            .map(|b| b.code)
+            .collect::<Vec<_>>()
+            .join("\n");
+    let issues = crate::platform_compat::check_platform_compatibility(&code, version);
+    if !issues.is_empty() {
+        let _ = app_handle.emit(
+            "compatibility-issues",
+            serde_json::json!({ "platform_version": version, "issues": issues }),
+        );
+    }
+}
+
+/// Next profile of the fallback chain that exists and has not been tried yet
+fn next_fallback_profile(
+    chain: &[String],
+    tried: &[String],
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    chain
+        .iter()
+        .find(|id| !tried.contains(id) && exists(id))
+        .cloned()
+}
+
+/// `retryable` is set when the request failed before any content was streamed with an
+/// error another provider may not have: connection failure, 429 after retries or 5xx.
+#[allow(clippy::too_many_arguments)]
+async fn stream_chat_completion_inner<R: tauri::Runtime>(
+    mut messages: Vec<ApiMessage>,
+    overrides: &RequestOverrides,
+    task_type: TaskType,
+    file_context: Option<&FileContext>,
+    attachments: &[FileAttachment],
+    git_diff: Option<&GitDiffAttachment>,
+    app_handle: tauri::AppHandle<R>,
+    request_id: &str,
+    retryable: &mut bool,
+) -> Result<ChatCompletion, AiClientError> {
+    if let Some(diff) = git_diff {
+        super::git_diff::prepend_git_diff(&mut messages, diff);
+    }
+    let pipeline = overrides.response_pipeline();
+
+    // Fail before any provider call when images are attached to a text-only model
+    let base_profile =
+        resolve_profile(overrides.profile_id.as_deref()).map_err(AiClientError::invalid_profile)?;
+    if messages.iter().any(ApiMessage::has_images) {
+        let p = overrides
+            .apply_to(&base_profile)
+            .map_err(AiClientError::invalid_profile)?;
+        super::capabilities::ensure_vision_supported(&p).map_err(AiClientError::invalid_profile)?;
+    }
+    if let Err(error) = super::reachability::ensure_reachable(&base_profile).await {
+        // A fallback profile may live on another host (a local model while offline)
+        *retryable = true;
+        return Err(error);
+    }
+
+    // Route 1С:Напарник to its dedicated client (non-OpenAI API)
+    {
+        let p = &base_profile;
+        if matches!(p.provider, LLMProvider::OneCNaparnik) {
+            let message =
+                super::naparnik_client::stream_naparnik_completion(messages, p, app_handle.clone())
+                    .await?;
+            return Ok(finish_completion(
+                &app_handle,
+                &p.model,
+                message,
+                CompletionMeta::default(),
+                Vec::new(),
+                pipeline.as_deref(),
+            ));
+        }
+    }
+
+    let profile = overrides
+        .apply_to(&base_profile)
+        .map_err(AiClientError::invalid_profile)?;
+    let has_tool_heavy_context = qwen_has_tool_heavy_context(&messages);
+    // Build system prompt: request override → profile → app default → built-in
+    // (lightweight variant for local providers to avoid smaller models rephrasing).
+    let tools_info = get_available_tools().await;
+    let tools: Vec<Tool> = tools_info.iter().map(|i| i.tool.clone()).collect();
+    let tools_opt = if tools.is_empty() { None } else { Some(tools) };
+
+    let (mut system_prompt, system_prompt_source) = resolve_system_prompt(
+        overrides.system_prompt.as_deref(),
+        &profile,
+        &overrides.prompt_context.clone().unwrap_or_default(),
+        &tools_info,
+        &messages,
+    );
+    if let Some(suffix) = overrides
+        .system_prompt_suffix
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+    {
+        system_prompt.push_str("\n\n");
+        system_prompt.push_str(suffix);
+    }
+    if profile.auto_language_match {
+        if let Some(hint) =
+            super::prompts::language_match_hint(answer_language(Some(&profile)), &messages)
+        {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(hint);
+        }
+    }
+
+    let few_shot = super::few_shot::load_few_shot_store();
+    let query = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .and_then(ApiMessage::text)
+        .unwrap_or_default();
+    let examples = super::few_shot::example_messages(&super::few_shot::select_examples(
+        &few_shot.examples,
+        task_type,
+        query,
+        few_shot.k,
+    ));
+
+    let mut api_messages = build_api_messages(
+        system_prompt,
+        task_type,
+        overrides.verbosity,
+        file_context,
+        attachments,
+        examples,
+        messages,
+    );
+    if !attachments.is_empty() {
+        check_context_budget(
+            &api_messages,
+            super::capabilities::get_model_capabilities(&profile).context_window,
+        )?;
+    }
+    if matches!(profile.provider, LLMProvider::OllamaCloud) {
+        api_messages = sanitize_messages_for_ollama_cloud(api_messages);
+    }
+    apply_prompt_caching(&mut api_messages, &profile);
+
+    if matches!(profile.provider, LLMProvider::CodexCli) {
+        let message = super::codex_client::stream_codex_completion(
+            api_messages,
+            &profile,
+            app_handle.clone(),
+        )
+        .await?;
+        return Ok(finish_completion(
+            &app_handle,
+            &profile.model,
+            message,
+            CompletionMeta::default(),
+            Vec::new(),
+            pipeline.as_deref(),
+        ));
+    }
+
+    let (api_key, url) = if matches!(profile.provider, LLMProvider::QwenCli) {
+        let token_info = crate::llm::cli_providers::qwen::QwenCliProvider::get_token(&profile.id)?;
+        let (access_token, refresh_token, expires_at, resource_url) =
+            token_info.ok_or("Qwen CLI: Требуется авторизация")?;
+
+        // Auto-refresh if token expired (or expires within next 60 seconds)
+        let (access_token, resource_url) = if chrono::Utc::now().timestamp() as u64 + 60
+            > expires_at
+        {
+            if let Some(rt) = refresh_token.as_deref() {
+                crate::app_log!(force: true, "[Qwen] Token expired/near-expiry, attempting refresh...");
+                let _ =
+                    app_handle.emit("chat-status", "Qwen: обновляю токен доступа...".to_string());
+                match crate::llm::cli_providers::qwen::QwenCliProvider::refresh_access_token(
+                    &profile.id,
+                    rt,
+                )
+                .await
+                {
+                    Ok(()) => {
+                        let new_info = crate::llm::cli_providers::qwen::QwenCliProvider::get_token(
+                            &profile.id,
+                        )?
+                        .ok_or("Qwen CLI: Токен не найден после обновления")?;
+                        crate::app_log!(force: true, "[Qwen] Token refreshed successfully");
+                        (new_info.0, new_info.3)
+                    }
+                    Err(e) => {
+                        crate::app_log!(force: true, "[Qwen] Token refresh failed: {}", e);
+                        return Err(AiClientError::invalid_profile("Qwen CLI: Токен истек и не удалось обновить. Требуется повторная авторизация"));
+                    }
+                }
+            } else {
+                return Err(AiClientError::invalid_profile(
+                    "Qwen CLI: Токен истек. Требуется повторная авторизация",
+                ));
+            }
+        } else {
+            (access_token, resource_url)
+        };
+
+        let base = if let Some(ru) = resource_url
+            .as_deref()
+            .filter(|s| !s.is_empty() && !s.contains("dashscope") && !s.contains("aliyun"))
+        {
+            format!("https://{}/v1", ru)
+        } else {
+            "https://portal.qwen.ai/v1".to_string()
+        };
+        (access_token, format!("{}/chat/completions", base))
+    } else {
+        let api_key = resolve_profile_api_key(&profile)?;
+        (api_key, chat_completions_url(&profile))
+    };
+
+    // `None` = provider default; the key is then omitted from the request body.
+    let api_max_tokens = if matches!(profile.provider, LLMProvider::QwenCli) {
+        // Qwen OAuth always needs an explicit limit
+        Some(qwen_max_tokens(
+            profile.max_tokens.unwrap_or(8_192),
+            has_tool_heavy_context,
+        ))
+    } else if matches!(profile.provider, LLMProvider::LMStudio) {
+        // Qwen3 thinking models need large token budget to finish thinking before producing content
+        profile.max_tokens.map(|max| max.max(8192))
+    } else if matches!(profile.provider, LLMProvider::MiniMax) {
         // Official docs (platform.minimax.io): context window 204,800; coding tools integration
         // recommends max_tokens=64,000. Cap at 64k, but respect lower user-set values.
-        profile.max_tokens.min(64_000).max(4_096)
-    } else if profile.max_tokens > 16384 {
-        4096
+        profile.max_tokens.map(|max| max.min(64_000).max(4_096))
     } else {
-        profile.max_tokens
+        profile
+            .max_tokens
+            .map(|max| if max > 16384 { 4096 } else { max })
     };
 
     let thinking_enabled = matches!(profile.provider, LLMProvider::QwenCli)
         && profile.enable_thinking.unwrap_or(false);
 
     let effective_temperature = if thinking_enabled {
-        1.0
+        Some(1.0)
     } else {
         profile.temperature
     };
@@ -424,9 +1636,9 @@ pub async fn stream_chat_completion(
     let dynamic_thinking_budget: Option<u32> = if thinking_enabled {
         let total_chars: usize = api_messages
             .iter()
-            .map(|m| m.content.as_deref().map(|c| c.len()).unwrap_or(0))
+            .map(|m| m.text().map(|c| c.len()).unwrap_or(0))
             .sum();
-        let estimated_tokens = (total_chars / 4) as u32;
+        let estimated_tokens = u32::try_from(total_chars / 4).unwrap_or(u32::MAX);
         let budget = qwen_thinking_budget(estimated_tokens, has_tool_heavy_context);
         crate::app_log!(
             "[AI] Thinking budget: {}t (input ~{}t)",
@@ -440,12 +1652,50 @@ pub async fn stream_chat_completion(
 
     let use_stream = !profile.disable_streaming.unwrap_or(false);
 
+    let stop = profile.stop.clone();
+    let seed = profile
+        .seed
+        .filter(|_| !provider_rejects_seed(&profile.provider));
+    let logit_bias = Some(profile.logit_bias.clone())
+        .filter(|bias| !bias.is_empty() && !provider_rejects_logit_bias(&profile.provider));
+    // Local servers have no accounts to attribute usage to
+    let user = Some(crate::settings::effective_user_id(
+        &crate::settings::load_settings(),
+    ))
+    .filter(|_| {
+        !matches!(
+            profile.provider,
+            LLMProvider::Ollama | LLMProvider::LMStudio
+        )
+    });
+
+    let system =
+        apply_system_prompt_placement(&mut api_messages, resolve_system_prompt_placement(&profile));
+
     let mut request_body = ChatRequest {
         model: profile.model.clone(),
         messages: api_messages,
         stream: use_stream,
         temperature: effective_temperature,
         max_tokens: api_max_tokens,
+        max_completion_tokens: None,
+        top_p: profile.top_p,
+        frequency_penalty: profile.frequency_penalty,
+        presence_penalty: profile.presence_penalty,
+        stop: if stop.is_empty() { None } else { Some(stop) },
+        seed,
+        logit_bias,
+        n: None,
+        user,
+        system,
+        stream_options: if use_stream && provider_supports_stream_usage(&profile.provider) {
+            Some(StreamOptions {
+                include_usage: true,
+            })
+        } else {
+            None
+        },
+        response_format: None,
         tools: tools_opt,
         enable_thinking: if thinking_enabled {
             Some(true)
@@ -455,24 +1705,56 @@ pub async fn stream_chat_completion(
             None
         },
         thinking_budget_tokens: dynamic_thinking_budget,
+        reasoning_effort: normalize_codex_reasoning_effort(profile.reasoning_effort.as_deref())
+            .filter(|effort| effort != "none"),
+        thinking: None,
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let capabilities = super::capabilities::get_model_capabilities(&profile);
+    super::capabilities::apply_model_rules(&mut request_body, &capabilities);
 
-    if !api_key.is_empty() {
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-        );
-    }
+    let n = overrides.n.unwrap_or(1).clamp(1, MAX_ALTERNATIVES);
+    // With tools offered the turn may end in tool calls, and native `n` would pay for
+    // alternatives of every tool-loop step; they are fetched after the final answer instead
+    let native_n = provider_supports_n(&profile.provider) && request_body.tools.is_none();
+    let sequential_alternatives = if n > 1 && !native_n { n - 1 } else { 0 };
+    request_body.n = Some(n).filter(|_| n > 1 && sequential_alternatives == 0);
 
-    if matches!(profile.provider, LLMProvider::OpenRouter) {
-        headers.insert(
-            "HTTP-Referer",
-            HeaderValue::from_static("https://mini-ai-1c.local"),
-        );
-        headers.insert("X-Title", HeaderValue::from_static("Mini AI 1C Agent"));
+    let request_id = request_id.to_string();
+    tracing::info!(request_id = %request_id, model = %request_body.model, "chat request started");
+    last_request_ids()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(profile.id.clone(), request_id.clone());
+
+    // Report the effective parameters (profile + per-request overrides + model rules)
+    let _ = app_handle.emit(
+        "chat-start",
+        serde_json::json!({
+            "request_id": request_id,
+            "profile_id": profile.id,
+            "profile_name": profile.name,
+            "model": request_body.model,
+            "temperature": request_body.temperature,
+            "max_tokens": request_body.max_tokens.or(request_body.max_completion_tokens),
+            "top_p": request_body.top_p,
+            "stop": request_body.stop,
+            "seed": request_body.seed,
+            "n": n,
+            "reasoning_effort": request_body.reasoning_effort,
+            "thinking_budget_tokens": request_body.thinking.as_ref().map(|t| t.budget_tokens),
+            "system_prompt_source": system_prompt_source,
+            "system_prompt_suffix": overrides.system_prompt_suffix.is_some(),
+            "preset": overrides.preset,
+        }),
+    );
+
+    let mut headers = openai_compatible_headers(&profile.provider, &api_key)?;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        headers.insert("X-Request-ID", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&crate::http_client::effective_user_agent(&profile)) {
+        headers.insert(USER_AGENT, value);
     }
 
     if matches!(profile.provider, LLMProvider::QwenCli) {
@@ -513,7 +1795,7 @@ pub async fn stream_chat_completion(
             std::collections::HashMap::new();
         for m in &request_body.messages {
             let role = m.role.as_str();
-            let chars = m.content.as_deref().map(|c| c.len()).unwrap_or(0);
+            let chars = m.text().map(|c| c.len()).unwrap_or(0);
             // tool_calls also count
             let tc_chars = m
                 .tool_calls
@@ -557,12 +1839,25 @@ pub async fn stream_chat_completion(
     }
     crate::app_log!(
         "[AI] Sending request to {} (Model: {})",
-        url,
+        super::redact::redact_url(&url),
         request_body.model
     );
+    let request_bytes = serde_json::to_vec(&request_body).map_or(0, |body| body.len());
+    tracing::Span::current()
+        .record("model", request_body.model.as_str())
+        .record("request_bytes", request_bytes);
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        tracing::debug!(
+            request_id = %request_id,
+            url = %super::redact::redact_url(&url),
+            headers = %super::redact::redact_headers(&headers),
+            body = %super::redact::redact_body(&request_body, &[api_key.as_str()]),
+            "chat request"
+        );
+    }
     if matches!(profile.provider, LLMProvider::QwenCli) && has_tool_heavy_context {
         crate::app_log!(
-            "[Qwen] Tool-heavy context detected, clamped request budget: max_tokens={} thinking_budget={:?}",
+            "[Qwen] Tool-heavy context detected, clamped request budget: max_tokens={:?} thinking_budget={:?}",
             request_body.max_tokens,
             request_body.thinking_budget_tokens
         );
@@ -571,25 +1866,24 @@ pub async fn stream_chat_completion(
     // Для стриминга НЕ ставим request-wide timeout: иначе он рубит долгие thinking-стримы
     // (MiniMax M2/Qwen3 могут генерировать 3-5 минут). Зависший коннект ловим через
     // tokio::time::timeout(stream.next()) ниже (per-chunk, по провайдеру).
-    // Ограничиваем только начальный connect и read-idle, чтобы выявлять мёртвые соединения.
-    let is_local = matches!(
-        profile.provider,
-        LLMProvider::Ollama | LLMProvider::LMStudio
-    );
-    let mut client_builder = crate::http_client::http_client_builder()?;
-    if !is_local {
-        client_builder = client_builder
-            .connect_timeout(std::time::Duration::from_secs(30))
-            .read_timeout(std::time::Duration::from_secs(180));
-    }
-    let client = client_builder
-        .build()
-        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let client = crate::http_client::cached_profile_client(
+        &profile,
+        crate::http_client::ClientPurpose::Stream,
+    )
+    .map_err(AiClientError::invalid_profile)?;
 
+    let estimated_tokens = u64::from(estimate_messages_tokens(&request_body.messages))
+        + u64::from(
+            request_body
+                .max_tokens
+                .or(request_body.max_completion_tokens)
+                .unwrap_or(0),
+        );
     let mut attempt = 0;
-    let max_retries = 3;
+    let max_retries = super::retry::max_retries();
     let response = loop {
         attempt += 1;
+        super::rate_limit::wait_for_capacity(&app_handle, &profile.id, estimated_tokens).await;
         if matches!(profile.provider, LLMProvider::QwenCli) {
             wait_for_qwen_request_slot(&profile.id, &app_handle).await;
             let status = if attempt == 1 {
@@ -605,6 +1899,15 @@ pub async fn stream_chat_completion(
             .json(&request_body)
             .send()
             .await;
+        if let Ok(r) = &res {
+            super::rate_limit::record(&profile.id, r.headers());
+            tracing::debug!(
+                request_id = %request_id,
+                attempt,
+                status = r.status().as_u16(),
+                "chat response"
+            );
+        }
 
         match res {
             Ok(r) if r.status().is_success() => {
@@ -616,16 +1919,24 @@ pub async fn stream_chat_completion(
                 }
                 break r;
             }
-            Ok(r) if r.status().as_u16() == 500 && attempt < max_retries => {
-                crate::app_log!(
-                    "[AI][RETRY] Attempt {} failed with 500. Retrying in 2s...",
-                    attempt
+            // Qwen 429s go through the quota handling below
+            Ok(r)
+                if super::retry::is_retryable_status(r.status().as_u16())
+                    && attempt <= max_retries
+                    && !(matches!(profile.provider, LLMProvider::QwenCli)
+                        && r.status().as_u16() == 429) =>
+            {
+                let delay = super::retry::retry_delay(
+                    attempt,
+                    super::retry::parse_retry_after(r.headers()),
                 );
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                let reason = format!("HTTP {}", r.status().as_u16());
+                super::retry::wait_before_retry(&app_handle, attempt, delay, &reason).await;
                 continue;
             }
             Ok(r) => {
                 let status = r.status();
+                *retryable = status.as_u16() == 429 || status.is_server_error();
                 let response_headers = r.headers().clone();
                 let error_body = r.text().await.unwrap_or_default();
                 crate::app_log!(
@@ -634,6 +1945,11 @@ pub async fn stream_chat_completion(
                     status,
                     error_body
                 );
+                tracing::error!(
+                    status = status.as_u16(),
+                    attempt,
+                    "chat completion API error"
+                );
                 if matches!(profile.provider, LLMProvider::QwenCli) && status.as_u16() == 429 {
                     let ctx = parse_qwen_rate_limit_context(&response_headers, &error_body);
                     if attempt < QWEN_MAX_429_ATTEMPTS {
@@ -645,7 +1961,7 @@ pub async fn stream_chat_completion(
                                 ctx.is_quota_exceeded,
                             );
                             crate::app_log!(
-                                "[Qwen][RETRY] 429 on attempt {}. cooldown={}s quota={} hard_quota={} burst={} request_changed={} max_tokens={} thinking_budget={:?}",
+                                "[Qwen][RETRY] 429 on attempt {}. cooldown={}s quota={} hard_quota={} burst={} request_changed={} max_tokens={:?} thinking_budget={:?}",
                                 attempt,
                                 retry_delay.as_secs(),
                                 ctx.is_quota_exceeded,
@@ -682,18 +1998,12 @@ pub async fn stream_chat_completion(
                         );
                     }
 
-                    return Err(build_qwen_rate_limit_message(&ctx));
-                }
-                if status.as_u16() == 429 && attempt < max_retries {
-                    let retry_after = extract_retry_after_secs(&response_headers).unwrap_or(10);
-                    crate::app_log!(
-                        "[AI][RETRY] 429 rate-limit (attempt {}, provider {:?}), waiting {}s...",
-                        attempt,
-                        profile.provider,
-                        retry_after
-                    );
-                    tokio::time::sleep(std::time::Duration::from_secs(retry_after)).await;
-                    continue;
+                    return Err(AiClientError::http(
+                        429,
+                        &error_body,
+                        &profile.provider,
+                        build_qwen_rate_limit_message(&ctx),
+                    ));
                 }
                 // OpenRouter 400 "Developer instruction is not enabled" — model doesn't support system role
                 if matches!(profile.provider, LLMProvider::OpenRouter)
@@ -703,30 +2013,21 @@ pub async fn stream_chat_completion(
                     let has_system = request_body.messages.iter().any(|m| m.role == "system");
                     if has_system {
                         crate::app_log!("[AI][RETRY] OpenRouter: модель '{}' не поддерживает system-сообщение. Конвертирую в user.", profile.model);
-                        // Merge system content into the first user message
-                        let system_content: String = request_body
-                            .messages
-                            .iter()
-                            .filter(|m| m.role == "system")
-                            .filter_map(|m| m.content.as_deref())
-                            .collect::<Vec<_>>()
-                            .join("\n\n");
-                        request_body.messages.retain(|m| m.role != "system");
-                        if let Some(first_user) =
-                            request_body.messages.iter_mut().find(|m| m.role == "user")
-                        {
-                            let original = first_user.content.clone().unwrap_or_default();
-                            first_user.content =
-                                Some(format!("{}\n\n---\n\n{}", system_content, original));
-                        }
+                        apply_system_prompt_placement(
+                            &mut request_body.messages,
+                            SystemPromptPlacement::PrependToUser,
+                        );
                         let _ = app_handle.emit("chat-chunk", "\n\n⚠️ Модель не поддерживает системный промпт — встраиваю инструкции в запрос.\n\n");
                         attempt = 0;
                         continue;
                     }
-                    return Err(
+                    return Err(AiClientError::http(
+                        status.as_u16(),
+                        &error_body,
+                        &profile.provider,
                         "Модель не поддерживает системные инструкции (Developer instruction is not enabled). Попробуйте другую модель."
                             .to_string(),
-                    );
+                    ));
                 }
                 // OpenRouter 429 exhausted all retries — extract human-readable message
                 if matches!(profile.provider, LLMProvider::OpenRouter) && status.as_u16() == 429 {
@@ -752,16 +2053,12 @@ pub async fn stream_chat_completion(
                                 "Превышен лимит запросов. Попробуйте позже.".to_string()
                             })
                     };
-                    return Err(hint);
-                }
-                if matches!(profile.provider, LLMProvider::OpenRouter)
-                    && status.as_u16() == 401
-                    && error_body.contains("No cookie auth credentials found")
-                {
-                    return Err(
-                        "OpenRouter не получил API key. Проверьте поле API Key в профиле и сохраните его заново."
-                            .to_string(),
-                    );
+                    return Err(AiClientError::http(
+                        status.as_u16(),
+                        &error_body,
+                        &profile.provider,
+                        hint,
+                    ));
                 }
                 if matches!(profile.provider, LLMProvider::OpenRouter)
                     && status.as_u16() == 404
@@ -774,38 +2071,65 @@ pub async fn stream_chat_completion(
                         attempt = 0;
                         continue;
                     }
-                    return Err(
+                    return Err(AiClientError::http(
+                        status.as_u16(),
+                        &error_body,
+                        &profile.provider,
                         "Модель не поддерживает инструменты (tool use) на OpenRouter. Попробуйте другую модель."
                             .to_string(),
-                    );
+                    ));
                 }
                 if matches!(profile.provider, LLMProvider::OllamaCloud)
                     && status.as_u16() == 403
                     && error_body.contains("requires a subscription")
                 {
-                    return Err(format!(
+                    let message = format!(
                         "Модель '{}' требует платной подписки Ollama Cloud. \
                         Оформите подписку на https://ollama.com/upgrade или выберите другую модель в профиле. \
                         Бесплатно доступны: gpt-oss:20b/120b, qwen3-coder:480b, qwen3-next:80b, kimi-k2-thinking, glm-4.6, minimax-m2 и другие.",
                         profile.model
+                    );
+                    return Err(AiClientError::http(
+                        status.as_u16(),
+                        &error_body,
+                        &profile.provider,
+                        message,
+                    ));
+                }
+                if super::error::is_auth_status(status.as_u16()) {
+                    return Err(AiClientError::auth_failed(
+                        status.as_u16(),
+                        &error_body,
+                        &profile,
+                        &api_key,
                     ));
                 }
-                return Err(format!("API error {}: {}", status, error_body));
+                let message =
+                    super::error::describe_http_error(status.as_u16(), &error_body, &profile.model);
+                return Err(AiClientError::http(
+                    status.as_u16(),
+                    &error_body,
+                    &profile.provider,
+                    message,
+                ));
             }
-            Err(e) if attempt < max_retries => {
-                crate::app_log!(
-                    "[AI][RETRY] Request failed (Attempt {}): {}. Retrying in 2s...",
-                    attempt,
-                    e
-                );
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            Err(e) if e.is_connect() && attempt <= max_retries => {
+                let delay = super::retry::retry_delay(attempt, None);
+                let reason = crate::http_client::describe_request_error(&e);
+                super::retry::wait_before_retry(&app_handle, attempt, delay, &reason).await;
                 continue;
             }
-            Err(e) => return Err(format!("Request failed after {} attempts: {}", attempt, e)),
+            Err(e) => {
+                *retryable = true;
+                return Err(AiClientError::request_failed(attempt, &e));
+            }
         }
     };
+    *retryable = false;
 
     crate::app_log!("[AI] Response received. Status: {}", response.status());
+    let mut meta = parse_completion_meta_headers(response.headers());
+    meta.request_id = Some(request_id.clone());
 
     if matches!(profile.provider, LLMProvider::QwenCli) {
         let hdrs = response.headers();
@@ -836,46 +2160,33 @@ pub async fn stream_chat_completion(
         }
     }
 
+    let max_response_bytes = profile
+        .max_response_bytes
+        .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+
     // === Non-streaming path (disable_streaming = true) ===
     if !use_stream {
         crate::app_log!("[AI] Non-streaming mode: waiting for full response...");
         let body = response
             .text()
             .await
-            .map_err(|e| format!("Failed to read response: {}", e))?;
-        let resp: NonStreamResponse = serde_json::from_str(&body).map_err(|e| {
-            format!(
-                "Failed to parse non-stream response: {} body={}",
-                e,
-                &body[..body.len().min(200)]
-            )
-        })?;
-        let choice = resp
-            .choices
-            .into_iter()
-            .next()
-            .ok_or("Empty response from API")?;
-        let content = choice.message.content.unwrap_or_default();
-        let raw_tool_calls = choice.message.tool_calls.unwrap_or_default();
-        // Convert NonStreamToolCall → ToolCall, normalising arguments to valid JSON string.
-        let tool_calls: Vec<ToolCall> = raw_tool_calls
-            .into_iter()
-            .map(|tc| ToolCall {
-                id: tc.id,
-                r#type: tc.r#type,
-                function: ToolCallFunction {
-                    name: tc.function.name,
-                    arguments: tc
-                        .function
-                        .arguments
-                        .filter(|s| !s.is_empty())
-                        .unwrap_or_else(|| "{}".to_string()),
-                },
-            })
-            .collect();
+            .map_err(|e| AiClientError::stream(format!("Failed to read response: {}", e)))?;
+        let (message, mut alternatives) = parse_non_stream_response(&body, &mut meta)?;
+        enforce_alternatives_limit(
+            &mut alternatives,
+            max_response_bytes.saturating_sub(message.text().unwrap_or_default().len()),
+        );
+        for (idx, text) in alternatives.iter().enumerate() {
+            let _ = app_handle.emit(
+                "chat-alternative-chunk",
+                serde_json::json!({ "index": idx + 1, "chunk": text }),
+            );
+        }
+        let content = message.text().unwrap_or_default();
+        let tool_calls = message.tool_calls.as_deref().unwrap_or_default();
         if !content.is_empty() {
             let _ = app_handle.emit("chat-status", "Выполнение...");
-            let _ = app_handle.emit("chat-chunk", content.clone());
+            let _ = app_handle.emit("chat-chunk", content.to_string());
         }
         for (idx, tc) in tool_calls.iter().enumerate() {
             let _ = app_handle.emit(
@@ -890,37 +2201,87 @@ pub async fn stream_chat_completion(
             content.len(),
             tool_calls.len()
         );
-        return Ok(ApiMessage {
-            role: "assistant".to_string(),
-            content: if content.is_empty() {
-                None
-            } else {
-                Some(content)
-            },
-            tool_calls: if tool_calls.is_empty() {
-                None
-            } else {
-                Some(tool_calls)
-            },
-            tool_call_id: None,
-            name: None,
-        });
+        alternatives.extend(
+            fetch_sequential_alternatives(
+                &client,
+                &url,
+                &headers,
+                &request_body,
+                sequential_alternatives,
+                &message,
+                max_response_bytes,
+                &app_handle,
+            )
+            .await,
+        );
+        meta.estimate_missing_usage(&request_body.messages, &message);
+        return Ok(finish_completion(
+            &app_handle,
+            &profile.model,
+            message,
+            meta,
+            alternatives,
+            pipeline.as_deref(),
+        ));
     }
 
     let mut stream = response.bytes_stream();
+    let mut smoother = AnswerSmoother::for_mode(profile.smoothing_mode);
     let mut byte_buffer = Vec::new();
     let mut full_content = String::new();
     let mut content_search_temp = String::new();
     let mut accumulated_tool_calls: Vec<ToolCall> = Vec::new();
     let mut announced_tool_calls = std::collections::HashSet::new();
+    // Choices 1.. of an `n > 1` request; choice 0 goes through the parsing below
+    let mut alternatives: Vec<String> = Vec::new();
     let mut is_thinking = false;
     let mut is_qwen_fn = false;
     let mut qwen_fn_buf = String::new();
     let mut has_switched_to_executing = false;
     let mut first_token_received = false;
     let start_gen_time = std::time::Instant::now();
+    let mut reconnect = SseReconnect::new(&profile);
+    // Set once the provider reports finish_reason (e.g. "stop" after a stop sequence).
+    let mut finish_reason: Option<String> = None;
+    let mut response_truncated = false;
+    // Reasoning text is streamed to the UI, not kept, but counts against the limit too
+    let mut thinking_bytes = 0usize;
 
     loop {
+        // A single network chunk adds little, so checking once per chunk keeps memory bounded.
+        // Alternatives count too: the answer keeps its bytes, they get what is left.
+        // Everything else held for this response (an unterminated SSE event, text waiting
+        // for a tag, tool call arguments) and the reasoning text share the same limit.
+        let answer_truncated = enforce_response_limit(&mut full_content, max_response_bytes);
+        let alternatives_truncated =
+            enforce_alternatives_limit(&mut alternatives, max_response_bytes - full_content.len());
+        let held_bytes = byte_buffer.len()
+            + content_search_temp.len()
+            + qwen_fn_buf.len()
+            + thinking_bytes
+            + accumulated_tool_calls
+                .iter()
+                .map(|tc| tc.function.arguments.len())
+                .sum::<usize>();
+        let response_bytes =
+            full_content.len() + alternatives.iter().map(String::len).sum::<usize>() + held_bytes;
+        if answer_truncated || alternatives_truncated || response_bytes > max_response_bytes {
+            crate::app_log!(
+                force: true,
+                "[AI] Response exceeded {} bytes, dropping the rest of the stream",
+                max_response_bytes
+            );
+            let _ = app_handle.emit(
+                "chat-truncated",
+                serde_json::json!({
+                    "bytes_accumulated": response_bytes,
+                    "limit": max_response_bytes,
+                }),
+            );
+            response_truncated = true;
+            break;
+        }
+
         let is_local = matches!(
             profile.provider,
             LLMProvider::Ollama | LLMProvider::LMStudio
@@ -943,11 +2304,20 @@ pub async fn stream_chat_completion(
         )
         .await
         {
+            Err(_) if finish_reason.is_some() => {
+                // Generation already finished; some providers keep the connection open
+                // without sending [DONE]. Treat it as a clean end of stream.
+                crate::app_log!(
+                    "[AI] Stream idle after finish_reason={:?}, closing",
+                    finish_reason
+                );
+                break;
+            }
             Err(_) => {
-                return Err(format!(
-                    "Stream timeout: no data from API for {}s",
-                    chunk_timeout
-                ))
+                return Err(AiClientError::Timeout {
+                    message: format!("Stream timeout: no data from API for {}s", chunk_timeout),
+                    secs: Some(chunk_timeout as u64),
+                })
             }
             Ok(None) => break,
             Ok(Some(r)) => r,
@@ -956,33 +2326,57 @@ pub async fn stream_chat_completion(
             first_token_received = true;
             let ttft = start_gen_time.elapsed().as_millis();
             crate::app_log!("[AI][TIMER] TTFT (Time to First Token): {} ms", ttft);
+            meta.time_to_first_token_ms = Some(ttft as u64);
         }
-        let chunk = chunk_result.map_err(|e| {
-            // Log full error chain for diagnostics (decode errors often hide in source())
-            use std::error::Error as _;
-            let mut details = format!("{}", e);
-            let mut src: Option<&(dyn std::error::Error + 'static)> = e.source();
-            while let Some(s) = src {
-                details.push_str(" → ");
-                details.push_str(&s.to_string());
-                src = s.source();
-            }
-            crate::app_log!(force: true, "[AI][STREAM-ERR] provider={:?} model={} details={}", profile.provider, profile.model, details);
-
-            // For Ollama Cloud, server-side glitches (chunked transfer reset, decode errors)
-            // happen on some models (e.g. glm-4.7). Surface a friendlier message.
-            if matches!(profile.provider, LLMProvider::OllamaCloud) {
-                format!(
-                    "Облако Ollama прервало поток для модели '{}' (server-side decode error). \
-                    Это временный сбой на стороне ollama.com — попробуйте повторить запрос или \
-                    выберите другую модель (qwen3-coder:480b, gpt-oss:120b, kimi-k2-thinking). \
-                    Подробности: {}",
-                    profile.model, details
-                )
-            } else {
-                format!("Stream error: {}", details)
+        let chunk = match chunk_result {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let details = describe_stream_error(&e);
+                crate::app_log!(force: true, "[AI][STREAM-ERR] provider={:?} model={} details={}", profile.provider, profile.model, details);
+
+                // Resume from the last received event instead of losing the whole generation.
+                let resumed = reconnect
+                    .resume(|| {
+                        client
+                            .post(&url)
+                            .headers(headers.clone())
+                            .json(&request_body)
+                    })
+                    .await;
+                if let Some(r) = resumed {
+                    stream = r.bytes_stream();
+                    // The server replays the interrupted event in full.
+                    byte_buffer.clear();
+                    let _ = app_handle.emit(
+                        "chat-reconnected",
+                        serde_json::json!({
+                            "attempt": reconnect.attempts,
+                            "last_event_id": reconnect.last_event_id,
+                        }),
+                    );
+                    continue;
+                }
+                if let Some(error) = reconnect.exhausted_error(&details, &full_content) {
+                    return Err(error);
+                }
+
+                // For Ollama Cloud, server-side glitches (chunked transfer reset, decode errors)
+                // happen on some models (e.g. glm-4.7). Surface a friendlier message.
+                return Err(AiClientError::stream(
+                    if matches!(profile.provider, LLMProvider::OllamaCloud) {
+                        format!(
+                        "Облако Ollama прервало поток для модели '{}' (server-side decode error). \
+                        Это временный сбой на стороне ollama.com — попробуйте повторить запрос или \
+                        выберите другую модель (qwen3-coder:480b, gpt-oss:120b, kimi-k2-thinking). \
+                        Подробности: {}",
+                        profile.model, details
+                    )
+                    } else {
+                        format!("Stream error: {}", details)
+                    },
+                ));
             }
-        })?;
+        };
         byte_buffer.extend_from_slice(&chunk);
 
         while let Some(pos) = byte_buffer.windows(2).position(|w| w == b"\n\n") {
@@ -990,6 +2384,10 @@ pub async fn stream_chat_completion(
             let event_str = String::from_utf8_lossy(&event_bytes);
 
             for line in event_str.lines() {
+                if let Some(field) = parse_sse_control_field(line) {
+                    reconnect.observe(field);
+                    continue;
+                }
                 if let Some(data) = line
                     .strip_prefix("data: ")
                     .or_else(|| line.strip_prefix("data:"))
@@ -1001,15 +2399,20 @@ pub async fn stream_chat_completion(
                                     .emit("chat-thinking-chunk", content_search_temp.clone());
                             } else if !is_qwen_fn {
                                 full_content.push_str(&content_search_temp);
-                                let _ = app_handle.emit("chat-chunk", content_search_temp.clone());
+                                emit_answer_chunk(
+                                    &app_handle,
+                                    &mut smoother,
+                                    content_search_temp.clone(),
+                                );
                             }
                             content_search_temp.clear();
                         }
                         if is_qwen_fn && !qwen_fn_buf.is_empty() {
                             full_content.push_str(&qwen_fn_buf);
-                            let _ = app_handle.emit("chat-chunk", qwen_fn_buf.clone());
+                            emit_answer_chunk(&app_handle, &mut smoother, qwen_fn_buf.clone());
                             qwen_fn_buf.clear();
                         }
+                        flush_answer_chunks(&app_handle, &mut smoother);
 
                         if matches!(profile.provider, LLMProvider::QwenCli) {
                             crate::llm::cli_providers::qwen::QwenCliProvider::increment_request_count(&profile.id);
@@ -1066,12 +2469,12 @@ pub async fn stream_chat_completion(
                                 tc.function.arguments = "{}".to_string();
                             }
                         }
-                        return Ok(ApiMessage {
+                        let message = ApiMessage {
                             role: "assistant".to_string(),
                             content: if full_content.is_empty() {
                                 None
                             } else {
-                                Some(full_content)
+                                Some(full_content.into())
                             },
                             tool_calls: if accumulated_tool_calls.is_empty() {
                                 None
@@ -1080,14 +2483,54 @@ pub async fn stream_chat_completion(
                             },
                             tool_call_id: None,
                             name: None,
-                        });
+                        };
+                        alternatives.extend(
+                            fetch_sequential_alternatives(
+                                &client,
+                                &url,
+                                &headers,
+                                &request_body,
+                                sequential_alternatives,
+                                &message,
+                                max_response_bytes,
+                                &app_handle,
+                            )
+                            .await,
+                        );
+                        meta.estimate_missing_usage(&request_body.messages, &message);
+                        return Ok(finish_completion(
+                            &app_handle,
+                            &profile.model,
+                            message,
+                            meta,
+                            alternatives,
+                            pipeline.as_deref(),
+                        ));
                     }
 
                     if let Ok(chunk) = serde_json::from_str::<StreamChunk>(data) {
-                        if let Some(choice) = chunk.choices.first() {
+                        tracing::trace!(bytes = data.len(), "SSE chunk");
+                        if let Some(usage) = &chunk.usage {
+                            meta.apply_usage(usage);
+                        }
+                        if chunk.system_fingerprint.is_some() {
+                            meta.system_fingerprint = chunk.system_fingerprint.clone();
+                        }
+                        for (index, delta) in collect_alternative_deltas(&chunk, &mut alternatives)
+                        {
+                            let _ = app_handle.emit(
+                                "chat-alternative-chunk",
+                                serde_json::json!({ "index": index, "chunk": delta }),
+                            );
+                        }
+                        if let Some(choice) = chunk.choices.iter().find(|c| c.index == 0) {
+                            if let Some(reason) = &choice.finish_reason {
+                                finish_reason = Some(reason.clone());
+                            }
                             // Handle Qwen3 native reasoning_content field (enable_thinking=true)
                             if let Some(reasoning) = &choice.delta.reasoning_content {
                                 if !reasoning.is_empty() {
+                                    thinking_bytes += reasoning.len();
                                     if !is_thinking {
                                         is_thinking = true;
                                         let _ = app_handle.emit("chat-status", "Размышляю...");
@@ -1118,6 +2561,9 @@ pub async fn stream_chat_completion(
                                     has_switched_to_executing = true;
                                 }
 
+                                if is_thinking {
+                                    thinking_bytes += content.len();
+                                }
                                 content_search_temp.push_str(content);
 
                                 loop {
@@ -1134,7 +2580,7 @@ pub async fn stream_chat_completion(
                                                 let text =
                                                     content_search_temp[..tc_start].to_string();
                                                 full_content.push_str(&text);
-                                                let _ = app_handle.emit("chat-chunk", text);
+                                                emit_answer_chunk(&app_handle, &mut smoother, text);
                                             }
                                             is_qwen_fn = true;
                                             // buffer includes the opening tag so we can detect </tool_call>
@@ -1151,7 +2597,7 @@ pub async fn stream_chat_completion(
                                                 let text =
                                                     content_search_temp[..fn_start].to_string();
                                                 full_content.push_str(&text);
-                                                let _ = app_handle.emit("chat-chunk", text);
+                                                emit_answer_chunk(&app_handle, &mut smoother, text);
                                             }
                                             is_qwen_fn = true;
                                             qwen_fn_buf =
@@ -1167,7 +2613,7 @@ pub async fn stream_chat_completion(
                                                 let text =
                                                     content_search_temp[..start_pos].to_string();
                                                 full_content.push_str(&text);
-                                                let _ = app_handle.emit("chat-chunk", text);
+                                                emit_answer_chunk(&app_handle, &mut smoother, text);
                                             }
                                             is_thinking = true;
                                             let _ = app_handle
@@ -1191,7 +2637,11 @@ pub async fn stream_chat_completion(
                                                         .replace("<tool_call>", "");
                                                     if !text.is_empty() {
                                                         full_content.push_str(&text);
-                                                        let _ = app_handle.emit("chat-chunk", text);
+                                                        emit_answer_chunk(
+                                                            &app_handle,
+                                                            &mut smoother,
+                                                            text,
+                                                        );
                                                     }
                                                     content_search_temp =
                                                         content_search_temp[last_lt..].to_string();
@@ -1203,7 +2653,11 @@ pub async fn stream_chat_completion(
                                                     .replace("<tool_call>", "");
                                                 if !text.is_empty() {
                                                     full_content.push_str(&text);
-                                                    let _ = app_handle.emit("chat-chunk", text);
+                                                    emit_answer_chunk(
+                                                        &app_handle,
+                                                        &mut smoother,
+                                                        text,
+                                                    );
                                                 }
                                                 content_search_temp.clear();
                                                 break;
@@ -1214,7 +2668,7 @@ pub async fn stream_chat_completion(
                                                 .replace("<tool_call>", "");
                                             if !text.is_empty() {
                                                 full_content.push_str(&text);
-                                                let _ = app_handle.emit("chat-chunk", text);
+                                                emit_answer_chunk(&app_handle, &mut smoother, text);
                                             }
                                             content_search_temp.clear();
                                             break;
@@ -1435,27 +2889,40 @@ pub async fn stream_chat_completion(
         }
     }
 
+    if response_truncated {
+        content_search_temp.clear();
+        qwen_fn_buf.clear();
+        // Arguments cut mid-stream are not valid calls
+        if !accumulated_tool_calls.is_empty() {
+            crate::app_log!(
+                "[AI] Dropping {} tool call(s) of the truncated response",
+                accumulated_tool_calls.len()
+            );
+            accumulated_tool_calls.clear();
+        }
+    }
     if !content_search_temp.is_empty() {
         if is_thinking {
             let _ = app_handle.emit("chat-thinking-chunk", content_search_temp.clone());
         } else if !is_qwen_fn {
             full_content.push_str(&content_search_temp);
-            let _ = app_handle.emit("chat-chunk", content_search_temp.clone());
+            emit_answer_chunk(&app_handle, &mut smoother, content_search_temp.clone());
         }
         content_search_temp.clear();
     }
     if is_qwen_fn && !qwen_fn_buf.is_empty() {
         full_content.push_str(&qwen_fn_buf);
-        let _ = app_handle.emit("chat-chunk", qwen_fn_buf.clone());
+        emit_answer_chunk(&app_handle, &mut smoother, qwen_fn_buf.clone());
         qwen_fn_buf.clear();
     }
+    flush_answer_chunks(&app_handle, &mut smoother);
 
-    Ok(ApiMessage {
+    let message = ApiMessage {
         role: "assistant".to_string(),
         content: if full_content.is_empty() {
             None
         } else {
-            Some(full_content)
+            Some(full_content.into())
         },
         tool_calls: if accumulated_tool_calls.is_empty() {
             None
@@ -1464,7 +2931,29 @@ pub async fn stream_chat_completion(
         },
         tool_call_id: None,
         name: None,
-    })
+    };
+    alternatives.extend(
+        fetch_sequential_alternatives(
+            &client,
+            &url,
+            &headers,
+            &request_body,
+            sequential_alternatives,
+            &message,
+            max_response_bytes,
+            &app_handle,
+        )
+        .await,
+    );
+    meta.estimate_missing_usage(&request_body.messages, &message);
+    Ok(finish_completion(
+        &app_handle,
+        &profile.model,
+        message,
+        meta,
+        alternatives,
+        pipeline.as_deref(),
+    ))
 }
 
 /// Helper to extract BSL code blocks from text
@@ -1498,35 +2987,27 @@ pub fn extract_bsl_code(text: &str) -> Vec<String> {
     blocks
 }
 
-/// Fetch models from provider
-pub async fn fetch_models(
+/// `GET {base}/models` with auth and provider headers; `after` selects the page following that cursor
+fn models_request(
     profile: &crate::llm_profiles::LLMProfile,
-) -> Result<Vec<String>, String> {
-    let api_key = resolve_profile_api_key(profile)?;
-    let raw_url = profile.get_base_url();
-    // Normalize Ollama URL: auto-add /v1 if missing
-    let base_url = {
-        let trimmed = raw_url.trim_end_matches('/');
-        if matches!(
-            profile.provider,
-            LLMProvider::Ollama | LLMProvider::OllamaCloud | LLMProvider::LMStudio
-        ) && !trimmed.ends_with("/v1")
-            && !trimmed.ends_with("/chat/completions")
-        {
-            format!("{}/v1", trimmed)
-        } else {
-            trimmed.to_string()
-        }
-    };
-    let url = if base_url.ends_with("/chat/completions") {
-        base_url.replace("/chat/completions", "/models")
-    } else {
-        format!("{}/models", base_url.trim_end_matches('/'))
-    };
+    api_key: &str,
+    after: Option<&str>,
+) -> Result<reqwest::RequestBuilder, AiClientError> {
+    let url = format!("{}/models", profile.get_base_url());
 
-    let client = crate::http_client::build_http_client()?;
+    let client = crate::http_client::cached_profile_client(
+        profile,
+        crate::http_client::ClientPurpose::Models,
+    )
+    .map_err(AiClientError::invalid_profile)?;
     let mut builder = client.get(&url);
-    builder = builder.header(CONTENT_TYPE, "application/json");
+    if let Some(after) = after {
+        builder = builder.query(&[("after", after)]);
+    }
+    builder = builder.header(CONTENT_TYPE, "application/json").header(
+        USER_AGENT,
+        crate::http_client::effective_user_agent(profile),
+    );
 
     if !api_key.is_empty() {
         builder = builder.header(AUTHORIZATION, format!("Bearer {}", api_key));
@@ -1538,13 +3019,10 @@ pub async fn fetch_models(
             .header("X-Title", "Mini AI 1C Agent");
     }
 
-    let response = builder.send().await.map_err(|e| e.to_string())?;
-
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch models: {}", response.status()));
-    }
+    Ok(builder)
+}
 
-    let data: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+fn parse_model_ids(data: &serde_json::Value) -> Vec<String> {
     let mut models = Vec::new();
     if let Some(list) = data.get("data").and_then(|d| d.as_array()) {
         for item in list {
@@ -1553,72 +3031,699 @@ pub async fn fetch_models(
             }
         }
     }
+    models
+}
 
-    models.sort();
-    Ok(models)
+/// Most pages of `/models` followed; guards against a cursor that never ends
+const MAX_MODEL_PAGES: usize = 10;
+
+/// Model of a `/models` response with the metadata providers add beyond `id`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ModelEntry {
+    pub id: String,
+    /// `context_window` (Groq), `context_length` (OpenRouter) or `max_context_length` (Mistral)
+    pub context_window: Option<u32>,
+    /// Top level or OpenRouter's `top_provider.max_completion_tokens`
+    pub max_completion_tokens: Option<u32>,
+    /// Unix seconds
+    pub created: Option<u64>,
 }
 
-/// Test connection
-pub async fn test_connection(profile: &crate::llm_profiles::LLMProfile) -> Result<String, String> {
-    match fetch_models(profile).await {
-        Ok(models) => Ok(format!("Success! Found {} models.", models.len())),
-        Err(e) => Err(format!("Connection failed: {}", e)),
+impl ModelEntry {
+    /// Reads what the entry has; fields of an unexpected type are skipped rather than
+    /// failing the whole list
+    fn from_value(value: &serde_json::Value) -> Self {
+        let number = |v: Option<&serde_json::Value>| v.and_then(serde_json::Value::as_u64);
+        let small = |v: Option<&serde_json::Value>| number(v).and_then(|n| u32::try_from(n).ok());
+        Self {
+            id: value
+                .get("id")
+                .and_then(|id| id.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            context_window: ["context_window", "context_length", "max_context_length"]
+                .iter()
+                .find_map(|key| small(value.get(*key))),
+            max_completion_tokens: small(value.get("max_completion_tokens")).or_else(|| {
+                small(
+                    value
+                        .get("top_provider")
+                        .and_then(|p| p.get("max_completion_tokens")),
+                )
+            }),
+            created: number(value.get("created")),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<'de> serde::Deserialize<'de> for ModelEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_json::Value::deserialize(deserializer).map(|value| Self::from_value(&value))
+    }
+}
 
-    #[test]
-    fn detects_quota_exceeded_from_qwen_body() {
-        let headers = HeaderMap::new();
-        let ctx = parse_qwen_rate_limit_context(
-            &headers,
-            r#"{"error":{"code":"insufficient_quota","message":"You exceeded your current quota."}}"#,
+/// One page of `/models`. Providers without pagination send only `data`.
+#[derive(Debug, serde::Deserialize)]
+struct PaginatedModelsResponse {
+    object: Option<String>,
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+    #[serde(default)]
+    has_more: bool,
+    next_cursor: Option<String>,
+}
+
+impl PaginatedModelsResponse {
+    /// Cursor of the following page: `next_cursor`, or the last id for OpenAI-style
+    /// lists that only set `has_more`
+    fn next_page_cursor(&self) -> Option<String> {
+        if self.object.as_deref() != Some("list") || !self.has_more {
+            return None;
+        }
+        self.next_cursor
+            .clone()
+            .or_else(|| self.data.last().map(|m| m.id.clone()))
+            .filter(|cursor| !cursor.is_empty())
+    }
+}
+
+/// Sends the `/models` request, retrying transient failures like the chat request
+async fn send_models_request(
+    profile: &crate::llm_profiles::LLMProfile,
+    api_key: &str,
+    after: Option<&str>,
+) -> Result<reqwest::Response, AiClientError> {
+    let max_retries = super::retry::max_retries();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let request = models_request(profile, api_key, after)?;
+        let (reason, delay) = match request.send().await {
+            Ok(r)
+                if super::retry::is_retryable_status(r.status().as_u16())
+                    && attempt <= max_retries =>
+            {
+                let delay = super::retry::retry_delay(
+                    attempt,
+                    super::retry::parse_retry_after(r.headers()),
+                );
+                (format!("HTTP {}", r.status().as_u16()), delay)
+            }
+            Err(e) if e.is_connect() && attempt <= max_retries => (
+                crate::http_client::describe_request_error(&e),
+                super::retry::retry_delay(attempt, None),
+            ),
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(e.into()),
+        };
+        crate::app_log!(
+            "[AI][RETRY] Fetch models: {} (attempt {}), retrying in {} ms",
+            reason,
+            attempt,
+            delay.as_millis()
         );
+        tokio::time::sleep(delay).await;
+    }
+}
 
-        assert!(ctx.is_quota_exceeded);
-        assert!(!ctx.is_hard_quota_exhausted);
-        assert!(!ctx.is_burst_limited);
+/// Fetch models from provider, following `has_more` pages up to `MAX_MODEL_PAGES`.
+/// A rejected key is `AuthFailed`, as in the chat.
+#[tracing::instrument(skip(profile), fields(provider = ?profile.provider, profile = %profile.id))]
+pub async fn fetch_models(
+    profile: &crate::llm_profiles::LLMProfile,
+) -> Result<Vec<ModelEntry>, AiClientError> {
+    let api_key = resolve_profile_api_key(profile)?;
+    let mut models = Vec::new();
+    let mut cursor: Option<String> = None;
+    for page_number in 1..=MAX_MODEL_PAGES {
+        let response = send_models_request(profile, &api_key, cursor.as_deref()).await?;
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            tracing::error!(status, "fetch models failed");
+            let body = response.text().await.unwrap_or_default();
+            if super::error::is_auth_status(status) {
+                return Err(AiClientError::auth_failed(status, &body, profile, &api_key));
+            }
+            let message = format!(
+                "Failed to fetch models: {}",
+                super::error::describe_http_error(status, &body, &profile.model)
+            );
+            return Err(AiClientError::http(
+                status,
+                &body,
+                &profile.provider,
+                message,
+            ));
+        }
+
+        let page: PaginatedModelsResponse = response.json().await?;
+        let next = page.next_page_cursor();
+        models.extend(page.data.into_iter().filter(|m| !m.id.is_empty()));
+        match next {
+            Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+            _ => break,
+        }
+        if page_number == MAX_MODEL_PAGES {
+            crate::app_log!(
+                "[AI] Fetch models: stopped after {} pages, list may be incomplete",
+                MAX_MODEL_PAGES
+            );
+        }
+    }
+
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models.dedup_by(|a, b| a.id == b.id);
+    Ok(models)
+}
+
+/// Step-by-step result of a connection test
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ConnectionDiagnostics {
+    pub dns_resolved: bool,
+    pub tcp_connected: bool,
+    pub tls_ok: bool,
+    pub auth_ok: bool,
+    pub models_fetched: u32,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub via_proxy: bool,
+}
+
+const CONNECTION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn emit_connection_probe(
+    app_handle: &tauri::AppHandle,
+    step: &str,
+    diagnostics: &ConnectionDiagnostics,
+) {
+    let _ = app_handle.emit(
+        "connection-probe",
+        serde_json::json!({ "step": step, "diagnostics": diagnostics }),
+    );
+}
+
+/// Test connection step by step: DNS → TCP → TLS/auth via `/models`.
+/// Every step runs even if a previous one failed (e.g. DNS may be blocked
+/// while the request still goes through a proxy), and partial results are
+/// emitted as `connection-probe` events.
+#[tracing::instrument(
+    skip(profile, app_handle),
+    fields(provider = ?profile.provider, profile = %profile.id)
+)]
+pub async fn test_connection_detailed(
+    profile: &crate::llm_profiles::LLMProfile,
+    app_handle: &tauri::AppHandle,
+) -> ConnectionDiagnostics {
+    let mut diag = ConnectionDiagnostics::default();
+
+    let base_url = profile.get_base_url();
+    let parsed = match url::Url::parse(&base_url) {
+        Ok(u) => u,
+        Err(e) => {
+            diag.error = Some(format!("Некорректный base URL '{}': {}", base_url, e));
+            emit_connection_probe(app_handle, "done", &diag);
+            return diag;
+        }
+    };
+    let is_https = parsed.scheme() == "https";
+    let host = parsed.host_str().unwrap_or_default().to_string();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    // 1. DNS
+    let addrs: Vec<std::net::SocketAddr> = match tokio::time::timeout(
+        CONNECTION_PROBE_TIMEOUT,
+        tokio::net::lookup_host((host.as_str(), port)),
+    )
+    .await
+    {
+        Ok(Ok(addrs)) => addrs.collect(),
+        Ok(Err(e)) => {
+            diag.error.get_or_insert(format!("DNS: {}", e));
+            Vec::new()
+        }
+        Err(_) => {
+            diag.error.get_or_insert("DNS: таймаут".to_string());
+            Vec::new()
+        }
+    };
+    diag.dns_resolved = !addrs.is_empty();
+    emit_connection_probe(app_handle, "dns", &diag);
+
+    // 2. TCP
+    if let Some(addr) = addrs.first() {
+        match tokio::time::timeout(
+            CONNECTION_PROBE_TIMEOUT,
+            tokio::net::TcpStream::connect(addr),
+        )
+        .await
+        {
+            Ok(Ok(_)) => diag.tcp_connected = true,
+            Ok(Err(e)) => {
+                diag.error.get_or_insert(format!("TCP {}: {}", addr, e));
+            }
+            Err(_) => {
+                diag.error.get_or_insert(format!("TCP {}: таймаут", addr));
+            }
+        }
+    }
+    emit_connection_probe(app_handle, "tcp", &diag);
+
+    // Behind the profile's proxy the host may be unreachable directly; only the /models
+    // request below, which goes through the proxy, decides the result.
+    diag.via_proxy = crate::http_client::profile_uses_explicit_proxy(profile);
+    if diag.via_proxy {
+        diag.error = None;
+    }
+
+    // 3. TLS + auth + models
+    let api_key = match resolve_profile_api_key(profile) {
+        Ok(key) => key,
+        Err(e) => {
+            diag.error.get_or_insert(e.to_string());
+            emit_connection_probe(app_handle, "done", &diag);
+            return diag;
+        }
+    };
+    let started = Instant::now();
+    let result = match models_request(profile, &api_key, None) {
+        Ok(request) => request
+            .send()
+            .await
+            .map_err(|e| crate::http_client::describe_request_error(&e)),
+        Err(e) => Err(e.to_string()),
+    };
+    diag.latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(response) => {
+            // Any HTTP response means the TLS handshake (if any) succeeded.
+            diag.tls_ok = true;
+            emit_connection_probe(app_handle, "tls", &diag);
+
+            let status = response.status();
+            diag.auth_ok = !super::error::is_auth_status(status.as_u16());
+            emit_connection_probe(app_handle, "auth", &diag);
+
+            if !diag.auth_ok {
+                let body = response.text().await.unwrap_or_default();
+                let error = AiClientError::auth_failed(status.as_u16(), &body, profile, &api_key);
+                error.emit_auth_error(app_handle);
+                diag.error.get_or_insert(error.to_string());
+            } else if status.is_success() {
+                match response.json::<serde_json::Value>().await {
+                    Ok(data) => {
+                        diag.models_fetched =
+                            u32::try_from(parse_model_ids(&data).len()).unwrap_or(u32::MAX)
+                    }
+                    Err(e) => {
+                        diag.error.get_or_insert(format!("Ответ /models: {}", e));
+                    }
+                }
+            } else {
+                diag.error.get_or_insert(format!("HTTP {}", status));
+            }
+        }
+        Err(e) => {
+            diag.tls_ok = !is_https;
+            diag.error.get_or_insert(e);
+        }
     }
+    emit_connection_probe(app_handle, "done", &diag);
+    diag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn detects_tool_heavy_context_from_recent_tool_messages() {
-        let messages = vec![
+    fn prompt_caching_marks_system_and_last_pinned_for_anthropic_only() {
+        let message = |role: &str, text: &str, pinned: bool| {
+            let mut content = MessageContent::from(text);
+            if pinned {
+                content.mark_cacheable();
+            }
             ApiMessage {
-                role: "user".to_string(),
-                content: Some("test".to_string()),
+                role: role.to_string(),
+                content: Some(content),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
-            },
-            ApiMessage {
-                role: "tool".to_string(),
-                content: Some("cached".to_string()),
-                tool_calls: None,
-                tool_call_id: Some("tc_1".to_string()),
-                name: Some("get_object_structure".to_string()),
-            },
+            }
+        };
+        let conversation = vec![
+            message("system", "base", false),
+            message("user", "api reference part 1", true),
+            message("user", "api reference part 2", true),
+            message("user", "question", false),
         ];
 
-        assert!(qwen_has_tool_heavy_context(&messages));
-    }
+        let mut profile = crate::llm_profiles::LLMProfile::default_profile();
+        profile.provider = LLMProvider::Anthropic;
+        profile.model = "claude-sonnet-4".to_string();
+        profile.prompt_caching = true;
+        let mut messages = conversation.clone();
+        apply_prompt_caching(&mut messages, &profile);
+        let cached: Vec<bool> = messages
+            .iter()
+            .map(|m| m.content.as_ref().unwrap().is_cacheable())
+            .collect();
+        assert_eq!(cached, vec![true, false, true, false]);
 
-    #[test]
-    fn clamps_qwen_budget_for_tool_heavy_context() {
-        assert_eq!(qwen_max_tokens(65_536, true), 16_384);
-        assert_eq!(qwen_thinking_budget(20_000, true), 8_192);
+        // OpenAI caches the prefix automatically: the body must look like unpinned messages
+        profile.provider = LLMProvider::OpenAI;
+        profile.model = "gpt-4o".to_string();
+        let mut messages = conversation.clone();
+        apply_prompt_caching(&mut messages, &profile);
+        assert!(messages
+            .iter()
+            .all(|m| matches!(m.content, Some(MessageContent::Text(_)))));
+
+        // Caching disabled for the profile: no markers even for Anthropic
+        profile.provider = LLMProvider::Anthropic;
+        profile.prompt_caching = false;
+        let mut messages = conversation;
+        apply_prompt_caching(&mut messages, &profile);
+        assert!(messages
+            .iter()
+            .all(|m| !m.content.as_ref().unwrap().is_cacheable()));
     }
 
     #[test]
-    fn insufficient_quota_uses_short_retry_schedule() {
-        let ctx = QwenRateLimitContext {
-            retry_after_secs: None,
-            message: Some("quota".to_string()),
-            is_quota_exceeded: true,
-            is_hard_quota_exhausted: false,
-            is_burst_limited: false,
+    fn task_addendum_is_appended_to_system_message() {
+        let user = ApiMessage {
+            role: "user".to_string(),
+            content: Some("Упрости процедуру".into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+
+        let messages = build_api_messages(
+            "base".to_string(),
+            TaskType::Refactor,
+            None,
+            None,
+            &[],
+            Vec::new(),
+            vec![user.clone()],
+        );
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        let system = messages[0].text().unwrap();
+        assert!(system.starts_with("base\n\n"));
+        assert!(system
+            .contains("Focus on reducing code complexity and following 1C naming conventions."));
+        assert!(!system.contains("TASK: Explain"));
+        assert_eq!(messages[1].text(), Some("Упрости процедуру"));
+
+        let messages = build_api_messages(
+            "base".to_string(),
+            TaskType::Review,
+            None,
+            None,
+            &[],
+            Vec::new(),
+            vec![user.clone()],
+        );
+        assert!(messages[0].text().unwrap().contains("TASK: Review"));
+
+        let messages = build_api_messages(
+            "base".to_string(),
+            TaskType::FreeForm,
+            None,
+            None,
+            &[],
+            Vec::new(),
+            vec![user.clone()],
+        );
+        assert_eq!(messages[0].text(), Some("base"));
+
+        // Few-shot examples follow the system prompt and the pinned context
+        let example = super::super::few_shot::FewShotExample {
+            id: "e1".to_string(),
+            task_type: TaskType::Refactor,
+            user_text: "пример вопроса".to_string(),
+            assistant_text: "пример ответа".to_string(),
+        };
+        let examples = super::super::few_shot::example_messages(&[&example]);
+        let mut pinned_content = MessageContent::from("закреплённый модуль");
+        pinned_content.mark_cacheable();
+        let pinned = ApiMessage {
+            role: "user".to_string(),
+            content: Some(pinned_content),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+        let messages = build_api_messages(
+            "base".to_string(),
+            TaskType::Refactor,
+            None,
+            None,
+            &[],
+            examples,
+            vec![pinned, user],
+        );
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "user", "assistant", "user"]);
+        assert!(messages[1].content.as_ref().unwrap().is_cacheable());
+        assert_eq!(messages[2].text(), Some("пример вопроса"));
+        assert_eq!(messages[3].text(), Some("пример ответа"));
+        assert_eq!(messages[4].text(), Some("Упрости процедуру"));
+    }
+
+    #[test]
+    fn verbosity_instruction_ends_the_system_message() {
+        let user = ApiMessage {
+            role: "user".to_string(),
+            content: Some("Напиши функцию".into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+        let system_text = |task_type, verbosity| {
+            build_api_messages(
+                "base".to_string(),
+                task_type,
+                verbosity,
+                None,
+                &[],
+                Vec::new(),
+                vec![user.clone()],
+            )[0]
+            .text()
+            .unwrap()
+            .to_string()
+        };
+
+        let short = system_text(TaskType::Generate, Some(Verbosity::Short));
+        assert!(short.contains("TASK: Generate."));
+        assert!(short.ends_with(
+            "\nVERBOSITY for this task: Reply with code only, no explanations, maximum 20 lines."
+        ));
+        assert!(system_text(TaskType::Explain, Some(Verbosity::Medium))
+            .ends_with("Balance code and explanation, aim for 100 words."));
+        assert_eq!(
+            system_text(TaskType::FreeForm, Some(Verbosity::Long)),
+            "base\n\nVERBOSITY: Provide detailed explanation, examples, and complete code."
+        );
+        assert!(!system_text(TaskType::Generate, None).contains("VERBOSITY"));
+    }
+
+    #[test]
+    fn attachments_message_precedes_the_question_and_counts_toward_budget() {
+        let message = |role: &str, text: &str| ApiMessage {
+            role: role.to_string(),
+            content: Some(text.into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+        let attachments = [FileAttachment {
+            path: "CommonModules/Общий/Module.bsl".to_string(),
+            content: "Процедура Общая() Экспорт\nКонецПроцедуры".to_string(),
+            language: "bsl".to_string(),
+        }];
+        let messages = build_api_messages(
+            "base".to_string(),
+            TaskType::FreeForm,
+            None,
+            None,
+            &attachments,
+            Vec::new(),
+            vec![
+                message("user", "Что делает модуль?"),
+                message("assistant", "Ничего."),
+                message("user", "Перенеси вызовы"),
+            ],
+        );
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user", "user"]);
+        assert!(messages[3].text().unwrap().starts_with(
+            "Here are the relevant files:\n\nCommonModules/Общий/Module.bsl (ОбщийМодуль «Общий»):\n```bsl\n"
+        ));
+        assert_eq!(messages[4].text(), Some("Перенеси вызовы"));
+
+        assert!(check_context_budget(&messages, 1_000).is_ok());
+        assert!(check_context_budget(&messages, 10).is_err());
+    }
+
+    #[test]
+    fn system_prompt_placement_produces_expected_message_arrays() {
+        let message = |role: &str, text: &str| ApiMessage {
+            role: role.to_string(),
+            content: Some(text.into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+        let conversation = || {
+            vec![
+                message("system", "Ты 1С-разработчик."),
+                message("user", "Привет"),
+                message("assistant", "Здравствуйте"),
+                message("user", "Напиши функцию"),
+            ]
+        };
+        let pairs = |messages: &[ApiMessage]| -> Vec<(String, String)> {
+            messages
+                .iter()
+                .map(|m| (m.role.clone(), m.text().unwrap_or_default().to_string()))
+                .collect()
+        };
+        let expected = |items: &[(&str, &str)]| -> Vec<(String, String)> {
+            items
+                .iter()
+                .map(|(r, t)| (r.to_string(), t.to_string()))
+                .collect()
+        };
+
+        let mut messages = conversation();
+        assert_eq!(
+            apply_system_prompt_placement(&mut messages, SystemPromptPlacement::System),
+            None
+        );
+        assert_eq!(pairs(&messages), pairs(&conversation()));
+
+        let mut messages = conversation();
+        assert_eq!(
+            apply_system_prompt_placement(&mut messages, SystemPromptPlacement::Developer),
+            None
+        );
+        assert_eq!(
+            pairs(&messages),
+            expected(&[
+                ("developer", "Ты 1С-разработчик."),
+                ("user", "Привет"),
+                ("assistant", "Здравствуйте"),
+                ("user", "Напиши функцию"),
+            ])
+        );
+
+        let mut messages = conversation();
+        assert_eq!(
+            apply_system_prompt_placement(&mut messages, SystemPromptPlacement::PrependToUser),
+            None
+        );
+        assert_eq!(
+            pairs(&messages),
+            expected(&[
+                ("user", "Ты 1С-разработчик.\n\n---\n\nПривет"),
+                ("assistant", "Здравствуйте"),
+                ("user", "Напиши функцию"),
+            ])
+        );
+
+        let mut messages = conversation();
+        assert_eq!(
+            apply_system_prompt_placement(&mut messages, SystemPromptPlacement::TopLevel),
+            Some("Ты 1С-разработчик.".to_string())
+        );
+        assert_eq!(
+            pairs(&messages),
+            expected(&[
+                ("user", "Привет"),
+                ("assistant", "Здравствуйте"),
+                ("user", "Напиши функцию"),
+            ])
+        );
+    }
+
+    #[test]
+    fn system_prompt_placement_defaults_by_provider_and_model() {
+        let mut profile = crate::llm_profiles::LLMProfile::default_profile();
+        assert_eq!(
+            resolve_system_prompt_placement(&profile),
+            SystemPromptPlacement::System
+        );
+        profile.model = "gpt-5-mini".to_string();
+        assert_eq!(
+            resolve_system_prompt_placement(&profile),
+            SystemPromptPlacement::Developer
+        );
+        profile.provider = LLMProvider::OpenRouter;
+        profile.model = "google/gemma-3-27b-it:free".to_string();
+        assert_eq!(
+            resolve_system_prompt_placement(&profile),
+            SystemPromptPlacement::PrependToUser
+        );
+        profile.system_prompt_placement = Some(SystemPromptPlacement::TopLevel);
+        assert_eq!(
+            resolve_system_prompt_placement(&profile),
+            SystemPromptPlacement::TopLevel
+        );
+    }
+
+    #[test]
+    fn detects_quota_exceeded_from_qwen_body() {
+        let headers = HeaderMap::new();
+        let ctx = parse_qwen_rate_limit_context(
+            &headers,
+            r#"{"error":{"code":"insufficient_quota","message":"You exceeded your current quota."}}"#,
+        );
+
+        assert!(ctx.is_quota_exceeded);
+        assert!(!ctx.is_hard_quota_exhausted);
+        assert!(!ctx.is_burst_limited);
+    }
+
+    #[test]
+    fn detects_tool_heavy_context_from_recent_tool_messages() {
+        let messages = vec![
+            ApiMessage {
+                role: "user".to_string(),
+                content: Some("test".into()),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+            ApiMessage {
+                role: "tool".to_string(),
+                content: Some("cached".into()),
+                tool_calls: None,
+                tool_call_id: Some("tc_1".to_string()),
+                name: Some("get_object_structure".to_string()),
+            },
+        ];
+
+        assert!(qwen_has_tool_heavy_context(&messages));
+    }
+
+    #[test]
+    fn clamps_qwen_budget_for_tool_heavy_context() {
+        assert_eq!(qwen_max_tokens(65_536, true), 16_384);
+        assert_eq!(qwen_thinking_budget(20_000, true), 8_192);
+    }
+
+    #[test]
+    fn insufficient_quota_uses_short_retry_schedule() {
+        let ctx = QwenRateLimitContext {
+            retry_after_secs: None,
+            message: Some("quota".to_string()),
+            is_quota_exceeded: true,
+            is_hard_quota_exhausted: false,
+            is_burst_limited: false,
         };
 
         assert_eq!(qwen_retry_delay(1, &ctx), Some(Duration::from_secs(2)));
@@ -1666,15 +3771,15 @@ mod tests {
 
         let sanitized = sanitize_messages_for_ollama_cloud(messages);
 
-        assert_eq!(sanitized[0].content.as_deref(), Some(""));
-        assert_eq!(sanitized[1].content.as_deref(), Some(""));
+        assert_eq!(sanitized[0].text(), Some(""));
+        assert_eq!(sanitized[1].text(), Some(""));
     }
 
     #[test]
     fn ollama_cloud_sanitizer_keeps_existing_content_unchanged() {
         let messages = vec![ApiMessage {
             role: "assistant".to_string(),
-            content: Some("ready".to_string()),
+            content: Some("ready".into()),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -1720,17 +3825,770 @@ mod tests {
             model: "qwen3-max".to_string(),
             messages: Vec::new(),
             stream: true,
-            temperature: 1.0,
-            max_tokens: 65_536,
+            temperature: Some(1.0),
+            max_tokens: Some(65_536),
+            max_completion_tokens: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            seed: None,
+            logit_bias: None,
+            n: None,
+            user: None,
+            system: None,
+            stream_options: None,
+            response_format: None,
             tools: None,
             enable_thinking: Some(true),
             thinking_budget_tokens: Some(24_000),
+            reasoning_effort: None,
+            thinking: None,
         };
 
         let changed = reduce_qwen_request_pressure(&mut request, true, true);
 
         assert!(changed);
-        assert_eq!(request.max_tokens, 8_192);
+        assert_eq!(request.max_tokens, Some(8_192));
         assert_eq!(request.thinking_budget_tokens, Some(4_096));
     }
+
+    #[test]
+    fn response_limit_truncates_oversized_stream_on_char_boundary() {
+        let limit = 1_000;
+        let mut content = String::new();
+        let mut chunks_read = 0;
+        // Endless stream of multi-byte chunks; the limit must stop it.
+        for chunk in std::iter::repeat("Процедура ").take(10_000) {
+            if enforce_response_limit(&mut content, limit) {
+                break;
+            }
+            content.push_str(chunk);
+            chunks_read += 1;
+        }
+
+        assert!(chunks_read < 10_000);
+        assert!(content.len() <= limit);
+        assert!(content.len() > limit - 4);
+        assert!(!enforce_response_limit(&mut content, limit));
+    }
+
+    #[test]
+    fn alternatives_share_the_response_limit() {
+        let mut alternatives = vec![
+            "Первый".to_string(),
+            String::new(),
+            "Второй вариант".to_string(),
+            "Третий".to_string(),
+        ];
+        assert!(!enforce_alternatives_limit(&mut alternatives, 1_000));
+        assert_eq!(alternatives.len(), 4);
+
+        // "Первый" is 12 bytes; the second one is cut on a char boundary, the third dropped
+        assert!(enforce_alternatives_limit(&mut alternatives, 12 + 7));
+        assert_eq!(alternatives, vec!["Первый", "", "Вто"]);
+        assert!(enforce_alternatives_limit(&mut alternatives, 0));
+        assert!(alternatives.is_empty());
+    }
+
+    #[test]
+    fn omits_unset_temperature_and_max_tokens_from_body() {
+        let request = ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: Vec::new(),
+            stream: false,
+            temperature: None,
+            max_tokens: None,
+            max_completion_tokens: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: None,
+            seed: None,
+            logit_bias: None,
+            n: None,
+            user: None,
+            system: None,
+            stream_options: None,
+            response_format: None,
+            tools: None,
+            enable_thinking: None,
+            thinking_budget_tokens: None,
+            reasoning_effort: None,
+            thinking: None,
+        };
+
+        let body = serde_json::to_value(&request).unwrap();
+        let body = body.as_object().unwrap();
+        assert!(!body.contains_key("temperature"));
+        assert!(!body.contains_key("max_tokens"));
+    }
+
+    #[test]
+    fn demultiplexes_alternative_deltas_by_choice_index() {
+        let chunks = [
+            r#"{"choices":[{"index":0,"delta":{"content":"Про"}},{"index":2,"delta":{"content":"Функ"}}]}"#,
+            r#"{"choices":[{"index":1,"delta":{"content":"Пере"}},{"index":2,"delta":{"content":"ция"}}]}"#,
+            r#"{"choices":[{"index":1,"delta":{"content":"менная"},"finish_reason":"stop"}]}"#,
+        ];
+        let mut alternatives = Vec::new();
+        let mut emitted = Vec::new();
+        for data in chunks {
+            let chunk: StreamChunk = serde_json::from_str(data).unwrap();
+            for (index, delta) in collect_alternative_deltas(&chunk, &mut alternatives) {
+                emitted.push((index, delta.to_string()));
+            }
+        }
+
+        assert_eq!(alternatives, vec!["Переменная", "Функция"]);
+        assert_eq!(emitted.len(), 4);
+        assert_eq!(emitted[0], (2, "Функ".to_string()));
+        // Without `index` every delta belongs to the primary choice
+        let chunk: StreamChunk =
+            serde_json::from_str(r#"{"choices":[{"delta":{"content":"x"}}]}"#).unwrap();
+        assert!(collect_alternative_deltas(&chunk, &mut alternatives).is_empty());
+    }
+
+    #[test]
+    fn non_stream_response_matches_streamed_content_and_usage() {
+        let sse = [
+            r#"{"choices":[{"index":0,"delta":{"content":"Процедура "}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"Тест()"}}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":42,"completion_tokens":7}}"#,
+        ];
+        let mut streamed_meta = CompletionMeta::default();
+        let mut streamed = String::new();
+        for data in sse {
+            let chunk: StreamChunk = serde_json::from_str(data).unwrap();
+            if let Some(usage) = &chunk.usage {
+                streamed_meta.apply_usage(usage);
+            }
+            for choice in &chunk.choices {
+                if let Some(text) = &choice.delta.content {
+                    streamed.push_str(text);
+                }
+            }
+        }
+
+        let body = r#"{"choices":[{"index":0,"message":{"content":"Процедура Тест()"}}],
+            "usage":{"prompt_tokens":42,"completion_tokens":7}}"#;
+        let mut meta = CompletionMeta::default();
+        let (message, alternatives) = parse_non_stream_response(body, &mut meta).unwrap();
+
+        assert_eq!(message.role, "assistant");
+        assert_eq!(message.text(), Some(streamed.as_str()));
+        assert!(message.tool_calls.is_none());
+        assert!(alternatives.is_empty());
+        assert_eq!(meta.prompt_tokens, streamed_meta.prompt_tokens);
+        assert_eq!(meta.completion_tokens, streamed_meta.completion_tokens);
+        assert!(parse_non_stream_response(r#"{"choices":[]}"#, &mut meta).is_err());
+    }
+
+    #[test]
+    fn parses_sse_id_and_retry_fields() {
+        assert_eq!(
+            parse_sse_control_field("id: evt-42"),
+            Some(SseControlField::Id(Some("evt-42".to_string())))
+        );
+        assert_eq!(
+            parse_sse_control_field("id:"),
+            Some(SseControlField::Id(None))
+        );
+        assert_eq!(
+            parse_sse_control_field("retry: 2500"),
+            Some(SseControlField::Retry(2500))
+        );
+        assert_eq!(parse_sse_control_field("retry: soon"), None);
+        assert_eq!(parse_sse_control_field("data: {}"), None);
+    }
+
+    fn reconnect_profile(min_delay_ms: u64) -> crate::llm_profiles::LLMProfile {
+        crate::llm_profiles::LLMProfile {
+            max_reconnect_attempts: Some(3),
+            min_reconnect_delay_ms: Some(min_delay_ms),
+            ..crate::llm_profiles::LLMProfile::default_profile()
+        }
+    }
+
+    #[test]
+    fn sse_retry_shorter_than_minimum_is_ignored() {
+        let mut reconnect = SseReconnect::new(&reconnect_profile(1_000));
+        reconnect.observe(SseControlField::Retry(100));
+        assert_eq!(reconnect.delay, Duration::from_millis(1_000));
+        reconnect.observe(SseControlField::Retry(5_000));
+        assert_eq!(reconnect.delay, Duration::from_millis(5_000));
+
+        // Without an event id there is nothing to resume from
+        assert!(reconnect.exhausted_error("reset", "").is_none());
+    }
+
+    #[tokio::test]
+    async fn broken_stream_waits_for_sse_retry_and_keeps_partial_response() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let resumed_with_id = Arc::new(AtomicUsize::new(0));
+        let (conn_counter, id_counter) = (connections.clone(), resumed_with_id.clone());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                conn_counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                if request.contains("last-event-id: 7") {
+                    id_counter.fetch_add(1, Ordering::SeqCst);
+                }
+                // Promises more than it sends, then closes: the body breaks mid-stream
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: 4096\r\n\r\nid: 7\nretry: 100\n\ndata: part\n\n",
+                    )
+                    .await;
+            }
+        });
+
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let url = format!("http://{}/v1/chat/completions", addr);
+        let mut reconnect = SseReconnect::new(&reconnect_profile(50));
+        let mut partial = String::new();
+        let started = Instant::now();
+        let mut stream = client.post(&url).send().await.unwrap().bytes_stream();
+        let error = loop {
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    for line in String::from_utf8_lossy(&chunk).lines() {
+                        if let Some(field) = parse_sse_control_field(line) {
+                            reconnect.observe(field);
+                        } else if let Some(data) = line.strip_prefix("data: ") {
+                            partial.push_str(data);
+                        }
+                    }
+                }
+                Some(Err(e)) => match reconnect.resume(|| client.post(&url)).await {
+                    Some(r) => stream = r.bytes_stream(),
+                    None => {
+                        break reconnect
+                            .exhausted_error(&describe_stream_error(&e), &partial)
+                            .expect("attempts should be exhausted")
+                    }
+                },
+                None => panic!("stream should break, not end"),
+            }
+        };
+
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(reconnect.delay, Duration::from_millis(100));
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+        assert_eq!(resumed_with_id.load(Ordering::SeqCst), 3);
+        let AiClientError::MaxReconnectsExceeded {
+            attempts,
+            partial_response,
+            ..
+        } = error
+        else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert_eq!(attempts, 3);
+        assert_eq!(partial_response, "partpartpartpart");
+    }
+
+    #[test]
+    fn completion_meta_reads_processing_time_and_remaining_tokens() {
+        let mut headers = HeaderMap::new();
+        headers.insert("openai-processing-ms", HeaderValue::from_static("842"));
+        headers.insert(
+            "x-ratelimit-remaining-tokens",
+            HeaderValue::from_static("149000"),
+        );
+
+        let mut meta = parse_completion_meta_headers(&headers);
+        meta.apply_usage(&Usage {
+            prompt_tokens: Some(120),
+            completion_tokens: Some(48),
+            ..Default::default()
+        });
+
+        assert_eq!(meta.processing_ms, Some(842));
+        assert_eq!(meta.remaining_tokens, Some(149_000));
+        assert_eq!(meta.prompt_tokens, Some(120));
+        assert_eq!(meta.completion_tokens, Some(48));
+        assert_eq!(meta.server_request_id, None);
+    }
+
+    #[test]
+    fn completion_meta_prefers_echoed_request_id_over_cf_ray() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-ray", HeaderValue::from_static("8c1f2a-FRA"));
+        assert_eq!(
+            parse_completion_meta_headers(&headers).server_request_id,
+            Some("8c1f2a-FRA".to_string())
+        );
+
+        headers.insert("x-request-id", HeaderValue::from_static("req_123"));
+        assert_eq!(
+            parse_completion_meta_headers(&headers).server_request_id,
+            Some("req_123".to_string())
+        );
+    }
+
+    #[test]
+    fn usage_only_stream_chunk_deserializes() {
+        let chunk: StreamChunk = serde_json::from_str(
+            r#"{"choices":[],"usage":{"prompt_tokens":10,"completion_tokens":5,"total_tokens":15}}"#,
+        )
+        .expect("usage chunk should parse");
+
+        assert!(chunk.choices.is_empty());
+        assert_eq!(chunk.usage.and_then(|u| u.completion_tokens), Some(5));
+    }
+
+    #[test]
+    fn fallback_chain_skips_tried_and_missing_profiles() {
+        let chain = vec![
+            "b".to_string(),
+            "gone".to_string(),
+            "a".to_string(),
+            "c".to_string(),
+        ];
+        let exists = |id: &str| id != "gone";
+        let mut tried = vec!["a".to_string()];
+
+        let mut order = Vec::new();
+        while let Some(next) = next_fallback_profile(&chain, &tried, exists) {
+            order.push(next.clone());
+            tried.push(next);
+        }
+        // "a" references back to the primary and is never retried
+        assert_eq!(order, vec!["b", "c"]);
+        assert_eq!(next_fallback_profile(&[], &tried, exists), None);
+    }
+
+    #[tokio::test]
+    async fn fetch_models_follows_pages() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let page = match request.lines().next().unwrap_or_default() {
+                    line if line.contains("after=c2") => 2,
+                    line if line.contains("after=c1") => 1,
+                    _ => 0,
+                };
+                let data: Vec<serde_json::Value> = (0..5)
+                    .map(|i| serde_json::json!({ "id": format!("model-{:02}", page * 5 + i) }))
+                    .collect();
+                let body = serde_json::json!({
+                    "object": "list",
+                    "data": data,
+                    "has_more": page < 2,
+                    "next_cursor": (page < 2).then(|| format!("c{}", page + 1)),
+                })
+                .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let profile = crate::llm_profiles::LLMProfile {
+            provider: LLMProvider::Custom,
+            base_url: Some(format!("http://{}", addr)),
+            ..crate::llm_profiles::LLMProfile::default_profile()
+        };
+        let models: Vec<String> = fetch_models(&profile)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+
+        assert_eq!(models.len(), 15);
+        assert_eq!(models.first().map(String::as_str), Some("model-00"));
+        assert_eq!(models.last().map(String::as_str), Some("model-14"));
+    }
+
+    #[test]
+    fn only_list_pages_with_more_continue() {
+        let page = |json: serde_json::Value| {
+            serde_json::from_value::<PaginatedModelsResponse>(json).unwrap()
+        };
+        assert_eq!(
+            page(serde_json::json!({"object": "list", "data": [{"id": "a"}], "has_more": true, "next_cursor": "x"}))
+                .next_page_cursor()
+                .as_deref(),
+            Some("x")
+        );
+        assert_eq!(
+            page(serde_json::json!({"object": "list", "data": [{"id": "a"}, {"id": "b"}], "has_more": true}))
+                .next_page_cursor()
+                .as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            page(serde_json::json!({"object": "list", "data": [], "has_more": false, "next_cursor": "x"}))
+                .next_page_cursor(),
+            None
+        );
+        assert_eq!(
+            page(serde_json::json!({"data": [{"id": "a"}], "has_more": true})).next_page_cursor(),
+            None
+        );
+    }
+
+    #[test]
+    fn model_entries_read_provider_metadata() {
+        let page: PaginatedModelsResponse = serde_json::from_value(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system"},
+                {"id": "llama-3.3-70b-versatile", "context_window": 131072, "max_completion_tokens": 32768},
+                {"id": "openai/gpt-4o", "context_length": 128000, "top_provider": {"max_completion_tokens": 16384}},
+                {"id": "odd", "context_length": "big", "created": -1},
+                {"object": "model"}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            page.data[0],
+            ModelEntry {
+                id: "gpt-4o".to_string(),
+                context_window: None,
+                max_completion_tokens: None,
+                created: Some(1715367049),
+            }
+        );
+        assert_eq!(page.data[1].context_window, Some(131072));
+        assert_eq!(page.data[1].max_completion_tokens, Some(32768));
+        assert_eq!(page.data[2].context_window, Some(128000));
+        assert_eq!(page.data[2].max_completion_tokens, Some(16384));
+        assert_eq!(page.data[3].context_window, None);
+        assert_eq!(page.data[3].created, None);
+        assert_eq!(page.data[4].id, "");
+
+        // The cached form reads back unchanged
+        let json = serde_json::to_value(&page.data[2]).unwrap();
+        assert_eq!(
+            serde_json::from_value::<ModelEntry>(json).unwrap(),
+            page.data[2]
+        );
+    }
+
+    /// Local OpenAI-compatible server answering every request with `response`
+    async fn stub_server(response: impl Into<Vec<u8>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let response = response.into();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 64 * 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(&response).await;
+            }
+        });
+        format!("http://{}/v1", addr)
+    }
+
+    /// Custom-provider profile talking to `base_url`, without reconnects
+    fn stub_profile(id: &str, base_url: String) -> crate::llm_profiles::LLMProfile {
+        crate::llm_profiles::LLMProfile {
+            id: id.to_string(),
+            name: id.to_string(),
+            provider: LLMProvider::Custom,
+            base_url: Some(base_url),
+            max_reconnect_attempts: Some(0),
+            ..crate::llm_profiles::LLMProfile::default_profile()
+        }
+    }
+
+    const TERMINAL_EVENTS: [&str; 3] = ["chat-done", "chat-error", "chat-cancelled"];
+
+    /// Runs a completion with `profile` and returns its result and the `recorded` events
+    async fn complete_with(
+        profile: crate::llm_profiles::LLMProfile,
+        recorded: &[&'static str],
+    ) -> (
+        Result<ChatCompletion, AiClientError>,
+        Vec<(&'static str, serde_json::Value)>,
+    ) {
+        complete_with_profiles(vec![profile], recorded).await
+    }
+
+    /// `complete_with` where the first of `profiles` is the active one
+    async fn complete_with_profiles(
+        profiles: Vec<crate::llm_profiles::LLMProfile>,
+        recorded: &[&'static str],
+    ) -> (
+        Result<ChatCompletion, AiClientError>,
+        Vec<(&'static str, serde_json::Value)>,
+    ) {
+        use std::sync::{Arc, Mutex};
+        use tauri::Listener;
+
+        crate::llm_profiles::set_test_store(Some(crate::llm_profiles::ProfileStore {
+            active_profile_id: profiles[0].id.clone(),
+            profiles,
+        }));
+        let app = tauri::test::mock_app();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for &name in recorded {
+            let events = events.clone();
+            app.handle().listen_any(name, move |event| {
+                let payload = serde_json::from_str(event.payload()).unwrap_or_default();
+                events.lock().unwrap().push((name, payload));
+            });
+        }
+        let messages = vec![ApiMessage {
+            role: "user".to_string(),
+            content: Some("Привет".into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }];
+        let result = stream_chat_completion(
+            messages,
+            &RequestOverrides::default(),
+            TaskType::default(),
+            None,
+            &[],
+            None,
+            app.handle().clone(),
+        )
+        .await;
+        crate::llm_profiles::set_test_store(None);
+        let events = events.lock().unwrap().clone();
+        (result, events)
+    }
+
+    #[tokio::test]
+    async fn server_error_ends_with_a_single_chat_error() {
+        let base_url = stub_server(
+            b"HTTP/1.1 500 Internal Server Error\r\nRetry-After: 0\r\nContent-Type: application/json\r\nContent-Length: 28\r\nConnection: close\r\n\r\n{\"error\":{\"message\":\"boom\"}}",
+        )
+        .await;
+        let (result, events) =
+            complete_with(stub_profile("stub-http-500", base_url), &TERMINAL_EVENTS).await;
+
+        assert_eq!(result.unwrap_err().http_status(), Some(500));
+        assert_eq!(events.len(), 1, "{:?}", events);
+        let (event, payload) = &events[0];
+        assert_eq!(*event, "chat-error");
+        assert_eq!(payload["kind"], "http");
+        assert_eq!(payload["http_status"], 500);
+        assert!(payload["request_id"]
+            .as_str()
+            .is_some_and(|id| !id.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn broken_stream_ends_with_a_single_chat_error() {
+        // Promises more than it sends, then closes: the body breaks mid-stream
+        let base_url = stub_server(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: 4096\r\n\r\ndata: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"part\"}}]}\n\n",
+        )
+        .await;
+        let (result, events) = complete_with(
+            stub_profile("stub-broken-stream", base_url),
+            &TERMINAL_EVENTS,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].0, "chat-error");
+        assert_ne!(events[0].1["kind"], "cancelled");
+    }
+
+    #[tokio::test]
+    async fn failed_profile_falls_back_to_the_next_one() {
+        let failing = stub_server(
+            b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Type: application/json\r\nContent-Length: 28\r\nConnection: close\r\n\r\n{\"error\":{\"message\":\"down\"}}",
+        )
+        .await;
+        let working = stub_server(sse_response(&[delta_event(
+            serde_json::json!({ "content": "Ответ запасного профиля" }),
+        )]))
+        .await;
+        let primary = crate::llm_profiles::LLMProfile {
+            fallback_profiles: vec!["stub-fallback-ok".to_string()],
+            ..stub_profile("stub-fallback-503", failing)
+        };
+
+        let (result, events) = complete_with_profiles(
+            vec![primary, stub_profile("stub-fallback-ok", working)],
+            &["chat-fallback", "chat-done", "chat-error"],
+        )
+        .await;
+
+        let completion = result.unwrap();
+        assert_eq!(completion.message.text(), Some("Ответ запасного профиля"));
+        let names: Vec<_> = events.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["chat-fallback", "chat-done"], "{:?}", events);
+        assert_eq!(events[0].1["from_profile_id"], "stub-fallback-503");
+        assert_eq!(events[0].1["profile_id"], "stub-fallback-ok");
+    }
+
+    /// SSE response: `events` as `data:` lines, then `[DONE]`
+    fn sse_response(events: &[String]) -> Vec<u8> {
+        let body: String = events
+            .iter()
+            .map(|data| format!("data: {}\n\n", data))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    fn delta_event(delta: serde_json::Value) -> String {
+        serde_json::json!({ "choices": [{ "index": 0, "delta": delta }] }).to_string()
+    }
+
+    #[tokio::test]
+    async fn oversized_stream_is_cut_at_max_response_bytes() {
+        const LIMIT: usize = 4 * 1024;
+        let recorded = ["chat-truncated", "chat-done", "chat-error"];
+        let limited = |id: &str, base_url| crate::llm_profiles::LLMProfile {
+            max_response_bytes: Some(LIMIT),
+            ..stub_profile(id, base_url)
+        };
+
+        // Answer text far over the limit
+        let answer: Vec<String> = (0..2_000)
+            .map(|_| delta_event(serde_json::json!({ "content": "Процедура " })))
+            .collect();
+        let base_url = stub_server(sse_response(&answer)).await;
+        let (result, events) =
+            complete_with(limited("stub-long-answer", base_url), &recorded).await;
+        let completion = result.unwrap();
+        let text = completion.message.text().unwrap_or_default();
+        assert!(
+            text.len() <= LIMIT && text.len() > LIMIT / 2,
+            "{}",
+            text.len()
+        );
+        assert_eq!(events[0].0, "chat-truncated");
+        assert_eq!(events[0].1["limit"], LIMIT);
+
+        // Reasoning and tool call arguments count as well
+        let mut thinking: Vec<String> = (0..2_000)
+            .map(|_| delta_event(serde_json::json!({ "reasoning_content": "думаю " })))
+            .collect();
+        thinking.extend((0..2_000).map(|i| {
+            delta_event(serde_json::json!({ "tool_calls": [{
+                "index": 0,
+                "id": (i == 0).then_some("call_1"),
+                "function": { "name": (i == 0).then_some("search_code"), "arguments": "{\"q\":" },
+            }] }))
+        }));
+        let base_url = stub_server(sse_response(&thinking)).await;
+        let (result, events) =
+            complete_with(limited("stub-long-thinking", base_url), &recorded).await;
+        assert!(result.unwrap().message.tool_calls.is_none());
+        assert_eq!(events[0].0, "chat-truncated");
+
+        // A single event that never ends is not buffered past the limit
+        let endless = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\ndata: {}",
+            "x".repeat(16 * LIMIT)
+        );
+        let base_url = stub_server(endless).await;
+        let (_, events) = complete_with(limited("stub-endless-event", base_url), &recorded).await;
+        assert_eq!(events[0].0, "chat-truncated");
+        assert!(events[0].1["bytes_accumulated"].as_u64().unwrap() > LIMIT as u64);
+    }
+
+    #[test]
+    fn transforms_apply_before_chat_end_and_skip_tool_call_turns() {
+        use std::sync::{Arc, Mutex};
+        use tauri::Listener;
+
+        let app = tauri::test::mock_app();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for name in ["chat-answer-replaced", "chat-alternatives", "chat-end"] {
+            let events = events.clone();
+            app.handle().listen_any(name, move |event| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push((name, event.payload().to_string()));
+            });
+        }
+        let pipeline = super::super::transforms::TransformPipeline::from_specs(&[
+            super::super::transforms::TransformSpec::AppendText {
+                text: "!".to_string(),
+            },
+        ]);
+        let answer = |tool_calls: Option<Vec<ToolCall>>| ApiMessage {
+            role: "assistant".to_string(),
+            content: Some("Готово".into()),
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        };
+
+        let completion = finish_completion(
+            app.handle(),
+            "stub",
+            answer(None),
+            CompletionMeta::default(),
+            vec!["Иначе".to_string()],
+            Some(&pipeline),
+        );
+        assert_eq!(completion.message.text(), Some("Готово!"));
+        assert_eq!(completion.alternatives, vec!["Готово!", "Иначе!"]);
+        let recorded: Vec<_> = events.lock().unwrap().drain(..).collect();
+        let names: Vec<_> = recorded.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec!["chat-answer-replaced", "chat-alternatives", "chat-end"]
+        );
+        assert_eq!(recorded[0].1, "\"Готово!\"");
+        assert_eq!(recorded[1].1, "[\"Готово!\",\"Иначе!\"]");
+
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: ToolCallFunction {
+                name: "search_code".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        let completion = finish_completion(
+            app.handle(),
+            "stub",
+            answer(Some(vec![call])),
+            CompletionMeta::default(),
+            Vec::new(),
+            Some(&pipeline),
+        );
+        assert_eq!(completion.message.text(), Some("Готово"));
+        let names: Vec<_> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        assert_eq!(names, vec!["chat-end"]);
+    }
 }
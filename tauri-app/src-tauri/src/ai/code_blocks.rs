@@ -0,0 +1,120 @@
+//! Code-only answers: fenced blocks of the response without the prose around them.
+
+use serde::{Deserialize, Serialize};
+
+use super::client::stream_chat_completion;
+use super::error::AiClientError;
+use super::models::{ApiMessage, RequestOverrides, TaskType};
+
+/// Appended to the system prompt when the caller asks for code only
+pub const CODE_ONLY_INSTRUCTION: &str = "Output ONLY code, no explanations.";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeBlock {
+    /// Fence info string (`bsl`, `1c`, `sql`...); empty for a bare fence
+    pub language: String,
+    pub code: String,
+}
+
+/// All fenced blocks in order of appearance; an unclosed last fence is ignored
+pub fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match current.take() {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    let language = info.split_whitespace().next().unwrap_or_default();
+                    current = Some((language.to_lowercase(), Vec::new()));
+                }
+            }
+            Some((language, lines)) if trimmed.trim_end() == "```" => {
+                blocks.push(CodeBlock {
+                    language,
+                    code: lines.join("\n"),
+                });
+            }
+            Some((language, mut lines)) => {
+                lines.push(line);
+                current = Some((language, lines));
+            }
+        }
+    }
+    blocks
+}
+
+/// Block bodies joined with a blank line
+pub fn merge_code_blocks(blocks: &[CodeBlock]) -> String {
+    blocks
+        .iter()
+        .map(|b| b.code.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Runs a regular chat completion and keeps only the code blocks of the answer.
+/// With `code_only_prompt` the model is also told to skip explanations.
+pub async fn code_only_completion(
+    messages: Vec<ApiMessage>,
+    code_only_prompt: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<CodeBlock>, AiClientError> {
+    let overrides = RequestOverrides {
+        system_prompt_suffix: code_only_prompt.then(|| CODE_ONLY_INSTRUCTION.to_string()),
+        ..Default::default()
+    };
+    let completion = stream_chat_completion(
+        messages,
+        &overrides,
+        TaskType::FreeForm,
+        None,
+        &[],
+        None,
+        app_handle,
+    )
+    .await?;
+    let blocks = extract_code_blocks(completion.message.text().unwrap_or_default());
+    if blocks.is_empty() {
+        return Err(AiClientError::parse("В ответе модели нет блоков кода"));
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_blocks_in_order_without_prose() {
+        let text = "Вот исправление:\n```bsl\nПроцедура А()\n\tБ();\nКонецПроцедуры\n```\n\
+                    И запрос:\n```SQL\nВЫБРАТЬ 1\n```\nТакже:\n```\nпросто текст\n```\n```bsl\nнезакрыт";
+        let blocks = extract_code_blocks(text);
+
+        assert_eq!(
+            blocks,
+            vec![
+                CodeBlock {
+                    language: "bsl".to_string(),
+                    code: "Процедура А()\n\tБ();\nКонецПроцедуры".to_string(),
+                },
+                CodeBlock {
+                    language: "sql".to_string(),
+                    code: "ВЫБРАТЬ 1".to_string(),
+                },
+                CodeBlock {
+                    language: String::new(),
+                    code: "просто текст".to_string(),
+                },
+            ]
+        );
+        assert!(extract_code_blocks("Только текст, без кода").is_empty());
+    }
+
+    #[test]
+    fn merges_blocks_with_blank_line() {
+        let blocks = extract_code_blocks("```bsl\nА = 1;\n```\nтекст\n```bsl\nБ = 2;\n```");
+        assert_eq!(merge_code_blocks(&blocks), "А = 1;\n\nБ = 2;");
+        assert_eq!(merge_code_blocks(&[]), "");
+    }
+}
@@ -12,6 +12,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tauri::Emitter;
 
+use super::error::AiClientError;
 use super::models::{ApiMessage, Tool, ToolCall, ToolCallFunction};
 use crate::llm_profiles::{
     get_active_profile, normalize_codex_reasoning_effort, DEFAULT_CODEX_REASONING_EFFORT,
@@ -228,15 +229,15 @@ fn messages_to_codex_payload(messages: &[ApiMessage]) -> (String, Vec<CodexInput
     for msg in messages {
         match msg.role.as_str() {
             "system" | "developer" => {
-                if let Some(content) = &msg.content {
+                if let Some(content) = msg.text() {
                     if !content.is_empty() {
-                        instructions_parts.push(content.clone());
+                        instructions_parts.push(content.to_string());
                     }
                 }
             }
 
             "user" => {
-                let content = msg.content.clone().unwrap_or_default();
+                let content = msg.text().unwrap_or_default().to_string();
                 input.push(CodexInputItem::Message(CodexMessage {
                     role: "user".to_string(),
                     content: Value::String(content),
@@ -245,11 +246,11 @@ fn messages_to_codex_payload(messages: &[ApiMessage]) -> (String, Vec<CodexInput
 
             "assistant" => {
                 // Content (text) part
-                if let Some(content) = &msg.content {
+                if let Some(content) = msg.text() {
                     if !content.is_empty() {
                         input.push(CodexInputItem::Message(CodexMessage {
                             role: "assistant".to_string(),
-                            content: Value::String(content.clone()),
+                            content: Value::String(content.to_string()),
                         }));
                     }
                 }
@@ -278,7 +279,7 @@ fn messages_to_codex_payload(messages: &[ApiMessage]) -> (String, Vec<CodexInput
                         continue;
                     }
 
-                    let output = msg.content.clone().unwrap_or_default();
+                    let output = msg.text().unwrap_or_default().to_string();
                     input.push(CodexInputItem::FunctionCallOutput(
                         CodexFunctionCallOutput {
                             r#type: "function_call_output".to_string(),
@@ -479,7 +480,7 @@ pub async fn quick_codex_invoke(prompt: String) -> Result<String, String> {
     let stream_timeout_secs = resolve_codex_stream_timeout_secs(profile.stream_timeout_secs);
     let messages = vec![ApiMessage {
         role: "user".to_string(),
-        content: Some(prompt),
+        content: Some(prompt.into()),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -596,17 +597,17 @@ pub async fn quick_codex_invoke(prompt: String) -> Result<String, String> {
 // ─── Main streaming function ──────────────────────────────────────────────
 
 /// Main entry point: called from ai/client.rs when provider == CodexCli
-pub async fn stream_codex_completion(
+pub async fn stream_codex_completion<R: tauri::Runtime>(
     messages: Vec<ApiMessage>,
-    app_handle: tauri::AppHandle,
-) -> Result<ApiMessage, String> {
-    let profile = get_active_profile().ok_or("No active LLM profile")?;
+    profile: &crate::llm_profiles::LLMProfile,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<ApiMessage, AiClientError> {
     let profile_id = profile.id.clone();
 
     // Get OAuth token & auto-refresh
     let (access_token, refresh_token, expires_at, account_id) =
         crate::llm::cli_providers::codex::CodexCliProvider::get_token(&profile_id)?
-            .ok_or("Codex CLI: требуется авторизация. Откройте настройки профиля и нажмите 'Войти через браузер'.")?;
+            .ok_or_else(|| AiClientError::invalid_profile("Codex CLI: требуется авторизация. Откройте настройки профиля и нажмите 'Войти через браузер'."))?;
 
     let access_token = if chrono::Utc::now().timestamp() as u64 + 60 > expires_at {
         if let Some(rt) = refresh_token.as_deref() {
@@ -660,7 +661,7 @@ pub async fn stream_codex_completion(
     } else {
         profile.model.clone()
     };
-    let reasoning_effort = resolve_codex_reasoning_effort(&profile);
+    let reasoning_effort = resolve_codex_reasoning_effort(profile);
 
     // Build request
     let (instructions, input) = messages_to_codex_payload(&messages);
@@ -683,7 +684,7 @@ pub async fn stream_codex_completion(
     let client = crate::http_client::http_client_builder()?
         .timeout(std::time::Duration::from_secs(120))
         .build()
-        .map_err(|e| format!("HTTP client build error: {}", e))?;
+        .map_err(|e| AiClientError::invalid_profile(format!("HTTP client build error: {}", e)))?;
 
     let url = format!("{}{}", CODEX_BASE_URL, CODEX_RESPONSES_ENDPOINT);
 
@@ -703,7 +704,9 @@ pub async fn stream_codex_completion(
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Codex: ошибка сети: {}", e))?;
+        .map_err(|e| AiClientError::Network {
+            message: format!("Codex: ошибка сети: {}", e),
+        })?;
 
     let status = response.status();
 
@@ -715,23 +718,21 @@ pub async fn stream_codex_completion(
             safe_api_error_summary(status, &body)
         );
 
-        // Human-readable errors
-        let message = match status.as_u16() {
-            401 => {
-                "Codex: токен недействителен. Переавторизуйтесь в настройках профиля.".to_string()
-            }
-            429 => "Codex: превышен лимит запросов. Попробуйте позже.".to_string(),
-            _ => {
-                // Try to parse OpenAI error format
-                serde_json::from_str::<Value>(&body)
-                    .ok()
-                    .and_then(|v| v["error"]["message"].as_str().map(|s| s.to_string()))
-                    .unwrap_or_else(|| {
-                        format!("Codex API error {} (подробности скрыты)", status.as_u16())
-                    })
-            }
-        };
-        return Err(message);
+        if super::error::is_auth_status(status.as_u16()) {
+            return Err(AiClientError::auth_rejected(
+                status.as_u16(),
+                &body,
+                profile,
+                "токен Codex недействителен или истёк — переавторизуйтесь в настройках профиля"
+                    .to_string(),
+            ));
+        }
+        return Err(AiClientError::http(
+            status.as_u16(),
+            &body,
+            &profile.provider,
+            codex_api_error_message(status, &body),
+        ));
     }
 
     // Parse SSE stream
@@ -758,16 +759,20 @@ pub async fn stream_codex_completion(
         .await
         {
             Err(_) => {
-                return Err(format!(
-                    "Codex: таймаут потока ({} сек без данных)",
-                    stream_timeout_secs
-                ))
+                return Err(AiClientError::Timeout {
+                    message: format!(
+                        "Codex: таймаут потока ({} сек без данных)",
+                        stream_timeout_secs
+                    ),
+                    secs: Some(stream_timeout_secs as u64),
+                })
             }
             Ok(None) => break 'stream_loop,
             Ok(Some(r)) => r,
         };
 
-        let chunk = chunk_result.map_err(|e| format!("Codex stream error: {}", e))?;
+        let chunk = chunk_result
+            .map_err(|e| AiClientError::stream(format!("Codex stream error: {}", e)))?;
         byte_buffer.extend_from_slice(&chunk);
 
         // Process complete SSE events (separated by \n\n)
@@ -965,7 +970,7 @@ pub async fn stream_codex_completion(
                         .ok()
                         .and_then(|v| v["message"].as_str().map(|s| s.to_string()))
                         .unwrap_or_else(|| format!("Codex stream error: {}", event_data));
-                    return Err(err_msg);
+                    return Err(AiClientError::stream(err_msg));
                 }
 
                 _ => {
@@ -992,7 +997,7 @@ pub async fn stream_codex_completion(
         content: if full_content.is_empty() {
             None
         } else {
-            Some(full_content)
+            Some(full_content.into())
         },
         tool_calls: if accumulated_tool_calls.is_empty() {
             None
@@ -1023,21 +1028,21 @@ mod tests {
         let messages = vec![
             ApiMessage {
                 role: "system".to_string(),
-                content: Some("system instructions".to_string()),
+                content: Some("system instructions".into()),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
             },
             ApiMessage {
                 role: "developer".to_string(),
-                content: Some("developer instructions".to_string()),
+                content: Some("developer instructions".into()),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
             },
             ApiMessage {
                 role: "user".to_string(),
-                content: Some("user request".to_string()),
+                content: Some("user request".into()),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
@@ -1061,7 +1066,7 @@ mod tests {
     fn messages_to_codex_payload_uses_default_instructions_without_system_messages() {
         let messages = vec![ApiMessage {
             role: "user".to_string(),
-            content: Some("hello".to_string()),
+            content: Some("hello".into()),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -1079,7 +1084,7 @@ mod tests {
         let messages = vec![
             ApiMessage {
                 role: "user".to_string(),
-                content: Some("fix it".to_string()),
+                content: Some("fix it".into()),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
@@ -1125,7 +1130,7 @@ mod tests {
             },
             ApiMessage {
                 role: "tool".to_string(),
-                content: Some("{\"ok\":true}".to_string()),
+                content: Some("{\"ok\":true}".into()),
                 tool_calls: None,
                 tool_call_id: Some("call_done".to_string()),
                 name: Some("check_bsl_syntax".to_string()),
@@ -1169,7 +1174,7 @@ mod tests {
 
         let messages = vec![ApiMessage {
             role: "user".to_string(),
-            content: Some("describe this method".to_string()),
+            content: Some("describe this method".into()),
             tool_calls: None,
             tool_call_id: None,
             name: None,
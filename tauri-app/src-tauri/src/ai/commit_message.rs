@@ -0,0 +1,146 @@
+//! git commit message (subject line and optional bullet body) suggested from a diff.
+
+use serde::{Deserialize, Serialize};
+
+use super::client::prompt_completion;
+use super::error::AiClientError;
+use super::git_diff::{truncate_diff, MAX_GIT_DIFF_BYTES};
+use super::models::TaskType;
+
+/// Conventional limit of the subject line
+pub const MAX_SUBJECT_CHARS: usize = 72;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitMessageSuggestion {
+    pub subject: String,
+    /// `- ` bullets, one per line
+    pub body: Option<String>,
+}
+
+pub fn build_commit_message_prompt(diff: &str) -> String {
+    format!(
+        "Write a git commit message for the changes below.\n\
+         First line: a summary in the imperative mood (\"Add\", \"Fix\", \"Refactor\"), \
+         at most {} characters, no trailing period.\n\
+         Then, only if the change needs explaining, an empty line and a short bullet list \
+         (\"- \" per item) of the notable changes.\n\
+         Output only the commit message, without quotes or code fences.\n\n\
+         ```diff\n{}\n```",
+        MAX_SUBJECT_CHARS,
+        truncate_diff(diff, MAX_GIT_DIFF_BYTES).trim_end()
+    )
+}
+
+/// Cuts the subject to `MAX_SUBJECT_CHARS` at a word boundary
+fn clip_subject(subject: &str) -> String {
+    if subject.chars().count() <= MAX_SUBJECT_CHARS {
+        return subject.to_string();
+    }
+    let clipped: String = subject.chars().take(MAX_SUBJECT_CHARS).collect();
+    match clipped.rfind(' ') {
+        Some(space) if space > 0 => clipped[..space].trim_end().to_string(),
+        _ => clipped,
+    }
+}
+
+/// Splits the model's answer into the subject and the bullet body; fences, quotes and
+/// "Subject:" labels are dropped
+pub fn parse_commit_message(response: &str) -> Option<CommitMessageSuggestion> {
+    let mut lines = response
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("```"));
+    let subject = lines.next()?;
+    let subject = subject
+        .strip_prefix("Subject:")
+        .or_else(|| subject.strip_prefix("subject:"))
+        .unwrap_or(subject)
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .trim_end_matches('.')
+        .trim();
+    if subject.is_empty() {
+        return None;
+    }
+
+    let bullets: Vec<String> = lines
+        .filter(|l| !l.eq_ignore_ascii_case("body:"))
+        .map(|l| {
+            let item = l
+                .strip_prefix("- ")
+                .or_else(|| l.strip_prefix("* "))
+                .or_else(|| l.strip_prefix("• "))
+                .unwrap_or(l);
+            format!("- {}", item.trim())
+        })
+        .collect();
+
+    Some(CommitMessageSuggestion {
+        subject: clip_subject(subject),
+        body: (!bullets.is_empty()).then(|| bullets.join("\n")),
+    })
+}
+
+pub async fn suggest_commit_message(
+    diff: String,
+) -> Result<CommitMessageSuggestion, AiClientError> {
+    if diff.trim().is_empty() {
+        return Err(AiClientError::empty_input(
+            "Нет изменений для описания коммита",
+        ));
+    }
+    let answer = prompt_completion(build_commit_message_prompt(&diff), TaskType::Document).await?;
+    parse_commit_message(&answer)
+        .ok_or_else(|| AiClientError::parse("Модель не вернула сообщение коммита"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_subject_and_bullet_body() {
+        let response = "```\nSubject: \"Add stock check to ПроведениеДокумента.\"\n\n\
+                        * Check balances before posting\n- Report shortage per line\n```";
+        let suggestion = parse_commit_message(response).unwrap();
+
+        assert_eq!(suggestion.subject, "Add stock check to ПроведениеДокумента");
+        assert_eq!(
+            suggestion.body.as_deref(),
+            Some("- Check balances before posting\n- Report shortage per line")
+        );
+    }
+
+    #[test]
+    fn subject_only_and_empty_answers() {
+        let suggestion = parse_commit_message("Fix rounding in ОкруглитьСумму").unwrap();
+        assert_eq!(suggestion.subject, "Fix rounding in ОкруглитьСумму");
+        assert_eq!(suggestion.body, None);
+
+        assert_eq!(parse_commit_message("  \n```\n```"), None);
+    }
+
+    #[test]
+    fn long_subject_is_clipped_at_word_boundary() {
+        let long = format!("Refactor {}", "ОбработкаЗаполнения ".repeat(6));
+        let subject = parse_commit_message(&long).unwrap().subject;
+
+        assert!(subject.chars().count() <= MAX_SUBJECT_CHARS);
+        assert!(subject.ends_with("ОбработкаЗаполнения"));
+    }
+
+    #[test]
+    fn prompt_embeds_the_diff() {
+        let prompt = build_commit_message_prompt("+Сообщить(1);\n");
+        assert!(prompt.contains("```diff\n+Сообщить(1);\n```"));
+        assert!(prompt.contains("at most 72 characters"));
+    }
+
+    #[tokio::test]
+    async fn blank_diff_is_empty_input() {
+        let error = suggest_commit_message(" \n\t".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AiClientError::EmptyInput { .. }));
+    }
+}
@@ -0,0 +1,133 @@
+//! Side-by-side answers of two profiles to the same conversation.
+
+use serde::Serialize;
+
+use super::client::{extract_bsl_code, silent_completion_with_meta};
+use super::error::AiClientError;
+use super::models::ApiMessage;
+use super::tokenizer::estimate_text_tokens;
+use crate::llm_profiles::LLMProfile;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelComparison {
+    pub model_a: String,
+    pub model_b: String,
+    pub response_a: String,
+    pub response_b: String,
+    pub latency_a_ms: u64,
+    pub latency_b_ms: u64,
+    pub tokens_a: u32,
+    pub tokens_b: u32,
+    /// Unified diff of the BSL blocks, or of the whole answers when neither has code
+    pub diff: String,
+}
+
+/// One answer of the comparison
+#[derive(Debug, Clone)]
+pub struct TimedResponse {
+    pub model: String,
+    pub text: String,
+    pub latency_ms: u64,
+    pub tokens: u32,
+}
+
+/// BSL blocks joined by a blank line, or the whole answer when it has none
+fn comparable_text(response: &str) -> String {
+    let blocks = extract_bsl_code(response);
+    if blocks.is_empty() {
+        response.to_string()
+    } else {
+        blocks.join("\n\n")
+    }
+}
+
+pub fn build_comparison(a: TimedResponse, b: TimedResponse) -> ModelComparison {
+    ModelComparison {
+        diff: super::diff::diff_bsl_code(&comparable_text(&a.text), &comparable_text(&b.text)),
+        model_a: a.model,
+        model_b: b.model,
+        response_a: a.text,
+        response_b: b.text,
+        latency_a_ms: a.latency_ms,
+        latency_b_ms: b.latency_ms,
+        tokens_a: a.tokens,
+        tokens_b: b.tokens,
+    }
+}
+
+async fn timed_completion(
+    messages: Vec<ApiMessage>,
+    profile: &LLMProfile,
+) -> Result<TimedResponse, AiClientError> {
+    let started = std::time::Instant::now();
+    let (text, meta) = silent_completion_with_meta(messages, profile).await?;
+    Ok(TimedResponse {
+        model: profile.model.clone(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        // Providers without usage: the shared text estimate
+        tokens: meta
+            .completion_tokens
+            .unwrap_or_else(|| estimate_text_tokens(&text)),
+        text,
+    })
+}
+
+/// Sends the same messages to both profiles concurrently, without chat events.
+pub async fn compare_completions(
+    messages: Vec<ApiMessage>,
+    profile_a: &LLMProfile,
+    profile_b: &LLMProfile,
+) -> Result<ModelComparison, AiClientError> {
+    let (a, b) = tokio::join!(
+        timed_completion(messages.clone(), profile_a),
+        timed_completion(messages, profile_b)
+    );
+    Ok(build_comparison(a?, b?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(model: &str, text: &str) -> TimedResponse {
+        TimedResponse {
+            model: model.to_string(),
+            text: text.to_string(),
+            latency_ms: 100,
+            tokens: 20,
+        }
+    }
+
+    #[test]
+    fn diffs_only_the_code_blocks_when_present() {
+        let a = response(
+            "gpt-4o",
+            "Вот код:\n```bsl\nПроцедура Тест()\n\tА = 1;\nКонецПроцедуры\n```\nГотово.",
+        );
+        let b = response(
+            "claude",
+            "Исправил:\n```bsl\nПроцедура Тест()\n\tА = 2;\nКонецПроцедуры\n```",
+        );
+        let comparison = build_comparison(a, b);
+
+        assert_eq!(comparison.model_a, "gpt-4o");
+        assert!(comparison.diff.contains("-\tА = 1;"));
+        assert!(comparison.diff.contains("+\tА = 2;"));
+        assert!(!comparison.diff.contains("Готово"));
+        assert!(!comparison.diff.contains("-Процедура Тест()"));
+    }
+
+    #[test]
+    fn diffs_whole_answers_without_code_and_is_empty_when_equal() {
+        let comparison = build_comparison(
+            response("a", "Первая строка\nИспользуйте запрос\n"),
+            response("b", "Первая строка\nИспользуйте кэш\n"),
+        );
+        assert!(comparison.diff.contains("-Используйте запрос"));
+        assert!(comparison.diff.contains("+Используйте кэш"));
+        assert!(!comparison.diff.contains("-Первая строка"));
+
+        let same = build_comparison(response("a", "Ответ\n"), response("b", "Ответ\n"));
+        assert!(same.diff.is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! AI removal of the dead code found by `bsl_analysis::detect_dead_code`.
+
+use super::client::prompt_completion;
+use super::code_blocks::{extract_code_blocks, merge_code_blocks};
+use super::error::AiClientError;
+use super::models::TaskType;
+use crate::bsl_analysis::{DeadCodeFinding, DeadCodeKind};
+
+fn describe(finding: &DeadCodeFinding) -> String {
+    match finding.kind {
+        DeadCodeKind::UnusedProcedure => format!(
+            "строка {}: метод {} нигде не вызывается",
+            finding.line, finding.name
+        ),
+        DeadCodeKind::UnusedVariable => format!(
+            "строка {}: значение переменной {} присваивается, но не используется",
+            finding.line, finding.name
+        ),
+        DeadCodeKind::UnreachableCode => format!(
+            "строка {}: недостижимый код после Возврат ({})",
+            finding.line, finding.name
+        ),
+    }
+}
+
+/// Code with numbered lines and the findings the model should remove
+pub fn build_dead_code_prompt(code: &str, findings: &[DeadCodeFinding]) -> String {
+    let numbered: Vec<String> = code
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>4}: {}", i + 1, line))
+        .collect();
+    let list: Vec<String> = findings
+        .iter()
+        .enumerate()
+        .map(|(i, f)| format!("{}. {}", i + 1, describe(f)))
+        .collect();
+    format!(
+        "Анализ нашёл в модуле 1С мёртвый код:\n{}\n\n\
+         Удали его и верни весь модуль целиком одним блоком ```bsl без номеров строк. \
+         Если метод может вызываться извне (обработчик события формы, команда, оповещение), \
+         оставь его и добавь над ним комментарий. Остальной код не меняй.\n\n\
+         Код модуля:\n```bsl\n{}\n```",
+        list.join("\n"),
+        numbered.join("\n")
+    )
+}
+
+/// Asks the model to remove the findings and returns the cleaned module
+pub async fn remove_dead_code(
+    code: String,
+    findings: Vec<DeadCodeFinding>,
+) -> Result<String, AiClientError> {
+    if findings.is_empty() {
+        return Err("Мёртвый код не найден — удалять нечего".into());
+    }
+    let answer =
+        prompt_completion(build_dead_code_prompt(&code, &findings), TaskType::Refactor).await?;
+    let blocks = extract_code_blocks(&answer);
+    if blocks.is_empty() {
+        return Err(AiClientError::parse("В ответе модели нет блоков кода"));
+    }
+    Ok(merge_code_blocks(&blocks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_describes_each_finding() {
+        let code = "Процедура А()\n\tВозврат;\n\tБ();\nКонецПроцедуры";
+        let findings = crate::bsl_analysis::detect_dead_code(code);
+        let prompt = build_dead_code_prompt(code, &findings);
+
+        assert!(prompt.contains("1. строка 1: метод А нигде не вызывается"));
+        assert!(prompt.contains("2. строка 3: недостижимый код после Возврат (Б())"));
+        assert!(prompt.contains("   3: \tБ();"));
+    }
+}
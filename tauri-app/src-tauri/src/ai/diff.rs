@@ -0,0 +1,241 @@
+//! Unified diffs between the original and AI-suggested BSL code, and applying them back.
+
+use std::fmt;
+
+use similar::TextDiff;
+
+/// Lines of context around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Why a unified diff could not be applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffApplyError {
+    /// `@@ -a,b +c,d @@` header that cannot be parsed
+    InvalidHunkHeader(String),
+    /// Line outside a hunk or without a ` `/`-`/`+` prefix (1-based line of the diff)
+    UnexpectedLine { line: usize, text: String },
+    /// Context or removed line does not match the original (1-based line of the original)
+    ContextMismatch {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// Hunk points before the previous hunk or past the end of the original
+    OutOfRange { line: usize },
+}
+
+impl fmt::Display for DiffApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffApplyError::InvalidHunkHeader(header) => {
+                write!(f, "Некорректный заголовок блока diff: {}", header)
+            }
+            DiffApplyError::UnexpectedLine { line, text } => {
+                write!(f, "Неожиданная строка {} в diff: {}", line, text)
+            }
+            DiffApplyError::ContextMismatch {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Diff не совпадает с исходным кодом в строке {}: ожидалось «{}», найдено «{}»",
+                line,
+                expected.trim_end(),
+                found.trim_end()
+            ),
+            DiffApplyError::OutOfRange { line } => {
+                write!(
+                    f,
+                    "Блок diff ссылается на строку {} вне исходного кода",
+                    line
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiffApplyError {}
+
+/// Unified diff (`---`/`+++`, 3 lines of context); empty when the texts are equal.
+pub fn diff_bsl_code(original: &str, suggested: &str) -> String {
+    if original == suggested {
+        return String::new();
+    }
+    TextDiff::from_lines(original, suggested)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header("original.bsl", "suggested.bsl")
+        .to_string()
+}
+
+struct Hunk {
+    old_start: usize,
+    old_len: usize,
+    /// (prefix, line including its terminator)
+    lines: Vec<(char, String)>,
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn parse_hunk_header(header: &str) -> Result<(usize, usize), DiffApplyError> {
+    let invalid = || DiffApplyError::InvalidHunkHeader(header.trim_end().to_string());
+    let old = header
+        .strip_prefix("@@ -")
+        .and_then(|rest| rest.split_whitespace().next())
+        .ok_or_else(invalid)?;
+    parse_range(old).ok_or_else(invalid)
+}
+
+fn parse_hunks(diff: &str) -> Result<Vec<Hunk>, DiffApplyError> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for (idx, raw) in diff.split_inclusive('\n').enumerate() {
+        if raw.starts_with("@@") {
+            let (old_start, old_len) = parse_hunk_header(raw)?;
+            hunks.push(Hunk {
+                old_start,
+                old_len,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // File headers before the first hunk
+            if raw.starts_with("---") || raw.starts_with("+++") || raw.trim().is_empty() {
+                continue;
+            }
+            return Err(DiffApplyError::UnexpectedLine {
+                line: idx + 1,
+                text: raw.trim_end().to_string(),
+            });
+        };
+        if raw.starts_with('\\') {
+            // "\ No newline at end of file" refers to the previous line
+            if let Some((_, text)) = hunk.lines.last_mut() {
+                if text.ends_with('\n') {
+                    text.pop();
+                }
+            }
+            continue;
+        }
+        let mut chars = raw.chars();
+        match chars.next() {
+            Some(prefix @ (' ' | '-' | '+')) => {
+                hunk.lines.push((prefix, chars.as_str().to_string()))
+            }
+            _ => {
+                return Err(DiffApplyError::UnexpectedLine {
+                    line: idx + 1,
+                    text: raw.trim_end().to_string(),
+                })
+            }
+        }
+    }
+    Ok(hunks)
+}
+
+/// Applies a unified diff produced by [`diff_bsl_code`] (or any tool with exact context).
+pub fn apply_bsl_diff(original: &str, diff: &str) -> Result<String, DiffApplyError> {
+    let source: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut result = String::with_capacity(original.len());
+    let mut pos = 0usize;
+
+    for hunk in parse_hunks(diff)? {
+        // A pure insertion (`-n,0`) goes after line n; otherwise the hunk starts at line n
+        let start = if hunk.old_len == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        if start < pos || start > source.len() {
+            return Err(DiffApplyError::OutOfRange {
+                line: hunk.old_start,
+            });
+        }
+        source[pos..start].iter().for_each(|l| result.push_str(l));
+        pos = start;
+
+        for (prefix, text) in hunk.lines {
+            if prefix == '+' {
+                result.push_str(&text);
+                continue;
+            }
+            let found = source
+                .get(pos)
+                .ok_or(DiffApplyError::OutOfRange { line: pos + 1 })?;
+            if *found != text {
+                return Err(DiffApplyError::ContextMismatch {
+                    line: pos + 1,
+                    expected: text,
+                    found: found.to_string(),
+                });
+            }
+            if prefix == ' ' {
+                result.push_str(found);
+            }
+            pos += 1;
+        }
+    }
+    source[pos..].iter().for_each(|l| result.push_str(l));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "Процедура Тест()\n\tА = 1;\n\tБ = 2;\n\tВ = 3;\n\tГ = 4;\n\tД = 5;\n\tЕ = 6;\n\tЖ = 7;\n\tЗ = 8;\nКонецПроцедуры\n";
+
+    #[test]
+    fn identical_code_gives_empty_diff_and_whitespace_does_not() {
+        assert_eq!(diff_bsl_code(ORIGINAL, ORIGINAL), "");
+
+        let reindented = ORIGINAL.replace("\tБ = 2;", "    Б = 2;");
+        let diff = diff_bsl_code(ORIGINAL, &reindented);
+        assert!(!diff.is_empty());
+        assert!(diff.starts_with("--- original.bsl\n+++ suggested.bsl\n@@ "));
+        assert!(diff.contains("-\tБ = 2;\n+    Б = 2;\n"));
+    }
+
+    #[test]
+    fn applying_diff_reproduces_suggested_code() {
+        let cases = [
+            ORIGINAL.replace("\tВ = 3;\n", "\tВ = 30;\n\tВ2 = 31;\n"),
+            ORIGINAL.replace("\tЖ = 7;\n", ""),
+            format!("// Комментарий\n{}", ORIGINAL),
+            ORIGINAL.trim_end().to_string(),
+            ORIGINAL.replace('\n', "\r\n"),
+            String::new(),
+        ];
+        for suggested in cases {
+            let diff = diff_bsl_code(ORIGINAL, &suggested);
+            assert_eq!(
+                apply_bsl_diff(ORIGINAL, &diff).unwrap(),
+                suggested,
+                "{}",
+                diff
+            );
+        }
+        assert_eq!(apply_bsl_diff(ORIGINAL, "").unwrap(), ORIGINAL);
+    }
+
+    #[test]
+    fn mismatched_context_is_reported_with_line() {
+        let diff = diff_bsl_code(ORIGINAL, &ORIGINAL.replace("\tД = 5;", "\tД = 50;"));
+        let changed = ORIGINAL.replace("\tГ = 4;", "\tГ = 40;");
+
+        match apply_bsl_diff(&changed, &diff) {
+            Err(DiffApplyError::ContextMismatch { line, .. }) => assert_eq!(line, 5),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            apply_bsl_diff(ORIGINAL, "@@ broken @@\n"),
+            Err(DiffApplyError::InvalidHunkHeader(_))
+        ));
+    }
+}
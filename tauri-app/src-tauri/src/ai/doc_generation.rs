@@ -0,0 +1,66 @@
+//! Documentation comments (`// Параметры:`, `// Возвращаемое значение:`) for the
+//! procedures and functions of a module, returned as a patch of added lines.
+
+use super::client::prompt_completion;
+use super::code_blocks::{extract_code_blocks, merge_code_blocks};
+use super::diff::diff_bsl_code;
+use super::error::AiClientError;
+use super::models::TaskType;
+use crate::bsl_analysis::{extract_bsl_docs, extract_bsl_signatures, insert_bsl_docs};
+
+pub fn build_docs_prompt(code: &str) -> String {
+    format!(
+        "Добавь комментарии-описания по стандарту 1С перед каждой процедурой и функцией модуля, \
+         у которой их нет: краткое описание, затем разделы\n\
+         // Параметры:\n//  ИмяПараметра - Тип - описание\n\
+         и для функций\n// Возвращаемое значение:\n//  Тип - описание\n\
+         Комментарий располагается непосредственно над директивой компиляции или ключевым \
+         словом Процедура/Функция. Код модуля не изменяй. Верни весь модуль одним блоком ```bsl.\n\n\
+         Код модуля:\n```bsl\n{}\n```",
+        code.trim_end()
+    )
+}
+
+/// Unified diff that only adds the documentation from `documented` above the undocumented
+/// routines of `original`; other changes the model made to the code are ignored
+pub fn docs_patch(original: &str, documented: &str) -> String {
+    let docs = extract_bsl_docs(documented);
+    diff_bsl_code(original, &insert_bsl_docs(original, &docs))
+}
+
+/// Asks the model to document the module and returns a patch for `apply_bsl_diff`;
+/// empty when every routine is already documented
+pub async fn generate_bsl_docs(code: String) -> Result<String, AiClientError> {
+    if extract_bsl_signatures(&code).is_empty() {
+        return Err("В коде нет процедур и функций для документирования".into());
+    }
+    let answer = prompt_completion(build_docs_prompt(&code), TaskType::Document).await?;
+    let blocks = extract_code_blocks(&answer);
+    if blocks.is_empty() {
+        return Err(AiClientError::parse("В ответе модели нет блоков кода"));
+    }
+    Ok(docs_patch(&code, &merge_code_blocks(&blocks)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_only_adds_documentation() {
+        let original = "Функция Удвоить(Число) Экспорт\n\tВозврат Число * 2;\nКонецФункции\n";
+        let documented = "// Удваивает число.\n//\n// Параметры:\n//  Число - Число - исходное значение.\n//\n\
+                          // Возвращаемое значение:\n//  Число - удвоенное значение.\n//\n\
+                          Функция Удвоить(Знач Число) Экспорт\n\tВозврат 2 * Число;\nКонецФункции\n";
+        let patch = docs_patch(original, documented);
+
+        assert!(patch.contains("+// Параметры:\n"));
+        assert!(patch.contains("+// Возвращаемое значение:\n"));
+        assert!(!patch
+            .lines()
+            .any(|l| l.starts_with('-') && !l.starts_with("---")));
+        let applied = crate::ai::diff::apply_bsl_diff(original, &patch).unwrap();
+        assert!(applied.ends_with(original));
+        assert!(docs_patch(&applied, documented).is_empty());
+    }
+}
@@ -0,0 +1,715 @@
+//! Error of a chat completion. Reaches the frontend as `{ kind, ... }`, so the UI can
+//! tell "check the API key" from "provider is down" without parsing the text.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::llm_profiles::{LLMProfile, LLMProvider};
+
+/// Longest provider text kept in `describe_http_error`
+const MAX_ERROR_TEXT_CHARS: usize = 300;
+
+lazy_static! {
+    /// "maximum context length is 128000 tokens" (OpenAI, OpenRouter) or
+    /// "prompt is too long: 210000 tokens > 200000 maximum" (Anthropic)
+    static ref CONTEXT_LIMIT: Regex =
+        Regex::new(r"(?i)maximum context length is (\d+)|> *(\d+) maximum").unwrap();
+    static ref HTML_TITLE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiClientError {
+    NoActiveProfile,
+    /// Profile missing, disabled or unusable as configured (key, proxy, model capabilities)
+    InvalidProfile {
+        message: String,
+    },
+    /// Connection failed before any response
+    Network {
+        message: String,
+    },
+    /// Provider host did not accept a connection in the pre-check (offline, VPN down)
+    Unreachable {
+        host: String,
+        message: String,
+    },
+    /// Provider answered with an error status
+    Http {
+        status: u16,
+        /// `error.code` / `error.type` of the provider's JSON body
+        code: Option<String>,
+        message: String,
+        provider_type: LLMProvider,
+        /// Body as received, for the debug view
+        raw_body: String,
+    },
+    /// Profile failed repeatedly and is cooling down; requests fail fast until `retry_at`
+    CircuitOpen {
+        profile_id: String,
+        retry_at: chrono::DateTime<chrono::Utc>,
+        message: String,
+    },
+    /// Provider rejected the key (401/403); the UI offers to open the profile's key field
+    AuthFailed {
+        status: u16,
+        profile_id: String,
+        profile_name: String,
+        provider_type: LLMProvider,
+        /// What to check, specific to the provider and the key
+        hint: String,
+        message: String,
+        raw_body: String,
+    },
+    /// Stream broke after the response started
+    Stream {
+        message: String,
+    },
+    /// Stream kept breaking and every resume attempt was used; the text received so far
+    /// is kept so the caller can still show it
+    MaxReconnectsExceeded {
+        attempts: u32,
+        message: String,
+        partial_response: String,
+    },
+    Timeout {
+        message: String,
+        /// Idle limit that was hit, when known
+        secs: Option<u64>,
+    },
+    Cancelled,
+    /// Response body could not be understood
+    Parse {
+        message: String,
+    },
+    /// Nothing to send: the input the request is built from is blank
+    EmptyInput {
+        message: String,
+    },
+    /// Failures of helpers that report plain text (tools, CLI providers)
+    Other {
+        message: String,
+    },
+}
+
+impl AiClientError {
+    pub fn invalid_profile(message: impl Into<String>) -> Self {
+        Self::InvalidProfile {
+            message: message.into(),
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::Parse {
+            message: message.into(),
+        }
+    }
+
+    pub fn empty_input(message: impl Into<String>) -> Self {
+        Self::EmptyInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn stream(message: impl Into<String>) -> Self {
+        Self::Stream {
+            message: message.into(),
+        }
+    }
+
+    /// Error status with the raw body; `message` is what the user sees
+    pub fn http(status: u16, body: &str, provider: &LLMProvider, message: String) -> Self {
+        Self::Http {
+            status,
+            code: provider_error_code(body),
+            message,
+            provider_type: provider.clone(),
+            raw_body: body.to_string(),
+        }
+    }
+
+    /// 401/403 of the profile's provider; `api_key` is the key that was sent
+    pub fn auth_failed(status: u16, body: &str, profile: &LLMProfile, api_key: &str) -> Self {
+        Self::auth_rejected(
+            status,
+            body,
+            profile,
+            auth_hint(status, body, profile, api_key),
+        )
+    }
+
+    /// 401/403 with a provider-specific `hint` (OAuth and session tokens instead of API keys)
+    pub fn auth_rejected(status: u16, body: &str, profile: &LLMProfile, hint: String) -> Self {
+        Self::AuthFailed {
+            status,
+            profile_id: profile.id.clone(),
+            profile_name: profile.name.clone(),
+            provider_type: profile.provider.clone(),
+            message: format!(
+                "Профиль «{}»: провайдер отклонил ключ API (HTTP {}) — {}",
+                profile.name, status, hint
+            ),
+            hint,
+            raw_body: body.to_string(),
+        }
+    }
+
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(self, Self::AuthFailed { .. })
+    }
+
+    /// The `kind` tag the frontend sees
+    pub fn kind(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value["kind"].as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// Status of the provider's response, when it answered with an error
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Self::Http { status, .. } | Self::AuthFailed { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed later or on another profile
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network { .. }
+            | Self::Unreachable { .. }
+            | Self::CircuitOpen { .. }
+            | Self::Stream { .. }
+            | Self::MaxReconnectsExceeded { .. }
+            | Self::Timeout { .. } => true,
+            Self::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Answer text received before the failure
+    pub fn partial_text(&self) -> Option<&str> {
+        match self {
+            Self::MaxReconnectsExceeded {
+                partial_response, ..
+            } => Some(partial_response),
+            _ => None,
+        }
+    }
+
+    /// Sends `auth-error` for an auth failure; other errors are ignored
+    pub fn emit_auth_error<R: tauri::Runtime>(&self, app_handle: &tauri::AppHandle<R>) {
+        if let Self::AuthFailed {
+            status,
+            profile_id,
+            profile_name,
+            hint,
+            ..
+        } = self
+        {
+            let _ = app_handle.emit(
+                "auth-error",
+                serde_json::json!({
+                    "profile_id": profile_id,
+                    "profile_name": profile_name,
+                    "status": status,
+                    "hint": hint,
+                }),
+            );
+        }
+    }
+
+    /// Failure of the request itself, with the attempts prefix used in the chat
+    pub fn request_failed(attempts: u32, error: &reqwest::Error) -> Self {
+        let message = format!(
+            "Request failed after {} attempts: {}",
+            attempts,
+            crate::http_client::describe_request_error(error)
+        );
+        if error.is_timeout() {
+            Self::Timeout {
+                message,
+                secs: None,
+            }
+        } else {
+            Self::Network { message }
+        }
+    }
+}
+
+pub fn is_auth_status(status: u16) -> bool {
+    matches!(status, 401 | 403)
+}
+
+/// Page where the provider's keys are created
+fn api_keys_page(provider: &LLMProvider) -> Option<&'static str> {
+    match provider {
+        LLMProvider::OpenAI => Some("https://platform.openai.com/api-keys"),
+        LLMProvider::Anthropic => Some("https://console.anthropic.com/settings/keys"),
+        LLMProvider::OpenRouter => Some("https://openrouter.ai/keys"),
+        LLMProvider::Google => Some("https://aistudio.google.com/apikey"),
+        LLMProvider::DeepSeek => Some("https://platform.deepseek.com/api_keys"),
+        LLMProvider::Groq => Some("https://console.groq.com/keys"),
+        LLMProvider::Mistral => Some("https://console.mistral.ai/api-keys"),
+        LLMProvider::XAI => Some("https://console.x.ai"),
+        _ => None,
+    }
+}
+
+/// Most likely cause of a rejected key: no key, a key of another provider, an Azure
+/// endpoint (key goes in `api-key`, not `Authorization`), or a revoked key
+fn auth_hint(status: u16, body: &str, profile: &LLMProfile, api_key: &str) -> String {
+    if api_key.trim().is_empty() || body.contains("No cookie auth credentials found") {
+        return "ключ не передан: укажите API key в профиле и сохраните его заново".to_string();
+    }
+    if profile.get_base_url().contains(".azure.com") {
+        return "Azure OpenAI ждёт ключ в заголовке api-key — подключайте его через шлюз, \
+                совместимый с OpenAI, или проверьте, что выбран верный провайдер"
+            .to_string();
+    }
+    if let Some(warning) = crate::llm_profiles::check_api_key(&profile.provider, api_key)
+        .ok()
+        .and_then(|check| check.warning)
+    {
+        return format!("{} — возможно, в профиле выбран не тот провайдер", warning);
+    }
+    let page = api_keys_page(&profile.provider)
+        .map(|url| format!(" (ключи: {})", url))
+        .unwrap_or_default();
+    if status == 403 {
+        format!(
+            "ключ принят, но доступа нет: проверьте права ключа, баланс и доступность модели {}{}",
+            profile.model, page
+        )
+    } else {
+        format!("ключ неверен, отозван или истёк — создайте новый{}", page)
+    }
+}
+
+/// `error.code` (string or number) or `error.type` of an OpenAI-style error body
+fn provider_error_code(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error")?;
+    match error.get("code") {
+        Some(serde_json::Value::String(code)) => Some(code.clone()),
+        Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+        _ => error
+            .get("type")
+            .and_then(|t| t.as_str())
+            .map(str::to_string),
+    }
+}
+
+/// Fields of the `error` object shared by OpenAI, Anthropic and OpenRouter bodies
+#[derive(Debug, Default)]
+struct ProviderErrorBody {
+    message: String,
+    /// `error.type`
+    kind: Option<String>,
+    /// `error.code` when it is a string; OpenRouter repeats the HTTP status there
+    code: Option<String>,
+    param: Option<String>,
+    /// OpenRouter `error.metadata`: upstream provider and its own error text
+    provider_name: Option<String>,
+    raw: Option<String>,
+}
+
+fn parse_error_body(body: &str) -> Option<ProviderErrorBody> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error")?;
+    if let Some(text) = error.as_str() {
+        return Some(ProviderErrorBody {
+            message: text.to_string(),
+            ..ProviderErrorBody::default()
+        });
+    }
+    let text = |v: Option<&serde_json::Value>| {
+        v.and_then(|f| f.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let metadata = error.get("metadata");
+    Some(ProviderErrorBody {
+        message: text(error.get("message")).unwrap_or_default(),
+        kind: text(error.get("type")),
+        code: text(error.get("code")),
+        param: text(error.get("param")),
+        provider_name: text(metadata.and_then(|m| m.get("provider_name"))),
+        raw: text(metadata.and_then(|m| m.get("raw"))),
+    })
+}
+
+fn clip(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_ERROR_TEXT_CHARS {
+        return text;
+    }
+    let clipped: String = text.chars().take(MAX_ERROR_TEXT_CHARS).collect();
+    format!("{}…", clipped)
+}
+
+fn is_context_overflow(error: &ProviderErrorBody) -> bool {
+    let message = error.message.to_lowercase();
+    error.code.as_deref() == Some("context_length_exceeded")
+        || message.contains("maximum context length")
+        || message.contains("prompt is too long")
+}
+
+/// Short readable text of a provider error: `code: hint` for well-known codes, the
+/// provider's own message otherwise, with OpenRouter's upstream error appended.
+/// An HTML page (usually a corporate proxy login) is reported as such.
+pub fn describe_http_error(status: u16, body: &str, model: &str) -> String {
+    let body = body.trim();
+    if body.starts_with('<') {
+        let title = HTML_TITLE
+            .captures(body)
+            .map(|c| clip(&c[1]))
+            .filter(|t| !t.is_empty())
+            .map(|t| format!(" «{}»", t))
+            .unwrap_or_default();
+        return format!(
+            "HTTP {}: вместо ответа API получена HTML-страница{} — возможно, запрос перехватил прокси или требуется вход в сеть",
+            status, title
+        );
+    }
+    let Some(error) = parse_error_body(body) else {
+        if body.is_empty() {
+            return format!("HTTP {}: пустой ответ сервера", status);
+        }
+        return format!("HTTP {}: {}", status, clip(body));
+    };
+
+    if is_context_overflow(&error) {
+        let limit = CONTEXT_LIMIT
+            .captures(&error.message)
+            .and_then(|c| c.get(1).or_else(|| c.get(2)))
+            .map(|m| format!(", лимит {}", m.as_str()))
+            .unwrap_or_default();
+        return format!(
+            "context_length_exceeded: уменьшите размер запроса (модель: {}{})",
+            model, limit
+        );
+    }
+
+    let label = error
+        .code
+        .clone()
+        .or_else(|| error.kind.clone())
+        .unwrap_or_else(|| format!("HTTP {}", status));
+    let hint = match label.as_str() {
+        "invalid_api_key" | "authentication_error" => {
+            Some("проверьте API key в профиле".to_string())
+        }
+        "model_not_found" | "not_found_error" => {
+            Some(format!("модель {} недоступна у провайдера", model))
+        }
+        "insufficient_quota" => Some("недостаточно средств на счёте провайдера".to_string()),
+        "rate_limit_exceeded" | "rate_limit_error" => {
+            Some("превышен лимит запросов, повторите позже".to_string())
+        }
+        "overloaded_error" => Some("провайдер перегружен, повторите позже".to_string()),
+        _ => None,
+    };
+    let mut text = match hint {
+        Some(hint) => format!("{}: {}", label, hint),
+        None if error.message.is_empty() => label,
+        None => format!("{}: {}", label, clip(&error.message)),
+    };
+    if let Some(param) = &error.param {
+        text.push_str(&format!(" (параметр: {})", param));
+    }
+    if let Some(raw) = &error.raw {
+        // `raw` is often the upstream provider's own JSON error
+        let upstream = parse_error_body(raw)
+            .map(|e| e.message)
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| raw.clone());
+        text.push_str(&format!(
+            " — {}: {}",
+            error.provider_name.as_deref().unwrap_or("провайдер"),
+            clip(&upstream)
+        ));
+    }
+    text
+}
+
+impl std::fmt::Display for AiClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoActiveProfile => f.write_str("Нет активного LLM профиля"),
+            Self::Cancelled => f.write_str("Cancelled"),
+            Self::InvalidProfile { message }
+            | Self::Network { message }
+            | Self::Unreachable { message, .. }
+            | Self::CircuitOpen { message, .. }
+            | Self::Http { message, .. }
+            | Self::AuthFailed { message, .. }
+            | Self::Stream { message }
+            | Self::MaxReconnectsExceeded { message, .. }
+            | Self::Timeout { message, .. }
+            | Self::Parse { message }
+            | Self::EmptyInput { message }
+            | Self::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for AiClientError {}
+
+impl From<reqwest::Error> for AiClientError {
+    fn from(e: reqwest::Error) -> Self {
+        let message = crate::http_client::describe_request_error(&e);
+        if e.is_timeout() {
+            Self::Timeout {
+                message,
+                secs: None,
+            }
+        } else if e.is_decode() {
+            Self::Parse { message }
+        } else if e.is_body() {
+            Self::Stream { message }
+        } else {
+            Self::Network { message }
+        }
+    }
+}
+
+impl From<serde_json::Error> for AiClientError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::parse(e.to_string())
+    }
+}
+
+impl From<String> for AiClientError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<&str> for AiClientError {
+    fn from(message: &str) -> Self {
+        Self::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Callers that still report plain text keep using `?`
+impl From<AiClientError> for String {
+    fn from(e: AiClientError) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_error_keeps_status_and_provider_code() {
+        let body = r#"{"error": {"message": "Incorrect API key", "type": "invalid_request_error", "code": "invalid_api_key"}}"#;
+        let error = AiClientError::http(
+            401,
+            body,
+            &LLMProvider::OpenAI,
+            format!("API error 401: {}", body),
+        );
+        assert!(matches!(
+            &error,
+            AiClientError::Http { status: 401, code: Some(code), .. } if code == "invalid_api_key"
+        ));
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "http");
+        assert_eq!(json["provider_type"], "OpenAI");
+
+        let overloaded = AiClientError::http(
+            529,
+            r#"{"error": {"type": "overloaded_error"}}"#,
+            &LLMProvider::Anthropic,
+            "overloaded".to_string(),
+        );
+        assert!(matches!(
+            &overloaded,
+            AiClientError::Http { code: Some(code), .. } if code == "overloaded_error"
+        ));
+        assert!(matches!(
+            AiClientError::http(500, "<html>", &LLMProvider::Custom, String::new()),
+            AiClientError::Http { code: None, .. }
+        ));
+    }
+
+    #[test]
+    fn conversions_pick_the_variant() {
+        let parse: AiClientError = serde_json::from_str::<serde_json::Value>("{")
+            .unwrap_err()
+            .into();
+        assert!(matches!(parse, AiClientError::Parse { .. }));
+
+        let other: AiClientError = "Инструмент не найден".into();
+        assert_eq!(other.to_string(), "Инструмент не найден");
+        assert_eq!(
+            String::from(AiClientError::NoActiveProfile),
+            "Нет активного LLM профиля"
+        );
+        assert_eq!(
+            serde_json::to_value(AiClientError::Cancelled).unwrap(),
+            serde_json::json!({ "kind": "cancelled" })
+        );
+    }
+
+    #[tokio::test]
+    async fn reqwest_errors_map_to_network_and_timeout() {
+        // Bound and immediately dropped: nothing listens on the port
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let error: AiClientError = reqwest::get(&closed_url).await.unwrap_err().into();
+        assert!(
+            matches!(error, AiClientError::Network { .. }),
+            "{:?}",
+            error
+        );
+
+        // Accepts the connection but never answers
+        let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let silent_url = format!("http://{}", silent.local_addr().unwrap());
+        let error = reqwest::Client::new()
+            .get(&silent_url)
+            .timeout(std::time::Duration::from_millis(200))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            AiClientError::request_failed(3, &error),
+            AiClientError::Timeout { .. }
+        ));
+        let error: AiClientError = error.into();
+        assert!(
+            matches!(error, AiClientError::Timeout { .. }),
+            "{:?}",
+            error
+        );
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn openai_error_bodies_become_short_messages() {
+        let body = r#"{"error": {"message": "This model's maximum context length is 128000 tokens. However, your messages resulted in 130512 tokens.", "type": "invalid_request_error", "param": "messages", "code": "context_length_exceeded"}}"#;
+        assert_eq!(
+            describe_http_error(400, body, "gpt-4o"),
+            "context_length_exceeded: уменьшите размер запроса (модель: gpt-4o, лимит 128000)"
+        );
+
+        let body = r#"{"error": {"message": "Invalid value for 'temperature'", "type": "invalid_request_error", "param": "temperature", "code": null}}"#;
+        assert_eq!(
+            describe_http_error(400, body, "gpt-4o"),
+            "invalid_request_error: Invalid value for 'temperature' (параметр: temperature)"
+        );
+
+        let body = r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "code": "invalid_api_key"}}"#;
+        assert_eq!(
+            describe_http_error(401, body, "gpt-4o"),
+            "invalid_api_key: проверьте API key в профиле"
+        );
+    }
+
+    #[test]
+    fn openrouter_metadata_names_the_upstream_error() {
+        let body = r#"{"error": {"message": "Provider returned error", "code": 400, "metadata": {"raw": "{\"error\":{\"message\":\"Unsupported parameter: top_k\"}}", "provider_name": "Azure"}}}"#;
+        assert_eq!(
+            describe_http_error(400, body, "openai/gpt-4o"),
+            "HTTP 400: Provider returned error — Azure: Unsupported parameter: top_k"
+        );
+
+        let body = r#"{"error": {"message": "This endpoint's maximum context length is 65536 tokens. However, you requested about 70000 tokens", "code": 400}}"#;
+        assert_eq!(
+            describe_http_error(400, body, "deepseek/deepseek-chat"),
+            "context_length_exceeded: уменьшите размер запроса (модель: deepseek/deepseek-chat, лимит 65536)"
+        );
+    }
+
+    #[test]
+    fn anthropic_error_bodies_use_the_type() {
+        let body = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "prompt is too long: 210000 tokens > 200000 maximum"}}"#;
+        assert_eq!(
+            describe_http_error(400, body, "claude-sonnet-4"),
+            "context_length_exceeded: уменьшите размер запроса (модель: claude-sonnet-4, лимит 200000)"
+        );
+
+        let body =
+            r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        assert_eq!(
+            describe_http_error(529, body, "claude-sonnet-4"),
+            "overloaded_error: провайдер перегружен, повторите позже"
+        );
+    }
+
+    #[test]
+    fn html_and_plain_bodies_are_not_dumped() {
+        let page = "<!DOCTYPE html>\n<html><head><title>\n  Вход в сеть\n</title></head><body>...</body></html>";
+        assert_eq!(
+            describe_http_error(407, page, "gpt-4o"),
+            "HTTP 407: вместо ответа API получена HTML-страница «Вход в сеть» — возможно, запрос перехватил прокси или требуется вход в сеть"
+        );
+        assert_eq!(
+            describe_http_error(502, "Bad Gateway", "gpt-4o"),
+            "HTTP 502: Bad Gateway"
+        );
+        assert_eq!(
+            describe_http_error(500, "", "gpt-4o"),
+            "HTTP 500: пустой ответ сервера"
+        );
+
+        let error = AiClientError::http(
+            407,
+            page,
+            &LLMProvider::OpenAI,
+            describe_http_error(407, page, "gpt-4o"),
+        );
+        assert!(matches!(error, AiClientError::Http { raw_body, .. } if raw_body == page));
+    }
+
+    #[test]
+    fn auth_failures_name_the_profile_and_the_likely_cause() {
+        let profile = LLMProfile {
+            id: "p1".to_string(),
+            name: "Рабочий".to_string(),
+            provider: LLMProvider::OpenRouter,
+            ..LLMProfile::default_profile()
+        };
+
+        let error = AiClientError::auth_failed(401, "{}", &profile, "sk-proj-0123456789abcdefghij");
+        let AiClientError::AuthFailed { hint, message, .. } = &error else {
+            panic!("{:?}", error);
+        };
+        assert!(message.starts_with("Профиль «Рабочий»"), "{}", message);
+        assert!(hint.contains("sk-or-v1-"), "{}", hint);
+        assert!(error.is_auth_failure());
+        assert_eq!(serde_json::to_value(&error).unwrap()["kind"], "auth_failed");
+
+        let hint = auth_hint(401, "{}", &profile, "sk-or-v1-0123456789abcdefghij");
+        assert!(hint.contains("https://openrouter.ai/keys"), "{}", hint);
+        let hint = auth_hint(403, "{}", &profile, "sk-or-v1-0123456789abcdefghij");
+        assert!(hint.starts_with("ключ принят, но доступа нет"), "{}", hint);
+        let hint = auth_hint(
+            401,
+            r#"{"error": {"message": "No cookie auth credentials found", "code": 401}}"#,
+            &profile,
+            "sk-or-v1-0123456789abcdefghij",
+        );
+        assert!(hint.starts_with("ключ не передан"), "{}", hint);
+
+        let azure = LLMProfile {
+            provider: LLMProvider::Custom,
+            base_url: Some("https://corp.openai.azure.com/openai/deployments/gpt4o".to_string()),
+            ..profile.clone()
+        };
+        assert!(auth_hint(401, "", &azure, "0123456789abcdef0123").contains("api-key"));
+        assert!(is_auth_status(403) && !is_auth_status(404));
+    }
+}
@@ -0,0 +1,180 @@
+//! Few-shot examples from `examples.json` in the app data directory, injected after
+//! the system prompt and pinned context (so the cached prefix stays stable) and before
+//! the conversation.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::models::{ApiMessage, TaskType};
+use crate::settings::get_settings_dir;
+
+pub const DEFAULT_FEW_SHOT_K: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FewShotExample {
+    /// Assigned on add
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub task_type: TaskType,
+    pub user_text: String,
+    pub assistant_text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FewShotStore {
+    /// Examples injected per request; 0 disables injection
+    #[serde(default = "default_k")]
+    pub k: u32,
+    #[serde(default)]
+    pub examples: Vec<FewShotExample>,
+}
+
+fn default_k() -> u32 {
+    DEFAULT_FEW_SHOT_K
+}
+
+impl Default for FewShotStore {
+    fn default() -> Self {
+        Self {
+            k: DEFAULT_FEW_SHOT_K,
+            examples: Vec::new(),
+        }
+    }
+}
+
+fn get_examples_file() -> PathBuf {
+    get_settings_dir().join("examples.json")
+}
+
+/// Load examples; a missing or broken file yields an empty store.
+pub fn load_few_shot_store() -> FewShotStore {
+    let path = get_examples_file();
+    if !path.exists() {
+        return FewShotStore::default();
+    }
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            crate::app_log!("[FewShot] Failed to parse {:?}: {}", path, e);
+            FewShotStore::default()
+        }),
+        Err(_) => FewShotStore::default(),
+    }
+}
+
+pub fn save_few_shot_store(store: &FewShotStore) -> Result<(), String> {
+    fs::create_dir_all(get_settings_dir()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    fs::write(get_examples_file(), content).map_err(|e| e.to_string())
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Top `k` examples of the request's task type, ranked by keyword overlap with `query`.
+/// Free-form requests get none: an example of another task only misleads the model.
+pub fn select_examples<'a>(
+    examples: &'a [FewShotExample],
+    task_type: TaskType,
+    query: &str,
+    k: u32,
+) -> Vec<&'a FewShotExample> {
+    if k == 0 || task_type == TaskType::FreeForm {
+        return Vec::new();
+    }
+    let query_words = keywords(query);
+    let overlap = |e: &FewShotExample| keywords(&e.user_text).intersection(&query_words).count();
+
+    let mut ranked: Vec<(usize, &FewShotExample)> = examples
+        .iter()
+        .filter(|e| e.task_type == task_type)
+        .map(|e| (overlap(e), e))
+        .collect();
+    // Stable sort keeps file order among equal scores
+    ranked.sort_by(|a, b| b.0.cmp(&a.0));
+    ranked
+        .into_iter()
+        .take(k as usize)
+        .map(|(_, e)| e)
+        .collect()
+}
+
+/// User/assistant message pairs for the selected examples.
+pub fn example_messages(examples: &[&FewShotExample]) -> Vec<ApiMessage> {
+    examples
+        .iter()
+        .flat_map(|e| {
+            [("user", &e.user_text), ("assistant", &e.assistant_text)].map(|(role, text)| {
+                ApiMessage {
+                    role: role.to_string(),
+                    content: Some(text.as_str().into()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(id: &str, task_type: TaskType, user_text: &str) -> FewShotExample {
+        FewShotExample {
+            id: id.to_string(),
+            task_type,
+            user_text: user_text.to_string(),
+            assistant_text: format!("ответ {}", id),
+        }
+    }
+
+    #[test]
+    fn selects_only_examples_of_the_task_type() {
+        let examples = vec![
+            example("a", TaskType::Explain, "Объясни запрос к регистру остатков"),
+            example(
+                "b",
+                TaskType::Refactor,
+                "Упрости процедуру проведения документа",
+            ),
+            example(
+                "c",
+                TaskType::Refactor,
+                "Вынеси запрос к регистру в функцию",
+            ),
+            example("d", TaskType::Refactor, "Переименуй переменные"),
+        ];
+
+        let selected = select_examples(&examples, TaskType::Refactor, "запрос к регистру", 2);
+        let ids: Vec<&str> = selected.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+
+        // No Debug examples: keyword overlap with other types does not count
+        assert!(select_examples(&examples, TaskType::Debug, "регистру остатков", 2).is_empty());
+        let untagged = vec![example("e", TaskType::FreeForm, "Запрос к регистру")];
+        assert!(select_examples(&untagged, TaskType::FreeForm, "запрос к регистру", 2).is_empty());
+        assert!(select_examples(&examples, TaskType::Refactor, "запрос", 0).is_empty());
+    }
+
+    #[test]
+    fn example_messages_are_user_assistant_pairs() {
+        let examples = [example("a", TaskType::Explain, "Что делает код?")];
+        let refs: Vec<&FewShotExample> = examples.iter().collect();
+        let messages = example_messages(&refs);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].text(), Some("Что делает код?"));
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].text(), Some("ответ a"));
+    }
+}
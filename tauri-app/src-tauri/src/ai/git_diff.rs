@@ -0,0 +1,167 @@
+//! Working-tree or staged `git diff` sent as context with the question.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+
+use super::models::ApiMessage;
+
+/// Larger diffs are cut: the model rarely needs more, and it keeps the request small.
+pub const MAX_GIT_DIFF_BYTES: usize = 32 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitDiffAttachment {
+    pub diff: String,
+    /// `git diff --cached` rather than the working tree
+    #[serde(default)]
+    pub staged: bool,
+}
+
+/// Cuts the diff to `max_bytes` at a line boundary and says how much was dropped.
+pub fn truncate_diff(diff: &str, max_bytes: usize) -> String {
+    if diff.len() <= max_bytes {
+        return diff.to_string();
+    }
+    let mut cut = max_bytes;
+    while !diff.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let cut = diff[..cut].rfind('\n').map(|i| i + 1).unwrap_or(cut);
+    format!(
+        "{}... [diff обрезан: показано {} из {} байт]\n",
+        &diff[..cut],
+        cut,
+        diff.len()
+    )
+}
+
+/// Unstaged changes of the repository (`git diff --unified=3`), truncated to 32 KB.
+pub async fn get_git_diff(repo_path: &str) -> Result<String, String> {
+    git_diff(repo_path, false).await
+}
+
+/// `git diff --unified=3`, with `--cached` for staged changes, truncated to 32 KB.
+pub async fn git_diff(repo_path: &str, staged: bool) -> Result<String, String> {
+    if !Path::new(repo_path).is_dir() {
+        return Err(format!("Папка не найдена: {}", repo_path));
+    }
+
+    let mut cmd = tokio::process::Command::new("git");
+    cmd.args(["diff", "--unified=3", "--no-color", "--no-ext-diff"]);
+    if staged {
+        cmd.arg("--cached");
+    }
+    cmd.current_dir(repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(target_os = "windows")]
+    {
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = cmd.output().await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            "Git не найден. Установите Git и добавьте его в PATH".to_string()
+        } else {
+            format!("Не удалось запустить git: {}", e)
+        }
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.to_lowercase().contains("not a git repository") {
+            return Err(format!("Папка {} не является git-репозиторием", repo_path));
+        }
+        return Err(format!("git diff завершился с ошибкой: {}", stderr.trim()));
+    }
+
+    // 1C exports are often windows-1251
+    let diff = super::attachments::decode_source(&output.stdout);
+    Ok(truncate_diff(&diff, MAX_GIT_DIFF_BYTES))
+}
+
+/// Prepends the diff as a fenced `diff` block to the last user message.
+pub fn prepend_git_diff(messages: &mut [ApiMessage], attachment: &GitDiffAttachment) {
+    if attachment.diff.trim().is_empty() {
+        return;
+    }
+    let Some(content) = messages
+        .iter_mut()
+        .rev()
+        .find(|m| m.role == "user")
+        .and_then(|m| m.content.as_mut())
+    else {
+        return;
+    };
+    let title = if attachment.staged {
+        "Staged changes (git diff --cached):"
+    } else {
+        "Working tree changes (git diff):"
+    };
+    content.prepend_text(&format!(
+        "{}\n```diff\n{}\n```\n\n",
+        title,
+        attachment.diff.trim_end()
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_large_diff_at_line_boundary() {
+        let diff = "+Строка\n".repeat(10_000);
+        let truncated = truncate_diff(&diff, MAX_GIT_DIFF_BYTES);
+
+        assert!(truncated.len() < MAX_GIT_DIFF_BYTES + 100);
+        let (kept, note) = truncated.split_once("... [diff обрезан").unwrap();
+        assert!(kept.ends_with("+Строка\n"));
+        assert!(note.contains(&diff.len().to_string()));
+        assert_eq!(truncate_diff("+a\n", MAX_GIT_DIFF_BYTES), "+a\n");
+    }
+
+    #[test]
+    fn diff_block_goes_before_the_question() {
+        let mut messages = vec![ApiMessage {
+            role: "user".to_string(),
+            content: Some("Что не так с моими изменениями?".into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }];
+        let attachment = GitDiffAttachment {
+            diff: "-А = 1;\n+А = 2;\n".to_string(),
+            staged: true,
+        };
+        prepend_git_diff(&mut messages, &attachment);
+
+        assert_eq!(
+            messages[0].text(),
+            Some(
+                "Staged changes (git diff --cached):\n```diff\n-А = 1;\n+А = 2;\n```\n\n\
+                 Что не так с моими изменениями?"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_missing_folder_and_non_repository() {
+        let err = git_diff("/definitely/not/here", false).await.unwrap_err();
+        assert!(err.contains("не найдена"), "{}", err);
+
+        let dir = std::env::temp_dir().join(format!("git-diff-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Without git installed the error says so; otherwise the folder is not a repository
+        let err = git_diff(dir.to_str().unwrap(), false).await.unwrap_err();
+        assert!(
+            err.contains("git-репозиторием") || err.contains("Git не найден"),
+            "{}",
+            err
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,37 @@
+pub mod annotations;
+pub mod attachments;
+pub mod capabilities;
 pub mod client;
+pub mod code_blocks;
 pub mod codex_client;
+pub mod commit_message;
+pub mod comparison;
+pub mod dead_code;
+pub mod diff;
+pub mod doc_generation;
+pub mod error;
+pub mod few_shot;
+pub mod git_diff;
 pub mod models;
 pub mod naparnik_client;
+pub mod pricing;
+pub mod prompt_template;
 pub mod prompts;
+pub mod rate_limit;
+pub mod reachability;
+pub mod redact;
+pub mod retry;
+pub mod runtime_errors;
+pub mod security_review;
+pub mod sentence_smoother;
+pub mod structured;
+pub mod terminal_event;
+pub mod test_stubs;
+pub mod tokenizer;
 pub mod tools;
+pub mod transforms;
+pub mod usage;
+pub mod word_smoother;
 
 pub use client::*;
 pub use models::*;
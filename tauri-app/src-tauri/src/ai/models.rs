@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+use crate::llm_profiles::LLMProfile;
+
 /// Deserialize `arguments` field that may be either a JSON string or a JSON object/value.
 /// OpenAI sends it as a JSON-encoded string; some providers (e.g. MiniMax) send it as a raw object.
 fn deserialize_arguments_flexible<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
@@ -20,7 +24,7 @@ where
 pub struct ApiMessage {
     pub role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
+    pub content: Option<MessageContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +33,171 @@ pub struct ApiMessage {
     pub name: Option<String>,
 }
 
+impl ApiMessage {
+    /// Text of the message; image parts are ignored.
+    pub fn text(&self) -> Option<&str> {
+        self.content.as_ref().and_then(MessageContent::as_text)
+    }
+
+    pub fn has_images(&self) -> bool {
+        self.content
+            .as_ref()
+            .map(MessageContent::has_images)
+            .unwrap_or(false)
+    }
+}
+
+/// Message content: a plain string or OpenAI content parts (text + images).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+        /// Anthropic prompt-caching breakpoint
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ImageUrl {
+        image_url: ImageUrl,
+    },
+}
+
+/// `cache_control` marker: the prompt prefix up to and including this part is cached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheControl {
+    pub r#type: String,
+}
+
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self {
+            r#type: "ephemeral".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    /// `data:image/png;base64,...` or a plain https URL
+    pub url: String,
+}
+
+impl MessageContent {
+    /// Text part followed by images; stays a plain string when there are no images.
+    pub fn with_images(text: String, image_urls: Vec<String>) -> Self {
+        if image_urls.is_empty() {
+            return MessageContent::Text(text);
+        }
+        let mut parts = Vec::with_capacity(image_urls.len() + 1);
+        if !text.is_empty() {
+            parts.push(ContentPart::Text {
+                text,
+                cache_control: None,
+            });
+        }
+        parts.extend(image_urls.into_iter().map(|url| ContentPart::ImageUrl {
+            image_url: ImageUrl { url },
+        }));
+        MessageContent::Parts(parts)
+    }
+
+    /// Plain text, or the first text part of a multipart message.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            MessageContent::Text(text) => Some(text),
+            MessageContent::Parts(parts) => parts.iter().find_map(|part| match part {
+                ContentPart::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            }),
+        }
+    }
+
+    /// Prepends text to the message, keeping attached images in place.
+    pub fn prepend_text(&mut self, prefix: &str) {
+        match self {
+            MessageContent::Text(text) => text.insert_str(0, prefix),
+            MessageContent::Parts(parts) => match parts.first_mut() {
+                Some(ContentPart::Text { text, .. }) => text.insert_str(0, prefix),
+                _ => parts.insert(
+                    0,
+                    ContentPart::Text {
+                        text: prefix.to_string(),
+                        cache_control: None,
+                    },
+                ),
+            },
+        }
+    }
+
+    /// Puts a cache breakpoint on the last text part (a plain string becomes a single part).
+    pub fn mark_cacheable(&mut self) {
+        if let MessageContent::Text(text) = self {
+            *self = MessageContent::Parts(vec![ContentPart::Text {
+                text: std::mem::take(text),
+                cache_control: None,
+            }]);
+        }
+        if let MessageContent::Parts(parts) = self {
+            if let Some(ContentPart::Text { cache_control, .. }) = parts
+                .iter_mut()
+                .rev()
+                .find(|p| matches!(p, ContentPart::Text { .. }))
+            {
+                *cache_control = Some(CacheControl::ephemeral());
+            }
+        }
+    }
+
+    pub fn is_cacheable(&self) -> bool {
+        matches!(self, MessageContent::Parts(parts) if parts.iter().any(|p| matches!(
+            p,
+            ContentPart::Text {
+                cache_control: Some(_),
+                ..
+            }
+        )))
+    }
+
+    /// Removes cache breakpoints; a lone text part goes back to a plain string
+    /// so the request body is byte-identical to an unmarked message.
+    pub fn strip_cache_control(&mut self) {
+        if let MessageContent::Parts(parts) = self {
+            for part in parts.iter_mut() {
+                if let ContentPart::Text { cache_control, .. } = part {
+                    *cache_control = None;
+                }
+            }
+            if let [ContentPart::Text { text, .. }] = parts.as_mut_slice() {
+                *self = MessageContent::Text(std::mem::take(text));
+            }
+        }
+    }
+
+    pub fn has_images(&self) -> bool {
+        matches!(self, MessageContent::Parts(parts)
+            if parts.iter().any(|p| matches!(p, ContentPart::ImageUrl { .. })))
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
@@ -56,13 +225,44 @@ pub struct ToolFunction {
 }
 
 /// Request body for OpenAI-compatible API
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<ApiMessage>,
     pub stream: bool,
-    pub temperature: f32,
-    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Token limit for reasoning models (o-series, gpt-5) that reject `max_tokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Token id (as string) -> bias in -100..100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<String, f32>>,
+    /// Number of alternative completions (`choices`) to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u8>,
+    /// End-user id for provider-side abuse tracking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// System prompt for `SystemPromptPlacement::TopLevel`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
     /// Qwen3 extended thinking mode (must use temperature=1.0)
@@ -71,18 +271,288 @@ pub struct ChatRequest {
     /// Token budget for thinking step (1024–38912, default 8192)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking_budget_tokens: Option<u32>,
+    /// OpenAI reasoning models: "low" | "medium" | "high"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    /// Anthropic extended thinking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+}
+
+/// `thinking` of an Anthropic extended-thinking request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThinkingConfig {
+    pub r#type: String,
+    pub budget_tokens: u32,
+}
+
+impl ThinkingConfig {
+    pub fn enabled(budget_tokens: u32) -> Self {
+        Self {
+            r#type: "enabled".to_string(),
+            budget_tokens,
+        }
+    }
+}
+
+/// Structured-output mode (`response_format`) of an OpenAI-compatible request.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// `{"type": "json_object"}` — any valid JSON object
+    JsonObject,
+    /// `{"type": "json_schema", "json_schema": {"name": ..., "schema": ...}}`
+    JsonSchema { json_schema: Value },
+}
+
+/// `stream_options` of an OpenAI-compatible streaming request.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamOptions {
+    /// Ask the provider to send a final chunk with the `usage` object.
+    pub include_usage: bool,
+}
+
+/// Token usage reported by the provider.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    /// OpenAI/OpenRouter: `prompt_tokens_details.cached_tokens`
+    #[serde(default)]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// Anthropic: prompt tokens read from the cache
+    #[serde(default)]
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<u32>,
+}
+
+impl Usage {
+    pub fn cached_tokens(&self) -> Option<u32> {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens)
+            .or(self.cache_read_input_tokens)
+    }
+}
+
+/// Response metadata collected from HTTP headers and the `usage` object.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompletionMeta {
+    /// `openai-processing-ms` header
+    pub processing_ms: Option<u64>,
+    /// `x-ratelimit-remaining-tokens` header
+    pub remaining_tokens: Option<u32>,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    /// Prompt tokens served from the provider prompt cache
+    pub cached_tokens: Option<u32>,
+    /// Backend configuration fingerprint; together with `seed` identifies a reproducible run
+    pub system_fingerprint: Option<String>,
+    /// Cost by the static price table, when the model and token usage are known
+    pub estimated_cost_usd: Option<f64>,
+    /// `X-Request-ID` sent with the request
+    pub request_id: Option<String>,
+    /// Request id echoed by the provider (`x-request-id` or `cf-ray`)
+    pub server_request_id: Option<String>,
+    /// From the response headers to the first streamed chunk
+    pub time_to_first_token_ms: Option<u64>,
+    /// Token counts are the chars/4 estimate: the provider sent no `usage`
+    pub usage_estimated: bool,
+}
+
+impl CompletionMeta {
+    pub fn apply_usage(&mut self, usage: &Usage) {
+        if usage.prompt_tokens.is_some() {
+            self.prompt_tokens = usage.prompt_tokens;
+        }
+        if usage.completion_tokens.is_some() {
+            self.completion_tokens = usage.completion_tokens;
+        }
+        if let Some(cached) = usage.cached_tokens() {
+            self.cached_tokens = Some(cached);
+        }
+    }
+
+    /// Token counts the provider did not report (no `stream_options` support), estimated
+    /// from the request and the answer
+    pub fn estimate_missing_usage(&mut self, request: &[ApiMessage], answer: &ApiMessage) {
+        if self.prompt_tokens.is_none() {
+            self.prompt_tokens = Some(super::tokenizer::estimate_messages_tokens(request));
+            self.usage_estimated = true;
+        }
+        if self.completion_tokens.is_none() {
+            self.completion_tokens = Some(super::tokenizer::estimate_message_tokens(answer));
+            self.usage_estimated = true;
+        }
+    }
+}
+
+/// Assistant message together with the response metadata.
+#[derive(Debug, Clone)]
+pub struct ChatCompletion {
+    pub message: ApiMessage,
+    pub meta: CompletionMeta,
+    /// Text of every alternative; `alternatives[0]` is the content of `message`
+    pub alternatives: Vec<String>,
+}
+
+/// File the user is editing, added to the system prompt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileContext {
+    pub file_path: String,
+    /// Inferred from the path when not set (see `prompts::detect_bsl_module_type`)
+    #[serde(default)]
+    pub module_type: Option<String>,
+    #[serde(default)]
+    pub selected_text: Option<String>,
+}
+
+/// Requested answer length; appended to the system prompt (see `prompts::verbosity_instruction`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Short,
+    Medium,
+    Long,
+}
+
+/// Kind of task the user asks for; selects a system prompt addendum (see `prompts::task_addendum`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskType {
+    Refactor,
+    Explain,
+    Debug,
+    Generate,
+    Review,
+    Document,
+    /// System prompt is used as-is
+    #[default]
+    FreeForm,
+}
+
+/// Per-request overrides of profile generation settings, sent by the frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestOverrides {
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Model id of the same provider to use instead of the profile model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Replaces the profile stop sequences; `Some(vec![])` disables them for this request.
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    /// Replaces the profile seed
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Replaces the profile reasoning effort ("low" | "medium" | "high")
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    /// Number of alternative completions (1..=`MAX_ALTERNATIVES`); requested sequentially
+    /// from providers without `n`
+    #[serde(default)]
+    pub n: Option<u8>,
+    /// Replaces the profile/app system prompt for this request only
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Appended to the system prompt for this request only
+    #[serde(default)]
+    pub system_prompt_suffix: Option<String>,
+    /// Values for `{{variable}}` placeholders in the configured system prompt
+    #[serde(default)]
+    pub prompt_context: Option<super::prompt_template::PromptContext>,
+    /// Answer length instruction for this request
+    #[serde(default)]
+    pub verbosity: Option<Verbosity>,
+    /// Post-processing of the final answer text, used when `pipeline` is not set
+    #[serde(default)]
+    pub transforms: Option<Vec<super::transforms::TransformSpec>>,
+    /// Post-processing built by the backend caller
+    #[serde(skip)]
+    pub pipeline: Option<std::sync::Arc<super::transforms::TransformPipeline>>,
+    /// Generation preset applied by `stream_chat`, reported in `chat-start`
+    #[serde(skip)]
+    pub preset: Option<String>,
+    /// Profile chosen by `stream_chat` instead of the active one
+    #[serde(skip)]
+    pub profile_id: Option<String>,
+}
+
+/// Upper bound of `RequestOverrides::n`: every alternative is a full completion
+pub const MAX_ALTERNATIVES: u8 = 8;
+
+impl RequestOverrides {
+    /// Profile with the overrides applied, validated the same way as a saved profile.
+    pub fn apply_to(&self, profile: &LLMProfile) -> Result<LLMProfile, String> {
+        if let Some(n) = self.n.filter(|n| !(1..=MAX_ALTERNATIVES).contains(n)) {
+            return Err(format!(
+                "Количество вариантов ответа должно быть от 1 до {}, указано {}",
+                MAX_ALTERNATIVES, n
+            ));
+        }
+        let mut profile = profile.clone();
+        if self.temperature.is_some() {
+            profile.temperature = self.temperature;
+        }
+        if self.max_tokens.is_some() {
+            profile.max_tokens = self.max_tokens;
+        }
+        if self.top_p.is_some() {
+            profile.top_p = self.top_p;
+        }
+        if let Some(model) = &self.model {
+            profile.model = model.clone();
+        }
+        if let Some(stop) = &self.stop {
+            profile.stop = stop.clone();
+        }
+        if self.seed.is_some() {
+            profile.seed = self.seed;
+        }
+        if self.reasoning_effort.is_some() {
+            profile.reasoning_effort = self.reasoning_effort.clone();
+        }
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// `pipeline`, or one built from `transforms`
+    pub fn response_pipeline(
+        &self,
+    ) -> Option<std::sync::Arc<super::transforms::TransformPipeline>> {
+        self.pipeline.clone().or_else(|| {
+            self.transforms.as_deref().map(|specs| {
+                std::sync::Arc::new(super::transforms::TransformPipeline::from_specs(specs))
+            })
+        })
+    }
 }
 
 /// Streaming chunk from OpenAI API
 #[derive(Debug, Deserialize)]
 pub struct StreamChunk {
+    #[serde(default)]
     pub choices: Vec<StreamChoice>,
+    /// Present on the last chunk when `stream_options.include_usage` is set.
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct StreamChoice {
+    #[serde(default)]
+    pub index: usize,
     pub delta: StreamDelta,
-    #[allow(dead_code)]
     pub finish_reason: Option<String>,
 }
 
@@ -116,10 +586,16 @@ pub struct ToolCallFunctionDelta {
 #[derive(Debug, Deserialize)]
 pub struct NonStreamResponse {
     pub choices: Vec<NonStreamChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct NonStreamChoice {
+    #[serde(default)]
+    pub index: usize,
     pub message: NonStreamMessage,
 }
 
@@ -151,3 +627,132 @@ pub struct ToolInfo {
     pub tool: Tool,
     pub server_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_message(role: &str, text: &str) -> ApiMessage {
+        ApiMessage {
+            role: role.to_string(),
+            content: Some(MessageContent::from(text)),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn missing_usage_is_estimated_and_reported_usage_kept() {
+        let request = [
+            text_message("system", "Ты помощник 1С."),
+            text_message("user", "Как создать запрос?"),
+        ];
+        let answer = text_message("assistant", "Используйте Новый Запрос.");
+
+        let mut meta = CompletionMeta::default();
+        meta.estimate_missing_usage(&request, &answer);
+        assert!(meta.usage_estimated);
+        assert_eq!(
+            meta.prompt_tokens,
+            Some(super::super::tokenizer::estimate_messages_tokens(&request))
+        );
+        assert_eq!(
+            meta.completion_tokens,
+            Some(super::super::tokenizer::estimate_message_tokens(&answer))
+        );
+
+        let mut meta = CompletionMeta::default();
+        meta.apply_usage(&Usage {
+            prompt_tokens: Some(120),
+            completion_tokens: Some(48),
+            ..Default::default()
+        });
+        meta.estimate_missing_usage(&request, &answer);
+        assert!(!meta.usage_estimated);
+        assert_eq!(meta.prompt_tokens, Some(120));
+        assert_eq!(meta.completion_tokens, Some(48));
+    }
+
+    #[test]
+    fn alternatives_count_is_bounded() {
+        let profile = LLMProfile::default_profile();
+        let overrides = |n| RequestOverrides {
+            n: Some(n),
+            ..RequestOverrides::default()
+        };
+        assert!(overrides(1).apply_to(&profile).is_ok());
+        assert!(overrides(MAX_ALTERNATIVES).apply_to(&profile).is_ok());
+        assert!(overrides(0).apply_to(&profile).is_err());
+        assert!(overrides(MAX_ALTERNATIVES + 1).apply_to(&profile).is_err());
+    }
+
+    #[test]
+    fn plain_text_content_serializes_as_string() {
+        let content = MessageContent::with_images("hi".to_string(), Vec::new());
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::json!("hi")
+        );
+    }
+
+    #[test]
+    fn image_content_serializes_as_openai_parts() {
+        let content = MessageContent::with_images(
+            "Что за ошибка?".to_string(),
+            vec!["data:image/png;base64,AAAA".to_string()],
+        );
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::json!([
+                {"type": "text", "text": "Что за ошибка?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ])
+        );
+        assert!(content.has_images());
+        assert_eq!(content.as_text(), Some("Что за ошибка?"));
+
+        let parsed: MessageContent =
+            serde_json::from_value(serde_json::to_value(&content).unwrap()).unwrap();
+        assert_eq!(parsed, content);
+    }
+
+    #[test]
+    fn cache_marker_round_trips_to_plain_string() {
+        let mut content = MessageContent::from("Справочник API общих модулей");
+        content.mark_cacheable();
+        assert!(content.is_cacheable());
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::json!([{
+                "type": "text",
+                "text": "Справочник API общих модулей",
+                "cache_control": {"type": "ephemeral"}
+            }])
+        );
+
+        content.strip_cache_control();
+        assert_eq!(
+            content,
+            MessageContent::from("Справочник API общих модулей")
+        );
+    }
+
+    #[test]
+    fn usage_reads_cached_tokens_from_either_format() {
+        let openai: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 2000,
+            "completion_tokens": 10,
+            "prompt_tokens_details": {"cached_tokens": 1920}
+        }))
+        .unwrap();
+        assert_eq!(openai.cached_tokens(), Some(1920));
+
+        let anthropic: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 2000,
+            "cache_read_input_tokens": 1800
+        }))
+        .unwrap();
+        assert_eq!(anthropic.cached_tokens(), Some(1800));
+    }
+}
@@ -11,10 +11,11 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 use tauri::Emitter;
 
+use super::error::AiClientError;
 use super::models::{ApiMessage, ToolInfo};
 use super::prompts::{get_system_prompt, has_code_context};
 use super::tools::get_available_tools;
-use crate::llm_profiles::get_active_profile;
+use crate::llm_profiles::LLMProfile;
 use crate::settings::{load_settings, McpServerConfig, McpTransport};
 
 const BASE_URL: &str = "https://code.1c.ai";
@@ -128,6 +129,26 @@ fn build_client() -> Result<reqwest::Client, String> {
         .map_err(|e| format!("Failed to build HTTP client: {}", e))
 }
 
+/// Error status of the Напарник API; 401/403 mean the code.1c.ai token was rejected
+fn api_error(
+    profile: &LLMProfile,
+    status: reqwest::StatusCode,
+    body: String,
+    what: &str,
+) -> AiClientError {
+    if super::error::is_auth_status(status.as_u16()) {
+        return AiClientError::auth_rejected(
+            status.as_u16(),
+            &body,
+            profile,
+            "токен code.1c.ai недействителен или истёк — обновите его в настройках профиля"
+                .to_string(),
+        );
+    }
+    let message = format!("Naparnik: {} {}: {}", what, status, body);
+    AiClientError::http(status.as_u16(), &body, &profile.provider, message)
+}
+
 fn build_headers(token: &str) -> reqwest::header::HeaderMap {
     use reqwest::header::*;
     let mut h = HeaderMap::new();
@@ -422,8 +443,9 @@ async fn execute_local_mcp_tool(
 
 async fn create_conversation(
     client: &reqwest::Client,
+    profile: &LLMProfile,
     token: &str,
-) -> Result<(String, Option<String>), String> {
+) -> Result<(String, Option<String>), AiClientError> {
     let url = format!("{}/chat_api/v1/conversations/", BASE_URL);
     let body = CreateConversationRequest {
         is_chat: true,
@@ -441,24 +463,28 @@ async fn create_conversation(
         .json(&body)
         .send()
         .await
-        .map_err(|e| format!("Naparnik: failed to create conversation: {}", e))?;
+        .map_err(|e| AiClientError::Network {
+            message: format!("Naparnik: failed to create conversation: {}", e),
+        })?;
 
     if !resp.status().is_success() {
         let status = resp.status();
         let text = resp.text().await.unwrap_or_default();
-        return Err(format!(
-            "Naparnik: conversation create error {}: {}",
-            status, text
+        return Err(api_error(
+            profile,
+            status,
+            text,
+            "conversation create error",
         ));
     }
 
     let data: serde_json::Value = resp
         .json()
         .await
-        .map_err(|e| format!("Naparnik: parse error: {}", e))?;
+        .map_err(|e| AiClientError::parse(format!("Naparnik: parse error: {}", e)))?;
     let uuid = data["uuid"]
         .as_str()
-        .ok_or("Naparnik: no uuid in response")?
+        .ok_or_else(|| AiClientError::parse("Naparnik: no uuid in response"))?
         .to_string();
     let root_msg_uuid = data["root_message_uuid"].as_str().map(|s| s.to_string());
 
@@ -473,17 +499,16 @@ async fn create_conversation(
 // ─── Main Streaming Function ──────────────────────────────────────────────────
 
 /// Main entry point: called from ai/client.rs when provider == OneCNaparnik
-pub async fn stream_naparnik_completion(
+pub async fn stream_naparnik_completion<R: tauri::Runtime>(
     messages: Vec<ApiMessage>,
-    app_handle: tauri::AppHandle,
-) -> Result<ApiMessage, String> {
-    let profile = get_active_profile().ok_or("No active LLM profile")?;
+    profile: &LLMProfile,
+    app_handle: tauri::AppHandle<R>,
+) -> Result<ApiMessage, AiClientError> {
     let token = profile.get_api_key();
     if token.is_empty() {
-        return Err(
-            "1С:Напарник: токен не задан. Укажите токен code.1c.ai в настройках профиля."
-                .to_string(),
-        );
+        return Err(AiClientError::invalid_profile(
+            "1С:Напарник: токен не задан. Укажите токен code.1c.ai в настройках профиля.",
+        ));
     }
 
     let profile_id = profile.id.clone();
@@ -494,7 +519,7 @@ pub async fn stream_naparnik_completion(
         Some(s) => s,
         None => {
             let _ = app_handle.emit("chat-status", "Создаю сессию Напарника...");
-            let (conv_id, root_uuid) = create_conversation(&client, &token).await?;
+            let (conv_id, root_uuid) = create_conversation(&client, profile, &token).await?;
             let s = OneCSession {
                 conversation_id: conv_id,
                 last_message_uuid: root_uuid,
@@ -509,12 +534,12 @@ pub async fn stream_naparnik_completion(
         .iter()
         .rev()
         .find(|m| m.role == "user")
-        .and_then(|m| m.content.as_deref())
+        .and_then(|m| m.text())
         .unwrap_or("")
         .to_string();
 
     if instruction.is_empty() {
-        return Err("Naparnik: empty user message".to_string());
+        return Err("Naparnik: empty user message".into());
     }
 
     let _ = app_handle.emit("chat-status", "Отправляю запрос Напарнику...");
@@ -523,7 +548,9 @@ pub async fn stream_naparnik_completion(
     let naparnik_tools_info = filter_naparnik_tools(&all_tools_info);
     let naparnik_tools = build_naparnik_tools(&naparnik_tools_info);
     let local_tool_routes = build_local_tool_routes(&naparnik_tools_info);
-    let system_prompt = get_system_prompt(&naparnik_tools_info, &messages);
+    let language = super::prompts::answer_language(Some(profile));
+    let system_prompt =
+        get_system_prompt(&naparnik_tools_info, &messages, language, profile.locale);
     let has_code_context = has_code_context(&messages);
     let instruction = build_naparnik_instruction(
         &system_prompt,
@@ -535,7 +562,7 @@ pub async fn stream_naparnik_completion(
     let full_content = run_message_loop(
         &client,
         &token,
-        &profile_id,
+        profile,
         &session.conversation_id,
         session.last_message_uuid.clone(),
         instruction,
@@ -551,7 +578,7 @@ pub async fn stream_naparnik_completion(
         content: if full_content.is_empty() {
             None
         } else {
-            Some(full_content)
+            Some(full_content.into())
         },
         tool_calls: None,
         tool_call_id: None,
@@ -561,18 +588,19 @@ pub async fn stream_naparnik_completion(
 
 /// Sends a message and handles server-side tool_calls round-trips.
 /// Returns the final accumulated text after all rounds complete.
-async fn run_message_loop(
+async fn run_message_loop<R: tauri::Runtime>(
     client: &reqwest::Client,
     token: &str,
-    profile_id: &str,
+    profile: &LLMProfile,
     conversation_id: &str,
     initial_parent_uuid: Option<String>,
     instruction: String,
     naparnik_tools: Vec<Value>,
     local_tool_routes: HashMap<String, String>,
     has_code_context: bool,
-    app_handle: &tauri::AppHandle,
-) -> Result<String, String> {
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<String, AiClientError> {
+    let profile_id = profile.id.as_str();
     let url = format!(
         "{}/chat_api/v1/conversations/{}/messages",
         BASE_URL, conversation_id
@@ -606,12 +634,14 @@ async fn run_message_loop(
             .json(&payload)
             .send()
             .await
-            .map_err(|e| format!("Naparnik: send error: {}", e))?;
+            .map_err(|e| AiClientError::Network {
+                message: format!("Naparnik: send error: {}", e),
+            })?;
 
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(format!("Naparnik: API error {}: {}", status, text));
+            return Err(api_error(profile, status, text, "API error"));
         }
 
         if is_first_round {
@@ -730,11 +760,11 @@ async fn run_message_loop(
 }
 
 /// Reads SSE stream, emits chat events, returns tool_calls list if server wants round-trip.
-async fn process_sse_stream(
+async fn process_sse_stream<R: tauri::Runtime>(
     response: reqwest::Response,
     profile_id: &str,
     assistant_segments: &mut Vec<String>,
-    app_handle: &tauri::AppHandle,
+    app_handle: &tauri::AppHandle<R>,
 ) -> Result<Vec<Value>, String> {
     let mut stream = response.bytes_stream();
     let mut byte_buffer = Vec::<u8>::new();
@@ -0,0 +1,128 @@
+//! Static per-token price table (USD) for cost estimates.
+//! Kept separate from the capability rules so prices can be updated independently.
+
+/// Price of a model in USD per 1k tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+/// Prices by prefix of the `price_key` of a model id, one row per model.
+/// First match wins, so more specific prefixes go first.
+const PRICES: &[(&str, ModelPrice)] = &[
+    ("gpt-4o-mini", price(0.000_15, 0.000_6)),
+    ("gpt-4o", price(0.005, 0.015)),
+    ("gpt-4.1-nano", price(0.000_1, 0.000_4)),
+    ("gpt-4.1-mini", price(0.000_4, 0.001_6)),
+    ("gpt-4.1", price(0.002, 0.008)),
+    ("gpt-4-turbo", price(0.01, 0.03)),
+    ("gpt-3.5-turbo", price(0.000_5, 0.001_5)),
+    ("o4-mini", price(0.001_1, 0.004_4)),
+    ("o3-mini", price(0.001_1, 0.004_4)),
+    ("o3", price(0.002, 0.008)),
+    ("o1-mini", price(0.001_1, 0.004_4)),
+    ("o1", price(0.015, 0.06)),
+    ("claude-3.5-sonnet", price(0.003, 0.015)),
+    ("claude-3.5-haiku", price(0.000_8, 0.004)),
+    ("claude-3-opus", price(0.015, 0.075)),
+    ("claude-3-haiku", price(0.000_25, 0.001_25)),
+    ("deepseek-chat", price(0.000_27, 0.001_1)),
+    ("deepseek-reasoner", price(0.000_55, 0.002_19)),
+    ("gemini-1.5-pro", price(0.001_25, 0.005)),
+    ("gemini-1.5-flash", price(0.000_075, 0.000_3)),
+];
+
+const fn price(input_per_1k: f64, output_per_1k: f64) -> ModelPrice {
+    ModelPrice {
+        input_per_1k,
+        output_per_1k,
+    }
+}
+
+/// Bare lowercase id with single-digit versions dotted, so the vendor and aggregator
+/// spellings meet: "claude-3-5-sonnet" and "anthropic/claude-3.5-sonnet" both become
+/// "claude-3.5-sonnet". Dates and longer numbers ("gpt-4-1106") are left as they are.
+fn price_key(model: &str) -> String {
+    let chars: Vec<char> = super::capabilities::bare_model_id(model).chars().collect();
+    let digit = |i: usize| chars.get(i).is_some_and(char::is_ascii_digit);
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let version_dash = c == '-'
+                && i >= 1
+                && digit(i - 1)
+                && !(i >= 2 && digit(i - 2))
+                && digit(i + 1)
+                && !digit(i + 2);
+            if version_dash {
+                '.'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Looks up the price of a model id ("openai/gpt-4o" and "gpt-4o" are the same model).
+pub fn price_for_model(model: &str) -> Option<ModelPrice> {
+    let id = price_key(model);
+    PRICES
+        .iter()
+        .find(|(prefix, _)| id.starts_with(prefix))
+        .map(|(_, price)| *price)
+}
+
+/// Estimated cost of a request in USD, `None` when the model is not in the table.
+pub fn estimate_cost(prompt_tokens: u32, completion_tokens: u32, model: &str) -> Option<f64> {
+    let price = price_for_model(model)?;
+    Some(
+        prompt_tokens as f64 / 1000.0 * price.input_per_1k
+            + completion_tokens as f64 / 1000.0 * price.output_per_1k,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specific_prefix_wins_over_family() {
+        assert_eq!(
+            price_for_model("gpt-4o-mini-2024-07-18"),
+            Some(price(0.000_15, 0.000_6))
+        );
+        assert_eq!(price_for_model("openai/gpt-4o"), Some(price(0.005, 0.015)));
+        assert_eq!(price_for_model("local-model"), None);
+    }
+
+    #[test]
+    fn vendor_and_aggregator_spellings_share_a_row() {
+        assert_eq!(
+            price_key("claude-3-5-sonnet-20241022"),
+            "claude-3.5-sonnet-20241022"
+        );
+        assert_eq!(
+            price_key("anthropic/claude-3.5-sonnet"),
+            "claude-3.5-sonnet"
+        );
+        assert_eq!(
+            price_key("gpt-4o-mini-2024-07-18"),
+            "gpt-4o-mini-2024-07-18"
+        );
+        assert_eq!(price_key("gpt-4-1106-preview"), "gpt-4-1106-preview");
+        assert_eq!(
+            price_for_model("claude-3-5-haiku-latest"),
+            price_for_model("anthropic/claude-3.5-haiku")
+        );
+        assert!(price_for_model("claude-3-5-haiku-latest").is_some());
+    }
+
+    #[test]
+    fn estimates_cost_from_both_directions() {
+        let cost = estimate_cost(2_000, 1_000, "gpt-4o").unwrap();
+        assert!((cost - 0.025).abs() < 1e-9);
+        assert_eq!(estimate_cost(10, 10, "unknown"), None);
+    }
+}
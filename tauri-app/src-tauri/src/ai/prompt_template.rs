@@ -0,0 +1,279 @@
+//! `{{variable}}` substitution for user-configured system prompts.
+//!
+//! `{{{{` and `}}}}` produce literal `{{` / `}}`; `{{key|default}}` falls back to
+//! `default` when `key` is not set. Unknown placeholders are left intact;
+//! `validate_template` reports them so the settings UI can warn.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Built-in placeholder names
+pub const KNOWN_VARIABLES: &[&str] = &["platform_version", "project_name", "today"];
+
+/// Values for template placeholders, supplied by the frontend per request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptContext {
+    #[serde(default)]
+    pub platform_version: Option<String>,
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// Overrides the current local date (`YYYY-MM-DD`)
+    #[serde(default)]
+    pub today: Option<String>,
+    /// Additional user-defined variables, e.g. `forbidden_apis`
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+impl PromptContext {
+    fn lookup(&self, name: &str) -> Option<String> {
+        match name {
+            "platform_version" => self.platform_version.clone(),
+            "project_name" => self.project_name.clone(),
+            "today" => Some(
+                self.today
+                    .clone()
+                    .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string()),
+            ),
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Placeholder {
+        raw: &'a str,
+        name: &'a str,
+        default: Option<&'a str>,
+    },
+}
+
+/// `name` or `name|default` inside the braces
+fn parse_placeholder(inner: &str) -> Option<(&str, Option<&str>)> {
+    let (name, default) = match inner.split_once('|') {
+        Some((name, default)) => (name.trim(), Some(default)),
+        None => (inner.trim(), None),
+    };
+    is_variable_name(name).then_some((name, default))
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("{{{{") {
+            tokens.push(Token::Text("{{"));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}}}") {
+            tokens.push(Token::Text("}}"));
+            rest = after;
+        } else if rest.starts_with("{{") {
+            match rest[2..]
+                .find("}}")
+                .and_then(|end| Some((end, parse_placeholder(&rest[2..2 + end])?)))
+            {
+                Some((end, (name, default))) => {
+                    tokens.push(Token::Placeholder {
+                        raw: &rest[..end + 4],
+                        name,
+                        default,
+                    });
+                    rest = &rest[end + 4..];
+                }
+                None => {
+                    tokens.push(Token::Text("{{"));
+                    rest = &rest[2..];
+                }
+            }
+        } else {
+            let next = rest
+                .char_indices()
+                .skip(1)
+                .find(|(i, _)| rest[*i..].starts_with("{{") || rest[*i..].starts_with("}}}}"))
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            tokens.push(Token::Text(&rest[..next]));
+            rest = &rest[next..];
+        }
+    }
+    tokens
+}
+
+/// Substitute placeholders; unknown or unset ones stay as written
+pub fn render_template(template: &str, context: &PromptContext) -> String {
+    let mut out = String::with_capacity(template.len());
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => out.push_str(text),
+            Token::Placeholder { raw, name, default } => match context.lookup(name) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(default.unwrap_or(raw)),
+            },
+        }
+    }
+    out
+}
+
+/// Warnings for placeholders that are neither built-in nor defined in `context.variables`
+pub fn validate_template(template: &str, context: &PromptContext) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for token in tokenize(template) {
+        if let Token::Placeholder { name, default, .. } = token {
+            let known = default.is_some()
+                || KNOWN_VARIABLES.contains(&name)
+                || context.variables.contains_key(name);
+            let warning = format!("Неизвестная переменная {{{{{}}}}}", name);
+            if !known && !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+    warnings
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// Comma-separated names of placeholders without a value or default
+    UnknownVariable(String),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::UnknownVariable(names) => {
+                write!(f, "Не заданы переменные шаблона: {}", names)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Strict rendering of a user prompt template: every `{{key}}` must be in `variables`
+/// or carry a `{{key|default}}` fallback.
+pub fn render_prompt_template(
+    template: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(template.len());
+    let mut missing: Vec<&str> = Vec::new();
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => out.push_str(text),
+            Token::Placeholder { name, default, .. } => {
+                match variables.get(name).map(String::as_str).or(default) {
+                    Some(value) => out.push_str(value),
+                    None if !missing.contains(&name) => missing.push(name),
+                    None => {}
+                }
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(out)
+    } else {
+        Err(TemplateError::UnknownVariable(missing.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> PromptContext {
+        PromptContext {
+            platform_version: Some("8.3.24".to_string()),
+            project_name: Some("ERP".to_string()),
+            today: Some("2026-10-16".to_string()),
+            variables: HashMap::from([("forbidden_apis".to_string(), "Выполнить()".to_string())]),
+        }
+    }
+
+    #[test]
+    fn substitutes_builtin_and_custom_variables() {
+        let rendered = render_template(
+            "Платформа {{platform_version}}, проект {{ project_name }}, {{today}}. Запрещено: {{forbidden_apis}}",
+            &context(),
+        );
+        assert_eq!(
+            rendered,
+            "Платформа 8.3.24, проект ERP, 2026-10-16. Запрещено: Выполнить()"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_intact_and_reported() {
+        let template = "Конфигурация {{configuration_name}} и {{configuration_name}}";
+        assert_eq!(render_template(template, &context()), template);
+        assert_eq!(
+            validate_template(template, &context()),
+            vec!["Неизвестная переменная {{configuration_name}}".to_string()]
+        );
+    }
+
+    #[test]
+    fn doubled_braces_escape_literal_braces() {
+        assert_eq!(
+            render_template("{{{{project_name}}}} = {{project_name}}", &context()),
+            "{{project_name}} = ERP"
+        );
+        assert!(validate_template("{{{{not_a_var}}}}", &context()).is_empty());
+    }
+
+    #[test]
+    fn default_values_fill_unset_placeholders() {
+        assert_eq!(
+            render_template("{{project_name|БП}} / {{module|Общий модуль}}", &context()),
+            "ERP / Общий модуль"
+        );
+        assert!(validate_template("{{module|Общий}}", &context()).is_empty());
+    }
+
+    #[test]
+    fn strict_rendering_lists_every_missing_variable() {
+        let variables = HashMap::from([("function_name".to_string(), "Провести".to_string())]);
+        assert_eq!(
+            render_prompt_template(
+                "Проанализируй функцию {{function_name}} в файле {{filename|Модуль.bsl}}",
+                &variables
+            ),
+            Ok("Проанализируй функцию Провести в файле Модуль.bsl".to_string())
+        );
+        assert_eq!(
+            render_prompt_template("{{a}} {{function_name}} {{b}} {{a}}", &variables),
+            Err(TemplateError::UnknownVariable("a, b".to_string()))
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn rendering_never_panics(template in "\\PC*", value in "\\PC*") {
+            let variables = HashMap::from([("x".to_string(), value)]);
+            let _ = render_prompt_template(&template, &variables);
+            let _ = render_template(&template, &context());
+            let _ = validate_template(&template, &context());
+        }
+
+        #[test]
+        fn brace_heavy_templates_never_panic(template in "[{}|a ]{0,40}") {
+            let _ = render_prompt_template(&template, &HashMap::new());
+        }
+    }
+
+    #[test]
+    fn unclosed_or_invalid_placeholders_are_plain_text() {
+        assert_eq!(
+            render_template("{{project_name", &context()),
+            "{{project_name"
+        );
+        assert_eq!(render_template("{{ a b }}", &context()), "{{ a b }}");
+    }
+}
@@ -1,6 +1,12 @@
-use super::models::{ApiMessage, ToolInfo};
-use crate::llm_profiles::LLMProvider;
-use crate::settings::{load_settings, CustomPromptsSettings, PromptBehaviorPreset};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use lazy_static::lazy_static;
+
+use super::models::{ApiMessage, FileContext, TaskType, ToolInfo, Verbosity};
+use super::prompt_template::{render_template, PromptContext};
+use crate::llm_profiles::{LLMProfile, LLMProvider, SystemPromptLocale};
+use crate::settings::{load_settings, AnswerLanguage, CustomPromptsSettings, PromptBehaviorPreset};
 
 /// Константа с инструкциями для diff-формата (Search/Replace)
 pub const DIFF_FORMAT_INSTRUCTIONS: &str = r#"
@@ -43,36 +49,272 @@ Your goal is to make **Targeted Edits** using strictly XML-based diff format.
 [/RULES]
 "#;
 
-/// Helper to detect target language based on message content
-pub fn detect_target_lang(messages: &[ApiMessage]) -> String {
-    for msg in messages.iter().rev() {
-        if msg.role == "user" {
-            let clean_text: String = if let Some(content) = &msg.content {
-                content
-                    .lines()
-                    .filter(|l| !l.trim().starts_with('/'))
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            } else {
-                "".to_string()
-            };
+/// Язык сообщения по письменности первых 50 символов: латиница — английский, кириллица
+/// с і/ї/є/ґ — украинский, остальная кириллица и пустой текст — русский.
+pub fn detect_locale_from_message(text: &str) -> SystemPromptLocale {
+    let (mut cyrillic, mut latin) = (0, 0);
+    for c in text.chars().take(50) {
+        match c {
+            'і' | 'ї' | 'є' | 'ґ' | 'І' | 'Ї' | 'Є' | 'Ґ' => {
+                return SystemPromptLocale::Ukrainian
+            }
+            '\u{0400}'..='\u{04FF}' => cyrillic += 1,
+            c if c.is_ascii_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+    if latin > cyrillic {
+        SystemPromptLocale::English
+    } else {
+        SystemPromptLocale::Russian
+    }
+}
+
+/// Подсказка о языке ответа, когда последнее сообщение пользователя написано не по-русски;
+/// только для `AnswerLanguage::Auto`, явно выбранный язык ответа не переопределяется
+pub fn language_match_hint(
+    language: AnswerLanguage,
+    messages: &[ApiMessage],
+) -> Option<&'static str> {
+    if language != AnswerLanguage::Auto {
+        return None;
+    }
+    let text = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .and_then(|m| m.text())?;
+    match detect_locale_from_message(text) {
+        SystemPromptLocale::English => Some("The user is writing in English. Reply in English."),
+        SystemPromptLocale::Ukrainian => {
+            Some("The user is writing in Ukrainian. Reply in Ukrainian.")
+        }
+        SystemPromptLocale::Russian => None,
+    }
+}
+
+/// Локаль встроенного промпта: локаль профиля, а для `Auto` — язык последнего
+/// сообщения пользователя.
+fn prompt_locale(
+    locale: SystemPromptLocale,
+    language: AnswerLanguage,
+    messages: &[ApiMessage],
+) -> SystemPromptLocale {
+    if language != AnswerLanguage::Auto {
+        return locale;
+    }
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .and_then(|m| m.text())
+        .map(detect_locale_from_message)
+        .unwrap_or(locale)
+}
+
+/// Дополнения к системному промпту по типу задачи (FreeForm — без дополнения).
+fn task_addenda() -> &'static HashMap<TaskType, &'static str> {
+    static ADDENDA: OnceLock<HashMap<TaskType, &'static str>> = OnceLock::new();
+    ADDENDA.get_or_init(|| {
+        HashMap::from([
+            (
+                TaskType::Refactor,
+                "TASK: Refactor. Focus on reducing code complexity and following 1C naming conventions. Preserve behaviour; do not change public method signatures unless asked.",
+            ),
+            (
+                TaskType::Explain,
+                "TASK: Explain. Describe what the code does step by step, including platform objects and BSP calls it relies on. Do not rewrite the code unless asked.",
+            ),
+            (
+                TaskType::Debug,
+                "TASK: Debug. Find the root cause first: name the exact line and the condition that fails, then propose the minimal fix.",
+            ),
+            (
+                TaskType::Generate,
+                "TASK: Generate. Write complete, compilable 1C code that follows the 1C development standards and uses BSP where it fits.",
+            ),
+            (
+                TaskType::Review,
+                "TASK: Review. List concrete problems (errors, performance, queries in loops, standards violations) ordered by severity, each with a suggested fix.",
+            ),
+            (
+                TaskType::Document,
+                "TASK: Document. Add 1C-standard description comments for methods and parameters (Параметры:, Возвращаемое значение:) without changing the code logic.",
+            ),
+        ])
+    })
+}
+
+/// Дополнение к системному промпту для типа задачи, `None` для FreeForm.
+pub fn task_addendum(task_type: TaskType) -> Option<&'static str> {
+    task_addenda().get(&task_type).copied()
+}
+
+/// Инструкция о длине ответа.
+pub fn verbosity_instruction(verbosity: Verbosity) -> &'static str {
+    match verbosity {
+        Verbosity::Short => "Reply with code only, no explanations, maximum 20 lines.",
+        Verbosity::Medium => "Balance code and explanation, aim for 100 words.",
+        Verbosity::Long => "Provide detailed explanation, examples, and complete code.",
+    }
+}
+
+/// Добавляет к системному промпту фокус задачи и требование к длине ответа;
+/// для задачи длина указывается как условие её выполнения.
+pub fn apply_task_addendum(prompt: &mut String, task_type: TaskType, verbosity: Option<Verbosity>) {
+    let addendum = task_addendum(task_type);
+    if let Some(addendum) = addendum {
+        prompt.push_str("\n\n");
+        prompt.push_str(addendum);
+    }
+    if let Some(verbosity) = verbosity {
+        prompt.push_str(if addendum.is_some() {
+            "\nVERBOSITY for this task: "
+        } else {
+            "\n\nVERBOSITY: "
+        });
+        prompt.push_str(verbosity_instruction(verbosity));
+    }
+}
+
+/// Вид модуля 1С, определённый по пути файла выгрузки.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BslModuleType {
+    ObjectModule,
+    ManagerModule,
+    ValueManagerModule,
+    RecordSetModule,
+    /// Имя формы; пустое, если его нет в пути
+    FormModule(String),
+    CommandModule,
+    /// Имя общего модуля
+    CommonModule(String),
+    ManagedApplicationModule,
+    OrdinaryApplicationModule,
+    SessionModule,
+    ExternalConnectionModule,
+    Unknown,
+}
+
+impl BslModuleType {
+    /// Название вида модуля, как в конфигураторе.
+    pub fn kind_name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::ObjectModule => "МодульОбъекта",
+            Self::ManagerModule => "МодульМенеджера",
+            Self::ValueManagerModule => "МодульМенеджераЗначения",
+            Self::RecordSetModule => "МодульНабораЗаписей",
+            Self::FormModule(_) => "МодульФормы",
+            Self::CommandModule => "МодульКоманды",
+            Self::CommonModule(_) => "ОбщийМодуль",
+            Self::ManagedApplicationModule => "МодульУправляемогоПриложения",
+            Self::OrdinaryApplicationModule => "МодульОбычногоПриложения",
+            Self::SessionModule => "МодульСеанса",
+            Self::ExternalConnectionModule => "МодульВнешнегоСоединения",
+            Self::Unknown => return None,
+        })
+    }
+
+    /// Вид модуля с именем формы или общего модуля: `ОбщийМодуль «ОбщегоНазначения»`.
+    pub fn describe(&self) -> Option<String> {
+        let kind = self.kind_name()?;
+        Some(match self {
+            Self::FormModule(name) | Self::CommonModule(name) if !name.is_empty() => {
+                format!("{} «{}»", kind, name)
+            }
+            _ => kind.to_string(),
+        })
+    }
+}
+
+/// Вид модуля по пути файла выгрузки EDT (`src/Catalogs/Товары/Forms/ФормаЭлемента/Module.bsl`)
+/// или конфигуратора (`…/Forms/ФормаЭлемента/Ext/Form/Module.bsl`).
+pub fn detect_bsl_module_type(path: &str) -> BslModuleType {
+    let normalized = path.replace('\\', "/");
+    let segments: Vec<&str> = normalized.split('/').filter(|s| !s.is_empty()).collect();
+    let file_name = segments
+        .last()
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+    // Имя объекта — сегмент после папки `dirs`, если за ним есть ещё что-то, кроме файла
+    let name_after = |dirs: &[&str]| {
+        let index = segments
+            .iter()
+            .rposition(|s| dirs.iter().any(|d| s.eq_ignore_ascii_case(d)))?;
+        (index + 2 < segments.len()).then(|| segments[index + 1].to_string())
+    };
 
-            if clean_text
-                .chars()
-                .any(|c| c >= '\u{0400}' && c <= '\u{04FF}')
-            {
-                return "Russian".to_string();
+    if file_name.ends_with(".form.module.bsl") {
+        return BslModuleType::FormModule(String::new());
+    }
+    match file_name.as_str() {
+        "objectmodule.bsl" => BslModuleType::ObjectModule,
+        "managermodule.bsl" => BslModuleType::ManagerModule,
+        "valuemanagermodule.bsl" => BslModuleType::ValueManagerModule,
+        "recordsetmodule.bsl" => BslModuleType::RecordSetModule,
+        "commandmodule.bsl" => BslModuleType::CommandModule,
+        "managedapplicationmodule.bsl" => BslModuleType::ManagedApplicationModule,
+        "ordinaryapplicationmodule.bsl" => BslModuleType::OrdinaryApplicationModule,
+        "sessionmodule.bsl" => BslModuleType::SessionModule,
+        "externalconnectionmodule.bsl" => BslModuleType::ExternalConnectionModule,
+        "module.bsl" => {
+            if let Some(form) = name_after(&["Forms", "CommonForms"]) {
+                BslModuleType::FormModule(form)
+            } else if let Some(module) = name_after(&["CommonModules"]) {
+                BslModuleType::CommonModule(module)
+            } else {
+                BslModuleType::Unknown
             }
-            break;
         }
+        _ => BslModuleType::Unknown,
+    }
+}
+
+/// Добавляет к системному промпту сведения о текущем файле.
+pub fn apply_file_context(prompt: &mut String, context: &FileContext) {
+    if context.file_path.trim().is_empty() {
+        return;
+    }
+    prompt.push_str(&format!("\nCurrent file: {}\n", context.file_path));
+    let module_type = context
+        .module_type
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+        .map(str::to_string)
+        .or_else(|| detect_bsl_module_type(&context.file_path).describe());
+    if let Some(module_type) = module_type {
+        prompt.push_str(&format!("Module type: {}\n", module_type));
+    }
+    if let Some(selected) = context
+        .selected_text
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+    {
+        prompt.push_str(&format!("Selected text:\n```bsl\n{}\n```\n", selected));
+    }
+}
+
+/// Язык ответа: настройка профиля имеет приоритет над настройкой приложения.
+pub fn answer_language(profile: Option<&LLMProfile>) -> AnswerLanguage {
+    profile
+        .and_then(|p| p.answer_language)
+        .unwrap_or_else(|| load_settings().code_generation.answer_language)
+}
+
+/// Короткая инструкция о языке ответа для пользовательских промптов и быстрых действий.
+pub fn answer_language_instruction(language: AnswerLanguage) -> &'static str {
+    match language {
+        AnswerLanguage::Ru => "Отвечай на русском языке.",
+        AnswerLanguage::En => "Always answer in English.",
+        AnswerLanguage::Uk => "Відповідай українською мовою.",
+        AnswerLanguage::Auto => "Answer in the language of the user's last message.",
     }
-    "Russian".to_string() // Default to Russian (system language)
 }
 
 /// Проверяет наличие BSL-кода в контексте диалога.
 pub fn has_code_context(messages: &[ApiMessage]) -> bool {
     for msg in messages {
-        if let Some(content) = &msg.content {
+        if let Some(content) = msg.text() {
             if content.contains("```bsl") || content.contains("```1c") {
                 return true;
             }
@@ -104,25 +346,24 @@ pub fn is_local_provider(provider: Option<&LLMProvider>) -> bool {
 /// Исключает тяжёлые инструкции (DIFF_FORMAT_INSTRUCTIONS полностью),
 /// огромную матрицу MCP-инструментов и правила маркировки — всё это перегружает
 /// малые модели (7-14B), заставляя их перефразировать вопрос вместо ответа.
-pub fn get_lightweight_system_prompt(
-    available_tools: &[ToolInfo],
-    messages: &[ApiMessage],
-) -> String {
-    let settings = load_settings();
-    build_lightweight_system_prompt_with_custom_prompts(
-        available_tools,
-        messages,
-        &settings.custom_prompts,
-    )
-}
-
 fn build_lightweight_system_prompt_with_custom_prompts(
     available_tools: &[ToolInfo],
     messages: &[ApiMessage],
     custom_prompts: &CustomPromptsSettings,
+    language: AnswerLanguage,
+    locale: SystemPromptLocale,
 ) -> String {
-    let target_lang = detect_target_lang(messages);
     let has_code = has_code_context(messages);
+    let language_line = answer_language_instruction(language);
+
+    if prompt_locale(locale, language, messages) == SystemPromptLocale::English {
+        return build_lightweight_english_prompt(
+            available_tools,
+            has_code,
+            custom_prompts,
+            language_line,
+        );
+    }
 
     let diff_section = if has_code {
         r#"
@@ -139,11 +380,11 @@ fn build_lightweight_system_prompt_with_custom_prompts(
 
     let mut prompt = format!(
         r#"Ты — AI-ассистент для разработки на платформе 1С:Предприятие.
-Отвечай ТОЛЬКО на {target_lang} языке.
+{language_line}
 Выполняй запросы пользователя точно и без лишних изменений.
 Не задавай уточняющих вопросов — выполняй задачу сразу.
 {diff_section}"#,
-        target_lang = target_lang,
+        language_line = language_line,
         diff_section = diff_section,
     );
 
@@ -163,6 +404,49 @@ fn build_lightweight_system_prompt_with_custom_prompts(
     prompt
 }
 
+/// Английский вариант компактного промпта (локаль `English`).
+fn build_lightweight_english_prompt(
+    available_tools: &[ToolInfo],
+    has_code: bool,
+    custom_prompts: &CustomPromptsSettings,
+    language_line: &str,
+) -> String {
+    let diff_section = if has_code {
+        r#"
+When changing code use ONLY the xml diff format:
+<diff>
+  <search>[exact fragment of the original]</search>
+  <replace>[new version]</replace>
+</diff>
+When writing code from scratch use a ```bsl block.
+Do not rewrite the whole file — change only the requested lines."#
+    } else {
+        "\nWhen writing new code use a ```bsl block."
+    };
+
+    let mut prompt = format!(
+        r#"You are an AI assistant for development on the 1C:Enterprise platform.
+{language_line}
+Carry out user requests precisely and without unrequested changes.
+Do not ask clarifying questions — do the task right away.
+{diff_section}"#
+    );
+
+    if !available_tools.is_empty() {
+        prompt.push_str("\n\nAvailable tools:\n");
+        for info in available_tools {
+            let name = &info.tool.function.name;
+            let desc = &info.tool.function.description;
+            let short_desc = desc.lines().next().unwrap_or(desc);
+            prompt.push_str(&format!("- `{name}`: {short_desc}\n"));
+        }
+    }
+
+    append_custom_prompt_settings(&mut prompt, custom_prompts);
+
+    prompt
+}
+
 fn append_custom_prompt_settings(prompt: &mut String, custom: &CustomPromptsSettings) {
     if !custom.system_prefix.trim().is_empty() {
         prompt.push_str("\n\n=== ПОЛЬЗОВАТЕЛЬСКИЕ ГЛОБАЛЬНЫЕ НАСТРОЙКИ (OVERRIDE) ===\n");
@@ -189,36 +473,156 @@ fn append_custom_prompt_settings(prompt: &mut String, custom: &CustomPromptsSett
     }
 }
 
-/// Get dynamic system prompt based on available tools
-pub fn get_system_prompt(available_tools: &[ToolInfo], messages: &[ApiMessage]) -> String {
+/// Откуда взят базовый системный промпт; сообщается в событии `chat-start`,
+/// чтобы было видно, почему модель ведёт себя по-разному в разных профилях
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemPromptSource {
+    /// Переопределение в запросе
+    Request,
+    /// `LLMProfile::system_prompt`
+    Profile,
+    /// Настройка приложения `default_system_prompt`
+    App,
+    BuiltIn,
+    /// Компактный встроенный промпт для локальных провайдеров
+    BuiltInLightweight,
+}
+
+/// Первый непустой промпт в порядке: запрос → профиль → приложение.
+/// Многострочный текст используется как есть.
+fn pick_configured_prompt<'a>(
+    request_prompt: Option<&'a str>,
+    profile_prompt: Option<&'a str>,
+    app_prompt: Option<&'a str>,
+) -> Option<(&'a str, SystemPromptSource)> {
+    [
+        (request_prompt, SystemPromptSource::Request),
+        (profile_prompt, SystemPromptSource::Profile),
+        (app_prompt, SystemPromptSource::App),
+    ]
+    .into_iter()
+    .find_map(|(prompt, source)| prompt.filter(|p| !p.trim().is_empty()).map(|p| (p, source)))
+}
+
+/// Системный промпт запроса и его источник. Порядок: переопределение запроса →
+/// профиль → настройка приложения → встроенный промпт (компактный для локальных
+/// провайдеров). В пользовательском промпте подставляются переменные `{{...}}` из `context`.
+pub fn resolve_system_prompt(
+    request_prompt: Option<&str>,
+    profile: &LLMProfile,
+    context: &PromptContext,
+    available_tools: &[ToolInfo],
+    messages: &[ApiMessage],
+) -> (String, SystemPromptSource) {
     let settings = load_settings();
-    let custom = &settings.custom_prompts;
-    let code_gen = &settings.code_generation;
+    let language = profile
+        .answer_language
+        .unwrap_or(settings.code_generation.answer_language);
+    let configured = pick_configured_prompt(
+        request_prompt,
+        profile.system_prompt.as_deref(),
+        settings.custom_prompts.default_system_prompt.as_deref(),
+    );
 
-    let mut prompt = String::new();
-    let target_lang = detect_target_lang(messages);
+    match configured {
+        Some((base, source)) => (
+            build_configured_system_prompt(
+                &render_template(base, context),
+                available_tools,
+                &settings.custom_prompts,
+                language,
+            ),
+            source,
+        ),
+        None if is_local_provider(Some(&profile.provider)) => (
+            build_lightweight_system_prompt_with_custom_prompts(
+                available_tools,
+                messages,
+                &settings.custom_prompts,
+                language,
+                profile.locale,
+            ),
+            SystemPromptSource::BuiltInLightweight,
+        ),
+        None => (
+            build_system_prompt(
+                available_tools,
+                messages,
+                language,
+                profile.locale,
+                profile.prompt_caching,
+            ),
+            SystemPromptSource::BuiltIn,
+        ),
+    }
+}
 
-    match code_gen.behavior_preset {
-        PromptBehaviorPreset::Project => {
-            prompt.push_str("Ты - эксперт-разработчик 1С. Твоя задача - писать чистый, поддерживаемый код, следуя стандартам 1С и БСП. Можешь исправлять ошибки и предлагать оптимальные решения в рамках запроса.\n\n");
-        }
-        PromptBehaviorPreset::Maintenance => {
-            prompt.push_str("Ты - специалист по поддержке 1С. Твоя ГЛАВНАЯ задача - вносить точечные изменения в существующий (возможно, чужой или типовой) код. НИКОГДА не проводи рефакторинг и не меняй логику, которую не просили затронуть.\n\n");
-            prompt.push_str("КРИТИЧЕСКОЕ ПРАВИЛО: Все свои изменения (добавление, изменение или удаление кода) ты обязан изолировать комментариями. НИКОГДА не удаляй существующие комментарии и копирайты.\n\n");
-        }
-        PromptBehaviorPreset::Cli => {
-            prompt.push_str("Ты - CLI-ассистент для 1С. Оптимизирован для работы через внешние CLI-провайдеры. Экономный расход токенов, фокус на конкретных изменениях. Пиши чистый код, следуя стандартам 1С.\n\n");
+/// Пользовательский промпт заменяет встроенный целиком; список инструментов
+/// и пользовательские инструкции добавляются как обычно.
+fn build_configured_system_prompt(
+    base: &str,
+    available_tools: &[ToolInfo],
+    custom_prompts: &CustomPromptsSettings,
+    language: AnswerLanguage,
+) -> String {
+    let mut prompt = base.trim().to_string();
+    prompt.push_str("\n\n");
+    prompt.push_str(answer_language_instruction(language));
+
+    if !available_tools.is_empty() {
+        prompt.push_str("\n\nДоступные инструменты:\n");
+        for info in available_tools {
+            let name = &info.tool.function.name;
+            let desc = &info.tool.function.description;
+            let short_desc = desc.lines().next().unwrap_or(desc);
+            prompt.push_str(&format!("- `{name}`: {short_desc}\n"));
         }
     }
 
-    let has_code = has_code_context(messages);
-    let code_rules = if has_code {
-        DIFF_FORMAT_INSTRUCTIONS
+    append_custom_prompt_settings(&mut prompt, custom_prompts);
+
+    prompt
+}
+
+fn english_edit_mode_instructions(has_code: bool) -> &'static str {
+    if has_code {
+        r#"QUESTION ANSWERING MODE (STRICT PRIORITY):
+- If the user's request is a QUESTION ("what does", "explain", "how does it work", "tell me", "why", "what is", "how is it used") — answer with text, do NOT use SEARCH/REPLACE.
+- IMPORTANT: the SEARCH/REPLACE ban in question mode does NOT forbid calling MCP tools (search_code, find_references, etc.) — always use them when you need information from the configuration.
+- In question mode it is FORBIDDEN to make ANY code changes, even "obvious improvements" or fixes.
+- Code changes (SEARCH/REPLACE) — when the request contains an explicit action: "fix", "add", "change", "rewrite", "remove", "create", "implement", "optimise", **"finish"**, **"complete"**, "extend".
+- EMPTY MODULE: If the BSL source is empty or contains only a marker/comments and the user asks to "add", "create" or "write" — generate the FULL module text from scratch in a ```bsl block. Do not try SEARCH/REPLACE on an empty file.
+
+**CRITICAL**: If you are given source code (context) and asked for a change — use SEARCH/REPLACE. Do NOT put the changed code into ```bsl``` blocks instead of SEARCH/REPLACE."#
     } else {
-        ""
-    };
+        r#"ANSWER MODE (NO CODE CONTEXT):
+- There is no loaded file to edit in the current dialog.
+- Answer ONLY with text, or with a ```bsl block when generating new code from scratch.
+- It is FORBIDDEN to use the SEARCH/REPLACE format — it does not apply without source code."#
+    }
+}
+
+fn ukrainian_edit_mode_instructions(has_code: bool) -> &'static str {
+    if has_code {
+        r#"РЕЖИМ ВІДПОВІДІ НА ЗАПИТАННЯ (СУВОРИЙ ПРІОРИТЕТ):
+- Якщо запит користувача є ЗАПИТАННЯМ ("що робить", "поясни", "як працює", "розкажи", "навіщо", "чому", "що таке", "як використовується") — відповідай текстом, НЕ використовуй SEARCH/REPLACE.
+- ВАЖЛИВО: заборона SEARCH/REPLACE у режимі запитання НЕ забороняє викликати MCP-інструменти (search_code, find_references тощо) — використовуй їх завжди, коли потрібно знайти інформацію в конфігурації.
+- У режимі запитання ЗАБОРОНЕНО вносити БУДЬ-ЯКІ зміни в код, навіть "очевидні покращення" чи виправлення.
+- Зміни коду (SEARCH/REPLACE) — якщо запит містить явну дію: "виправ", "додай", "зміни", "перепиши", "видали", "створи", "реалізуй", "оптимізуй", **"допиши"**, **"заверши"**, "доповни".
+- ПОРОЖНІЙ МОДУЛЬ: Якщо вихідний код BSL порожній або містить лише маркер/коментарі, а користувач просить "додати", "створити" чи "написати" — генеруй ПОВНИЙ текст модуля з нуля в блоці ```bsl. Не намагайся застосувати SEARCH/REPLACE до порожнього файлу.
+
+**КРИТИЧНО ВАЖЛИВО**: Якщо тобі надано вихідний код (контекст) і запитано зміну — використовуй SEARCH/REPLACE. НЕ оформлюй змінений код у блоки ```bsl``` замість SEARCH/REPLACE."#
+    } else {
+        r#"РЕЖИМ ВІДПОВІДІ (КОНТЕКСТ КОДУ ВІДСУТНІЙ):
+- У поточному діалозі немає завантаженого файлу для редагування.
+- Відповідай ЛИШЕ текстом або блоком ```bsl під час генерації нового коду з нуля.
+- ЗАБОРОНЕНО використовувати формат SEARCH/REPLACE — він не застосовний без вихідного коду."#
+    }
+}
 
-    let edit_mode_instructions = if has_code {
+fn russian_edit_mode_instructions(has_code: bool) -> &'static str {
+    if has_code {
         r#"РЕЖИМ ОТВЕТА НА ВОПРОСЫ (СТРОГИЙ ПРИОРИТЕТ):
 - Если запрос пользователя является ВОПРОСОМ (содержит слова: "что делает", "объясни", "как работает", "расскажи", "зачем", "почему", "что такое", "как используется") — отвечай текстом, НЕ используй SEARCH/REPLACE.
 - ВАЖНО: запрет на SEARCH/REPLACE в режиме вопроса НЕ запрещает вызывать MCP-инструменты (search_code, find_references и др.) — их используй всегда когда нужно найти информацию в конфигурации.
@@ -232,18 +636,61 @@ pub fn get_system_prompt(available_tools: &[ToolInfo], messages: &[ApiMessage])
 - В текущем диалоге нет загруженного файла для редактирования.
 - Отвечай ТОЛЬКО текстом или блоком ```bsl при генерации нового кода с нуля.
 - ЗАПРЕЩЕНО использовать формат SEARCH/REPLACE — он не применим без исходного кода."#
+    }
+}
+
+fn edit_mode_instructions(locale: SystemPromptLocale, has_code: bool) -> &'static str {
+    match locale {
+        SystemPromptLocale::Russian => russian_edit_mode_instructions(has_code),
+        SystemPromptLocale::Ukrainian => ukrainian_edit_mode_instructions(has_code),
+        SystemPromptLocale::English => english_edit_mode_instructions(has_code),
+    }
+}
+
+/// Правила языка ответа; одинаковы для всех локалей промпта, поэтому на английском.
+fn language_rules(language: AnswerLanguage) -> String {
+    let name = match language {
+        AnswerLanguage::Ru => "Russian",
+        AnswerLanguage::En => "English",
+        AnswerLanguage::Uk => "Ukrainian",
+        AnswerLanguage::Auto => {
+            return r#"- Respond in the SAME language as the user's LAST message (Russian → Russian, English → English, etc.).
+- You MAY think inside `<thinking>` in any language (English is preferred for efficiency).
+- Code identifiers and BSL keywords stay as written in the source regardless of the answer language."#
+                .to_string()
+        }
     };
+    format!(
+        r#"- ALWAYS respond in **{name}**, even if the user writes in another language. This is MANDATORY.
+- You MAY think inside `<thinking>` in any language (English is preferred for efficiency).
+- But the FINAL ANSWER (outside `<thinking>`) MUST ALWAYS be in {name}.
+- Code identifiers, BSL keywords and 1C metadata names stay as written in the source."#
+    )
+}
 
-    prompt.push_str(&format!(
-        r#"Ты - AI-ассистент для разработки на платформе 1С:Предприятие.
+fn final_reminder(language: AnswerLanguage) -> &'static str {
+    match language {
+        AnswerLanguage::Ru => "ФИНАЛЬНОЕ НАПОМИНАНИЕ: твой ответ НА РУССКОМ ЯЗЫКЕ!",
+        AnswerLanguage::En => "FINAL REMINDER: your answer must be IN ENGLISH!",
+        AnswerLanguage::Uk => "ФІНАЛЬНЕ НАГАДУВАННЯ: твоя відповідь УКРАЇНСЬКОЮ МОВОЮ!",
+        AnswerLanguage::Auto => {
+            "FINAL REMINDER: answer in the language of the user's last message!"
+        }
+    }
+}
+
+lazy_static! {
+    /// Основная часть встроенного промпта по локалям профиля; `{language_rules}`,
+    /// `{code_rules}`, `{edit_mode_instructions}` и `{final_reminder}` подставляются при сборке.
+    pub static ref SYSTEM_PROMPTS: HashMap<SystemPromptLocale, &'static str> = HashMap::from([
+        (
+            SystemPromptLocale::Russian,
+            r#"Ты - AI-ассистент для разработки на платформе 1С:Предприятие.
 
 === ЯЗЫК ОТВЕТА (КРИТИЧЕСКИ ВАЖНО) ===
-- ALWAYS respond in **{}** language. This is MANDATORY and MUST NOT be violated under any circumstances.
-- You MAY think inside `<thinking>` in any language (English is preferred for efficiency).
-- But the FINAL ANSWER (outside `<thinking>`) MUST ALWAYS be in {} — NEVER in English or any other language.
-- If the user writes in Russian — answer in Russian. If in another language — answer in Russian anyway.
+{language_rules}
 
-{}
+{code_rules}
 Твоя ГЛАВНАЯ ЦЕЛЬ: Выполнять запросы пользователя МАКСИМАЛЬНО ТОЧНО, НЕ ВНОСЯ НИКАКИХ ЛИШНИХ ИЗМЕНЕНИЙ.
 
 Твои задачи:
@@ -257,9 +704,9 @@ pub fn get_system_prompt(available_tools: &[ToolInfo], messages: &[ApiMessage])
 - ЗАПРЕЩЕНО изменять код за пределами запрашиваемых модификаций.
 - НЕ исправляй опечатки в переменных, если об этом не просили, так как это нарушит ссылки в других модулях.
 
-{}
+{edit_mode_instructions}
 
-ФИНАЛЬНОЕ НАПОМИНАНИЕ: твой ответ НА РУССКОМ ЯЗЫКЕ!
+{final_reminder}
 
 === ОТСТУПЫ В КОДЕ (КРИТИЧЕСКИ ВАЖНО) ===
 - При генерации ЛЮБОГО кода BSL используй СИМВОЛ ТАБУЛЯЦИИ (\t) для отступов — НЕ пробелы.
@@ -277,13 +724,181 @@ pub fn get_system_prompt(available_tools: &[ToolInfo], messages: &[ApiMessage])
 //
 // Возвращаемое значение:
 //   Тип - Описание"#,
-        target_lang, target_lang, code_rules, edit_mode_instructions
-    ));
+        ),
+        (
+            SystemPromptLocale::Ukrainian,
+            r#"Ти - AI-асистент для розробки на платформі 1С:Підприємство.
+
+=== МОВА ВІДПОВІДІ (КРИТИЧНО ВАЖЛИВО) ===
+{language_rules}
+
+{code_rules}
+Твоя ГОЛОВНА МЕТА: виконувати запити користувача МАКСИМАЛЬНО ТОЧНО, НЕ ВНОСЯЧИ ЖОДНИХ ЗАЙВИХ ЗМІН.
+
+Твої завдання:
+1. Виконувати конкретні запити щодо коду (додати коментар, змінити умову тощо).
+2. Пояснювати логіку коду.
+3. Шукати помилки ЛИШЕ тоді, коли про це просять.
+
+ГОЛОВНА ДИРЕКТИВА (STRICT COMPLIANCE):
+- Вноси зміни ЛИШЕ в суворій відповідності до запиту користувача.
+- ЗАБОРОНЯЄТЬСЯ будь-який самовільний рефакторинг, оптимізація алгоритмів чи видалення коментарів.
+- ЗАБОРОНЕНО змінювати код поза межами запитаних модифікацій.
+- НЕ виправляй описки в іменах змінних, якщо про це не просили, бо це зламає посилання в інших модулях.
+
+{edit_mode_instructions}
+
+{final_reminder}
+
+=== ВІДСТУПИ В КОДІ (КРИТИЧНО ВАЖЛИВО) ===
+- Під час генерації БУДЬ-ЯКОГО коду BSL використовуй СИМВОЛ ТАБУЛЯЦІЇ (	) для відступів — НЕ пробіли.
+- Конфігуратор 1С за замовчуванням використовує табуляцію (опцію "Заменять табуляцию при вводе" вимкнено).
+- Змішування табуляції та пробілів НЕПРИПУСТИМЕ.
+
+=== ФОРМАТ ДОКУМЕНТАЦІЇ (КРИТИЧНО ВАЖЛИВО) ===
+- Для описів (шапок) процедур і функцій використовуй ЛИШЕ стандартний формат коментарів 1С (символи //).
+- КАТЕГОРИЧНО ЗАБОРОНЕНО використовувати XML-подібні теги, як-от `<ОписаниеФункции>`, `<Параметры>`, `<ВозвращаемоеЗначение>`.
+- Ключові слова шапки 1С залишай російською. ШАБЛОН:
+// Рассчитывает...
+//
+// Параметры:
+//   ИмяПараметра - Тип - Описание
+//
+// Возвращаемое значение:
+//   Тип - Описание"#,
+        ),
+        (
+            SystemPromptLocale::English,
+            r#"You are an AI assistant for development on the 1C:Enterprise platform.
+
+=== ANSWER LANGUAGE (CRITICAL) ===
+{language_rules}
+
+{code_rules}
+Your MAIN GOAL: carry out user requests AS PRECISELY AS POSSIBLE, WITHOUT ANY UNREQUESTED CHANGES.
+
+Your tasks:
+1. Carry out concrete code requests (add a comment, change a condition, etc.).
+2. Explain code logic.
+3. Look for errors ONLY when asked.
+
+MAIN DIRECTIVE (STRICT COMPLIANCE):
+- Make changes ONLY in strict accordance with the user's request.
+- Any spontaneous refactoring, algorithm optimisation or removal of comments is FORBIDDEN.
+- It is FORBIDDEN to change code outside the requested modifications.
+- Do NOT fix typos in variable names unless asked, as that would break references in other modules.
+
+{edit_mode_instructions}
+
+{final_reminder}
+
+=== CODE INDENTATION (CRITICAL) ===
+- When generating ANY BSL code use the TAB CHARACTER (	) for indentation — NOT spaces.
+- The 1C Configurator uses tabs by default ("Replace tabs on input" is off).
+- Mixing tabs and spaces is NOT ALLOWED.
+
+=== DOCUMENTATION FORMAT (CRITICAL) ===
+- For procedure and function headers use ONLY the standard 1C comment format (// characters).
+- XML-like tags such as `<ОписаниеФункции>`, `<Параметры>`, `<ВозвращаемоеЗначение>` are STRICTLY FORBIDDEN.
+- Keep the standard 1C header keywords in Russian. TEMPLATE:
+// Рассчитывает...
+//
+// Параметры:
+//   ИмяПараметра - Тип - Описание
+//
+// Возвращаемое значение:
+//   Тип - Описание"#,
+        ),
+    ]);
+}
+
+/// Get dynamic system prompt based on available tools
+pub fn get_system_prompt(
+    available_tools: &[ToolInfo],
+    messages: &[ApiMessage],
+    language: AnswerLanguage,
+    locale: SystemPromptLocale,
+) -> String {
+    build_system_prompt(available_tools, messages, language, locale, false)
+}
+
+/// `stable_prefix`: без текущего времени в маркерах изменений, чтобы промпт
+/// был побайтно одинаковым между запросами (кэширование промпта у провайдера).
+fn build_system_prompt(
+    available_tools: &[ToolInfo],
+    messages: &[ApiMessage],
+    language: AnswerLanguage,
+    locale: SystemPromptLocale,
+    stable_prefix: bool,
+) -> String {
+    let settings = load_settings();
+    let custom = &settings.custom_prompts;
+    let code_gen = &settings.code_generation;
+
+    let mut prompt = String::new();
+    let locale = prompt_locale(locale, language, messages);
+    let english = locale == SystemPromptLocale::English;
+    let ukrainian = locale == SystemPromptLocale::Ukrainian;
+
+    match code_gen.behavior_preset {
+        PromptBehaviorPreset::Project if english => {
+            prompt.push_str("You are an expert 1C developer. Your job is to write clean, maintainable code following the 1C and SSL (БСП) standards. You may fix errors and suggest optimal solutions within the scope of the request.\n\n");
+        }
+        PromptBehaviorPreset::Maintenance if english => {
+            prompt.push_str("You are a 1C maintenance specialist. Your MAIN job is to make targeted changes to existing (possibly third-party or vendor) code. NEVER refactor or change logic you were not asked to touch.\n\n");
+            prompt.push_str("CRITICAL RULE: You must isolate all your changes (added, modified or removed code) with comments. NEVER remove existing comments and copyrights.\n\n");
+        }
+        PromptBehaviorPreset::Cli if english => {
+            prompt.push_str("You are a CLI assistant for 1C, optimised for external CLI providers. Spend tokens sparingly and focus on concrete changes. Write clean code following the 1C standards.\n\n");
+        }
+        PromptBehaviorPreset::Project if ukrainian => {
+            prompt.push_str("Ти - експерт-розробник 1С. Твоє завдання - писати чистий, підтримуваний код, дотримуючись стандартів 1С і БСП. Можеш виправляти помилки та пропонувати оптимальні рішення в межах запиту.\n\n");
+        }
+        PromptBehaviorPreset::Maintenance if ukrainian => {
+            prompt.push_str("Ти - фахівець із супроводу 1С. Твоє ГОЛОВНЕ завдання - вносити точкові зміни в наявний (можливо, чужий або типовий) код. НІКОЛИ не проводь рефакторинг і не змінюй логіку, яку не просили зачіпати.\n\n");
+            prompt.push_str("КРИТИЧНЕ ПРАВИЛО: Усі свої зміни (додавання, зміну чи видалення коду) ти зобов'язаний ізолювати коментарями. НІКОЛИ не видаляй наявні коментарі та копірайти.\n\n");
+        }
+        PromptBehaviorPreset::Cli if ukrainian => {
+            prompt.push_str("Ти - CLI-асистент для 1С, оптимізований для роботи через зовнішні CLI-провайдери. Ощадливо витрачай токени, зосереджуйся на конкретних змінах. Пиши чистий код за стандартами 1С.\n\n");
+        }
+        PromptBehaviorPreset::Project => {
+            prompt.push_str("Ты - эксперт-разработчик 1С. Твоя задача - писать чистый, поддерживаемый код, следуя стандартам 1С и БСП. Можешь исправлять ошибки и предлагать оптимальные решения в рамках запроса.\n\n");
+        }
+        PromptBehaviorPreset::Maintenance => {
+            prompt.push_str("Ты - специалист по поддержке 1С. Твоя ГЛАВНАЯ задача - вносить точечные изменения в существующий (возможно, чужой или типовой) код. НИКОГДА не проводи рефакторинг и не меняй логику, которую не просили затронуть.\n\n");
+            prompt.push_str("КРИТИЧЕСКОЕ ПРАВИЛО: Все свои изменения (добавление, изменение или удаление кода) ты обязан изолировать комментариями. НИКОГДА не удаляй существующие комментарии и копирайты.\n\n");
+        }
+        PromptBehaviorPreset::Cli => {
+            prompt.push_str("Ты - CLI-ассистент для 1С. Оптимизирован для работы через внешние CLI-провайдеры. Экономный расход токенов, фокус на конкретных изменениях. Пиши чистый код, следуя стандартам 1С.\n\n");
+        }
+    }
+
+    let has_code = has_code_context(messages);
+    let code_rules = if has_code {
+        DIFF_FORMAT_INSTRUCTIONS
+    } else {
+        ""
+    };
+
+    prompt.push_str(
+        &SYSTEM_PROMPTS[&locale]
+            .replace("{language_rules}", &language_rules(language))
+            .replace("{final_reminder}", final_reminder(language))
+            .replace(
+                "{edit_mode_instructions}",
+                edit_mode_instructions(locale, has_code),
+            )
+            .replace("{code_rules}", code_rules),
+    );
 
     if code_gen.mark_changes || code_gen.behavior_preset == PromptBehaviorPreset::Maintenance {
         let now = chrono::Local::now();
         let date_str = now.format("%Y-%m-%d").to_string();
-        let datetime_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
+        let datetime_str = if stable_prefix {
+            date_str.clone()
+        } else {
+            now.format("%Y-%m-%d %H:%M:%S").to_string()
+        };
 
         let addition_marker = code_gen
             .addition_marker_template
@@ -568,10 +1183,241 @@ mod tests {
     use crate::settings::{CustomPromptsSettings, PromptTemplate};
     use serde_json::json;
 
+    #[test]
+    fn configured_prompt_source_follows_precedence() {
+        let profile_prompt = "Отвечай кратко.\nТолько код на BSL.";
+        assert_eq!(
+            pick_configured_prompt(Some("  "), Some(profile_prompt), Some("app")),
+            Some((profile_prompt, SystemPromptSource::Profile))
+        );
+        assert_eq!(
+            pick_configured_prompt(Some("req"), Some(profile_prompt), None),
+            Some(("req", SystemPromptSource::Request))
+        );
+        assert_eq!(
+            pick_configured_prompt(None, Some("\n"), Some("app")),
+            Some(("app", SystemPromptSource::App))
+        );
+        assert_eq!(pick_configured_prompt(None, None, Some("")), None);
+        assert_eq!(
+            serde_json::to_value(SystemPromptSource::BuiltInLightweight).unwrap(),
+            json!("built_in_lightweight")
+        );
+    }
+
+    #[test]
+    fn detects_module_kind_from_export_paths() {
+        let cases = [
+            (
+                "Catalogs/Товары/Forms/ФормаЭлемента/Module.bsl",
+                Some("МодульФормы"),
+            ),
+            (
+                "src/Catalogs/Товары/Forms/ФормаЭлемента/Ext/Form/Module.bsl",
+                Some("МодульФормы"),
+            ),
+            ("src/CommonForms/Вопрос/Module.bsl", Some("МодульФормы")),
+            ("Документ.Заказ.Form.Module.bsl", Some("МодульФормы")),
+            (
+                "src\\CommonModules\\ОбщегоНазначения\\Module.bsl",
+                Some("ОбщийМодуль"),
+            ),
+            (
+                "src/Documents/Заказ/ObjectModule.bsl",
+                Some("МодульОбъекта"),
+            ),
+            (
+                "src/Documents/Заказ/ManagerModule.bsl",
+                Some("МодульМенеджера"),
+            ),
+            (
+                "src/Constants/Валюта/ValueManagerModule.bsl",
+                Some("МодульМенеджераЗначения"),
+            ),
+            (
+                "src/InformationRegisters/Цены/RecordSetModule.bsl",
+                Some("МодульНабораЗаписей"),
+            ),
+            (
+                "src/CommonCommands/Печать/CommandModule.bsl",
+                Some("МодульКоманды"),
+            ),
+            (
+                "src/Configuration/ManagedApplicationModule.bsl",
+                Some("МодульУправляемогоПриложения"),
+            ),
+            (
+                "src/Configuration/OrdinaryApplicationModule.bsl",
+                Some("МодульОбычногоПриложения"),
+            ),
+            ("src/Configuration/SessionModule.bsl", Some("МодульСеанса")),
+            (
+                "src/Configuration/ExternalConnectionModule.bsl",
+                Some("МодульВнешнегоСоединения"),
+            ),
+            ("scripts/build.bsl", None),
+            ("src/Catalogs/Товары/Module.bsl", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                detect_bsl_module_type(path).kind_name(),
+                expected,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn detects_module_type_with_form_and_common_module_names() {
+        use BslModuleType::*;
+        let form = |name: &str| FormModule(name.to_string());
+        let common = |name: &str| CommonModule(name.to_string());
+        let cases = [
+            // EDT: src/<Metadata>/<Object>/...
+            ("src/Catalogs/Товары/ObjectModule.bsl", ObjectModule),
+            ("src/Documents/Заказ/ObjectModule.bsl", ObjectModule),
+            ("src/DataProcessors/Загрузка/ObjectModule.bsl", ObjectModule),
+            ("src/Reports/Продажи/ObjectModule.bsl", ObjectModule),
+            ("src/ExchangePlans/Полный/ObjectModule.bsl", ObjectModule),
+            (
+                "src/BusinessProcesses/Согласование/ObjectModule.bsl",
+                ObjectModule,
+            ),
+            ("src/Tasks/Задача/ObjectModule.bsl", ObjectModule),
+            (
+                "src/ChartsOfCharacteristicTypes/Свойства/ObjectModule.bsl",
+                ObjectModule,
+            ),
+            ("src/Catalogs/Товары/ManagerModule.bsl", ManagerModule),
+            ("src/Documents/Заказ/ManagerModule.bsl", ManagerModule),
+            ("src/Enums/Статусы/ManagerModule.bsl", ManagerModule),
+            (
+                "src/InformationRegisters/Цены/ManagerModule.bsl",
+                ManagerModule,
+            ),
+            (
+                "src/Constants/Валюта/ValueManagerModule.bsl",
+                ValueManagerModule,
+            ),
+            (
+                "src/InformationRegisters/Цены/RecordSetModule.bsl",
+                RecordSetModule,
+            ),
+            (
+                "src/AccumulationRegisters/Остатки/RecordSetModule.bsl",
+                RecordSetModule,
+            ),
+            (
+                "src/Catalogs/Товары/Forms/ФормаЭлемента/Module.bsl",
+                form("ФормаЭлемента"),
+            ),
+            (
+                "src/Documents/Заказ/Forms/ФормаДокумента/Module.bsl",
+                form("ФормаДокумента"),
+            ),
+            (
+                "src/DataProcessors/Загрузка/Forms/Форма/Module.bsl",
+                form("Форма"),
+            ),
+            ("src/CommonForms/Вопрос/Module.bsl", form("Вопрос")),
+            ("src/CommonCommands/Печать/CommandModule.bsl", CommandModule),
+            (
+                "src/Catalogs/Товары/Commands/Открыть/CommandModule.bsl",
+                CommandModule,
+            ),
+            (
+                "src/CommonModules/ОбщегоНазначения/Module.bsl",
+                common("ОбщегоНазначения"),
+            ),
+            (
+                "src/Configuration/ManagedApplicationModule.bsl",
+                ManagedApplicationModule,
+            ),
+            (
+                "src/Configuration/OrdinaryApplicationModule.bsl",
+                OrdinaryApplicationModule,
+            ),
+            ("src/Configuration/SessionModule.bsl", SessionModule),
+            (
+                "src/Configuration/ExternalConnectionModule.bsl",
+                ExternalConnectionModule,
+            ),
+            // Configurator dump and Windows separators
+            (
+                "Catalogs/Товары/Forms/ФормаСписка/Ext/Form/Module.bsl",
+                form("ФормаСписка"),
+            ),
+            ("CommonModules\\Общий\\Ext\\Module.bsl", common("Общий")),
+            ("Документ.Заказ.Form.Module.bsl", form("")),
+            // Case of the folders does not matter, the names keep theirs
+            (
+                "SRC/commonmodules/РаботаСФайлами/module.bsl",
+                common("РаботаСФайлами"),
+            ),
+            // Not enough path to tell
+            ("src/Catalogs/Товары/Module.bsl", Unknown),
+            ("Forms/Module.bsl", Unknown),
+            ("CommonModules/Module.bsl", Unknown),
+            ("scripts/build.bsl", Unknown),
+            ("", Unknown),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(detect_bsl_module_type(path), expected, "{}", path);
+        }
+
+        assert_eq!(
+            common("ОбщегоНазначения").describe().as_deref(),
+            Some("ОбщийМодуль «ОбщегоНазначения»")
+        );
+        assert_eq!(form("").describe().as_deref(), Some("МодульФормы"));
+        assert_eq!(Unknown.describe(), None);
+    }
+
+    #[test]
+    fn file_context_section_uses_inferred_module_type() {
+        let mut prompt = "base".to_string();
+        apply_file_context(
+            &mut prompt,
+            &FileContext {
+                file_path: "src/Documents/Заказ/ObjectModule.bsl".to_string(),
+                module_type: None,
+                selected_text: None,
+            },
+        );
+        assert_eq!(
+            prompt,
+            "base\nCurrent file: src/Documents/Заказ/ObjectModule.bsl\nModule type: МодульОбъекта\n"
+        );
+
+        let mut prompt = String::new();
+        apply_file_context(
+            &mut prompt,
+            &FileContext {
+                file_path: "Обработка.bsl".to_string(),
+                module_type: Some("МодульОбъекта".to_string()),
+                selected_text: Some("А = 1;".to_string()),
+            },
+        );
+        assert!(prompt.contains("Module type: МодульОбъекта\n"));
+        assert!(prompt.contains("Selected text:\n```bsl\nА = 1;\n```"));
+
+        let mut prompt = String::new();
+        apply_file_context(
+            &mut prompt,
+            &FileContext {
+                file_path: "src/Catalogs/Товары/Forms/ФормаЭлемента/Module.bsl".to_string(),
+                module_type: None,
+                selected_text: None,
+            },
+        );
+        assert!(prompt.ends_with("Module type: МодульФормы «ФормаЭлемента»\n"));
+    }
+
     fn make_user_message(content: &str) -> ApiMessage {
         ApiMessage {
             role: "user".to_string(),
-            content: Some(content.to_string()),
+            content: Some(content.into()),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -606,9 +1452,156 @@ mod tests {
             on_code_change: String::new(),
             on_code_generate: String::new(),
             templates,
+            default_system_prompt: None,
         }
     }
 
+    #[test]
+    fn configured_system_prompt_replaces_builtin_and_keeps_tools() {
+        let custom = make_custom_prompts_with_templates(Vec::new());
+        let prompt = build_configured_system_prompt(
+            "Пиши код по стандартам нашей команды.",
+            &[make_check_bsl_tool()],
+            &custom,
+            AnswerLanguage::En,
+        );
+
+        assert!(prompt.starts_with("Пиши код по стандартам нашей команды."));
+        assert!(prompt.contains("`check_bsl_syntax`"));
+        assert!(!prompt.contains("1С:Предприятие"));
+        assert!(prompt.contains("Always answer in English."));
+    }
+
+    #[test]
+    fn answer_language_is_independent_of_prompt_locale() {
+        let msgs = [make_user_message("Напиши функцию")];
+        let ru = SystemPromptLocale::Russian;
+
+        let russian = get_system_prompt(&[], &msgs, AnswerLanguage::Ru, ru);
+        assert!(russian.contains("1С:Предприятие"));
+        assert!(russian.contains("твой ответ НА РУССКОМ ЯЗЫКЕ"));
+
+        let english = get_system_prompt(&[], &msgs, AnswerLanguage::En, ru);
+        assert!(english.contains("1С:Предприятие"));
+        assert!(english.contains("ALWAYS respond in **English**"));
+        assert!(!english.contains("НА РУССКОМ ЯЗЫКЕ"));
+
+        let auto = get_system_prompt(&[], &msgs, AnswerLanguage::Auto, ru);
+        assert!(auto.contains("SAME language as the user's LAST message"));
+        assert!(!auto.contains("ALWAYS respond in"));
+
+        let custom = make_custom_prompts_with_templates(Vec::new());
+        let light = build_lightweight_system_prompt_with_custom_prompts(
+            &[],
+            &msgs,
+            &custom,
+            AnswerLanguage::En,
+            SystemPromptLocale::English,
+        );
+        assert!(light.contains("1C:Enterprise"));
+        assert!(light.contains("Always answer in English."));
+    }
+
+    #[test]
+    fn profile_locale_selects_prompt_and_auto_follows_message() {
+        let msgs = [make_user_message("Напиши функцію")];
+        let ukrainian = get_system_prompt(
+            &[],
+            &msgs,
+            AnswerLanguage::Uk,
+            SystemPromptLocale::Ukrainian,
+        );
+        assert!(ukrainian.contains("1С:Підприємство"));
+        assert!(ukrainian.contains("ALWAYS respond in **Ukrainian**"));
+        assert!(!ukrainian.contains("НА РУССКОМ ЯЗЫКЕ"));
+
+        let english =
+            get_system_prompt(&[], &msgs, AnswerLanguage::Ru, SystemPromptLocale::English);
+        assert!(english.contains("1C:Enterprise"));
+        assert!(english.contains("ALWAYS respond in **Russian**"));
+
+        let ru = SystemPromptLocale::Russian;
+        let auto = get_system_prompt(&[], &msgs, AnswerLanguage::Auto, ru);
+        assert!(auto.contains("1С:Підприємство"));
+        let english_msgs = [make_user_message("Write a function that sums two numbers")];
+        let auto_en = get_system_prompt(&[], &english_msgs, AnswerLanguage::Auto, ru);
+        assert!(auto_en.contains("1C:Enterprise"));
+        let no_messages = get_system_prompt(&[], &[], AnswerLanguage::Auto, ru);
+        assert!(no_messages.contains("1С:Предприятие"));
+    }
+
+    #[test]
+    fn every_locale_has_all_placeholders() {
+        for locale in [
+            SystemPromptLocale::Russian,
+            SystemPromptLocale::Ukrainian,
+            SystemPromptLocale::English,
+        ] {
+            let template = SYSTEM_PROMPTS[&locale];
+            for placeholder in [
+                "{language_rules}",
+                "{code_rules}",
+                "{edit_mode_instructions}",
+                "{final_reminder}",
+            ] {
+                assert!(template.contains(placeholder), "{locale:?}: {placeholder}");
+            }
+        }
+    }
+
+    #[test]
+    fn language_hint_follows_last_user_message() {
+        let message = |role: &str, text: &str| ApiMessage {
+            role: role.to_string(),
+            content: Some(text.into()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+        let messages = vec![
+            message("user", "Explain this module"),
+            message("assistant", "Модуль проводит документ."),
+            message("user", "А что делает процедура ОбработкаПроведения?"),
+        ];
+        assert_eq!(language_match_hint(AnswerLanguage::Auto, &messages), None);
+        assert_eq!(
+            language_match_hint(AnswerLanguage::Auto, &messages[..2]),
+            Some("The user is writing in English. Reply in English.")
+        );
+        assert_eq!(language_match_hint(AnswerLanguage::Auto, &[]), None);
+        // An explicitly chosen answer language is kept
+        for language in [AnswerLanguage::Ru, AnswerLanguage::En, AnswerLanguage::Uk] {
+            assert_eq!(language_match_hint(language, &messages[..2]), None);
+        }
+    }
+
+    #[test]
+    fn detects_locale_by_script() {
+        assert_eq!(
+            detect_locale_from_message("Привет, как дела?"),
+            SystemPromptLocale::Russian
+        );
+        assert_eq!(
+            detect_locale_from_message("Привіт, як справи?"),
+            SystemPromptLocale::Ukrainian
+        );
+        assert_eq!(
+            detect_locale_from_message("Explain this code"),
+            SystemPromptLocale::English
+        );
+        assert_eq!(
+            detect_locale_from_message("Explain what `Запрос` does here"),
+            SystemPromptLocale::English
+        );
+        assert_eq!(detect_locale_from_message(""), SystemPromptLocale::Russian);
+        // Only the first 50 characters count
+        let late = format!("{}ї", "а".repeat(50));
+        assert_eq!(
+            detect_locale_from_message(&late),
+            SystemPromptLocale::Russian
+        );
+    }
+
     #[test]
     fn lightweight_system_prompt_includes_enabled_custom_templates() {
         let custom = make_custom_prompts_with_templates(vec![PromptTemplate {
@@ -623,6 +1616,8 @@ mod tests {
             &[],
             &[make_user_message("Напиши функцию")],
             &custom,
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
         );
 
         assert!(prompt.contains("ISSUE160_CHECK_BSL_AFTER_EACH_ANSWER"));
@@ -643,6 +1638,8 @@ mod tests {
             &[],
             &[make_user_message("Напиши функцию")],
             &custom,
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
         );
 
         assert!(!prompt.contains("ISSUE160_DISABLED_RULE_SHOULD_NOT_APPEAR"));
@@ -651,8 +1648,12 @@ mod tests {
 
     #[test]
     fn system_prompt_describes_strict_rule_for_selective_fix_scope() {
-        let prompt =
-            get_system_prompt(&[make_check_bsl_tool()], &[make_user_message("/исправить")]);
+        let prompt = get_system_prompt(
+            &[make_check_bsl_tool()],
+            &[make_user_message("/исправить")],
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
+        );
 
         assert!(prompt.contains("=== SELECTIVE BSL FIX SCOPE ==="));
         assert!(prompt.contains("НЕ вызывай `check_bsl_syntax` до внесения правок"));
@@ -664,8 +1665,19 @@ mod tests {
         let tools = vec![make_check_bsl_tool()];
         let msgs = vec![make_user_message("напиши функцию")];
 
-        let full = get_system_prompt(&tools, &msgs);
-        let light = get_lightweight_system_prompt(&tools, &msgs);
+        let full = get_system_prompt(
+            &tools,
+            &msgs,
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
+        );
+        let light = build_lightweight_system_prompt_with_custom_prompts(
+            &tools,
+            &msgs,
+            &load_settings().custom_prompts,
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
+        );
 
         // Лёгкий промпт должен быть не длиннее половины полного.
         assert!(
@@ -747,7 +1759,13 @@ mod tests {
         let user_msg_content = "Напиши простую BSL-функцию ФункцияПример() без параметров, которая возвращает строку \"Привет, 1С!\".";
         let user_msg = make_user_message(user_msg_content);
         let tools: Vec<ToolInfo> = vec![];
-        let system_content = get_lightweight_system_prompt(&tools, &[user_msg.clone()]);
+        let system_content = build_lightweight_system_prompt_with_custom_prompts(
+            &tools,
+            &[user_msg.clone()],
+            &load_settings().custom_prompts,
+            AnswerLanguage::Ru,
+            SystemPromptLocale::Russian,
+        );
 
         eprintln!(
             "[INFO] Лёгкий промпт ({} chars):\n{}",
@@ -0,0 +1,328 @@
+//! Rate-limit headers of the last response per profile (`x-ratelimit-*`, Anthropic's
+//! `anthropic-ratelimit-*`). A request that would not fit into the remaining budget
+//! waits for the reset instead of running into 429.
+//!
+//! Only the profile's own state is consulted and no lock is held while waiting, so a
+//! throttled profile never delays requests of other profiles.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use tauri::Emitter;
+
+/// Longest wait for a reset; after it the request goes out and 429 retries take over
+const MAX_THROTTLE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RateLimitStatus {
+    pub limit_requests: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    /// Unix time in ms when the request budget is restored
+    pub reset_requests_at_ms: Option<i64>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// Unix time in ms when the token budget is restored
+    pub reset_tokens_at_ms: Option<i64>,
+    pub updated_at_ms: i64,
+}
+
+static RATE_LIMITS: OnceLock<Mutex<HashMap<String, RateLimitStatus>>> = OnceLock::new();
+
+fn rate_limits() -> &'static Mutex<HashMap<String, RateLimitStatus>> {
+    RATE_LIMITS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn header<'a>(headers: &'a HeaderMap, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+fn header_u64(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+    header(headers, names)?.parse().ok()
+}
+
+/// Go-style duration as sent by OpenAI and Groq (`20ms`, `1.5s`, `6m0s`, `1h2m3s`)
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        total += number
+            * match &rest[..unit_len] {
+                "ms" => 0.001,
+                "s" | "" => 1.0,
+                "m" => 60.0,
+                "h" => 3600.0,
+                _ => return None,
+            };
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+/// Reset as a duration (OpenAI, Groq) or an RFC 3339 time (Anthropic)
+fn parse_reset(value: &str, now: DateTime<Utc>) -> Option<i64> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.timestamp_millis());
+    }
+    let delay = chrono::Duration::from_std(parse_duration(value)?).ok()?;
+    Some((now + delay).timestamp_millis())
+}
+
+/// `None` when the response has no rate-limit headers at all
+fn parse_rate_limit_headers(headers: &HeaderMap, now: DateTime<Utc>) -> Option<RateLimitStatus> {
+    let reset = |names: &[&str]| header(headers, names).and_then(|v| parse_reset(v, now));
+    let status = RateLimitStatus {
+        limit_requests: header_u64(
+            headers,
+            &[
+                "x-ratelimit-limit-requests",
+                "x-ratelimit-requests-limit",
+                "anthropic-ratelimit-requests-limit",
+            ],
+        ),
+        remaining_requests: header_u64(
+            headers,
+            &[
+                "x-ratelimit-remaining-requests",
+                "x-ratelimit-requests-remaining",
+                "anthropic-ratelimit-requests-remaining",
+            ],
+        ),
+        reset_requests_at_ms: reset(&[
+            "x-ratelimit-reset-requests",
+            "x-ratelimit-requests-reset",
+            "anthropic-ratelimit-requests-reset",
+        ]),
+        limit_tokens: header_u64(
+            headers,
+            &[
+                "x-ratelimit-limit-tokens",
+                "anthropic-ratelimit-tokens-limit",
+            ],
+        ),
+        remaining_tokens: header_u64(
+            headers,
+            &[
+                "x-ratelimit-remaining-tokens",
+                "anthropic-ratelimit-tokens-remaining",
+            ],
+        ),
+        reset_tokens_at_ms: reset(&[
+            "x-ratelimit-reset-tokens",
+            "anthropic-ratelimit-tokens-reset",
+        ]),
+        updated_at_ms: now.timestamp_millis(),
+    };
+    (status.remaining_requests.is_some() || status.remaining_tokens.is_some()).then_some(status)
+}
+
+/// Remembers the limits reported with a response of the profile
+pub fn record(profile_id: &str, headers: &HeaderMap) {
+    if let Some(status) = parse_rate_limit_headers(headers, Utc::now()) {
+        rate_limits()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(profile_id.to_string(), status);
+    }
+}
+
+/// Limits from the profile's last response, if the provider sends them
+pub fn status(profile_id: &str) -> Option<RateLimitStatus> {
+    rate_limits()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(profile_id)
+        .cloned()
+}
+
+/// Wait before a request of `estimated_tokens` and what it waits for
+fn throttle_delay(
+    status: &RateLimitStatus,
+    estimated_tokens: u64,
+    now: DateTime<Utc>,
+) -> Option<(Duration, &'static str)> {
+    let until = |reset_at_ms: Option<i64>| {
+        let ms = reset_at_ms? - now.timestamp_millis();
+        (ms > 0).then(|| Duration::from_millis(ms as u64).min(MAX_THROTTLE))
+    };
+    let requests = (status.remaining_requests == Some(0))
+        .then(|| until(status.reset_requests_at_ms))
+        .flatten()
+        .map(|delay| (delay, "requests"));
+    let tokens = status
+        .remaining_tokens
+        .is_some_and(|remaining| remaining < estimated_tokens)
+        .then(|| until(status.reset_tokens_at_ms))
+        .flatten()
+        .map(|delay| (delay, "tokens"));
+    requests
+        .into_iter()
+        .chain(tokens)
+        .max_by_key(|(delay, _)| *delay)
+}
+
+/// Waits for the reset when the profile's last reported budget is too small for the
+/// request, sending `rate-limit-wait` first
+pub async fn wait_for_capacity<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    profile_id: &str,
+    estimated_tokens: u64,
+) {
+    let Some(status) = status(profile_id) else {
+        return;
+    };
+    let Some((delay, reason)) = throttle_delay(&status, estimated_tokens, Utc::now()) else {
+        return;
+    };
+    crate::app_log!(
+        force: true,
+        "[AI][RATE-LIMIT] profile {}: {} budget exhausted (~{} tokens needed), waiting {} ms",
+        profile_id,
+        reason,
+        estimated_tokens,
+        delay.as_millis()
+    );
+    let _ = app_handle.emit(
+        "rate-limit-wait",
+        serde_json::json!({
+            "profile_id": profile_id,
+            "wait_ms": delay.as_millis() as u64,
+            "reason": reason,
+            "remaining_tokens": status.remaining_tokens,
+            "remaining_requests": status.remaining_requests,
+        }),
+    );
+    tokio::time::sleep(delay).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn go_durations_are_parsed() {
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1_500)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("1h2m3s"), Some(Duration::from_secs(3_723)));
+        assert_eq!(parse_duration("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[test]
+    fn openai_and_anthropic_headers_are_read() {
+        let base = now().timestamp_millis();
+        let openai = parse_rate_limit_headers(
+            &headers(&[
+                ("x-ratelimit-limit-tokens", "30000"),
+                ("x-ratelimit-remaining-tokens", "1200"),
+                ("x-ratelimit-reset-tokens", "6s"),
+                ("x-ratelimit-remaining-requests", "499"),
+                ("x-ratelimit-reset-requests", "120ms"),
+            ]),
+            now(),
+        )
+        .unwrap();
+        assert_eq!(openai.limit_tokens, Some(30_000));
+        assert_eq!(openai.remaining_tokens, Some(1_200));
+        assert_eq!(openai.reset_tokens_at_ms, Some(base + 6_000));
+        assert_eq!(openai.reset_requests_at_ms, Some(base + 120));
+
+        let anthropic = parse_rate_limit_headers(
+            &headers(&[
+                ("anthropic-ratelimit-tokens-remaining", "0"),
+                ("anthropic-ratelimit-tokens-reset", "2026-03-01T12:00:30Z"),
+            ]),
+            now(),
+        )
+        .unwrap();
+        assert_eq!(anthropic.remaining_tokens, Some(0));
+        assert_eq!(anthropic.reset_tokens_at_ms, Some(base + 30_000));
+
+        assert_eq!(parse_rate_limit_headers(&HeaderMap::new(), now()), None);
+    }
+
+    #[test]
+    fn waits_only_when_the_request_does_not_fit() {
+        let base = now().timestamp_millis();
+        let status = RateLimitStatus {
+            remaining_tokens: Some(1_000),
+            reset_tokens_at_ms: Some(base + 4_000),
+            remaining_requests: Some(10),
+            reset_requests_at_ms: Some(base + 500),
+            ..Default::default()
+        };
+        assert_eq!(throttle_delay(&status, 800, now()), None);
+        assert_eq!(
+            throttle_delay(&status, 2_000, now()),
+            Some((Duration::from_secs(4), "tokens"))
+        );
+
+        let no_requests = RateLimitStatus {
+            remaining_requests: Some(0),
+            ..status.clone()
+        };
+        assert_eq!(
+            throttle_delay(&no_requests, 800, now()),
+            Some((Duration::from_millis(500), "requests"))
+        );
+
+        // A passed reset does not hold the request, a distant one holds it for MAX_THROTTLE
+        let passed = RateLimitStatus {
+            reset_tokens_at_ms: Some(base - 1),
+            ..status.clone()
+        };
+        assert_eq!(throttle_delay(&passed, 2_000, now()), None);
+        let far = RateLimitStatus {
+            reset_tokens_at_ms: Some(base + 600_000),
+            ..status
+        };
+        assert_eq!(
+            throttle_delay(&far, 2_000, now()),
+            Some((MAX_THROTTLE, "tokens"))
+        );
+    }
+
+    #[test]
+    fn state_is_kept_per_profile() {
+        record(
+            "rate-limit-a",
+            &headers(&[("x-ratelimit-remaining-tokens", "0")]),
+        );
+        assert_eq!(
+            status("rate-limit-a").and_then(|s| s.remaining_tokens),
+            Some(0)
+        );
+        assert_eq!(status("rate-limit-b"), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! TCP probe of the provider host before a completion: offline or behind a dropped VPN
+//! the chat fails in ~2 s with a readable hint instead of hanging on the full request.
+//!
+//! Skipped for local hosts, for profiles going through a proxy (the provider host is not
+//! connected to directly; this includes the OS proxy in `system` mode) and when `AppSettings::skip_reachability_check` is set.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::error::AiClientError;
+use crate::llm_profiles::LLMProfile;
+use crate::settings::ProxyMode;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A host that answered is not probed again for this long
+const REACHABLE_TTL: Duration = Duration::from_secs(60);
+
+static REACHABLE_HOSTS: OnceLock<Mutex<HashMap<(String, u16), Instant>>> = OnceLock::new();
+
+fn reachable_hosts() -> &'static Mutex<HashMap<(String, u16), Instant>> {
+    REACHABLE_HOSTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Host and port the profile's requests connect to
+fn target(base_url: &str) -> Option<(String, u16)> {
+    let url = reqwest::Url::parse(base_url).ok()?;
+    let host = url.host_str()?.trim_matches(['[', ']']).to_string();
+    Some((host, url.port_or_known_default()?))
+}
+
+fn is_local_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Proxy configured in the environment or the OS settings, used by `system` proxy mode.
+/// The OS settings are read once per session.
+fn system_proxy_configured() -> bool {
+    const ENV_VARS: &[&str] = &[
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ];
+    static OS_PROXY: OnceLock<bool> = OnceLock::new();
+    ENV_VARS
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|value| !value.trim().is_empty()))
+        || *OS_PROXY.get_or_init(os_proxy_configured)
+}
+
+/// Proxy or PAC script enabled in the Internet Settings of the user
+#[cfg(target_os = "windows")]
+fn os_proxy_configured() -> bool {
+    use std::os::windows::process::CommandExt;
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings",
+        ])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output();
+    let Ok(output) = output else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["ProxyEnable", _, value] => *value != "0x0",
+            ["AutoConfigURL", _, ..] => true,
+            _ => false,
+        }
+    })
+}
+
+/// Proxy or PAC script enabled for the current network service
+#[cfg(target_os = "macos")]
+fn os_proxy_configured() -> bool {
+    let Ok(output) = std::process::Command::new("scutil").arg("--proxy").output() else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        [
+            "HTTPEnable : 1",
+            "HTTPSEnable : 1",
+            "SOCKSEnable : 1",
+            "ProxyAutoConfigEnable : 1",
+        ]
+        .contains(&line.as_str())
+    })
+}
+
+/// Desktop proxy settings on Linux reach applications as environment variables
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn os_proxy_configured() -> bool {
+    false
+}
+
+/// Requests of the profile connect to the provider host itself, not to a proxy
+fn connects_directly(profile: &LLMProfile, mode: ProxyMode, system_proxy: bool) -> bool {
+    match profile.proxy.as_ref().map(|config| config.url.trim()) {
+        Some(url) if url.eq_ignore_ascii_case("system") => !system_proxy,
+        Some(_) => !crate::http_client::profile_uses_explicit_proxy(profile),
+        None => match mode {
+            ProxyMode::Disabled => true,
+            ProxyMode::Custom => false,
+            ProxyMode::System => !system_proxy,
+        },
+    }
+}
+
+fn should_probe(profile: &LLMProfile) -> bool {
+    let settings = crate::settings::load_settings();
+    !settings.skip_reachability_check
+        && connects_directly(profile, settings.proxy.mode, system_proxy_configured())
+}
+
+async fn probe(host: &str, port: u16, timeout: Duration) -> Result<(), AiClientError> {
+    if is_local_host(host) {
+        return Ok(());
+    }
+    let key = (host.to_string(), port);
+    if let Ok(hosts) = reachable_hosts().lock() {
+        if hosts
+            .get(&key)
+            .is_some_and(|at| at.elapsed() < REACHABLE_TTL)
+        {
+            return Ok(());
+        }
+    }
+
+    let error =
+        match tokio::time::timeout(timeout, tokio::net::TcpStream::connect((host, port))).await {
+            Ok(Ok(_)) => {
+                if let Ok(mut hosts) = reachable_hosts().lock() {
+                    hosts.insert(key, Instant::now());
+                }
+                return Ok(());
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("нет ответа за {} с", timeout.as_secs()),
+        };
+    crate::app_log!(
+        force: true,
+        "[AI][REACHABILITY] {}:{} unreachable: {}",
+        host,
+        port,
+        error
+    );
+    Err(AiClientError::Unreachable {
+        host: host.to_string(),
+        message: format!(
+            "Сервер {} недоступен ({}). Проверьте подключение к сети и VPN; \
+            если сеть в порядке, отключите предварительную проверку в настройках",
+            host, error
+        ),
+    })
+}
+
+/// Fails fast with `Unreachable` when the profile's host does not accept connections
+pub async fn ensure_reachable(profile: &LLMProfile) -> Result<(), AiClientError> {
+    if !should_probe(profile) {
+        return Ok(());
+    }
+    match target(&profile.get_base_url()) {
+        Some((host, port)) => probe(&host, port, PROBE_TIMEOUT).await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_uses_default_ports_and_strips_ipv6_brackets() {
+        assert_eq!(
+            target("https://api.openai.com/v1"),
+            Some(("api.openai.com".to_string(), 443))
+        );
+        assert_eq!(
+            target("http://[::1]:11434/v1"),
+            Some(("::1".to_string(), 11434))
+        );
+        assert_eq!(target("not a url"), None);
+        assert!(is_local_host("::1") && is_local_host("LOCALHOST") && is_local_host("127.0.0.1"));
+        assert!(!is_local_host("10.0.0.5"));
+    }
+
+    #[test]
+    fn requests_through_any_proxy_are_not_probed() {
+        use crate::llm_profiles::ProxyConfig;
+        let mut profile = LLMProfile::default_profile();
+        assert!(connects_directly(&profile, ProxyMode::System, false));
+        assert!(!connects_directly(&profile, ProxyMode::System, true));
+        assert!(connects_directly(&profile, ProxyMode::Disabled, true));
+        assert!(!connects_directly(&profile, ProxyMode::Custom, false));
+
+        let proxy = |url: &str| {
+            Some(ProxyConfig {
+                url: url.to_string(),
+                ..Default::default()
+            })
+        };
+        profile.proxy = proxy("system");
+        assert!(!connects_directly(&profile, ProxyMode::Disabled, true));
+        assert!(connects_directly(&profile, ProxyMode::Custom, false));
+        profile.proxy = proxy("none");
+        assert!(connects_directly(&profile, ProxyMode::Custom, true));
+        profile.proxy = proxy("http://proxy.local:3128");
+        assert!(!connects_directly(&profile, ProxyMode::Disabled, false));
+    }
+
+    #[tokio::test]
+    async fn unroutable_host_fails_fast_and_local_host_is_not_probed() {
+        // TEST-NET-1 (RFC 5737): never answers
+        let error = probe("192.0.2.1", 443, Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        let AiClientError::Unreachable { host, message } = error else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert_eq!(host, "192.0.2.1");
+        assert!(message.contains("VPN"), "{message}");
+
+        // Nothing listens there, but local servers are not probed
+        assert!(probe("localhost", 9, Duration::from_millis(200))
+            .await
+            .is_ok());
+    }
+}
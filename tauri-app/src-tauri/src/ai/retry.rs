@@ -0,0 +1,156 @@
+//! Retries of the initial provider request on rate limits, gateway errors and failed
+//! connects: exponential backoff with jitter, `Retry-After` wins when the server sends it.
+//!
+//! Only the request that opens a response is retried — once a stream is accepted its
+//! chunks reach the chat, and repeating it would duplicate them. Cancelling the chat
+//! aborts the task, which drops a pending backoff sleep immediately.
+
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use tauri::Emitter;
+
+/// Used when `AppSettings::max_request_retries` is not set
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry; doubled for each next one
+const BASE_DELAY_MS: u64 = 1_000;
+
+const MAX_BACKOFF_MS: u64 = 30_000;
+
+/// Longest `Retry-After` waited for; a server asking for more gets this instead
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Retries after the first attempt, from the settings
+pub fn max_retries() -> u32 {
+    crate::settings::load_settings()
+        .max_request_retries
+        .unwrap_or(DEFAULT_MAX_RETRIES)
+}
+
+/// Rate limit and gateway/server errors that usually pass on an immediate retry
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 500 | 502 | 503 | 504)
+}
+
+/// `Retry-After` as delta-seconds or an HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("retry-after")?.to_str().ok()?;
+    parse_retry_after_value(value, chrono::Utc::now())
+}
+
+fn parse_retry_after_value(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// `BASE_DELAY_MS * 2^(attempt-1)` capped at `MAX_BACKOFF_MS`; `jitter` in `0.0..=1.0`
+/// spreads the second half of it so parallel clients do not retry in lockstep
+fn backoff_with_jitter(attempt: u32, jitter: f64) -> Duration {
+    let exponential = BASE_DELAY_MS
+        .saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF_MS);
+    let half = exponential / 2;
+    Duration::from_millis(half + (half as f64 * jitter.clamp(0.0, 1.0)) as u64)
+}
+
+/// Delay before retry number `attempt` (1-based)
+pub fn retry_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    match retry_after {
+        Some(delay) => delay.min(MAX_RETRY_AFTER),
+        None => backoff_with_jitter(attempt, rand::thread_rng().gen_range(0.0..=1.0)),
+    }
+}
+
+/// Tells the chat about the retry (`chat-retrying`) and sleeps for `delay`
+pub async fn wait_before_retry<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    attempt: u32,
+    delay: Duration,
+    reason: &str,
+) {
+    crate::app_log!(
+        "[AI][RETRY] {} (attempt {}), retrying in {} ms",
+        reason,
+        attempt,
+        delay.as_millis()
+    );
+    let _ = app_handle.emit(
+        "chat-retrying",
+        serde_json::json!({
+            "attempt": attempt,
+            "delay_ms": delay.as_millis() as u64,
+            "reason": reason,
+        }),
+    );
+    tokio::time::sleep(delay).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        for status in [429, 500, 502, 503, 504] {
+            assert!(is_retryable_status(status), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 422, 501] {
+            assert!(!is_retryable_status(status), "{}", status);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_with_jitter(1, 1.0), Duration::from_millis(1_000));
+        assert_eq!(backoff_with_jitter(2, 1.0), Duration::from_millis(2_000));
+        assert_eq!(backoff_with_jitter(3, 0.0), Duration::from_millis(2_000));
+        assert_eq!(
+            backoff_with_jitter(40, 1.0),
+            Duration::from_millis(MAX_BACKOFF_MS)
+        );
+
+        for _ in 0..100 {
+            let delay = retry_delay(2, None);
+            assert!(delay >= Duration::from_millis(1_000) && delay <= Duration::from_millis(2_000));
+        }
+    }
+
+    #[test]
+    fn retry_after_wins_and_is_capped() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            parse_retry_after_value(" 7 ", now),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            parse_retry_after_value("Sat, 10 Jan 2026 12:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after_value("Sat, 10 Jan 2026 11:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after_value("soon", now), None);
+
+        assert_eq!(
+            retry_delay(1, Some(Duration::from_secs(5))),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            retry_delay(1, Some(Duration::from_secs(3_600))),
+            MAX_RETRY_AFTER
+        );
+    }
+}
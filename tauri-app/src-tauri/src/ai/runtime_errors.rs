@@ -0,0 +1,318 @@
+//! 1С runtime error messages (`{Модуль(строка)}: текст`) and the prompt that asks
+//! the model to explain them.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use super::client::prompt_completion;
+use super::error::AiClientError;
+use super::models::TaskType;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParsedError {
+    /// e.g. `Справочник.Контрагенты.МодульОбъекта`
+    pub module: String,
+    pub line: u32,
+    pub message: String,
+    /// Category of the message, `runtime` when it is not recognised
+    pub error_type: String,
+}
+
+lazy_static! {
+    // `{Module(line)}` or `{Module(line,column)}`, then the message after `:`
+    static ref ERROR_LOCATION: Regex =
+        Regex::new(r"\{([^{}()\r\n]+)\((\d+)(?:\s*,\s*\d+)?\)\}\s*:?\s*(.*)").unwrap();
+    // Stack frame of 8.3 (`{Module(line)}`) or 8.2 (`[Module(line)]`, `{Module : line}`)
+    static ref STACK_FRAME: Regex = Regex::new(
+        r"^\s*(?:\{([^{}()\[\]\r\n]+)\((\d+)(?:\s*,\s*\d+)?\)\}|\[([^{}()\[\]\r\n]+)\((\d+)(?:\s*,\s*\d+)?\)\]|\{([^{}()\[\]:\r\n]+?)\s*:\s*(\d+)\s*\})\s*:?\s*(.*)$"
+    )
+    .unwrap();
+}
+
+/// Frame of a 1С call stack or "по причине:" chain
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackFrame {
+    pub module: String,
+    pub line: u32,
+    pub message: String,
+}
+
+/// Line that only links a frame to the next one in a chain of causes
+fn is_cause_marker(line: &str) -> bool {
+    let line = line.trim().trim_end_matches(':').to_lowercase();
+    line == "по причине" || line == "by reason of"
+}
+
+/// Frames of a 1С stack trace in the order given. Lines without a location continue
+/// the message of the frame above; "по причине:" markers are dropped.
+pub fn parse_1c_stack_trace(trace: &str) -> Vec<StackFrame> {
+    let mut frames: Vec<StackFrame> = Vec::new();
+    for line in trace.lines() {
+        if let Some(captures) = STACK_FRAME.captures(line) {
+            let Some((module, line_no)) =
+                [(1, 2), (3, 4), (5, 6)].into_iter().find_map(|(m, l)| {
+                    Some((captures.get(m)?, captures.get(l)?.as_str().parse().ok()?))
+                })
+            else {
+                continue;
+            };
+            frames.push(StackFrame {
+                module: module.as_str().trim().to_string(),
+                line: line_no,
+                message: captures[7].trim().to_string(),
+            });
+        } else if let Some(frame) = frames.last_mut() {
+            let text = line.trim();
+            if text.is_empty() || is_cause_marker(text) {
+                continue;
+            }
+            if !frame.message.is_empty() {
+                frame.message.push(' ');
+            }
+            frame.message.push_str(text);
+        }
+    }
+    frames
+}
+
+/// Question for the model listing every frame of the stack
+pub fn build_stack_trace_prompt(frames: &[StackFrame]) -> String {
+    let mut prompt = String::from(
+        "Проанализируй стек вызовов ошибки 1С и найди первопричину.\n\nКадры стека (в порядке из текста ошибки):\n",
+    );
+    for (index, frame) in frames.iter().enumerate() {
+        prompt.push_str(&format!(
+            "{}. Модуль: {}, строка {}",
+            index + 1,
+            frame.module,
+            frame.line
+        ));
+        if !frame.message.is_empty() {
+            prompt.push_str(&format!(" — {}", frame.message));
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str(
+        "\nУкажи кадр, в котором возникла первопричина, объясни её, \
+         опиши, как ошибка распространилась по остальным кадрам, и предложи исправление.",
+    );
+    prompt
+}
+
+/// Message fragments (lowercase, Russian and English platform texts) per category
+const ERROR_TYPES: &[(&str, &[&str])] = &[
+    (
+        "not_object_type",
+        &[
+            "не является значением объектного типа",
+            "is not a value of object type",
+        ],
+    ),
+    (
+        "method_not_found",
+        &["метод объекта не обнаружен", "object method not found"],
+    ),
+    (
+        "field_not_found",
+        &["поле объекта не обнаружено", "object field not found"],
+    ),
+    (
+        "variable_not_defined",
+        &["переменная не определена", "variable not defined"],
+    ),
+    (
+        "type_conversion",
+        &["преобразование значения к типу", "convert value to type"],
+    ),
+    ("division_by_zero", &["деление на 0", "division by zero"]),
+    (
+        "index_out_of_range",
+        &[
+            "за границу диапазона",
+            "за границами",
+            "index out of range",
+            "out of bounds",
+        ],
+    ),
+    (
+        "query",
+        &["ошибка выполнения запроса", "query execution error"],
+    ),
+    ("lock", &["конфликт блокировок", "lock conflict"]),
+];
+
+fn classify(message: &str) -> &'static str {
+    let lower = message.to_lowercase();
+    ERROR_TYPES
+        .iter()
+        .find(|(_, fragments)| fragments.iter().any(|f| lower.contains(f)))
+        .map(|(kind, _)| *kind)
+        .unwrap_or("runtime")
+}
+
+/// Module, line and message of a 1С error text; `None` when there is no `{Модуль(N)}` location.
+/// In a chain of "по причине:" locations the last one is the root cause and is used.
+pub fn parse_1c_error(error_str: &str) -> Option<ParsedError> {
+    let last = ERROR_LOCATION.captures_iter(error_str).last()?;
+    let message = last[3].trim().to_string();
+    Some(ParsedError {
+        module: last[1].trim().to_string(),
+        line: last[2].parse().ok()?,
+        error_type: classify(&message).to_string(),
+        message,
+    })
+}
+
+/// Question for the model: the error text, the parsed location and, when given, the code
+pub fn build_explain_prompt(error_str: &str, context_code: Option<&str>) -> String {
+    let mut prompt = match parse_1c_error(error_str) {
+        Some(parsed) => format!(
+            "Объясни ошибку времени выполнения 1С и предложи исправление.\n\n\
+             Модуль: {}\nСтрока: {}\nТекст ошибки: {}\nКатегория: {}\n",
+            parsed.module, parsed.line, parsed.message, parsed.error_type
+        ),
+        None => format!(
+            "Объясни ошибку 1С и предложи исправление.\n\nТекст ошибки:\n{}\n",
+            error_str.trim()
+        ),
+    };
+    if let Some(code) = context_code.map(str::trim).filter(|c| !c.is_empty()) {
+        prompt.push_str(&format!("\nКод модуля:\n```bsl\n{}\n```\n", code));
+    }
+    prompt.push_str(
+        "\nУкажи наиболее вероятную причину, как её проверить в отладчике и исправленный фрагмент кода.",
+    );
+    prompt
+}
+
+/// Explanation of a 1С error, answered outside the chat
+pub async fn explain_1c_error(
+    error_str: String,
+    context_code: Option<String>,
+) -> Result<String, AiClientError> {
+    if error_str.trim().is_empty() {
+        return Err("Текст ошибки пуст".into());
+    }
+    prompt_completion(
+        build_explain_prompt(&error_str, context_code.as_deref()),
+        TaskType::Debug,
+    )
+    .await
+}
+
+/// Root-cause analysis of a 1С stack trace, answered outside the chat
+pub async fn analyse_stack_trace(trace: String) -> Result<String, AiClientError> {
+    let frames = parse_1c_stack_trace(&trace);
+    if frames.is_empty() {
+        return Err(AiClientError::parse(
+            "В тексте нет кадров стека вида {Модуль(строка)}",
+        ));
+    }
+    prompt_completion(build_stack_trace_prompt(&frames), TaskType::Debug).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_russian_error() {
+        let parsed = parse_1c_error(
+            "{МодульОбъекта.Справочник.Контрагенты(35)}: Значение не является значением объектного типа (Наименование)",
+        )
+        .unwrap();
+        assert_eq!(parsed.module, "МодульОбъекта.Справочник.Контрагенты");
+        assert_eq!(parsed.line, 35);
+        assert_eq!(
+            parsed.message,
+            "Значение не является значением объектного типа (Наименование)"
+        );
+        assert_eq!(parsed.error_type, "not_object_type");
+    }
+
+    #[test]
+    fn parses_english_error_with_column_and_cause_chain() {
+        let text = "{Document.Invoice.ObjectModule(14)}: Error calling context method (Записать)\n\
+                    by reason of:\n\
+                    {CommonModule.Sales.Module(120,5)}: Object method not found (Провести)";
+        let parsed = parse_1c_error(text).unwrap();
+        assert_eq!(parsed.module, "CommonModule.Sales.Module");
+        assert_eq!(parsed.line, 120);
+        assert_eq!(parsed.message, "Object method not found (Провести)");
+        assert_eq!(parsed.error_type, "method_not_found");
+    }
+
+    #[test]
+    fn text_without_location_is_not_parsed() {
+        assert_eq!(parse_1c_error("Деление на 0"), None);
+        let prompt = build_explain_prompt("Деление на 0", Some("А = 1 / 0;"));
+        assert!(prompt.contains("Деление на 0"));
+        assert!(prompt.contains("```bsl\nА = 1 / 0;\n```"));
+    }
+
+    #[test]
+    fn parses_multi_frame_8_3_trace_with_causes() {
+        let trace = "{ОбщийМодуль.Работа.Модуль(123)}: Ошибка при вызове метода контекста (Записать)\n\
+                     по причине:\n\
+                     {ОбщийМодуль.Работа.Модуль(45)}: Значение не является значением объектного типа (Сумма)\n\
+                     \n\
+                     {Документ.Заказ.МодульОбъекта(7, 12)}: Деление на 0";
+        assert_eq!(
+            parse_1c_stack_trace(trace),
+            vec![
+                StackFrame {
+                    module: "ОбщийМодуль.Работа.Модуль".to_string(),
+                    line: 123,
+                    message: "Ошибка при вызове метода контекста (Записать)".to_string(),
+                },
+                StackFrame {
+                    module: "ОбщийМодуль.Работа.Модуль".to_string(),
+                    line: 45,
+                    message: "Значение не является значением объектного типа (Сумма)".to_string(),
+                },
+                StackFrame {
+                    module: "Документ.Заказ.МодульОбъекта".to_string(),
+                    line: 7,
+                    message: "Деление на 0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_8_2_bracket_styles_and_continuation_lines() {
+        let trace =
+            "[Справочник.Номенклатура.Форма.ФормаЭлемента(88)]: Поле объекта не обнаружено\n\
+                     (Артикул)\n\
+                     {ОбщийМодуль.ОбщегоНазначения : 310}: Ошибка выполнения запроса\n\
+                     Таблица не найдена";
+        let frames = parse_1c_stack_trace(trace);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            frames[0].module,
+            "Справочник.Номенклатура.Форма.ФормаЭлемента"
+        );
+        assert_eq!(frames[0].line, 88);
+        assert_eq!(frames[0].message, "Поле объекта не обнаружено (Артикул)");
+        assert_eq!(frames[1].module, "ОбщийМодуль.ОбщегоНазначения");
+        assert_eq!(frames[1].line, 310);
+        assert_eq!(
+            frames[1].message,
+            "Ошибка выполнения запроса Таблица не найдена"
+        );
+
+        assert!(parse_1c_stack_trace("Деление на 0").is_empty());
+    }
+
+    #[test]
+    fn stack_trace_prompt_lists_every_frame() {
+        let frames = parse_1c_stack_trace(
+            "{ОбщийМодуль.Работа.Модуль(123)}: Ошибка\n{ОбщийМодуль.Работа.Модуль(45)}:",
+        );
+        let prompt = build_stack_trace_prompt(&frames);
+        assert!(prompt.contains("1. Модуль: ОбщийМодуль.Работа.Модуль, строка 123 — Ошибка\n"));
+        assert!(prompt.contains("2. Модуль: ОбщийМодуль.Работа.Модуль, строка 45\n"));
+        assert!(prompt.contains("первопричин"));
+    }
+}
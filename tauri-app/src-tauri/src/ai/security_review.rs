@@ -0,0 +1,66 @@
+//! AI explanation of the findings of `bsl_analysis::audit_bsl_security`.
+
+use super::client::prompt_completion;
+use super::error::AiClientError;
+use super::models::TaskType;
+use crate::bsl_analysis::SecurityFinding;
+
+/// Code with numbered lines and the list of findings to explain one by one
+pub fn build_security_review_prompt(code: &str, findings: &[SecurityFinding]) -> String {
+    let numbered: Vec<String> = code
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>4}: {}", i + 1, line))
+        .collect();
+    let list: Vec<String> = findings
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            format!(
+                "{}. [{}] строка {}: {}",
+                i + 1,
+                f.pattern,
+                f.line,
+                f.description
+            )
+        })
+        .collect();
+    format!(
+        "Статический аудит безопасности нашёл в модуле 1С следующие замечания:\n{}\n\n\
+         Для каждого замечания объясни, реально ли здесь есть уязвимость, каким может быть \
+         сценарий атаки и приведи исправленный фрагмент кода. Ложные срабатывания отметь явно.\n\n\
+         Код модуля:\n```bsl\n{}\n```",
+        list.join("\n"),
+        numbered.join("\n")
+    )
+}
+
+/// Detailed explanation of the findings, answered outside the chat
+pub async fn explain_security_findings(
+    code: String,
+    findings: Vec<SecurityFinding>,
+) -> Result<String, AiClientError> {
+    if findings.is_empty() {
+        return Err("Аудит не нашёл опасных конструкций — объяснять нечего".into());
+    }
+    prompt_completion(
+        build_security_review_prompt(&code, &findings),
+        TaskType::Review,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_lists_findings_and_numbers_code_lines() {
+        let code = "Процедура А(Алгоритм)\n\tВыполнить(Алгоритм);\nКонецПроцедуры";
+        let findings = crate::bsl_analysis::audit_bsl_security(code);
+        let prompt = build_security_review_prompt(code, &findings);
+
+        assert!(prompt.contains("1. [dynamic_execute] строка 2:"));
+        assert!(prompt.contains("   2: \tВыполнить(Алгоритм);"));
+    }
+}
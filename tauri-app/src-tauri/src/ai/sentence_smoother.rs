@@ -0,0 +1,100 @@
+//! Whole sentences of the streamed answer for `chat-sentence` (screen readers and other
+//! accessibility tools), `SmoothingMode::Sentence`. `chat-chunk` is emitted as usual.
+//!
+//! BSL code blocks (from ```` ```bsl ```` to the closing fence) have no sentences: their
+//! text is passed on as it arrives.
+
+/// Pattern the buffered text ends with when a sentence or paragraph is complete
+const SENTENCE_ENDS: &[&str] = &[". ", "! ", "? ", "… ", "\n\n"];
+
+const BSL_FENCES: &[&str] = &["```bsl", "```BSL"];
+
+#[derive(Debug, Default)]
+pub struct SentenceSmoother {
+    pending: String,
+    in_code: bool,
+    /// Backticks in a row at the end of the code passed on, for the closing fence
+    code_backticks: u8,
+}
+
+impl SentenceSmoother {
+    /// Completed sentences and the code received in this chunk, in answer order
+    pub fn push_chunk(&mut self, chunk: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut code = String::new();
+        for c in chunk.chars() {
+            if self.in_code {
+                code.push(c);
+                self.code_backticks = if c == '`' { self.code_backticks + 1 } else { 0 };
+                if self.code_backticks == 3 {
+                    self.in_code = false;
+                    out.push(std::mem::take(&mut code));
+                }
+                continue;
+            }
+
+            self.pending.push(c);
+            if let Some(fence) = BSL_FENCES.iter().find(|f| self.pending.ends_with(*f)) {
+                let fence = self.pending.split_off(self.pending.len() - fence.len());
+                if !self.pending.is_empty() {
+                    out.push(std::mem::take(&mut self.pending));
+                }
+                code.push_str(&fence);
+                self.in_code = true;
+                self.code_backticks = 0;
+            } else if SENTENCE_ENDS.iter().any(|end| self.pending.ends_with(end)) {
+                out.push(std::mem::take(&mut self.pending));
+            }
+        }
+        if !code.is_empty() {
+            out.push(code);
+        }
+        out
+    }
+
+    /// The unfinished last sentence, at the end of the answer
+    pub fn flush(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences_are_emitted_once_complete() {
+        let mut smoother = SentenceSmoother::default();
+        assert!(smoother.push_chunk("Запрос выполн").is_empty());
+        assert_eq!(
+            smoother.push_chunk("ен. Всё ли в порядке? Да"),
+            vec!["Запрос выполнен. ", "Всё ли в порядке? "]
+        );
+        assert_eq!(smoother.push_chunk("! "), vec!["Да! "]);
+        assert_eq!(smoother.push_chunk("Итак… "), vec!["Итак… "]);
+        assert_eq!(smoother.push_chunk("Абзац\n\nДалее"), vec!["Абзац\n\n"]);
+        assert_eq!(smoother.flush().as_deref(), Some("Далее"));
+        assert_eq!(smoother.flush(), None);
+    }
+
+    #[test]
+    fn bsl_code_passes_through_without_waiting_for_sentences() {
+        let mut smoother = SentenceSmoother::default();
+        assert_eq!(
+            smoother.push_chunk("Пример:\n```bsl\nА = 1"),
+            vec!["Пример:\n", "```bsl\nА = 1"]
+        );
+        // No sentence end inside the code, still nothing is held back
+        assert_eq!(smoother.push_chunk(". Б = 2;"), vec![". Б = 2;"]);
+        assert_eq!(smoother.push_chunk("\n``"), vec!["\n``"]);
+        assert_eq!(smoother.push_chunk("`\nГотово. "), vec!["`", "\nГотово. "]);
+        assert_eq!(smoother.flush(), None);
+    }
+
+    #[test]
+    fn fence_split_across_chunks_is_recognised() {
+        let mut smoother = SentenceSmoother::default();
+        assert!(smoother.push_chunk("Код ``").is_empty());
+        assert_eq!(smoother.push_chunk("`bsl\nX"), vec!["Код ", "```bsl\nX"]);
+    }
+}
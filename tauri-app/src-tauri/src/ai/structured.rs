@@ -0,0 +1,270 @@
+//! Structured (JSON) completions: `response_format` + parsing of the model answer
+
+use serde_json::Value;
+
+use super::client::{chat_completions_url, openai_compatible_headers, resolve_profile_api_key};
+use super::error::AiClientError;
+use super::models::*;
+use crate::llm_profiles::{get_active_profile, LLMProvider};
+
+/// Schema name sent in `json_schema.name` (OpenAI requires one).
+const STRUCTURED_SCHEMA_NAME: &str = "structured_output";
+
+/// Strips a surrounding markdown fence (```json ... ```) some models add despite JSON mode.
+fn strip_json_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+fn json_type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Minimal JSON Schema check: `type`, `required`, `properties` and `items`.
+/// Keywords beyond that are left to the provider-side `json_schema` enforcement.
+pub fn validate_against_schema(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type").and_then(|t| t.as_str()) {
+        if !json_type_matches(value, expected) {
+            return Err(format!("{}: ожидался тип '{}'", path, expected));
+        }
+    }
+
+    if let (Some(obj), Some(required)) = (
+        value.as_object(),
+        schema.get("required").and_then(|r| r.as_array()),
+    ) {
+        for key in required.iter().filter_map(|k| k.as_str()) {
+            if !obj.contains_key(key) {
+                return Err(format!("{}: отсутствует обязательное поле '{}'", path, key));
+            }
+        }
+    }
+
+    if let (Some(obj), Some(properties)) = (
+        value.as_object(),
+        schema.get("properties").and_then(|p| p.as_object()),
+    ) {
+        for (key, sub_schema) in properties {
+            if let Some(child) = obj.get(key) {
+                validate_against_schema(child, sub_schema, &format!("{}.{}", path, key))?;
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (idx, item) in items.iter().enumerate() {
+            validate_against_schema(item, item_schema, &format!("{}[{}]", path, idx))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the model answer as JSON and optionally validates it against `schema`.
+pub fn parse_structured_output(text: &str, schema: Option<&Value>) -> Result<Value, String> {
+    let value: Value = serde_json::from_str(strip_json_fence(text))
+        .map_err(|e| format!("ответ не является корректным JSON: {}", e))?;
+    if let Some(schema) = schema {
+        validate_against_schema(&value, schema, "$")?;
+    }
+    Ok(value)
+}
+
+/// Runs a non-streaming completion with `response_format` and returns the parsed JSON.
+/// With `schema` the request uses `json_schema` mode, otherwise `json_object`.
+/// An invalid answer is retried once with a corrective system message.
+pub async fn chat_completion_structured(
+    messages: Vec<ApiMessage>,
+    schema: Option<&Value>,
+) -> Result<Value, AiClientError> {
+    let profile = get_active_profile().ok_or(AiClientError::NoActiveProfile)?;
+    if matches!(
+        profile.provider,
+        LLMProvider::CodexCli | LLMProvider::QwenCli | LLMProvider::OneCNaparnik
+    ) {
+        return Err(AiClientError::invalid_profile(format!(
+            "Структурированный JSON-ответ не поддерживается для провайдера {:?}",
+            profile.provider
+        )));
+    }
+
+    let api_key = resolve_profile_api_key(&profile)?;
+    let url = chat_completions_url(&profile);
+    let headers = openai_compatible_headers(&profile.provider, &api_key)?;
+    let client = crate::http_client::build_profile_client(&profile)
+        .map_err(AiClientError::invalid_profile)?;
+
+    let response_format = match schema {
+        Some(schema) => ResponseFormat::JsonSchema {
+            json_schema: serde_json::json!({
+                "name": STRUCTURED_SCHEMA_NAME,
+                "schema": schema,
+            }),
+        },
+        None => ResponseFormat::JsonObject,
+    };
+
+    let mut request_body = ChatRequest {
+        model: profile.model.clone(),
+        messages,
+        stream: false,
+        temperature: profile.temperature,
+        max_tokens: profile.max_tokens,
+        max_completion_tokens: None,
+        top_p: profile.top_p,
+        frequency_penalty: profile.frequency_penalty,
+        presence_penalty: profile.presence_penalty,
+        stop: None,
+        seed: profile.seed,
+        logit_bias: None,
+        n: None,
+        user: None,
+        system: None,
+        stream_options: None,
+        response_format: Some(response_format),
+        tools: None,
+        enable_thinking: None,
+        thinking_budget_tokens: None,
+        reasoning_effort: None,
+        thinking: None,
+    };
+    super::capabilities::apply_model_rules(
+        &mut request_body,
+        &super::capabilities::get_model_capabilities(&profile),
+    );
+
+    let mut last_error = String::new();
+    for attempt in 1..=2 {
+        let response = client
+            .post(&url)
+            .headers(headers.clone())
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            if super::error::is_auth_status(status.as_u16()) {
+                return Err(AiClientError::auth_failed(
+                    status.as_u16(),
+                    &body,
+                    &profile,
+                    &api_key,
+                ));
+            }
+            let message = super::error::describe_http_error(status.as_u16(), &body, &profile.model);
+            return Err(AiClientError::http(
+                status.as_u16(),
+                &body,
+                &profile.provider,
+                message,
+            ));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AiClientError::stream(format!("Failed to read response: {}", e)))?;
+        let resp: NonStreamResponse = serde_json::from_str(&body).map_err(|e| {
+            AiClientError::parse(format!("Failed to parse non-stream response: {}", e))
+        })?;
+        let content = resp
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .unwrap_or_default();
+
+        match parse_structured_output(&content, schema) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                crate::app_log!(
+                    "[AI][STRUCTURED] Attempt {} returned invalid JSON: {}",
+                    attempt,
+                    e
+                );
+                last_error = e.clone();
+                request_body.messages.push(ApiMessage {
+                    role: "assistant".to_string(),
+                    content: Some(content.into()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                });
+                request_body.messages.push(ApiMessage {
+                    role: "system".to_string(),
+                    content: Some(format!(
+                        "Предыдущий ответ отклонён: {}. Верни только корректный JSON без пояснений и markdown.",
+                        e
+                    ).into()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                });
+            }
+        }
+    }
+
+    Err(AiClientError::parse(format!(
+        "Модель не вернула корректный JSON: {}",
+        last_error
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn response_format_serializes_as_openai_shape() {
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::JsonObject).unwrap(),
+            json!({"type": "json_object"})
+        );
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::JsonSchema {
+                json_schema: json!({"name": "x", "schema": {"type": "object"}}),
+            })
+            .unwrap(),
+            json!({"type": "json_schema", "json_schema": {"name": "x", "schema": {"type": "object"}}})
+        );
+    }
+
+    #[test]
+    fn parses_fenced_json_and_checks_required_fields() {
+        let schema = json!({
+            "type": "object",
+            "required": ["issues"],
+            "properties": {
+                "issues": {"type": "array", "items": {"type": "object", "required": ["line"]}}
+            }
+        });
+
+        let ok = parse_structured_output(
+            "```json\n{\"issues\": [{\"line\": 12}]}\n```",
+            Some(&schema),
+        )
+        .expect("fenced JSON should parse");
+        assert_eq!(ok["issues"][0]["line"], 12);
+
+        let missing = parse_structured_output(r#"{"issues": [{}]}"#, Some(&schema));
+        assert!(missing.unwrap_err().contains("line"));
+
+        assert!(parse_structured_output("not json", None).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Exactly one terminal event per completion request: `chat-done`, `chat-error` or
+//! `chat-cancelled`, all carrying the `request_id` from `chat-start`.
+//!
+//! Events of a request may come from any exit path (early validation, fallback chain,
+//! the chat task being aborted), so the event is sent by a guard: the outcome of the
+//! completion decides which one, and a guard dropped without an outcome (the future was
+//! dropped mid-flight) reports `chat-cancelled`.
+
+use std::future::Future;
+
+use serde_json::{json, Value};
+use tauri::Emitter;
+
+use super::error::AiClientError;
+
+/// Where terminal events go; the app handle in the app, a recorder in tests
+pub trait EventSink {
+    fn emit_event(&self, event: &str, payload: Value);
+}
+
+impl<R: tauri::Runtime> EventSink for tauri::AppHandle<R> {
+    fn emit_event(&self, event: &str, payload: Value) {
+        let _ = self.emit(event, payload);
+    }
+}
+
+/// Event name and payload for the outcome of a request
+fn terminal_event(request_id: &str, error: Option<&AiClientError>) -> (&'static str, Value) {
+    match error {
+        None => ("chat-done", json!({ "request_id": request_id })),
+        Some(AiClientError::Cancelled) => ("chat-cancelled", json!({ "request_id": request_id })),
+        Some(error) => (
+            "chat-error",
+            json!({
+                "request_id": request_id,
+                "kind": error.kind(),
+                "message": error.to_string(),
+                "http_status": error.http_status(),
+                "retryable": error.is_retryable(),
+                "partial_text_len": error.partial_text().map_or(0, |text| text.chars().count()),
+            }),
+        ),
+    }
+}
+
+struct TerminalEvent<S: EventSink> {
+    sink: S,
+    request_id: String,
+    sent: bool,
+}
+
+impl<S: EventSink> TerminalEvent<S> {
+    fn send(&mut self, error: Option<&AiClientError>) {
+        if std::mem::replace(&mut self.sent, true) {
+            return;
+        }
+        let (event, payload) = terminal_event(&self.request_id, error);
+        self.sink.emit_event(event, payload);
+    }
+}
+
+impl<S: EventSink> Drop for TerminalEvent<S> {
+    fn drop(&mut self) {
+        self.send(Some(&AiClientError::Cancelled));
+    }
+}
+
+/// Runs the completion and sends its terminal event, also when the returned future is
+/// dropped before it finishes
+pub async fn with_terminal_event<S, T, F>(
+    sink: S,
+    request_id: String,
+    completion: F,
+) -> Result<T, AiClientError>
+where
+    S: EventSink,
+    F: Future<Output = Result<T, AiClientError>>,
+{
+    let mut guard = TerminalEvent {
+        sink,
+        request_id,
+        sent: false,
+    };
+    let result = completion.await;
+    guard.send(result.as_ref().err());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_profiles::LLMProvider;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(String, Value)>>>);
+
+    impl EventSink for Recorder {
+        fn emit_event(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    impl Recorder {
+        /// The single event sent for `request_id`; fails on none or several
+        fn only_event(&self, request_id: &str) -> (String, Value) {
+            let events: Vec<_> = self
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, payload)| payload["request_id"] == request_id)
+                .cloned()
+                .collect();
+            assert_eq!(events.len(), 1, "{:?}", events);
+            events.into_iter().next().unwrap()
+        }
+    }
+
+    async fn run(recorder: &Recorder, id: &str, outcome: Result<u32, AiClientError>) {
+        let result =
+            with_terminal_event(recorder.clone(), id.to_string(), async { outcome.clone() }).await;
+        assert_eq!(result, outcome);
+    }
+
+    #[tokio::test]
+    async fn every_outcome_ends_with_exactly_one_terminal_event() {
+        let recorder = Recorder::default();
+
+        run(&recorder, "ok", Ok(1)).await;
+        assert_eq!(recorder.only_event("ok").0, "chat-done");
+
+        let http = AiClientError::http(
+            503,
+            r#"{"error":{"code":"overloaded"}}"#,
+            &LLMProvider::OpenAI,
+            "Сервис перегружен".to_string(),
+        );
+        run(&recorder, "http", Err(http)).await;
+        let (event, payload) = recorder.only_event("http");
+        assert_eq!(event, "chat-error");
+        assert_eq!(payload["kind"], "http");
+        assert_eq!(payload["http_status"], 503);
+        assert_eq!(payload["retryable"], true);
+        assert_eq!(payload["partial_text_len"], 0);
+
+        run(&recorder, "stream", Err(AiClientError::stream("обрыв"))).await;
+        let (event, payload) = recorder.only_event("stream");
+        assert_eq!(event, "chat-error");
+        assert_eq!(payload["kind"], "stream");
+        assert_eq!(payload["http_status"], Value::Null);
+
+        let reconnects = AiClientError::MaxReconnectsExceeded {
+            attempts: 3,
+            message: "обрыв".to_string(),
+            partial_response: "Частичный".to_string(),
+        };
+        run(&recorder, "partial", Err(reconnects)).await;
+        assert_eq!(recorder.only_event("partial").1["partial_text_len"], 9);
+
+        run(
+            &recorder,
+            "bad-key",
+            Err(AiClientError::invalid_profile("нет ключа")),
+        )
+        .await;
+        assert_eq!(recorder.only_event("bad-key").1["retryable"], false);
+
+        run(&recorder, "cancel", Err(AiClientError::Cancelled)).await;
+        assert_eq!(recorder.only_event("cancel").0, "chat-cancelled");
+    }
+
+    #[tokio::test]
+    async fn aborted_request_reports_cancelled_once() {
+        let recorder = Recorder::default();
+        let task = tokio::spawn(with_terminal_event(
+            recorder.clone(),
+            "aborted".to_string(),
+            async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok::<_, AiClientError>(())
+            },
+        ));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(recorder.0.lock().unwrap().is_empty());
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_eq!(recorder.only_event("aborted").0, "chat-cancelled");
+    }
+}
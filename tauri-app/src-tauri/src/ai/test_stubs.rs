@@ -0,0 +1,130 @@
+//! Unit test stubs (YaxUnit, `ЮТест`) for the procedures and functions of a module.
+
+use super::client::prompt_completion;
+use super::code_blocks::{extract_code_blocks, merge_code_blocks, CodeBlock};
+use super::error::AiClientError;
+use super::models::TaskType;
+use crate::bsl_analysis::{extract_bsl_signatures, BslSignature};
+
+fn test_name(signature: &BslSignature) -> String {
+    format!("Тест_{}", signature.name)
+}
+
+/// Asks for one YaxUnit test per signature plus the `ИсполняемыеСценарии` registration
+pub fn build_test_stubs_prompt(code: &str, signatures: &[BslSignature]) -> String {
+    let list: Vec<String> = signatures
+        .iter()
+        .map(|s| format!("- {} → {}", s.signature, test_name(s)))
+        .collect();
+    format!(
+        "Сгенерируй заготовки модульных тестов YaxUnit для методов модуля 1С.\n\
+         Для каждого метода создай экспортную процедуру с указанным именем; первой строкой \
+         над ней — комментарий с сигнатурой тестируемого метода. В теле: подготовка данных, \
+         вызов метода и проверка через ЮТест.ОжидаетЧто(...). Зарегистрируй все тесты в \
+         процедуре ИсполняемыеСценарии() Экспорт через ЮТТесты.ДобавитьТест(\"ИмяТеста\").\n\
+         Верни весь тестовый модуль одним блоком ```bsl.\n\n\
+         Методы:\n{}\n\nКод модуля:\n```bsl\n{}\n```",
+        list.join("\n"),
+        code.trim()
+    )
+}
+
+fn fallback_stub(signature: &BslSignature) -> String {
+    let call = if signature.is_function {
+        format!(
+            "\t// Результат = {}();\n\t// ЮТест.ОжидаетЧто(Результат).Равно(Ожидаемое);",
+            signature.name
+        )
+    } else {
+        format!(
+            "\t// {}();\n\t// ЮТест.ОжидаетЧто(...).Равно(...);",
+            signature.name
+        )
+    };
+    format!(
+        "// {}\nПроцедура {}() Экспорт\n\n{}\n\nКонецПроцедуры",
+        signature.signature,
+        test_name(signature),
+        call
+    )
+}
+
+/// Adds a skeleton test for every signature the model skipped and the
+/// `ИсполняемыеСценарии` registration when it is missing
+pub fn ensure_stub_for_each(stubs: &str, signatures: &[BslSignature]) -> String {
+    let lower = stubs.to_lowercase();
+    let mut parts: Vec<String> = Vec::new();
+    if !lower.contains("исполняемыесценарии") {
+        let registrations: Vec<String> = signatures
+            .iter()
+            .map(|s| format!("\tЮТТесты.ДобавитьТест(\"{}\");", test_name(s)))
+            .collect();
+        parts.push(format!(
+            "Процедура ИсполняемыеСценарии() Экспорт\n\n{}\n\nКонецПроцедуры",
+            registrations.join("\n")
+        ));
+    }
+    if !stubs.trim().is_empty() {
+        parts.push(stubs.trim().to_string());
+    }
+    parts.extend(
+        signatures
+            .iter()
+            .filter(|s| !lower.contains(&test_name(s).to_lowercase()))
+            .map(fallback_stub),
+    );
+    parts.join("\n\n")
+}
+
+/// Generates a YaxUnit test module for `code` with a stub for each procedure and function
+pub async fn generate_test_stubs(code: String) -> Result<CodeBlock, AiClientError> {
+    let signatures = extract_bsl_signatures(&code);
+    if signatures.is_empty() {
+        return Err("В коде нет процедур и функций для тестирования".into());
+    }
+    let answer = prompt_completion(
+        build_test_stubs_prompt(&code, &signatures),
+        TaskType::Generate,
+    )
+    .await?;
+    let blocks = extract_code_blocks(&answer);
+    Ok(CodeBlock {
+        language: "bsl".to_string(),
+        code: ensure_stub_for_each(&merge_code_blocks(&blocks), &signatures),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "Функция Сумма(А, Б) Экспорт\n\tВозврат А + Б;\nКонецФункции\n\n\
+                        Процедура Очистить(Список)\n\tСписок.Очистить();\nКонецПроцедуры";
+
+    #[test]
+    fn stub_is_generated_for_each_signature() {
+        let signatures = extract_bsl_signatures(CODE);
+        let stubs = ensure_stub_for_each("", &signatures);
+
+        assert!(stubs.contains("ЮТТесты.ДобавитьТест(\"Тест_Сумма\");"));
+        assert!(stubs.contains("ЮТТесты.ДобавитьТест(\"Тест_Очистить\");"));
+        assert!(stubs.contains("// Функция Сумма(А, Б) Экспорт\nПроцедура Тест_Сумма() Экспорт"));
+        assert!(stubs.contains("// Процедура Очистить(Список)\nПроцедура Тест_Очистить() Экспорт"));
+    }
+
+    #[test]
+    fn only_missing_stubs_are_added_to_the_answer() {
+        let signatures = extract_bsl_signatures(CODE);
+        let answer = "Процедура ИсполняемыеСценарии() Экспорт\n\tЮТТесты.ДобавитьТест(\"Тест_Сумма\");\nКонецПроцедуры\n\n\
+                      // Функция Сумма(А, Б) Экспорт\nПроцедура Тест_Сумма() Экспорт\nКонецПроцедуры";
+        let stubs = ensure_stub_for_each(answer, &signatures);
+
+        assert!(stubs.starts_with(answer));
+        assert_eq!(stubs.matches("Процедура Тест_Сумма()").count(), 1);
+        assert_eq!(stubs.matches("Процедура Тест_Очистить()").count(), 1);
+
+        let prompt = build_test_stubs_prompt(CODE, &signatures);
+        assert!(prompt.contains("- Функция Сумма(А, Б) Экспорт → Тест_Сумма"));
+        assert!(prompt.contains("- Процедура Очистить(Список) → Тест_Очистить"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Bundled BPE tokenizer for turning words into `logit_bias` token ids.
+//!
+//! Ids are only meaningful for the tokenizer family of the model (OpenAI-style
+//! `o200k_base` / `cl100k_base`); unknown models fall back to `o200k_base` and
+//! their ids are marked `approximate`.
+//!
+//! Also holds the model-agnostic token estimate used for context budgets,
+//! rate limits and usage the provider did not report.
+
+use serde::Serialize;
+use tiktoken_rs::CoreBPE;
+
+use super::models::ApiMessage;
+
+/// Token ids of one spelling of a word.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WordTokens {
+    pub text: String,
+    pub token_ids: Vec<u32>,
+    /// The model's tokenizer is unknown and the ids come from `o200k_base`
+    pub approximate: bool,
+}
+
+/// Tokenizer of the model and whether it is the `o200k_base` fallback
+fn bpe_for_model(model: &str) -> Result<(CoreBPE, bool), String> {
+    // OpenRouter-style ids: "openai/gpt-4o"
+    let name = model.rsplit('/').next().unwrap_or(model);
+    if let Ok(bpe) = tiktoken_rs::get_bpe_from_model(name) {
+        return Ok((bpe, false));
+    }
+    tiktoken_rs::o200k_base()
+        .map(|bpe| (bpe, true))
+        .map_err(|e| format!("Не удалось загрузить токенизатор: {}", e))
+}
+
+/// Token ids for each word as written and with a leading space, since mid-sentence
+/// words are usually a different token than at the start of a line.
+pub fn tokenize_words(words: &[String], model: &str) -> Result<Vec<WordTokens>, String> {
+    let (bpe, approximate) = bpe_for_model(model)?;
+    let mut result = Vec::new();
+    for word in words.iter().map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        for text in [word.to_string(), format!(" {}", word)] {
+            let token_ids = bpe.encode_ordinary(&text);
+            result.push(WordTokens {
+                text,
+                token_ids,
+                approximate,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Per-message overhead for the `role`/`content` structure (OpenAI counting spec).
+pub const TOKENS_PER_MESSAGE: u32 = 4;
+
+/// Estimates tokens in a text: ASCII words ≈ 4/3 token each,
+/// non-ASCII (Cyrillic) words ≈ bytes / 1.5.
+pub fn estimate_text_tokens(text: &str) -> u32 {
+    let mut ascii_words = 0u32;
+    let mut non_ascii_bytes = 0usize;
+    for word in text.split_whitespace() {
+        if word.is_ascii() {
+            ascii_words += 1;
+        } else {
+            non_ascii_bytes += word.len();
+        }
+    }
+    let ascii_tokens = (ascii_words * 4).div_ceil(3);
+    let cyrillic_tokens = (non_ascii_bytes as f64 / 1.5).ceil() as u32;
+    ascii_tokens + cyrillic_tokens
+}
+
+pub fn estimate_message_tokens(message: &ApiMessage) -> u32 {
+    let content = message.text().map(estimate_text_tokens).unwrap_or(0);
+    let tool_calls = message
+        .tool_calls
+        .as_ref()
+        .map(|tc| {
+            tc.iter()
+                .map(|t| {
+                    estimate_text_tokens(&t.function.name)
+                        + estimate_text_tokens(&t.function.arguments)
+                })
+                .sum::<u32>()
+        })
+        .unwrap_or(0);
+    TOKENS_PER_MESSAGE + content + tool_calls
+}
+
+pub fn estimate_messages_tokens(messages: &[ApiMessage]) -> u32 {
+    messages.iter().map(estimate_message_tokens).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_each_word_with_and_without_leading_space() {
+        let words = vec!["EndProcedure".to_string(), "  ".to_string()];
+        let tokens = tokenize_words(&words, "openai/gpt-4o").unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].text, "EndProcedure");
+        assert_eq!(tokens[1].text, " EndProcedure");
+        assert!(tokens.iter().all(|t| !t.token_ids.is_empty()));
+        assert_ne!(tokens[0].token_ids, tokens[1].token_ids);
+        assert!(tokens.iter().all(|t| !t.approximate));
+
+        // Unknown model falls back to o200k_base and says so
+        let fallback = tokenize_words(&words, "my-local-model").unwrap();
+        assert!(fallback.iter().all(|t| t.approximate));
+        assert_eq!(fallback[0].token_ids, tokens[0].token_ids);
+    }
+
+    #[test]
+    fn text_token_estimate_weights_cyrillic_by_bytes() {
+        // 3 ASCII words → ceil(12 / 3) = 4 tokens
+        assert_eq!(estimate_text_tokens("return the value"), 4);
+        // "Процедура" = 18 bytes → 12 tokens
+        assert_eq!(estimate_text_tokens("Процедура"), 12);
+        assert_eq!(estimate_text_tokens(""), 0);
+    }
+
+    #[test]
+    fn message_token_estimate_includes_structure_overhead() {
+        let message = ApiMessage {
+            role: "user".to_string(),
+            content: None,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        };
+
+        assert_eq!(estimate_message_tokens(&message), TOKENS_PER_MESSAGE);
+    }
+}
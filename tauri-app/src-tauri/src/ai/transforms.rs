@@ -0,0 +1,169 @@
+//! Post-processing of the final answer text: composable transforms applied in order.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::code_blocks::extract_code_blocks;
+
+pub trait ResponseTransform: Send + Sync {
+    fn transform(&self, input: &str) -> String;
+}
+
+lazy_static! {
+    static ref HEADING: Regex = Regex::new(r"^(\s*)#{1,6}\s+").unwrap();
+    static ref UNDERSCORE_EMPHASIS: Regex = Regex::new(r"(^|\W)_([^_\n]+)_(\W|$)").unwrap();
+}
+
+/// Removes `**`/`__` bold, `_italic_` and `#` heading markers; fenced code is left as is
+pub struct StripMarkdown;
+
+impl ResponseTransform for StripMarkdown {
+    fn transform(&self, input: &str) -> String {
+        let mut in_fence = false;
+        let lines: Vec<String> = input
+            .lines()
+            .map(|line| {
+                if line.trim_start().starts_with("```") {
+                    in_fence = !in_fence;
+                    return line.to_string();
+                }
+                if in_fence {
+                    return line.to_string();
+                }
+                let line = HEADING.replace(line, "$1");
+                let line = line.replace("**", "").replace("__", "");
+                UNDERSCORE_EMPHASIS
+                    .replace_all(&line, "$1$2$3")
+                    .into_owned()
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// Body of the first fenced block; the input unchanged when there is none
+pub struct ExtractFirstCodeBlock;
+
+impl ResponseTransform for ExtractFirstCodeBlock {
+    fn transform(&self, input: &str) -> String {
+        extract_code_blocks(input)
+            .into_iter()
+            .next()
+            .map(|block| block.code)
+            .unwrap_or_else(|| input.to_string())
+    }
+}
+
+pub struct TruncateToNLines(pub u32);
+
+impl ResponseTransform for TruncateToNLines {
+    fn transform(&self, input: &str) -> String {
+        input
+            .lines()
+            .take(self.0 as usize)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub struct PrependText(pub String);
+
+impl ResponseTransform for PrependText {
+    fn transform(&self, input: &str) -> String {
+        format!("{}{}", self.0, input)
+    }
+}
+
+pub struct AppendText(pub String);
+
+impl ResponseTransform for AppendText {
+    fn transform(&self, input: &str) -> String {
+        format!("{}{}", input, self.0)
+    }
+}
+
+/// Transform as sent by the frontend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformSpec {
+    StripMarkdown,
+    ExtractFirstCodeBlock,
+    TruncateToNLines { lines: u32 },
+    PrependText { text: String },
+    AppendText { text: String },
+}
+
+impl TransformSpec {
+    pub fn build(&self) -> Box<dyn ResponseTransform> {
+        match self {
+            TransformSpec::StripMarkdown => Box::new(StripMarkdown),
+            TransformSpec::ExtractFirstCodeBlock => Box::new(ExtractFirstCodeBlock),
+            TransformSpec::TruncateToNLines { lines } => Box::new(TruncateToNLines(*lines)),
+            TransformSpec::PrependText { text } => Box::new(PrependText(text.clone())),
+            TransformSpec::AppendText { text } => Box::new(AppendText(text.clone())),
+        }
+    }
+}
+
+/// Transforms applied left to right
+#[derive(Default)]
+pub struct TransformPipeline(pub Vec<Box<dyn ResponseTransform>>);
+
+impl TransformPipeline {
+    pub fn from_specs(specs: &[TransformSpec]) -> Self {
+        Self(specs.iter().map(TransformSpec::build).collect())
+    }
+
+    pub fn apply(&self, input: &str) -> String {
+        self.0.iter().fold(input.to_string(), |text, transform| {
+            transform.transform(&text)
+        })
+    }
+}
+
+impl std::fmt::Debug for TransformPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TransformPipeline({} transforms)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_markdown_keeps_code_and_identifiers() {
+        let input =
+            "## Итог\n**Важно:** _проверьте_ Имя_Реквизита\n```bsl\n// ## не заголовок\n```";
+        assert_eq!(
+            StripMarkdown.transform(input),
+            "Итог\nВажно: проверьте Имя_Реквизита\n```bsl\n// ## не заголовок\n```"
+        );
+    }
+
+    #[test]
+    fn extract_first_code_block_falls_back_to_input() {
+        let input = "Вот код:\n```bsl\nСообщить(1);\n```\n```bsl\nСообщить(2);\n```";
+        assert_eq!(ExtractFirstCodeBlock.transform(input), "Сообщить(1);");
+        assert_eq!(ExtractFirstCodeBlock.transform("без кода"), "без кода");
+    }
+
+    #[test]
+    fn pipeline_applies_specs_in_order() {
+        let specs: Vec<TransformSpec> = serde_json::from_str(
+            r#"[
+                {"type": "extract_first_code_block"},
+                {"type": "truncate_to_n_lines", "lines": 2},
+                {"type": "prepend_text", "text": "// AI\n"},
+                {"type": "append_text", "text": "\n// конец"}
+            ]"#,
+        )
+        .unwrap();
+        let pipeline = TransformPipeline::from_specs(&specs);
+
+        let input = "Ответ\n```bsl\nА = 1;\nБ = 2;\nВ = 3;\n```";
+        assert_eq!(pipeline.apply(input), "// AI\nА = 1;\nБ = 2;\n// конец");
+        assert_eq!(TransformPipeline::default().apply(input), input);
+    }
+}
@@ -0,0 +1,264 @@
+//! Usage totals: in memory for the current app session, and per profile by day
+//! in `usage_stats.json` in the settings directory.
+
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+
+use super::models::CompletionMeta;
+use crate::json_file::JsonFile;
+
+/// Accumulated usage since app start.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+static USAGE_TOTALS: OnceLock<Mutex<UsageTotals>> = OnceLock::new();
+
+fn usage_totals() -> &'static Mutex<UsageTotals> {
+    USAGE_TOTALS.get_or_init(|| Mutex::new(UsageTotals::default()))
+}
+
+/// Adds one completed request to the totals.
+pub fn record_usage(meta: &CompletionMeta) {
+    let mut totals = usage_totals().lock().unwrap_or_else(|e| e.into_inner());
+    totals.requests += 1;
+    totals.prompt_tokens += meta.prompt_tokens.unwrap_or(0) as u64;
+    totals.completion_tokens += meta.completion_tokens.unwrap_or(0) as u64;
+    totals.estimated_cost_usd += meta.estimated_cost_usd.unwrap_or(0.0);
+}
+
+pub fn reset_total_usage() {
+    *usage_totals().lock().unwrap_or_else(|e| e.into_inner()) = UsageTotals::default();
+}
+
+pub fn get_total_usage() -> UsageTotals {
+    usage_totals()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Counters of one profile for one day (or summed over a period)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageCounters {
+    pub requests: u64,
+    /// Requests that failed before the provider reported usage
+    #[serde(default)]
+    pub errors: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Only requests whose model is in the price table contribute
+    pub estimated_cost_usd: f64,
+}
+
+impl UsageCounters {
+    fn add(&mut self, other: &UsageCounters) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.estimated_cost_usd += other.estimated_cost_usd;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStatsStore {
+    /// Profile id → day → counters
+    pub profiles: HashMap<String, BTreeMap<NaiveDate, UsageCounters>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    Today,
+    /// Last 7 days including today
+    Week,
+    /// Last 30 days including today
+    Month,
+    All,
+}
+
+impl UsagePeriod {
+    /// First day of the period ending on `today`; `None` for all time
+    fn start(self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            UsagePeriod::Today => Some(today),
+            UsagePeriod::Week => Some(today - Duration::days(6)),
+            UsagePeriod::Month => Some(today - Duration::days(29)),
+            UsagePeriod::All => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub counters: UsageCounters,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    pub totals: UsageCounters,
+    /// Days with any activity, oldest first, summed over the selected profiles
+    pub days: Vec<DailyUsage>,
+    /// Totals by profile id
+    pub by_profile: HashMap<String, UsageCounters>,
+}
+
+impl UsageStatsStore {
+    fn day_mut(&mut self, profile_id: &str, day: NaiveDate) -> &mut UsageCounters {
+        self.profiles
+            .entry(profile_id.to_string())
+            .or_default()
+            .entry(day)
+            .or_default()
+    }
+
+    pub fn record_completion(&mut self, profile_id: &str, meta: &CompletionMeta, day: NaiveDate) {
+        let counters = self.day_mut(profile_id, day);
+        counters.requests += 1;
+        counters.prompt_tokens += meta.prompt_tokens.unwrap_or(0) as u64;
+        counters.completion_tokens += meta.completion_tokens.unwrap_or(0) as u64;
+        counters.estimated_cost_usd += meta.estimated_cost_usd.unwrap_or(0.0);
+    }
+
+    pub fn record_error(&mut self, profile_id: &str, day: NaiveDate) {
+        self.day_mut(profile_id, day).errors += 1;
+    }
+
+    /// One profile or all of them, over `period` ending on `today`
+    pub fn stats(
+        &self,
+        profile_id: Option<&str>,
+        period: UsagePeriod,
+        today: NaiveDate,
+    ) -> UsageStats {
+        let start = period.start(today);
+        let mut totals = UsageCounters::default();
+        let mut days: BTreeMap<NaiveDate, UsageCounters> = BTreeMap::new();
+        let mut by_profile = HashMap::new();
+        for (id, history) in &self.profiles {
+            if profile_id.is_some_and(|wanted| wanted != id) {
+                continue;
+            }
+            let mut profile_totals = UsageCounters::default();
+            for (day, counters) in history {
+                if start.is_some_and(|start| *day < start) || *day > today {
+                    continue;
+                }
+                profile_totals.add(counters);
+                days.entry(*day).or_default().add(counters);
+            }
+            totals.add(&profile_totals);
+            by_profile.insert(id.clone(), profile_totals);
+        }
+        UsageStats {
+            totals,
+            days: days
+                .into_iter()
+                .map(|(date, counters)| DailyUsage { date, counters })
+                .collect(),
+            by_profile,
+        }
+    }
+}
+
+static USAGE_FILE: JsonFile = JsonFile::new("usage_stats.json");
+
+/// Load the stats; a missing or broken file yields empty ones
+pub fn load_usage_stats() -> UsageStatsStore {
+    USAGE_FILE.load()
+}
+
+fn update_usage_stats(apply: impl FnOnce(&mut UsageStatsStore)) -> Result<(), String> {
+    USAGE_FILE.update(apply)
+}
+
+/// Adds the outcome of a completion with the profile: usage on success, an error otherwise
+pub fn record_profile_usage(profile_id: &str, meta: Option<&CompletionMeta>) {
+    let today = Local::now().date_naive();
+    let result = update_usage_stats(|store| match meta {
+        Some(meta) => store.record_completion(profile_id, meta, today),
+        None => store.record_error(profile_id, today),
+    });
+    if let Err(e) = result {
+        crate::app_log!("[Usage] Failed to save usage stats: {}", e);
+    }
+}
+
+pub fn get_usage_stats(profile_id: Option<&str>, period: UsagePeriod) -> UsageStats {
+    load_usage_stats().stats(profile_id, period, Local::now().date_naive())
+}
+
+/// Clears the history of one profile, or of all of them
+pub fn reset_usage_stats(profile_id: Option<&str>) -> Result<(), String> {
+    update_usage_stats(|store| match profile_id {
+        Some(id) => {
+            store.profiles.remove(id);
+        }
+        None => store.profiles.clear(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    fn meta(prompt: u32, completion: u32, cost: Option<f64>) -> CompletionMeta {
+        CompletionMeta {
+            prompt_tokens: Some(prompt),
+            completion_tokens: Some(completion),
+            estimated_cost_usd: cost,
+            ..CompletionMeta::default()
+        }
+    }
+
+    #[test]
+    fn aggregates_by_day_profile_and_period() {
+        let mut store = UsageStatsStore::default();
+        store.record_completion("a", &meta(100, 50, Some(0.01)), day(1));
+        store.record_completion("a", &meta(10, 5, None), day(10));
+        store.record_error("a", day(10));
+        store.record_completion("b", &meta(1, 1, Some(0.5)), day(10));
+
+        let week = store.stats(None, UsagePeriod::Week, day(10));
+        assert_eq!(week.totals.requests, 2);
+        assert_eq!(week.totals.errors, 1);
+        assert_eq!(week.totals.prompt_tokens, 11);
+        assert_eq!(week.days.len(), 1);
+        assert_eq!(week.days[0].date, day(10));
+        assert_eq!(week.by_profile["a"].completion_tokens, 5);
+
+        let all = store.stats(Some("a"), UsagePeriod::All, day(10));
+        assert_eq!(all.totals.requests, 2);
+        assert_eq!(all.totals.prompt_tokens, 110);
+        assert!((all.totals.estimated_cost_usd - 0.01).abs() < 1e-9);
+        assert_eq!(
+            all.days.iter().map(|d| d.date).collect::<Vec<_>>(),
+            [day(1), day(10)]
+        );
+        assert!(!all.by_profile.contains_key("b"));
+    }
+
+    #[test]
+    fn stats_file_format_round_trips() {
+        let mut store = UsageStatsStore::default();
+        store.record_completion("a", &meta(3, 4, None), day(2));
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(json.contains("\"2026-03-02\""));
+
+        let restored: UsageStatsStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.profiles["a"][&day(2)].completion_tokens, 4);
+    }
+}
@@ -0,0 +1,81 @@
+//! Holds back the word a stream chunk ends in, so the chat shows whole words
+//! (`LLMProfile::word_smoothing`).
+
+/// `_` is part of 1С identifiers (`Имя_Реквизита`) and does not end a word
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace()
+        || (c.is_ascii_punctuation() && c != '_')
+        || matches!(c, '«' | '»' | '—' | '…')
+}
+
+#[derive(Debug, Default)]
+pub struct WordSmoother {
+    pending: String,
+}
+
+impl WordSmoother {
+    /// Text up to the last word boundary of everything received so far; empty while the
+    /// chunk is still inside a word
+    pub fn push_chunk(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(chunk);
+        let Some((pos, boundary)) = self
+            .pending
+            .char_indices()
+            .rev()
+            .find(|(_, c)| is_word_boundary(*c))
+        else {
+            return Vec::new();
+        };
+        let rest = self.pending.split_off(pos + boundary.len_utf8());
+        vec![std::mem::replace(&mut self.pending, rest)]
+    }
+
+    /// The held-back word, at the end of the answer
+    pub fn flush(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(chunks: &[&str]) -> Vec<String> {
+        let mut smoother = WordSmoother::default();
+        let mut out: Vec<String> = chunks
+            .iter()
+            .flat_map(|chunk| smoother.push_chunk(chunk))
+            .collect();
+        out.extend(smoother.flush());
+        out
+    }
+
+    #[test]
+    fn word_split_across_chunks_is_emitted_whole() {
+        let mut smoother = WordSmoother::default();
+        assert_eq!(smoother.push_chunk("Hello wo"), vec!["Hello "]);
+        assert!(smoother.push_chunk("rl").is_empty());
+        assert_eq!(smoother.push_chunk("d, again"), vec!["world, "]);
+        assert_eq!(smoother.flush().as_deref(), Some("again"));
+        assert_eq!(smoother.flush(), None);
+    }
+
+    #[test]
+    fn spaces_are_kept_as_sent() {
+        let out = feed(&["a  ", "  b", "\n\n", "c"]);
+        assert_eq!(out.concat(), "a    b\n\nc");
+        assert_eq!(out, vec!["a  ", "  ", "b\n\n", "c"]);
+    }
+
+    #[test]
+    fn cyrillic_words_and_identifiers_stay_whole() {
+        let mut smoother = WordSmoother::default();
+        assert_eq!(smoother.push_chunk("Процедура Пров"), vec!["Процедура "]);
+        assert_eq!(smoother.push_chunk("ерка_Остат"), Vec::<String>::new());
+        assert_eq!(
+            smoother.push_chunk("ков() «да»"),
+            vec!["Проверка_Остатков() «да»"]
+        );
+        assert_eq!(smoother.flush(), None);
+    }
+}
@@ -0,0 +1,1329 @@
+//! Static metrics for BSL code that do not need BSL Language Server.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::annotations::AnnotationSeverity;
+
+/// Keywords that open a control-flow branch (lowercase). `Для` covers both
+/// `Для ... По` and `Для Каждого`; `Когда` is a branch of a query `ВЫБОР`.
+const BRANCH_KEYWORDS: &[&str] = &[
+    "если",
+    "иначеесли",
+    "пока",
+    "для",
+    "покаждому",
+    "и",
+    "или",
+    "когда",
+    "if",
+    "elsif",
+    "while",
+    "for",
+    "and",
+    "or",
+    "when",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComplexityReport {
+    pub score: u32,
+    pub rating: &'static str,
+    /// Branch keywords found (`score - 1`)
+    pub decision_points: u32,
+}
+
+/// Code with comments, string literals and preprocessor/compiler directives blanked out.
+pub(crate) fn strip_non_code(code: &str) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut in_string = false;
+    for line in code.lines() {
+        let trimmed = line.trim_start();
+        // Multi-line string literals continue on lines starting with `|`
+        if in_string && !trimmed.starts_with('|') {
+            in_string = false;
+        }
+        if !in_string && (trimmed.starts_with('#') || trimmed.starts_with('&')) {
+            result.push('\n');
+            continue;
+        }
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_string {
+                if c == '"' {
+                    // `""` is an escaped quote
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                    } else {
+                        in_string = false;
+                    }
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '/' if chars.peek() == Some(&'/') => break,
+                _ => result.push(c),
+            }
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// `1 + <number of branch keywords>` outside comments and string literals.
+pub fn estimate_cyclomatic_complexity(code: &str) -> u32 {
+    let code = strip_non_code(code).to_lowercase();
+    let branches = code
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| BRANCH_KEYWORDS.contains(word))
+        .count();
+    1 + branches as u32
+}
+
+pub fn complexity_rating(score: u32) -> &'static str {
+    match score {
+        0..=5 => "Simple",
+        6..=10 => "Moderate",
+        11..=20 => "Complex",
+        _ => "Very Complex",
+    }
+}
+
+pub fn analyze_complexity(code: &str) -> ComplexityReport {
+    let score = estimate_cyclomatic_complexity(code);
+    ComplexityReport {
+        score,
+        rating: complexity_rating(score),
+        decision_points: score - 1,
+    }
+}
+
+/// Potentially dangerous construct found by `audit_bsl_security`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityFinding {
+    /// Rule id, e.g. `sql_concatenation`
+    pub pattern: String,
+    /// 1-based line
+    pub line: u32,
+    pub severity: AnnotationSeverity,
+    /// Why the construct is risky
+    pub description: String,
+    pub suggestion: String,
+}
+
+const QUERY_KEYWORDS: &[&str] = &[
+    "выбрать",
+    "где",
+    "из",
+    "соединение",
+    "select",
+    "where",
+    "from",
+    "join",
+];
+const RIGHTS_CHECKS: &[&str] = &[
+    "праводоступа(",
+    "рольдоступна(",
+    "выполнитьпроверкуправдоступа(",
+    "accessright(",
+    "isinrole(",
+    "verifyaccessrights(",
+];
+const EXTERNAL_PROCESSING: &[&str] = &[
+    "загрузитьвнешнююобработку",
+    "подключитьвнешнююобработку",
+    "внешниеобработки.подключить",
+    "внешниеобработки.создать",
+    "externaldataprocessors.connect",
+    "externaldataprocessors.create",
+];
+
+lazy_static::lazy_static! {
+    // Global context call (not `Запрос.Выполнить()`) with a non-literal argument
+    static ref DYNAMIC_EXECUTE: regex::Regex = regex::Regex::new(
+        r"(?i)(?:^|[^.\p{L}\p{N}_])(выполнить|execute|вычислить|eval)\s*\(\s*[^)\s]"
+    )
+    .unwrap();
+    // `+` followed by a variable or call rather than another literal
+    static ref CONCAT_WITH_VALUE: regex::Regex = regex::Regex::new(r"\+\s*[\p{L}_]").unwrap();
+    static ref DATA_WRITE: regex::Regex =
+        regex::Regex::new(r"(?i)\.(записать|удалить|write|delete)\s*\(").unwrap();
+    static ref ROUTINE_START: regex::Regex =
+        regex::Regex::new(r"(?i)^\s*(процедура|функция|procedure|function)\s").unwrap();
+}
+
+fn words(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+}
+
+fn finding(
+    pattern: &str,
+    line: usize,
+    severity: AnnotationSeverity,
+    description: &str,
+    suggestion: &str,
+) -> SecurityFinding {
+    SecurityFinding {
+        pattern: pattern.to_string(),
+        line: line as u32 + 1,
+        severity,
+        description: description.to_string(),
+        suggestion: suggestion.to_string(),
+    }
+}
+
+/// Line-based audit for constructs that commonly lead to injections, running
+/// untrusted code or writes that bypass access rights. Comments are ignored.
+pub fn audit_bsl_security(code: &str) -> Vec<SecurityFinding> {
+    let stripped = strip_non_code(code).to_lowercase();
+    let mut findings = Vec::new();
+    let mut rights_checked = false;
+
+    for (i, (raw, code_line)) in code.lines().zip(stripped.lines()).enumerate() {
+        let raw = raw.split("//").next().unwrap_or_default().to_lowercase();
+        if ROUTINE_START.is_match(code_line) {
+            rights_checked = false;
+        }
+        if RIGHTS_CHECKS.iter().any(|check| code_line.contains(check)) {
+            rights_checked = true;
+        }
+
+        // Query keyword inside a string literal, concatenated with a value
+        let code_words: Vec<&str> = words(code_line).collect();
+        let query_in_string =
+            words(&raw).any(|w| QUERY_KEYWORDS.contains(&w) && !code_words.contains(&w));
+        if query_in_string && CONCAT_WITH_VALUE.is_match(code_line) {
+            findings.push(finding(
+                "sql_concatenation",
+                i,
+                AnnotationSeverity::Error,
+                "Значение подставляется в текст запроса конкатенацией строк — возможна инъекция в запрос.",
+                "Используйте параметры: &Параметр в тексте и Запрос.УстановитьПараметр().",
+            ));
+        }
+        if DYNAMIC_EXECUTE.is_match(code_line) {
+            findings.push(finding(
+                "dynamic_execute",
+                i,
+                AnnotationSeverity::Error,
+                "Выполнить/Вычислить с вычисляемой строкой исполняет произвольный код с правами сеанса.",
+                "Замените на явные вызовы или ОбщегоНазначения.ВыполнитьМетодКонфигурации с проверкой имени метода.",
+            ));
+        }
+        if EXTERNAL_PROCESSING
+            .iter()
+            .any(|call| code_line.replace(' ', "").contains(call))
+        {
+            findings.push(finding(
+                "external_processing",
+                i,
+                AnnotationSeverity::Warning,
+                "Подключение внешней обработки выполняет код вне конфигурации.",
+                "Подключайте только обработки из справочника ДополнительныеОтчетыИОбработки в безопасном режиме.",
+            ));
+        }
+        if DATA_WRITE.is_match(code_line) && !rights_checked {
+            findings.push(finding(
+                "missing_rights_check",
+                i,
+                AnnotationSeverity::Warning,
+                "Запись или удаление данных без предварительной проверки прав в этой процедуре.",
+                "Проверьте права через ПравоДоступа() или ВыполнитьПроверкуПравДоступа() перед записью.",
+            ));
+        }
+        if code_line.contains("управлениепользователями.") {
+            findings.push(finding(
+                "user_management",
+                i,
+                AnnotationSeverity::Warning,
+                "Прямой вызов УправлениеПользователями меняет пользователей и их права.",
+                "Убедитесь, что вызов выполняется только администратором, и не передавайте в него данные от клиента без проверки.",
+            ));
+        }
+    }
+    findings
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadCodeKind {
+    UnusedProcedure,
+    UnusedVariable,
+    UnreachableCode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadCodeFinding {
+    pub kind: DeadCodeKind,
+    /// Routine or variable name; the first statement for unreachable code
+    pub name: String,
+    /// 1-based line
+    pub line: u32,
+}
+
+lazy_static::lazy_static! {
+    static ref ROUTINE_DECLARATION: regex::Regex = regex::Regex::new(
+        r"(?i)^\s*(?:асинх\s+|async\s+)?(?:процедура|функция|procedure|function)\s+([\p{L}_][\p{L}\p{N}_]*)\s*\(([^)]*)\)?(.*)$"
+    )
+    .unwrap();
+    static ref ROUTINE_END: regex::Regex =
+        regex::Regex::new(r"(?i)^\s*(конецпроцедуры|конецфункции|endprocedure|endfunction)\b").unwrap();
+    static ref ASSIGNMENT: regex::Regex =
+        regex::Regex::new(r"^\s*([\p{L}_][\p{L}\p{N}_]*)\s*=").unwrap();
+    static ref RETURN_STATEMENT: regex::Regex =
+        regex::Regex::new(r"(?i)^\s*(возврат|return)\b").unwrap();
+    // Lines that may follow `Возврат` without being dead: block ends, branches, labels
+    static ref BLOCK_BOUNDARY: regex::Regex = regex::Regex::new(
+        r"(?i)^\s*(~|конецесли|иначе|иначеесли|конеццикла|исключение|конецпопытки|конецпроцедуры|конецфункции|endif|else|elsif|enddo|except|endtry|endprocedure|endfunction)\b"
+    )
+    .unwrap();
+}
+
+struct Routine {
+    name: String,
+    line: usize,
+    end: usize,
+    exported: bool,
+    params: Vec<String>,
+}
+
+fn lowercase_words(line: &str) -> Vec<String> {
+    words(line).map(str::to_lowercase).collect()
+}
+
+/// Pass 1: routine declarations with their body range, parameters and `Экспорт`
+fn collect_routines(lines: &[&str]) -> Vec<Routine> {
+    let mut routines: Vec<Routine> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(caps) = ROUTINE_DECLARATION.captures(line) {
+            let params = caps
+                .get(2)
+                .map(|p| {
+                    lowercase_words(p.as_str())
+                        .into_iter()
+                        .filter(|w| w != "знач" && w != "val")
+                        .collect()
+                })
+                .unwrap_or_default();
+            let tail = caps.get(3).map(|m| m.as_str()).unwrap_or_default();
+            routines.push(Routine {
+                name: caps[1].to_string(),
+                line: i,
+                end: lines.len(),
+                exported: lowercase_words(tail)
+                    .iter()
+                    .any(|w| w == "экспорт" || w == "export"),
+                params,
+            });
+        } else if ROUTINE_END.is_match(line) {
+            if let Some(routine) = routines.last_mut().filter(|r| r.end == lines.len()) {
+                routine.end = i;
+            }
+        }
+    }
+    routines
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BslSignature {
+    pub name: String,
+    pub is_function: bool,
+    pub exported: bool,
+    /// Declaration line as written, e.g. `Функция Сумма(А, Б) Экспорт`
+    pub signature: String,
+    /// 1-based line
+    pub line: u32,
+}
+
+/// Procedures and functions declared in the module, in order
+pub fn extract_bsl_signatures(code: &str) -> Vec<BslSignature> {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let raw_lines: Vec<&str> = code.lines().collect();
+    collect_routines(&lines)
+        .into_iter()
+        .map(|routine| {
+            let first_word = lowercase_words(lines[routine.line])
+                .into_iter()
+                .find(|w| w != "асинх" && w != "async")
+                .unwrap_or_default();
+            BslSignature {
+                is_function: first_word == "функция" || first_word == "function",
+                exported: routine.exported,
+                signature: raw_lines[routine.line].trim().to_string(),
+                line: routine.line as u32 + 1,
+                name: routine.name,
+            }
+        })
+        .collect()
+}
+
+/// Line range of the `//` comment block directly above the declaration on `decl_line`;
+/// compilation directives (`&НаСервере`) between the comment and the declaration are skipped.
+/// Empty when the routine is not documented.
+fn doc_comment_range(raw_lines: &[&str], decl_line: usize) -> std::ops::Range<usize> {
+    let mut end = decl_line;
+    while end > 0 && raw_lines[end - 1].trim_start().starts_with('&') {
+        end -= 1;
+    }
+    let mut start = end;
+    while start > 0 && raw_lines[start - 1].trim_start().starts_with("//") {
+        start -= 1;
+    }
+    start..end
+}
+
+/// Documentation comment of every routine (lowercase name → comment lines); undocumented
+/// routines are not listed
+pub fn extract_bsl_docs(code: &str) -> std::collections::HashMap<String, Vec<String>> {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let raw_lines: Vec<&str> = code.lines().collect();
+    collect_routines(&lines)
+        .into_iter()
+        .filter_map(|routine| {
+            let range = doc_comment_range(&raw_lines, routine.line);
+            (!range.is_empty()).then(|| {
+                (
+                    routine.name.to_lowercase(),
+                    raw_lines[range].iter().map(|l| l.to_string()).collect(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Code without the documentation comments above procedures and functions, so docs can be
+/// regenerated from scratch. Other comments are kept.
+pub fn strip_bsl_docs(code: &str) -> String {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let raw_lines: Vec<&str> = code.lines().collect();
+    let mut removed = vec![false; raw_lines.len()];
+    for routine in collect_routines(&lines) {
+        for i in doc_comment_range(&raw_lines, routine.line) {
+            removed[i] = true;
+        }
+    }
+    let mut result = raw_lines
+        .iter()
+        .zip(&removed)
+        .filter(|(_, removed)| !**removed)
+        .map(|(line, _)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if code.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Inserts `docs` (lowercase routine name → comment lines) above every routine of `code`
+/// that has no documentation yet; the rest of the code is unchanged
+pub fn insert_bsl_docs(
+    code: &str,
+    docs: &std::collections::HashMap<String, Vec<String>>,
+) -> String {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let raw_lines: Vec<&str> = code.lines().collect();
+    let mut insertions: std::collections::HashMap<usize, &Vec<String>> =
+        std::collections::HashMap::new();
+    for routine in collect_routines(&lines) {
+        let range = doc_comment_range(&raw_lines, routine.line);
+        if let (true, Some(doc)) = (range.is_empty(), docs.get(&routine.name.to_lowercase())) {
+            insertions.insert(range.start, doc);
+        }
+    }
+    let mut result: Vec<&str> = Vec::with_capacity(raw_lines.len());
+    for (i, line) in raw_lines.iter().enumerate() {
+        if let Some(doc) = insertions.get(&i) {
+            result.extend(doc.iter().map(String::as_str));
+        }
+        result.push(line);
+    }
+    let mut result = result.join("\n");
+    if code.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Heuristic dead code search within one module: routines never called (exported ones
+/// and names mentioned in strings, e.g. `ОписаниеОповещения("Имя", ...)`, count as used),
+/// local variables assigned but never read and statements right after `Возврат`.
+pub fn detect_dead_code(code: &str) -> Vec<DeadCodeFinding> {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let routines = collect_routines(&lines);
+    let raw_lines: Vec<&str> = code.lines().collect();
+    let raw_lower = code.to_lowercase();
+    let line_words: Vec<Vec<String>> = lines.iter().map(|l| lowercase_words(l)).collect();
+    let mut findings = Vec::new();
+
+    // Pass 2: usages
+    for routine in &routines {
+        let name = routine.name.to_lowercase();
+        let called = line_words
+            .iter()
+            .enumerate()
+            .any(|(i, words)| i != routine.line && words.contains(&name));
+        let mentioned_in_string = raw_lower.contains(&format!("\"{}\"", name));
+        if !routine.exported && !called && !mentioned_in_string {
+            findings.push(DeadCodeFinding {
+                kind: DeadCodeKind::UnusedProcedure,
+                name: routine.name.clone(),
+                line: routine.line as u32 + 1,
+            });
+        }
+
+        let body = routine.line + 1..routine.end.min(lines.len());
+        let mut assigned: Vec<(String, String, usize)> = Vec::new();
+        for i in body.clone() {
+            if let Some(caps) = ASSIGNMENT.captures(lines[i]) {
+                let var = caps[1].to_lowercase();
+                if !routine.params.contains(&var) && !assigned.iter().any(|(v, _, _)| *v == var) {
+                    assigned.push((var, caps[1].to_string(), i));
+                }
+            }
+        }
+        for (var, original, first_line) in assigned {
+            let read = body.clone().any(|i| {
+                let occurrences = line_words[i].iter().filter(|w| **w == var).count();
+                let as_target = ASSIGNMENT
+                    .captures(lines[i])
+                    .is_some_and(|c| c[1].to_lowercase() == var);
+                occurrences > usize::from(as_target)
+            });
+            if !read {
+                findings.push(DeadCodeFinding {
+                    kind: DeadCodeKind::UnusedVariable,
+                    name: original,
+                    line: first_line as u32 + 1,
+                });
+            }
+        }
+
+        let mut after_return = false;
+        for i in body {
+            let line = lines[i].trim();
+            if line.is_empty() || line == ";" {
+                continue;
+            }
+            if after_return && !BLOCK_BOUNDARY.is_match(line) {
+                findings.push(DeadCodeFinding {
+                    kind: DeadCodeKind::UnreachableCode,
+                    name: raw_lines[i].trim().trim_end_matches(';').to_string(),
+                    line: i as u32 + 1,
+                });
+            }
+            after_return = RETURN_STATEMENT.is_match(line);
+        }
+    }
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+lazy_static::lazy_static! {
+    /// `Коллекция.Имя[.Член]` over the global metadata collections, Russian and English
+    static ref METADATA_REFERENCE: regex::Regex = regex::Regex::new(
+        r"(?i)\b(Справочники|Документы|ЖурналыДокументов|Перечисления|ПланыВидовХарактеристик|ПланыСчетов|ПланыВидовРасчета|РегистрыСведений|РегистрыНакопления|РегистрыБухгалтерии|РегистрыРасчета|БизнесПроцессы|Задачи|ПланыОбмена|Константы|Обработки|Отчеты|Последовательности|ВнешниеОбработки|ВнешниеОтчеты|Catalogs|Documents|DocumentJournals|Enums|ChartsOfCharacteristicTypes|ChartsOfAccounts|ChartsOfCalculationTypes|InformationRegisters|AccumulationRegisters|AccountingRegisters|CalculationRegisters|BusinessProcesses|Tasks|ExchangePlans|Constants|DataProcessors|Reports|Sequences|ExternalDataProcessors|ExternalReports)\s*\.\s*([\p{L}_][\p{L}\d_]*)(?:\s*\.\s*([\p{L}_][\p{L}\d_]*))?"
+    )
+    .unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataRef {
+    /// Collection as written: `Справочники`, `Catalogs`...
+    pub kind: String,
+    /// Object name, e.g. `Контрагенты`
+    pub name: String,
+    /// Member accessed on the manager with a leading dot (`.НайтиПоКоду`); empty when none
+    pub access: String,
+}
+
+/// Metadata objects referenced through the global collections, each distinct reference once
+/// in order of appearance; comments and string literals are ignored
+pub fn extract_metadata_references(code: &str) -> Vec<MetadataRef> {
+    let stripped = strip_non_code(code);
+    let mut refs: Vec<MetadataRef> = Vec::new();
+    for captures in METADATA_REFERENCE.captures_iter(&stripped) {
+        let reference = MetadataRef {
+            kind: captures[1].to_string(),
+            name: captures[2].to_string(),
+            access: captures
+                .get(3)
+                .map(|m| format!(".{}", m.as_str()))
+                .unwrap_or_default(),
+        };
+        if !refs.contains(&reference) {
+            refs.push(reference);
+        }
+    }
+    refs
+}
+
+lazy_static::lazy_static! {
+    pub static ref CYRILLIC_TO_LATIN: std::collections::HashMap<char, &'static str> = [
+        ('а', "a"), ('б', "b"), ('в', "v"), ('г', "g"), ('д', "d"), ('е', "e"), ('ё', "yo"),
+        ('ж', "zh"), ('з', "z"), ('и', "i"), ('й', "y"), ('к', "k"), ('л', "l"), ('м', "m"),
+        ('н', "n"), ('о', "o"), ('п', "p"), ('р', "r"), ('с', "s"), ('т', "t"), ('у', "u"),
+        ('ф', "f"), ('х', "kh"), ('ц', "ts"), ('ч', "ch"), ('ш', "sh"), ('щ', "shch"),
+        ('ъ', ""), ('ы', "y"), ('ь', ""), ('э', "e"), ('ю', "yu"), ('я', "ya"),
+    ]
+    .into_iter()
+    .collect();
+    static ref VARIABLE_DECLARATION: regex::Regex =
+        regex::Regex::new(r"(?i)^\s*(?:перем|var)\s+([^;]*)").unwrap();
+    static ref LOOP_VARIABLE: regex::Regex = regex::Regex::new(
+        r"(?i)^\s*(?:для\s+каждого|for\s+each|для|for)\s+([\p{L}_][\p{L}\p{N}_]*)"
+    )
+    .unwrap();
+}
+
+/// Keywords of both dialects; never renamed
+const BSL_KEYWORDS: &[&str] = &[
+    "если",
+    "тогда",
+    "иначеесли",
+    "иначе",
+    "конецесли",
+    "для",
+    "каждого",
+    "из",
+    "по",
+    "цикл",
+    "конеццикла",
+    "пока",
+    "прервать",
+    "продолжить",
+    "процедура",
+    "конецпроцедуры",
+    "функция",
+    "конецфункции",
+    "перем",
+    "знач",
+    "экспорт",
+    "возврат",
+    "попытка",
+    "исключение",
+    "конецпопытки",
+    "вызватьисключение",
+    "новый",
+    "выполнить",
+    "и",
+    "или",
+    "не",
+    "истина",
+    "ложь",
+    "неопределено",
+    "null",
+    "асинх",
+    "ждать",
+    "перейти",
+    "добавитьобработчик",
+    "удалитьобработчик",
+    "if",
+    "then",
+    "elsif",
+    "else",
+    "endif",
+    "for",
+    "each",
+    "in",
+    "to",
+    "do",
+    "enddo",
+    "while",
+    "break",
+    "continue",
+    "procedure",
+    "endprocedure",
+    "function",
+    "endfunction",
+    "var",
+    "val",
+    "export",
+    "return",
+    "try",
+    "except",
+    "endtry",
+    "raise",
+    "new",
+    "execute",
+    "and",
+    "or",
+    "not",
+    "true",
+    "false",
+    "undefined",
+    "async",
+    "await",
+    "goto",
+    "addhandler",
+    "removehandler",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransliterationReport {
+    pub original: String,
+    pub transliterated: String,
+    /// (old, new) in order of declaration; to be reviewed before applying
+    pub names_changed: Vec<(String, String)>,
+}
+
+/// CamelCase Latin spelling of a Cyrillic identifier: `КурсыВалют` → `KursyValyut`
+pub fn transliterate_identifier(name: &str) -> String {
+    let mut result = String::with_capacity(name.len() * 2);
+    for (i, c) in name.chars().enumerate() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let capitalize = i == 0 || c.is_uppercase();
+        match CYRILLIC_TO_LATIN.get(&lower) {
+            Some(latin) if capitalize => {
+                let mut chars = latin.chars();
+                if let Some(first) = chars.next() {
+                    result.extend(first.to_uppercase());
+                    result.push_str(chars.as_str());
+                }
+            }
+            Some(latin) => result.push_str(latin),
+            None if i == 0 => result.extend(c.to_uppercase()),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Platform events bound to handlers by name: object, form, application and session modules
+const EVENT_HANDLERS: &[&str] = &[
+    "обработкапроведения",
+    "обработкаудаленияпроведения",
+    "обработказаполнения",
+    "обработкапроверкизаполнения",
+    "обработкапроверкизаполнениянасервере",
+    "передзаписью",
+    "призаписи",
+    "послезаписи",
+    "передудалением",
+    "прикопировании",
+    "приустановкеновогономера",
+    "приустановкеновогокода",
+    "приоткрытии",
+    "передзакрытием",
+    "призакрытии",
+    "присозданиинасервере",
+    "причтениинасервере",
+    "передзаписьюнасервере",
+    "призаписинасервере",
+    "послезаписинасервере",
+    "обработкаоповещения",
+    "обработкавыбора",
+    "обработкаполученияданныхвыбора",
+    "обработкаполученияформы",
+    "обработкаполученияпредставления",
+    "обработкаполученияполейпредставления",
+    "передначаломработысистемы",
+    "приначалеработысистемы",
+    "передзавершениемработысистемы",
+    "призавершенииработысистемы",
+    "установкапараметровсеанса",
+    "обработкавнешнегособытия",
+    "filling",
+    "posting",
+    "undoposting",
+    "fillcheckprocessing",
+    "beforewrite",
+    "onwrite",
+    "afterwrite",
+    "beforedelete",
+    "oncopy",
+    "onopen",
+    "beforeclose",
+    "onclose",
+    "oncreateatserver",
+    "onreadatserver",
+    "beforewriteatserver",
+    "onwriteatserver",
+    "afterwriteatserver",
+    "notificationprocessing",
+    "choiceprocessing",
+    "sessionparameterssetting",
+];
+
+/// Endings of form item handlers, which are named after the item: `КонтрагентПриИзменении`
+const EVENT_HANDLER_SUFFIXES: &[&str] = &[
+    "приизменении",
+    "началовыбора",
+    "обработкавыбора",
+    "нажатие",
+    "выбор",
+    "очистка",
+    "автоподбор",
+    "окончаниевводатекста",
+    "приактивизациистроки",
+    "передначаломдобавления",
+    "передначаломизменения",
+    "передудалением",
+    "приокончанииредактирования",
+    "onchange",
+    "startchoice",
+    "choiceprocessing",
+    "click",
+    "selection",
+    "onactivaterow",
+    "beforeaddrow",
+    "beforerowchange",
+    "beforedeleterow",
+    "oneditend",
+];
+
+fn is_event_handler(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    EVENT_HANDLERS.contains(&lower.as_str())
+        || EVENT_HANDLER_SUFFIXES.iter().any(|s| lower.ends_with(s))
+}
+
+fn has_export_word(text: &str) -> bool {
+    lowercase_words(text)
+        .iter()
+        .any(|w| w == "экспорт" || w == "export")
+}
+
+/// Names the module itself declares: routines, parameters, `Перем` and loop variables.
+/// Names other code depends on are left out: `Экспорт` routines and variables, event
+/// handlers bound by name, and plain assignment targets, which may be attributes of
+/// the object or form rather than locals
+fn declared_names(lines: &[&str]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut kept: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut add = |name: &str| {
+        let name = name.trim();
+        if !name.is_empty()
+            && name.chars().all(is_identifier_char)
+            && !names
+                .iter()
+                .any(|n| n.to_lowercase() == name.to_lowercase())
+        {
+            names.push(name.to_string());
+        }
+    };
+    for line in lines {
+        if let Some(caps) = ROUTINE_DECLARATION.captures(line) {
+            let tail = caps.get(3).map(|m| m.as_str()).unwrap_or_default();
+            if has_export_word(tail) || is_event_handler(&caps[1]) {
+                kept.insert(caps[1].to_lowercase());
+            } else {
+                add(&caps[1]);
+            }
+            for param in caps
+                .get(2)
+                .map(|p| p.as_str())
+                .unwrap_or_default()
+                .split(',')
+            {
+                let mut parts = param.split(['=', ' ', '\t']).filter(|w| !w.is_empty());
+                let first = parts.next().unwrap_or_default();
+                let is_val = first.eq_ignore_ascii_case("val") || first.to_lowercase() == "знач";
+                add(if is_val {
+                    parts.next().unwrap_or_default()
+                } else {
+                    first
+                });
+            }
+        } else if let Some(caps) = VARIABLE_DECLARATION.captures(line) {
+            for var in caps[1].split(',') {
+                let name = var.split_whitespace().next().unwrap_or_default();
+                if has_export_word(var) {
+                    kept.insert(name.to_lowercase());
+                } else {
+                    add(name);
+                }
+            }
+        } else if let Some(caps) = LOOP_VARIABLE.captures(line) {
+            add(&caps[1]);
+        }
+    }
+    names.retain(|n| !kept.contains(&n.to_lowercase()));
+    names
+}
+
+/// Replaces identifiers found in `renames` (lowercase keys) outside strings, comments,
+/// directives and member access (`Объект.Имя` keeps `Имя`)
+fn rename_identifiers(code: &str, renames: &std::collections::HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut in_string = false;
+    for line in code.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if in_string && !trimmed.starts_with('|') {
+            in_string = false;
+        }
+        if !in_string && (trimmed.starts_with('#') || trimmed.starts_with('&')) {
+            result.push_str(line);
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if in_string {
+                result.push(c);
+                if c == '"' {
+                    if chars.get(i + 1) == Some(&'"') {
+                        result.push('"');
+                        i += 1;
+                    } else {
+                        in_string = false;
+                    }
+                }
+                i += 1;
+                continue;
+            }
+            if c == '"' {
+                in_string = true;
+            } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+                result.extend(&chars[i..]);
+                break;
+            } else if is_identifier_start(c) {
+                let start = i;
+                while i < chars.len() && is_identifier_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let member = start > 0 && chars[start - 1] == '.';
+                match renames.get(&word.to_lowercase()) {
+                    Some(new_name) if !member => result.push_str(new_name),
+                    _ => result.push_str(&word),
+                }
+                continue;
+            }
+            result.push(c);
+            i += 1;
+        }
+    }
+    result
+}
+
+/// Latin names for the Cyrillic identifiers the module declares; keywords, platform calls,
+/// metadata, strings and comments stay as written. A name that would clash with an existing
+/// one gets a numeric suffix
+pub fn transliterate_bsl_identifiers(code: &str) -> TransliterationReport {
+    let stripped = strip_non_code(code);
+    let lines: Vec<&str> = stripped.lines().collect();
+    let mut taken: std::collections::HashSet<String> =
+        lowercase_words(&stripped).into_iter().collect();
+    let mut renames = std::collections::HashMap::new();
+    let mut names_changed = Vec::new();
+    for name in declared_names(&lines) {
+        let lower = name.to_lowercase();
+        if BSL_KEYWORDS.contains(&lower.as_str())
+            || !name.chars().any(|c| ('\u{0400}'..='\u{04FF}').contains(&c))
+        {
+            continue;
+        }
+        let base = transliterate_identifier(&name);
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while taken.contains(&candidate.to_lowercase()) {
+            candidate = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        taken.insert(candidate.to_lowercase());
+        renames.insert(lower, candidate.clone());
+        names_changed.push((name, candidate));
+    }
+    TransliterationReport {
+        original: code.to_string(),
+        transliterated: rename_identifiers(code, &renames),
+        names_changed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_code_has_complexity_one() {
+        let code = "Процедура Тест()\n\tА = 1;\n\tСообщить(А);\nКонецПроцедуры\n";
+        assert_eq!(estimate_cyclomatic_complexity(code), 1);
+        assert_eq!(estimate_cyclomatic_complexity(""), 1);
+    }
+
+    #[test]
+    fn counts_branches_and_boolean_operators() {
+        // Если, И, ИначеЕсли, Для Каждого, Пока, ИЛИ -> 6 decision points
+        let code = r#"
+Функция Проверить(Товары, Лимит) Экспорт
+	Если Лимит > 0 И Товары.Количество() > 0 Тогда
+		Возврат Истина;
+	ИначеЕсли Лимит = 0 Тогда
+		Возврат Ложь;
+	КонецЕсли;
+	Для Каждого Товар Из Товары Цикл
+		Пока Товар.Остаток > 0 ИЛИ Товар.Резерв > 0 Цикл
+			Товар.Остаток = Товар.Остаток - 1;
+		КонецЦикла;
+	КонецЦикла;
+	Возврат Неопределено;
+КонецФункции
+"#;
+        assert_eq!(estimate_cyclomatic_complexity(code), 7);
+
+        // If, ElsIf, For, And -> 4 decision points
+        let code = "If A And B Then\nElsIf C Then\nEndIf;\nFor i = 1 To 10 Do\nEndDo;";
+        assert_eq!(estimate_cyclomatic_complexity(code), 5);
+    }
+
+    #[test]
+    fn ignores_comments_strings_and_directives() {
+        let code = r#"
+#Если Сервер Тогда
+&НаСервере
+Процедура Тест()
+	// Если здесь условие и цикл Для
+	Текст = "Если ""Пока"" или Для";
+	Запрос = "ВЫБРАТЬ
+	|	ВЫБОР КОГДА Т.Поле ИЛИ Т.Флаг ТОГДА 1 КОНЕЦ
+	|ИЗ Таблица КАК Т";
+	Если Текст <> "" Тогда // или нет
+	КонецЕсли;
+КонецПроцедуры
+#КонецЕсли
+"#;
+        assert_eq!(estimate_cyclomatic_complexity(code), 2);
+    }
+
+    #[test]
+    fn rating_boundaries() {
+        assert_eq!(complexity_rating(1), "Simple");
+        assert_eq!(complexity_rating(5), "Simple");
+        assert_eq!(complexity_rating(6), "Moderate");
+        assert_eq!(complexity_rating(10), "Moderate");
+        assert_eq!(complexity_rating(11), "Complex");
+        assert_eq!(complexity_rating(20), "Complex");
+        assert_eq!(complexity_rating(21), "Very Complex");
+
+        let report = analyze_complexity("Если А Тогда КонецЕсли;");
+        assert_eq!(report.score, 2);
+        assert_eq!(report.decision_points, 1);
+        assert_eq!(report.rating, "Simple");
+    }
+
+    #[test]
+    fn audit_flags_dangerous_patterns() {
+        let code = r#"
+Процедура Обработать(Код, Алгоритм, ИмяФайла) Экспорт
+	Запрос = Новый Запрос;
+	Запрос.Текст = "ВЫБРАТЬ Ссылка ИЗ Справочник.Контрагенты ГДЕ Код = """ + Код + """";
+	Результат = Запрос.Выполнить();
+	Выполнить(Алгоритм);
+	Выполнить("А = 1");
+	Обработка = ВнешниеОбработки.Создать(ИмяФайла);
+	Объект = Результат.Выгрузить()[0].Ссылка.ПолучитьОбъект();
+	Объект.Записать();
+	УправлениеПользователями.УстановитьРолиПользователя(Объект);
+КонецПроцедуры
+"#;
+        let findings = audit_bsl_security(code);
+        let found: Vec<(&str, u32)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.line))
+            .collect();
+        assert_eq!(findings[0].severity, AnnotationSeverity::Error);
+        assert_eq!(
+            found,
+            vec![
+                ("sql_concatenation", 4),
+                ("dynamic_execute", 6),
+                ("external_processing", 8),
+                ("missing_rights_check", 10),
+                ("user_management", 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_accepts_parameters_rights_checks_and_comments() {
+        let code = r#"
+Процедура Записать(Объект) Экспорт
+	// Выполнить(Алгоритм); Запрос.Текст = "ВЫБРАТЬ" + Код;
+	Запрос = Новый Запрос("ВЫБРАТЬ Ссылка ИЗ Справочник.Контрагенты ГДЕ Код = &Код");
+	Запрос.УстановитьПараметр("Код", Объект.Код);
+	Текст = "ВЫБРАТЬ 1" + "
+	|ИЗ Таблица";
+	ВыполнитьПроверкуПравДоступа("Изменение", Объект.Метаданные());
+	Объект.Записать();
+КонецПроцедуры
+"#;
+        assert!(audit_bsl_security(code).is_empty());
+    }
+
+    #[test]
+    fn detects_unused_routines_variables_and_unreachable_code() {
+        let code = r#"
+Процедура Основная() Экспорт
+	Итог = Посчитать(1);
+	Сообщить(Итог);
+	ОписаниеОповещения("ПослеВопроса", ЭтотОбъект);
+КонецПроцедуры
+
+Функция Посчитать(Знач Число)
+	Временная = Число * 2;
+	Лишняя = 0;
+	Лишняя = 1;
+	Если Число > 0 Тогда
+		Возврат Временная;
+		Сообщить("недостижимо");
+	КонецЕсли;
+	Возврат 0;
+	Число = 5;
+КонецФункции
+
+Процедура ПослеВопроса(Ответ, Параметры) Экспорт
+КонецПроцедуры
+
+Процедура НигдеНеВызывается()
+КонецПроцедуры
+
+Процедура ЭкспортнаяНоНеВызывается() Экспорт
+КонецПроцедуры
+"#;
+        let findings = detect_dead_code(code);
+        let found: Vec<(DeadCodeKind, &str, u32)> = findings
+            .iter()
+            .map(|f| (f.kind, f.name.as_str(), f.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DeadCodeKind::UnusedVariable, "Лишняя", 10),
+                (
+                    DeadCodeKind::UnreachableCode,
+                    "Сообщить(\"недостижимо\")",
+                    14
+                ),
+                (DeadCodeKind::UnreachableCode, "Число = 5", 17),
+                (DeadCodeKind::UnusedProcedure, "НигдеНеВызывается", 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_signatures_with_kind_and_export() {
+        let code = "&НаСервере\nФункция Сумма(А, Б) Экспорт // сложение\n\tВозврат А + Б;\nКонецФункции\n\n\
+                    Асинх Процедура Загрузить()\nКонецПроцедуры";
+        let signatures = extract_bsl_signatures(code);
+
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].name, "Сумма");
+        assert!(signatures[0].is_function && signatures[0].exported);
+        assert_eq!(
+            signatures[0].signature,
+            "Функция Сумма(А, Б) Экспорт // сложение"
+        );
+        assert_eq!(signatures[0].line, 2);
+        assert_eq!(signatures[1].name, "Загрузить");
+        assert!(!signatures[1].is_function && !signatures[1].exported);
+    }
+
+    #[test]
+    fn strips_and_inserts_docs_above_routines() {
+        let documented = "// Складывает числа.\n//\n// Параметры:\n//  А - Число\n//\n\
+                          &НаСервере\nФункция Сумма(А) Экспорт\n\t// внутренний комментарий\n\tВозврат А;\nКонецФункции\n\n\
+                          Процедура Пусто()\nКонецПроцедуры\n";
+        let bare = strip_bsl_docs(documented);
+        assert_eq!(
+            bare,
+            "&НаСервере\nФункция Сумма(А) Экспорт\n\t// внутренний комментарий\n\tВозврат А;\nКонецФункции\n\n\
+             Процедура Пусто()\nКонецПроцедуры\n"
+        );
+
+        let docs = extract_bsl_docs(documented);
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs["сумма"][2], "// Параметры:");
+        assert_eq!(insert_bsl_docs(&bare, &docs), documented);
+        // Already documented routines keep their comment
+        assert_eq!(insert_bsl_docs(documented, &docs), documented);
+    }
+
+    #[test]
+    fn extracts_metadata_references_in_both_languages() {
+        let code = "Процедура Тест()\n\
+                    \tСсылка = Справочники.Контрагенты.НайтиПоКоду(\"001\");\n\
+                    \tДок = Документы.ЗаказПокупателя.СоздатьДокумент();\n\
+                    \tНабор = РегистрыСведений . КурсыВалют.СоздатьНаборЗаписей();\n\
+                    \tВид = Перечисления.ВидыОплат;\n\
+                    \tItems = Catalogs.Products.Select();\n\
+                    \t// Справочники.Закомментирован.Найти()\n\
+                    \tТекст = \"Документы.ВСтроке\";\n\
+                    \tСсылка = Справочники.Контрагенты.НайтиПоКоду(\"002\");\n\
+                    КонецПроцедуры\n";
+        let refs = extract_metadata_references(code);
+        let found: Vec<(&str, &str, &str)> = refs
+            .iter()
+            .map(|r| (r.kind.as_str(), r.name.as_str(), r.access.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                ("Справочники", "Контрагенты", ".НайтиПоКоду"),
+                ("Документы", "ЗаказПокупателя", ".СоздатьДокумент"),
+                ("РегистрыСведений", "КурсыВалют", ".СоздатьНаборЗаписей"),
+                ("Перечисления", "ВидыОплат", ""),
+                ("Catalogs", "Products", ".Select"),
+            ]
+        );
+    }
+
+    #[test]
+    fn collection_name_inside_identifier_is_not_a_reference() {
+        assert!(extract_metadata_references("МоиДокументы.Список = 1;").is_empty());
+    }
+
+    #[test]
+    fn transliterates_identifiers_to_camel_case() {
+        assert_eq!(transliterate_identifier("КурсыВалют"), "KursyValyut");
+        assert_eq!(transliterate_identifier("счётчик"), "Schyotchik");
+        assert_eq!(transliterate_identifier("ОбъектЩит2"), "ObektShchit2");
+    }
+
+    #[test]
+    fn transliteration_keeps_keywords_calls_strings_and_members() {
+        let code = "Перем мСчетчик;\n\
+                    \n\
+                    &НаСервере\n\
+                    Функция ПолучитьСумму(Знач Документ, Коэффициент = 1)\n\
+                    \tПерем Сумма;\n\
+                    \tСумма = 0;\n\
+                    \tДля Каждого Строка Из Документ.Товары Цикл\n\
+                    \t\tСумма = Сумма + Строка.Сумма * Коэффициент; // Сумма строки\n\
+                    \tКонецЦикла;\n\
+                    \tЕсли Сумма = 0 Тогда\n\
+                    \t\tСообщить(\"Сумма не заполнена\");\n\
+                    \tКонецЕсли;\n\
+                    \tмСчетчик = мСчетчик + 1;\n\
+                    \tВозврат Сумма;\n\
+                    КонецФункции\n";
+        let report = transliterate_bsl_identifiers(code);
+
+        assert_eq!(
+            report.names_changed,
+            [
+                ("мСчетчик", "MSchetchik"),
+                ("ПолучитьСумму", "PoluchitSummu"),
+                ("Документ", "Dokument"),
+                ("Коэффициент", "Koeffitsient"),
+                ("Сумма", "Summa"),
+                ("Строка", "Stroka"),
+            ]
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+        );
+        let expected = "Перем MSchetchik;\n\
+                        \n\
+                        &НаСервере\n\
+                        Функция PoluchitSummu(Знач Dokument, Koeffitsient = 1)\n\
+                        \tПерем Summa;\n\
+                        \tSumma = 0;\n\
+                        \tДля Каждого Stroka Из Dokument.Товары Цикл\n\
+                        \t\tSumma = Summa + Stroka.Сумма * Koeffitsient; // Сумма строки\n\
+                        \tКонецЦикла;\n\
+                        \tЕсли Summa = 0 Тогда\n\
+                        \t\tСообщить(\"Сумма не заполнена\");\n\
+                        \tКонецЕсли;\n\
+                        \tMSchetchik = MSchetchik + 1;\n\
+                        \tВозврат Summa;\n\
+                        КонецФункции\n";
+        assert_eq!(report.transliterated, expected);
+
+        // Every keyword survives, and a second pass has nothing left to rename
+        let keywords = |text: &str| {
+            lowercase_words(&strip_non_code(text))
+                .into_iter()
+                .filter(|w| BSL_KEYWORDS.contains(&w.as_str()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keywords(&report.transliterated), keywords(code));
+        let again = transliterate_bsl_identifiers(&report.transliterated);
+        assert!(again.names_changed.is_empty());
+        assert_eq!(again.transliterated, report.transliterated);
+    }
+
+    #[test]
+    fn transliteration_avoids_name_clashes() {
+        let code = "Перем Summa, Сумма;\nSumma = 1;\nСумма = 2;\nСообщить(Summa + Сумма);\n";
+        let report = transliterate_bsl_identifiers(code);
+        assert_eq!(
+            report.names_changed,
+            [("Сумма".to_string(), "Summa2".to_string())]
+        );
+        assert!(report.transliterated.contains("Сообщить(Summa + Summa2)"));
+    }
+
+    #[test]
+    fn transliteration_keeps_exported_names() {
+        let code = "Перем мКэш Экспорт;\n\
+                    Функция ЦенаТовара(Товар) Экспорт\n\
+                    \tВозврат мКэш[Товар];\n\
+                    КонецФункции\n";
+        let report = transliterate_bsl_identifiers(code);
+        assert_eq!(
+            report.names_changed,
+            [("Товар".to_string(), "Tovar".to_string())]
+        );
+        assert!(report
+            .transliterated
+            .contains("Функция ЦенаТовара(Tovar) Экспорт"));
+        assert!(report.transliterated.contains("мКэш[Tovar]"));
+    }
+
+    #[test]
+    fn transliteration_keeps_event_handlers() {
+        let code = "Процедура ОбработкаПроведения(Отказ, РежимПроведения)\n\
+                    КонецПроцедуры\n\
+                    &НаКлиенте\n\
+                    Процедура ПриОткрытии(Отказ)\n\
+                    КонецПроцедуры\n\
+                    &НаКлиенте\n\
+                    Процедура КонтрагентПриИзменении(Элемент)\n\
+                    КонецПроцедуры\n";
+        let report = transliterate_bsl_identifiers(code);
+        let renamed: Vec<&str> = report
+            .names_changed
+            .iter()
+            .map(|(old, _)| old.as_str())
+            .collect();
+        assert_eq!(renamed, ["Отказ", "РежимПроведения", "Элемент"]);
+        for handler in [
+            "ОбработкаПроведения",
+            "ПриОткрытии",
+            "КонтрагентПриИзменении",
+        ] {
+            assert!(report.transliterated.contains(handler), "{}", handler);
+        }
+    }
+
+    #[test]
+    fn transliteration_keeps_undeclared_assignment_targets() {
+        // In an object or form module these are attributes, not locals
+        let code = "Процедура Заполнить()\n\
+                    \tКомментарий = \"Создан автоматически\";\n\
+                    \tДатаОтгрузки = ТекущаяДата();\n\
+                    КонецПроцедуры\n";
+        let report = transliterate_bsl_identifiers(code);
+        assert_eq!(
+            report.names_changed,
+            [("Заполнить".to_string(), "Zapolnit".to_string())]
+        );
+        assert!(report.transliterated.contains("\tКомментарий = "));
+        assert!(report.transliterated.contains("\tДатаОтгрузки = "));
+    }
+}
@@ -0,0 +1,292 @@
+//! Local SQLite database (`chat.db` in the settings directory).
+//!
+//! Keeps the full history of AI code applied to files; the in-memory
+//! `history_manager` records only cover the last few applies.
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+
+use crate::history_manager::ApplyRecord;
+use crate::settings::get_settings_dir;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApplyHistoryRow {
+    pub id: String,
+    pub file_path: String,
+    pub original_content: String,
+    pub applied_content: String,
+    pub message_id: String,
+    /// Unix time in milliseconds
+    pub applied_at: i64,
+    /// Undone by `rollback_to` of this or an earlier row
+    pub rolled_back: bool,
+}
+
+pub struct ChatStore {
+    conn: Connection,
+}
+
+impl ChatStore {
+    pub fn open(path: &std::path::Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Не удалось открыть базу {}: {}", path.display(), e))?;
+        Self::init(conn)
+    }
+
+    /// Opens an existing database without creating the file or its tables
+    pub fn open_read_only(path: &std::path::Path) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Не удалось открыть базу {}: {}", path.display(), e))?;
+        Ok(Self { conn })
+    }
+
+    #[cfg(test)]
+    fn open_in_memory() -> Result<Self, String> {
+        Self::init(Connection::open_in_memory().map_err(|e| e.to_string())?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS apply_history (
+                id TEXT PRIMARY KEY,
+                file_path TEXT NOT NULL,
+                original_content TEXT NOT NULL,
+                applied_content TEXT NOT NULL,
+                message_id TEXT NOT NULL,
+                applied_at INTEGER NOT NULL,
+                rolled_back INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS apply_history_file ON apply_history(file_path);",
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Stores an apply and returns the id of its row
+    pub fn record_apply(&self, record: &ApplyRecord) -> Result<String, String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO apply_history
+                    (id, file_path, original_content, applied_content, message_id, applied_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    id,
+                    record.file_path,
+                    record.original_content,
+                    record.applied_content,
+                    record.message_id,
+                    record.timestamp_ms as i64
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(id)
+    }
+
+    /// Applies to the file, newest first
+    pub fn apply_history(
+        &self,
+        file_path: &str,
+        limit: u32,
+    ) -> Result<Vec<ApplyHistoryRow>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, file_path, original_content, applied_content, message_id, applied_at, rolled_back
+                 FROM apply_history WHERE file_path = ?1 ORDER BY rowid DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![file_path, limit], row_to_history)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Writes the content the row replaced back to disk and marks the row and
+    /// every later apply to the same file as rolled back. Returns the file path.
+    pub fn rollback_to(&self, apply_id: &str) -> Result<String, String> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT rowid, file_path, original_content FROM apply_history WHERE id = ?1",
+                params![apply_id],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let (rowid, file_path, original_content) =
+            found.ok_or_else(|| format!("Запись истории не найдена: {}", apply_id))?;
+
+        std::fs::write(&file_path, original_content.as_bytes())
+            .map_err(|e| format!("Не удалось восстановить файл {}: {}", file_path, e))?;
+        self.conn
+            .execute(
+                "UPDATE apply_history SET rolled_back = 1 WHERE file_path = ?1 AND rowid >= ?2",
+                params![file_path, rowid],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(file_path)
+    }
+
+    /// Every apply, oldest first
+    pub fn all_apply_history(&self) -> Result<Vec<ApplyHistoryRow>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, file_path, original_content, applied_content, message_id, applied_at, rolled_back
+                 FROM apply_history ORDER BY rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], row_to_history)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Distinct files that AI code was ever applied to
+    pub fn modified_files(&self) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT file_path FROM apply_history ORDER BY file_path")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+}
+
+fn row_to_history(row: &rusqlite::Row) -> rusqlite::Result<ApplyHistoryRow> {
+    Ok(ApplyHistoryRow {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        original_content: row.get(2)?,
+        applied_content: row.get(3)?,
+        message_id: row.get(4)?,
+        applied_at: row.get(5)?,
+        rolled_back: row.get::<_, i64>(6)? != 0,
+    })
+}
+
+lazy_static::lazy_static! {
+    static ref STORE: std::sync::Mutex<Option<ChatStore>> = std::sync::Mutex::new(None);
+}
+
+fn get_db_file() -> std::path::PathBuf {
+    get_settings_dir().join("chat.db")
+}
+
+/// Full apply history, oldest first, read without creating `chat.db` or its tables;
+/// empty when the database or the table does not exist yet
+pub fn read_apply_history() -> Result<Vec<ApplyHistoryRow>, String> {
+    let path = get_db_file();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let store = ChatStore::open_read_only(&path)?;
+    let has_table = store
+        .conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'apply_history'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if !has_table {
+        return Ok(Vec::new());
+    }
+    store.all_apply_history()
+}
+
+/// Closes the shared connection so `chat.db` can be removed; the next `with_store` reopens it
+pub fn close_store() {
+    *STORE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Runs `f` with the shared store, opening `chat.db` on first use
+pub fn with_store<T>(f: impl FnOnce(&ChatStore) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = STORE.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        let dir = get_settings_dir();
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        *guard = Some(ChatStore::open(&get_db_file())?);
+    }
+    f(guard.as_ref().expect("store opened above"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(file_path: &str, original: &str, applied: &str) -> ApplyRecord {
+        ApplyRecord {
+            file_path: file_path.to_string(),
+            original_content: original.to_string(),
+            applied_content: applied.to_string(),
+            timestamp_ms: 1_700_000_000_000,
+            message_id: "m1".to_string(),
+        }
+    }
+
+    #[test]
+    fn history_is_per_file_newest_first() {
+        let store = ChatStore::open_in_memory().unwrap();
+        store.record_apply(&record("A.bsl", "v0", "v1")).unwrap();
+        store.record_apply(&record("B.bsl", "b0", "b1")).unwrap();
+        store.record_apply(&record("A.bsl", "v1", "v2")).unwrap();
+
+        let history = store.apply_history("A.bsl", 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].applied_content, "v2");
+        assert_eq!(history[1].original_content, "v0");
+        assert!(!history[0].rolled_back);
+        assert_eq!(store.apply_history("A.bsl", 1).unwrap().len(), 1);
+        assert_eq!(store.modified_files().unwrap(), vec!["A.bsl", "B.bsl"]);
+
+        let all: Vec<String> = store
+            .all_apply_history()
+            .unwrap()
+            .into_iter()
+            .map(|row| row.applied_content)
+            .collect();
+        assert_eq!(all, vec!["v1", "b1", "v2"]);
+    }
+
+    #[test]
+    fn rollback_to_restores_content_and_marks_later_rows() {
+        let dir = std::env::temp_dir().join(format!("chat-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Module.bsl");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, "v3").unwrap();
+
+        let store = ChatStore::open_in_memory().unwrap();
+        store.record_apply(&record(path_str, "v0", "v1")).unwrap();
+        let second = store.record_apply(&record(path_str, "v1", "v2")).unwrap();
+        store.record_apply(&record(path_str, "v2", "v3")).unwrap();
+        store
+            .record_apply(&record("Other.bsl", "o0", "o1"))
+            .unwrap();
+
+        assert_eq!(store.rollback_to(&second).unwrap(), path_str);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v1");
+        let flags: Vec<bool> = store
+            .apply_history(path_str, 10)
+            .unwrap()
+            .iter()
+            .map(|row| row.rolled_back)
+            .collect();
+        assert_eq!(flags, vec![true, true, false]);
+        assert!(!store.apply_history("Other.bsl", 10).unwrap()[0].rolled_back);
+        assert!(store.rollback_to("missing").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,11 @@
-use crate::ai::{extract_bsl_code, stream_chat_completion, ApiMessage};
+use crate::ai::attachments::{FileAttachment, ImageAttachment};
+use crate::ai::error::AiClientError;
+use crate::ai::git_diff::GitDiffAttachment;
+use crate::ai::tokenizer::{estimate_message_tokens, estimate_text_tokens, TOKENS_PER_MESSAGE};
+use crate::ai::{
+    extract_bsl_code, stream_chat_completion, ApiMessage, FileContext, MessageContent,
+    RequestOverrides, TaskType,
+};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
 use tauri::{AppHandle, Emitter, Manager};
@@ -28,6 +35,9 @@ pub struct ChatMessage {
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Stable context (e.g. API reference) sent first and marked for prompt caching
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// State for managing active chat task
@@ -37,6 +47,46 @@ pub struct ChatState {
     pub approval_tx: tokio::sync::Mutex<Option<tokio::sync::mpsc::Sender<bool>>>,
     /// Channel for injecting user messages mid-loop (interrupt)
     pub interrupt_tx: tokio::sync::Mutex<Option<tokio::sync::mpsc::UnboundedSender<String>>>,
+    /// Label of the window that started the active chat task
+    pub window_label: tokio::sync::Mutex<Option<String>>,
+    /// Images (data URLs) attached to the next user message
+    pub pending_images: tokio::sync::Mutex<Vec<String>>,
+    /// Source files appended to the next user message
+    pub pending_files: tokio::sync::Mutex<Vec<FileAttachment>>,
+}
+
+impl ChatState {
+    /// Abort the active chat task and unblock any pending tool approval.
+    /// Returns true if a running task was cancelled.
+    pub async fn cancel_active(&self) -> bool {
+        {
+            let mut tx_guard = self.approval_tx.lock().await;
+            if let Some(tx) = tx_guard.take() {
+                // Send reject to unblock any pending rx.recv() in the streaming loop
+                let _ = tx.send(false).await;
+            }
+        }
+        *self.window_label.lock().await = None;
+        let mut handle_guard = self.abort_handle.lock().await;
+        match handle_guard.take() {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel the active chat task if it was started from the given window.
+    /// Called when the window is destroyed so the stream does not outlive its UI.
+    pub async fn cancel_for_window(&self, label: &str) -> bool {
+        let owned = self.window_label.lock().await.as_deref() == Some(label);
+        if !owned {
+            return false;
+        }
+        crate::app_log!("[AI] Window '{}' destroyed, cancelling active chat", label);
+        self.cancel_active().await
+    }
 }
 
 use super::bsl::BSLDiagnostic;
@@ -123,11 +173,11 @@ fn sanitize_codex_tool_name(name: &str) -> String {
 }
 
 /// Estimates token count for a slice of messages (chars / 4 approximation).
-fn estimate_tokens(messages: &[ApiMessage]) -> usize {
+fn estimate_context_tokens(messages: &[ApiMessage]) -> usize {
     messages
         .iter()
         .map(|m| {
-            let content_len = m.content.as_deref().map(|c| c.len()).unwrap_or(0);
+            let content_len = m.text().map(|c| c.len()).unwrap_or(0);
             let tc_len = m
                 .tool_calls
                 .as_ref()
@@ -142,6 +192,164 @@ fn estimate_tokens(messages: &[ApiMessage]) -> usize {
         .sum()
 }
 
+/// Token estimate for a request, returned by the `estimate_tokens` command.
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenEstimate {
+    pub prompt_tokens: u32,
+    pub with_system_prompt: u32,
+    pub remaining_in_context: Option<u32>,
+}
+
+/// Token ids of the given words for the active profile's model, to fill `logit_bias`
+#[tauri::command]
+pub fn tokenize_words(words: Vec<String>) -> Result<Vec<crate::ai::tokenizer::WordTokens>, String> {
+    let model = crate::llm_profiles::get_active_profile()
+        .map(|p| p.model)
+        .unwrap_or_default();
+    crate::ai::tokenizer::tokenize_words(&words, &model)
+}
+
+/// Check a system prompt template for unknown `{{variable}}` placeholders
+#[tauri::command]
+pub fn validate_prompt_template(
+    template: String,
+    context: Option<crate::ai::prompt_template::PromptContext>,
+) -> Vec<String> {
+    crate::ai::prompt_template::validate_template(&template, &context.unwrap_or_default())
+}
+
+/// Fill `{{variable}}` placeholders of a user prompt; unset ones without a default are an error
+#[tauri::command]
+pub fn render_template(
+    template: String,
+    variables: std::collections::HashMap<String, String>,
+) -> Result<String, String> {
+    crate::ai::prompt_template::render_prompt_template(&template, &variables)
+        .map_err(|e| e.to_string())
+}
+
+/// Render a saved prompt template by id, ready to send as the chat message
+#[tauri::command]
+pub fn render_saved_template(
+    template_id: String,
+    variables: std::collections::HashMap<String, String>,
+) -> Result<String, String> {
+    let settings = crate::settings::load_settings();
+    let template = settings
+        .custom_prompts
+        .templates
+        .iter()
+        .find(|t| t.id == template_id)
+        .ok_or_else(|| format!("Шаблон не найден: {}", template_id))?;
+    crate::ai::prompt_template::render_prompt_template(&template.content, &variables)
+        .map_err(|e| e.to_string())
+}
+
+/// Quick question outside the conversation: answered with the system prompt only,
+/// without chat events and not added to the chat history.
+#[tauri::command]
+pub async fn one_shot(prompt: String) -> Result<String, AiClientError> {
+    crate::ai::client::one_shot_completion(prompt).await
+}
+
+/// Ask two profiles the same conversation at once and diff their answers
+#[tauri::command]
+pub async fn compare_completions(
+    messages: Vec<ApiMessage>,
+    profile_id_a: String,
+    profile_id_b: String,
+) -> Result<crate::ai::comparison::ModelComparison, AiClientError> {
+    let store = crate::llm_profiles::load_profiles();
+    let find = |id: &str| {
+        store
+            .profiles
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| AiClientError::invalid_profile(format!("Профиль не найден: {}", id)))
+    };
+    let (profile_a, profile_b) = (find(&profile_id_a)?, find(&profile_id_b)?);
+    crate::ai::comparison::compare_completions(messages, profile_a, profile_b).await
+}
+
+/// Chat completion reduced to the code blocks of the answer (for applying suggestions to files)
+#[tauri::command]
+pub async fn extract_code_from_completion(
+    messages: Vec<ApiMessage>,
+    code_only_prompt: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<crate::ai::code_blocks::CodeBlock>, AiClientError> {
+    crate::ai::code_blocks::code_only_completion(
+        messages,
+        code_only_prompt.unwrap_or(true),
+        app_handle,
+    )
+    .await
+}
+
+/// Explain a 1С runtime error (`{Модуль(строка)}: текст`), optionally with the module code
+#[tauri::command]
+pub async fn explain_error(
+    error_str: String,
+    context_code: Option<String>,
+) -> Result<String, AiClientError> {
+    crate::ai::runtime_errors::explain_1c_error(error_str, context_code).await
+}
+
+/// Root cause of a 1С error from its stack trace (`{Модуль(строка)}: текст` per frame)
+#[tauri::command]
+pub async fn analyse_stack_trace(trace: String) -> Result<String, AiClientError> {
+    crate::ai::runtime_errors::analyse_stack_trace(trace).await
+}
+
+/// Parsed location of a 1С runtime error, `None` when the text has no `{Модуль(N)}`
+#[tauri::command]
+pub fn parse_error(error_str: String) -> Option<crate::ai::runtime_errors::ParsedError> {
+    crate::ai::runtime_errors::parse_1c_error(&error_str)
+}
+
+/// Most recent `X-Request-ID` sent with the active profile, for support tickets
+#[tauri::command]
+pub fn get_last_request_id() -> Option<String> {
+    let profile = crate::llm_profiles::get_active_profile()?;
+    crate::ai::client::last_request_id(&profile.id)
+}
+
+/// Estimate how many tokens the request will consume before sending it.
+/// The system prompt is built without MCP tool descriptions, so `with_system_prompt`
+/// is a lower bound when MCP servers are enabled.
+#[tauri::command]
+pub fn estimate_tokens(messages: Vec<ApiMessage>) -> TokenEstimate {
+    let prompt_tokens: u32 = messages.iter().map(estimate_message_tokens).sum();
+
+    let profile = crate::llm_profiles::get_active_profile();
+    let system_prompt = match &profile {
+        Some(p) => {
+            crate::ai::prompts::resolve_system_prompt(None, p, &Default::default(), &[], &messages)
+                .0
+        }
+        None => crate::ai::prompts::get_system_prompt(
+            &[],
+            &messages,
+            crate::ai::prompts::answer_language(None),
+            Default::default(),
+        ),
+    };
+    let with_system_prompt =
+        prompt_tokens + TOKENS_PER_MESSAGE + estimate_text_tokens(&system_prompt);
+
+    let remaining_in_context = profile.map(|p| {
+        crate::ai::capabilities::get_model_capabilities(&p)
+            .context_window
+            .saturating_sub(with_system_prompt)
+    });
+
+    TokenEstimate {
+        prompt_tokens,
+        with_system_prompt,
+        remaining_in_context,
+    }
+}
+
 /// Payload emitted as `context-usage` Tauri event to update the UI indicator.
 #[derive(Serialize, Clone)]
 struct ContextUsagePayload {
@@ -153,7 +361,7 @@ struct ContextUsagePayload {
 
 /// Emits `context-usage` event with current token estimate and fill percentage.
 fn emit_context_usage(app: &AppHandle, messages: &[ApiMessage], context_window: usize) {
-    let tokens = estimate_tokens(messages);
+    let tokens = estimate_context_tokens(messages);
     let percent = if context_window > 0 {
         (tokens as f32 / context_window as f32 * 100.0).min(100.0)
     } else {
@@ -183,7 +391,7 @@ fn emit_context_usage(app: &AppHandle, messages: &[ApiMessage], context_window:
 /// Rounds are removed oldest-first. The most recent round is always preserved.
 /// User messages and system messages are never removed.
 fn prune_tool_context(messages: &mut Vec<ApiMessage>, max_tokens: usize) {
-    if estimate_tokens(messages) <= max_tokens {
+    if estimate_context_tokens(messages) <= max_tokens {
         return;
     }
 
@@ -226,14 +434,19 @@ fn prune_tool_context(messages: &mut Vec<ApiMessage>, max_tokens: usize) {
         let count = actual_end - actual_start + 1;
         messages.drain(actual_start..=actual_end);
         removed_total += count;
+        tracing::warn!(
+            removed = removed_total,
+            max_tokens,
+            "context truncated: pruned tool round"
+        );
         crate::app_log!(
             "[AI][PRUNE] Removed tool round (was [{},{}]), {} msgs pruned total. Tokens now ~{}t",
             start,
             end,
             removed_total,
-            estimate_tokens(messages)
+            estimate_context_tokens(messages)
         );
-        if estimate_tokens(messages) <= max_tokens {
+        if estimate_context_tokens(messages) <= max_tokens {
             break;
         }
     }
@@ -241,10 +454,7 @@ fn prune_tool_context(messages: &mut Vec<ApiMessage>, max_tokens: usize) {
 
 /// Clear 1С:Напарник session (called on chat clear when provider == OneCNaparnik)
 fn assistant_message_has_meaningful_payload(message: &ApiMessage) -> bool {
-    message
-        .content
-        .as_deref()
-        .is_some_and(|content| !content.is_empty())
+    message.text().is_some_and(|content| !content.is_empty())
         || message
             .tool_calls
             .as_ref()
@@ -270,21 +480,176 @@ pub async fn stop_chat(
     state: tauri::State<'_, ChatState>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
-    // Release the approval channel first to unblock approve_tool waiters
-    {
-        let mut tx_guard = state.approval_tx.lock().await;
-        if let Some(tx) = tx_guard.take() {
-            // Send reject to unblock any pending rx.recv() in the streaming loop
-            let _ = tx.send(false).await;
-        }
+    // Release the approval channel first to unblock approve_tool waiters, then abort
+    state.cancel_active().await;
+    // Always end the turn so the frontend isLoading state is reset
+    let _ = app_handle.emit("chat-status", "");
+    let _ = app_handle.emit("chat-turn-done", ());
+    Ok(())
+}
+
+/// Attach an image to the next chat message: from a file, or from the clipboard when no path is given
+#[tauri::command]
+pub async fn attach_image(
+    path: Option<String>,
+    state: tauri::State<'_, ChatState>,
+) -> Result<ImageAttachment, String> {
+    let attachment = tokio::task::spawn_blocking(move || match path {
+        Some(path) => crate::ai::attachments::load_image_file(std::path::Path::new(&path)),
+        None => crate::ai::attachments::load_clipboard_image(),
+    })
+    .await
+    .map_err(|e| format!("Task panic: {}", e))??;
+
+    crate::app_log!(
+        "[AI] Image attached: {}x{}, {} bytes",
+        attachment.width,
+        attachment.height,
+        attachment.data_url.len()
+    );
+    state
+        .pending_images
+        .lock()
+        .await
+        .push(attachment.data_url.clone());
+    Ok(attachment)
+}
+
+/// Drop images attached to the next message
+#[tauri::command]
+pub async fn clear_image_attachments(state: tauri::State<'_, ChatState>) -> Result<(), String> {
+    state.pending_images.lock().await.clear();
+    Ok(())
+}
+
+/// Reads files within the attachment limits, counting `attached_bytes` already in use.
+fn read_source_files(paths: &[String], attached_bytes: u64) -> Result<Vec<FileAttachment>, String> {
+    let limits = crate::settings::load_settings().attachments;
+    let max_file_bytes = limits.max_file_kb as u64 * 1024;
+
+    let mut total_bytes = attached_bytes;
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let file = crate::ai::attachments::load_source_file(
+            std::path::Path::new(path),
+            max_file_bytes,
+            total_bytes,
+            limits.max_attachment_bytes,
+        )?;
+        total_bytes += file.bytes();
+        files.push(file);
     }
-    let mut handle_guard = state.abort_handle.lock().await;
-    if let Some(handle) = handle_guard.take() {
-        handle.abort();
+    Ok(files)
+}
+
+/// Read source files (UTF-8/windows-1251) to pass as `attachments` of `stream_chat`
+#[tauri::command]
+pub async fn read_and_attach_files(paths: Vec<String>) -> Result<Vec<FileAttachment>, String> {
+    tokio::task::spawn_blocking(move || read_source_files(&paths, 0))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// `git diff` of the repository (staged or working tree), truncated to 32 KB,
+/// to pass as `git_diff` of `stream_chat`
+#[tauri::command]
+pub async fn attach_git_diff(repo_path: String, staged: bool) -> Result<String, String> {
+    use crate::ai::git_diff::{get_git_diff, git_diff};
+    let diff = if staged {
+        git_diff(&repo_path, true).await?
+    } else {
+        get_git_diff(&repo_path).await?
+    };
+    if diff.trim().is_empty() {
+        return Err(if staged {
+            "Нет проиндексированных изменений".to_string()
+        } else {
+            "Нет изменений в рабочем дереве".to_string()
+        });
     }
-    // Always emit chat-done so the frontend isLoading state is reset
-    let _ = app_handle.emit("chat-status", "");
-    let _ = app_handle.emit("chat-done", ());
+    Ok(diff)
+}
+
+/// Text passed through a pipeline of transforms, as `stream_chat` does with `transforms`
+#[tauri::command]
+pub fn apply_response_transforms(
+    text: String,
+    transforms: Vec<crate::ai::transforms::TransformSpec>,
+) -> String {
+    crate::ai::transforms::TransformPipeline::from_specs(&transforms).apply(&text)
+}
+
+/// Commit message (imperative subject up to 72 characters, optional bullets) for a diff
+#[tauri::command]
+pub async fn suggest_commit_message(
+    diff: String,
+) -> Result<crate::ai::commit_message::CommitMessageSuggestion, AiClientError> {
+    crate::ai::commit_message::suggest_commit_message(diff).await
+}
+
+/// Attach source files (UTF-8/windows-1251) to the next chat message as fenced blocks.
+/// All or nothing: when one file fails or the batch exceeds the limit, none is attached.
+/// Emits `attachment-added` per file so the UI can warn about size before sending.
+#[tauri::command]
+pub async fn attach_files(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+    state: tauri::State<'_, ChatState>,
+) -> Result<Vec<FileAttachment>, String> {
+    let attached_bytes = pending_files_bytes(&state.pending_files.lock().await);
+    // Read without holding the lock, then check again against what is attached by now
+    let added = tokio::task::spawn_blocking(move || read_source_files(&paths, attached_bytes))
+        .await
+        .map_err(|e| e.to_string())??;
+    let added_bytes: u64 = added.iter().map(FileAttachment::bytes).sum();
+
+    let mut pending = state.pending_files.lock().await;
+    let mut total_bytes = pending_files_bytes(&pending);
+    let max_total_bytes = crate::settings::load_settings()
+        .attachments
+        .max_attachment_bytes;
+    if total_bytes + added_bytes > max_total_bytes {
+        return Err(format!(
+            "Файлы не помещаются в лимит вложений: {} КБ уже прикреплено, лимит {} КБ",
+            total_bytes.div_ceil(1024),
+            max_total_bytes / 1024
+        ));
+    }
+    pending.extend(added.iter().cloned());
+    drop(pending);
+
+    for file in &added {
+        total_bytes += file.bytes();
+        let estimated_tokens =
+            estimate_text_tokens(&crate::ai::attachments::format_file_block(file));
+        crate::app_log!(
+            "[AI] File attached: {} ({} bytes, ~{} tokens)",
+            file.name(),
+            file.bytes(),
+            estimated_tokens
+        );
+        let _ = app_handle.emit(
+            "attachment-added",
+            serde_json::json!({
+                "name": file.name(),
+                "path": file.path,
+                "bytes": file.bytes(),
+                "estimated_tokens": estimated_tokens,
+                "total_bytes": total_bytes,
+            }),
+        );
+    }
+    Ok(added)
+}
+
+fn pending_files_bytes(files: &[FileAttachment]) -> u64 {
+    files.iter().map(FileAttachment::bytes).sum()
+}
+
+/// Drop files attached to the next message
+#[tauri::command]
+pub async fn clear_file_attachments(state: tauri::State<'_, ChatState>) -> Result<(), String> {
+    state.pending_files.lock().await.clear();
     Ok(())
 }
 
@@ -332,10 +697,20 @@ pub async fn interrupt_chat(
 #[tauri::command]
 pub async fn stream_chat(
     messages: Vec<ChatMessage>,
+    overrides: Option<RequestOverrides>,
+    task_type: Option<TaskType>,
+    file_context: Option<FileContext>,
+    attachments: Option<Vec<FileAttachment>>,
+    git_diff: Option<GitDiffAttachment>,
+    preset: Option<String>,
+    profile_id: Option<String>,
     app_handle: AppHandle,
+    window: tauri::Window,
     _state: tauri::State<'_, Arc<tokio::sync::Mutex<crate::bsl_client::BSLClient>>>,
     chat_state: tauri::State<'_, ChatState>,
-) -> Result<(), String> {
+) -> Result<(), AiClientError> {
+    *chat_state.window_label.lock().await = Some(window.label().to_string());
+
     // Create channel for tool approval
     let (tx, mut rx) = tokio::sync::mpsc::channel::<bool>(1);
     {
@@ -350,9 +725,15 @@ pub async fn stream_chat(
         *guard = Some(interrupt_tx);
     }
 
+    // Pinned context goes first so the prompt prefix stays identical between requests
+    let (pinned, rest): (Vec<ChatMessage>, Vec<ChatMessage>) = messages
+        .into_iter()
+        .partition(|m| m.pinned && m.tool_calls.is_none() && m.tool_call_id.is_none());
+
     // Convert to API messages
-    let mut api_messages: Vec<ApiMessage> = messages
+    let mut api_messages: Vec<ApiMessage> = pinned
         .into_iter()
+        .chain(rest)
         .map(|m| {
             // Convert frontend tool_calls to backend ToolCall format
             let tool_calls = m.tool_calls.map(|tcs| {
@@ -368,13 +749,18 @@ pub async fn stream_chat(
                     .collect::<Vec<_>>()
             });
 
+            let mut content: MessageContent = m.content.into();
+            if m.pinned {
+                content.mark_cacheable();
+            }
+
             ApiMessage {
                 role: m.role,
-                content: if m.content.is_empty() && tool_calls.is_some() {
+                content: if content.as_text() == Some("") && tool_calls.is_some() {
                     // assistant message with tool_calls may have empty content (valid)
                     None
                 } else {
-                    Some(m.content)
+                    Some(content)
                 },
                 tool_calls,
                 tool_call_id: m.tool_call_id,
@@ -384,10 +770,58 @@ pub async fn stream_chat(
         .collect();
 
     // Resolve effective context window for UI indicator (override → profile default → 128k fallback)
-    let effective_context_window = crate::llm_profiles::get_active_profile()
+    // An explicit profile must exist; without one the active profile is used as before
+    let request_profile = match profile_id.as_deref() {
+        Some(id) => Some(
+            crate::llm_profiles::resolve_profile(Some(id))
+                .map_err(AiClientError::invalid_profile)?,
+        ),
+        None => crate::llm_profiles::get_active_profile(),
+    };
+    let effective_context_window = request_profile
         .and_then(|p| p.context_window_override)
         .unwrap_or(128_000) as usize;
 
+    let mut overrides = overrides.unwrap_or_default();
+    overrides.profile_id = profile_id;
+    if let Some(name) = preset {
+        crate::generation_presets::find_preset(&name)
+            .ok_or_else(|| format!("Пресет не найден: {}", name))?
+            .apply_to(&mut overrides);
+    }
+    let task_type = task_type.unwrap_or_default();
+
+    // Pending files and images are taken only once every check passed, so a rejected
+    // request (too large, text-only model) keeps them attached for a retry.
+    // Pending and explicitly passed files go into one "relevant files" message.
+    let (files, images) = {
+        let mut pending_files = chat_state.pending_files.lock().await;
+        let mut pending_images = chat_state.pending_images.lock().await;
+        let mut files = pending_files.clone();
+        files.extend(attachments.unwrap_or_default());
+        crate::ai::attachments::check_attachments_size(
+            &files,
+            crate::settings::load_settings()
+                .attachments
+                .max_attachment_bytes,
+        )?;
+        if !pending_images.is_empty() {
+            let profile = crate::llm_profiles::resolve_profile(overrides.profile_id.as_deref())
+                .and_then(|p| overrides.apply_to(&p))
+                .map_err(AiClientError::invalid_profile)?;
+            crate::ai::capabilities::ensure_vision_supported(&profile)
+                .map_err(AiClientError::invalid_profile)?;
+        }
+        pending_files.clear();
+        (files, std::mem::take(&mut *pending_images))
+    };
+    if !images.is_empty() {
+        if let Some(last_user) = api_messages.iter_mut().rev().find(|m| m.role == "user") {
+            let text = last_user.text().unwrap_or_default().to_string();
+            last_user.content = Some(MessageContent::with_images(text, images));
+        }
+    }
+
     // Spawn the work into a cancellable task
     let task_app_handle = app_handle.clone();
 
@@ -419,11 +853,19 @@ pub async fn stream_chat(
             emit_context_usage(&task_app_handle, &api_messages, effective_context_window);
 
             // Stream chat completion
-            let response_msg =
-                stream_chat_completion(api_messages.clone(), task_app_handle.clone()).await;
+            let response_msg = stream_chat_completion(
+                api_messages.clone(),
+                &overrides,
+                task_type,
+                file_context.as_ref(),
+                &files,
+                git_diff.as_ref(),
+                task_app_handle.clone(),
+            )
+            .await;
 
             let assistant_msg = match response_msg {
-                Ok(m) => m,
+                Ok(completion) => completion.message,
                 Err(e) => {
                     return Err(e);
                 }
@@ -476,7 +918,7 @@ pub async fn stream_chat(
                     for tool_call in &tool_calls_limited {
                         api_messages.push(ApiMessage {
                             role: "tool".to_string(),
-                            content: Some("Error: Action rejected by user".to_string()),
+                            content: Some("Error: Action rejected by user".into()),
                             tool_call_id: Some(tool_call.id.clone()),
                             tool_calls: None,
                             name: Some(tool_call.function.name.clone()),
@@ -617,7 +1059,7 @@ pub async fn stream_chat(
                     }
                     api_messages.push(ApiMessage {
                         role: "tool".to_string(),
-                        content: Some(tool_result),
+                        content: Some(tool_result.into()),
                         tool_call_id: Some(tool_call.id.clone()),
                         tool_calls: None,
                         name: Some(tool_name.clone()),
@@ -634,7 +1076,7 @@ pub async fn stream_chat(
                     );
                     api_messages.push(ApiMessage {
                         role: "user".to_string(),
-                        content: Some(wrapped),
+                        content: Some(wrapped.into()),
                         tool_calls: None,
                         tool_call_id: None,
                         name: None,
@@ -645,7 +1087,7 @@ pub async fn stream_chat(
             }
 
             // 2. If no tool calls — check for empty response (thinking-only, TTFT=0)
-            let full_text = assistant_msg.content.as_deref().unwrap_or("");
+            let full_text = assistant_msg.text().unwrap_or("");
 
             if full_text.is_empty() {
                 if !asked_for_text_response {
@@ -653,7 +1095,7 @@ pub async fn stream_chat(
                     let _ = task_app_handle.emit("chat-status", "Запрашиваю текстовый ответ...");
                     api_messages.push(ApiMessage {
                         role: "user".to_string(),
-                        content: Some("Напиши свой ответ текстом.".to_string()),
+                        content: Some("Напиши свой ответ текстом.".into()),
                         tool_calls: None,
                         tool_call_id: None,
                         name: None,
@@ -662,7 +1104,7 @@ pub async fn stream_chat(
                 } else {
                     // Model returned empty response twice — likely context too large
                     crate::app_log!("[AI] Model returned empty response twice (context ~{}t). Emitting fallback.",
-                        api_messages.iter().map(|m| m.content.as_deref().unwrap_or("").len() / 4).sum::<usize>());
+                        api_messages.iter().map(|m| m.text().unwrap_or("").len() / 4).sum::<usize>());
                     let _ = task_app_handle.emit("chat-chunk",
                         "\n\n> **[Система]** Модель не смогла сформировать ответ (вероятно, контекст диалога слишком велик). Попробуйте начать новый чат или сократить историю.");
                     break;
@@ -681,7 +1123,7 @@ pub async fn stream_chat(
                     );
                     api_messages.push(ApiMessage {
                         role: "user".to_string(),
-                        content: Some(wrapped),
+                        content: Some(wrapped.into()),
                         tool_calls: None,
                         tool_call_id: None,
                         name: None,
@@ -787,7 +1229,7 @@ pub async fn stream_chat(
                     );
                     api_messages.push(ApiMessage {
                         role: "user".to_string(),
-                        content: Some(wrapped),
+                        content: Some(wrapped.into()),
                         tool_calls: None,
                         tool_call_id: None,
                         name: None,
@@ -806,7 +1248,7 @@ pub async fn stream_chat(
                 );
                 api_messages.push(ApiMessage {
                     role: "user".to_string(),
-                    content: Some(wrapped),
+                    content: Some(wrapped.into()),
                     tool_calls: None,
                     tool_call_id: None,
                     name: None,
@@ -823,7 +1265,7 @@ pub async fn stream_chat(
         }
 
         let _ = task_app_handle.emit("chat-status", "");
-        let _ = task_app_handle.emit("chat-done", ());
+        let _ = task_app_handle.emit("chat-turn-done", ());
         Ok(())
     });
 
@@ -839,9 +1281,9 @@ pub async fn stream_chat(
         Err(e) => {
             if e.is_cancelled() {
                 let _ = app_handle.emit("chat-status", "");
-                Err("Cancelled".to_string())
+                Err(AiClientError::Cancelled)
             } else {
-                Err(format!("Task panic: {}", e))
+                Err(AiClientError::from(format!("Task panic: {}", e)))
             }
         }
     };
@@ -851,10 +1293,26 @@ pub async fn stream_chat(
         let mut guard = chat_state.interrupt_tx.lock().await;
         *guard = None;
     }
+    *chat_state.window_label.lock().await = None;
 
     result
 }
 
+/// Non-streaming completion that must answer with JSON (optionally matching `schema`);
+/// returns the parsed value. Takes the messages as JSON, like `compact_context`.
+#[tauri::command]
+pub async fn chat_structured(
+    messages_json: String,
+    schema: Option<serde_json::Value>,
+) -> Result<serde_json::Value, AiClientError> {
+    let messages: Vec<ApiMessage> = serde_json::from_str(&messages_json)
+        .map_err(|e| AiClientError::parse(format!("Ошибка парсинга сообщений: {}", e)))?;
+    if messages.is_empty() {
+        return Err("Нет сообщений для запроса".into());
+    }
+    crate::ai::structured::chat_completion_structured(messages, schema.as_ref()).await
+}
+
 /// Non-streaming context summarization.
 /// Takes the current chat history as JSON, calls the active LLM profile
 /// with a structured summarization prompt, returns the summary text.
@@ -870,7 +1328,7 @@ pub async fn compact_context(messages_json: String) -> Result<String, String> {
     let mut conv_text = String::new();
     for msg in &history {
         let role = &msg.role;
-        let content = msg.content.as_deref().unwrap_or("");
+        let content = msg.text().unwrap_or("");
         if content.is_empty() {
             continue;
         }
@@ -887,17 +1345,20 @@ pub async fn compact_context(messages_json: String) -> Result<String, String> {
     let summarize_messages = vec![
         ApiMessage {
             role: "system".to_string(),
-            content: Some(system_prompt.to_string()),
+            content: Some(system_prompt.into()),
             tool_calls: None,
             tool_call_id: None,
             name: None,
         },
         ApiMessage {
             role: "user".to_string(),
-            content: Some(format!(
-                "Сожми следующий диалог в краткий конспект:\n\n{}",
-                conv_text
-            )),
+            content: Some(
+                format!(
+                    "Сожми следующий диалог в краткий конспект:\n\n{}",
+                    conv_text
+                )
+                .into(),
+            ),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -920,7 +1381,7 @@ pub async fn compact_context(messages_json: String) -> Result<String, String> {
 
     let api_key = crate::ai::client::resolve_profile_api_key(&profile)?;
     let raw_url = profile.get_base_url();
-    let client = crate::http_client::build_http_client()?;
+    let client = crate::http_client::build_profile_client(&profile)?;
 
     if matches!(profile.provider, crate::llm_profiles::LLMProvider::Ollama) {
         let trimmed = raw_url.trim_end_matches('/');
@@ -954,7 +1415,11 @@ pub async fn compact_context(messages_json: String) -> Result<String, String> {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(format!("API error {}: {}", status, body));
+            return Err(crate::ai::error::describe_http_error(
+                status.as_u16(),
+                &body,
+                &profile.model,
+            ));
         }
 
         let json: serde_json::Value = response
@@ -972,56 +1437,12 @@ pub async fn compact_context(messages_json: String) -> Result<String, String> {
         return Ok(summary);
     }
 
-    let base_url = {
-        let trimmed = raw_url.trim_end_matches('/');
-        if matches!(
-            profile.provider,
-            crate::llm_profiles::LLMProvider::Ollama | crate::llm_profiles::LLMProvider::LMStudio
-        ) && !trimmed.ends_with("/v1")
-        {
-            format!("{}/v1/chat/completions", trimmed)
-        } else {
-            format!("{}/chat/completions", trimmed)
-        }
-    };
-
-    let request_body = serde_json::json!({
-        "model": profile.model,
-        "messages": summarize_messages,
-        "stream": false,
-        "temperature": 0.3,
-        "max_tokens": 1024,
-    });
-
-    let client = crate::http_client::build_http_client()?;
-    let response = client
-        .post(&base_url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Ошибка HTTP: {}", e))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, body));
-    }
-
-    let json: serde_json::Value = response
-        .json()
+    let mut profile = profile;
+    profile.temperature = Some(0.3);
+    profile.max_tokens = Some(1024);
+    crate::ai::client::silent_completion(summarize_messages, &profile)
         .await
-        .map_err(|e| format!("Ошибка парсинга ответа: {}", e))?;
-
-    let summary = json["choices"][0]["message"]["content"]
-        .as_str()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .ok_or_else(|| "Пустой ответ от LLM".to_string())?
-        .to_string();
-
-    Ok(summary)
+        .map_err(String::from)
 }
 
 #[cfg(test)]
@@ -1029,6 +1450,29 @@ mod tests {
     use super::*;
     use crate::ai::models::ToolCallFunction;
 
+    #[tokio::test]
+    async fn window_destroy_cancels_streaming_task_promptly() {
+        let state = ChatState::default();
+        // Simulated stream that never finishes on its own
+        let task = tokio::spawn(async {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        });
+        *state.abort_handle.lock().await = Some(task.abort_handle());
+        *state.window_label.lock().await = Some("main".to_string());
+
+        assert!(!state.cancel_for_window("overlay").await);
+        assert!(state.cancel_for_window("main").await);
+
+        let joined = tokio::time::timeout(std::time::Duration::from_millis(500), task)
+            .await
+            .expect("streaming task did not stop within 500 ms");
+        assert!(joined.unwrap_err().is_cancelled());
+        assert!(state.abort_handle.lock().await.is_none());
+        assert!(state.window_label.lock().await.is_none());
+    }
+
     #[test]
     fn cache_key_is_stable_for_equivalent_json_arguments() {
         let left = serde_json::json!({
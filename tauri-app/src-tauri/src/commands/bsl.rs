@@ -1,7 +1,9 @@
+use crate::ai::error::AiClientError;
 use crate::settings;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
+use tauri::Emitter;
 use tokio_tungstenite::connect_async;
 
 /// BSL analysis result for UI
@@ -20,6 +22,166 @@ pub struct BslStatus {
     pub connected: bool,
 }
 
+/// Unified diff between the original and the AI-suggested code (empty when equal)
+#[tauri::command]
+pub fn diff_bsl_code(original: String, suggested: String) -> String {
+    crate::ai::diff::diff_bsl_code(&original, &suggested)
+}
+
+/// Apply a unified diff to the original code
+#[tauri::command]
+pub fn apply_bsl_diff(original: String, diff: String) -> Result<String, String> {
+    crate::ai::diff::apply_bsl_diff(&original, &diff).map_err(|e| e.to_string())
+}
+
+/// Write AI-suggested code to a file; the previous content can be restored by `rollback_last_apply`
+#[tauri::command]
+pub fn apply_bsl_code(
+    file_path: String,
+    code: String,
+    message_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let record = crate::history_manager::apply_code_to_file(
+        &file_path,
+        &code,
+        message_id.as_deref().unwrap_or_default(),
+    )?;
+    // The file is already written; a failed history insert must not report the apply as failed
+    if let Err(e) = crate::chat_store::with_store(|store| store.record_apply(&record)) {
+        crate::app_log!(force: true, "[Apply] Failed to record apply history: {}", e);
+    }
+    let _ = app_handle.emit(
+        "code-applied",
+        serde_json::json!({
+            "file_path": record.file_path,
+            "message_id": record.message_id,
+            "timestamp_ms": record.timestamp_ms,
+        }),
+    );
+    Ok(())
+}
+
+/// Restore the file changed by the most recent `apply_bsl_code`; returns its path
+#[tauri::command]
+pub fn rollback_last_apply(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let file_path = crate::history_manager::rollback_last_apply()?;
+    let _ = app_handle.emit("code-rolled-back", &file_path);
+    Ok(file_path)
+}
+
+/// Applies to the file from the persistent history, newest first
+#[tauri::command]
+pub fn get_apply_history(
+    file_path: String,
+    limit: u32,
+) -> Result<Vec<crate::chat_store::ApplyHistoryRow>, String> {
+    crate::chat_store::with_store(|store| store.apply_history(&file_path, limit))
+}
+
+/// Restore the file to its content before the given apply; later applies are marked rolled back
+#[tauri::command]
+pub fn rollback_to(apply_id: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let file_path = crate::chat_store::with_store(|store| store.rollback_to(&apply_id))?;
+    let _ = app_handle.emit("code-rolled-back", &file_path);
+    Ok(())
+}
+
+/// Files that AI code has been applied to
+#[tauri::command]
+pub fn list_modified_files() -> Result<Vec<String>, String> {
+    crate::chat_store::with_store(|store| store.modified_files())
+}
+
+/// Cyclomatic complexity estimate of BSL code (no BSL LS needed)
+#[tauri::command]
+pub fn analyze_bsl_complexity(code: String) -> crate::bsl_analysis::ComplexityReport {
+    crate::bsl_analysis::analyze_complexity(&code)
+}
+
+/// APIs in `code` that need a newer 1С platform than `platform_version`
+#[tauri::command]
+pub fn check_compatibility(
+    code: String,
+    platform_version: String,
+) -> Vec<crate::platform_compat::CompatibilityIssue> {
+    crate::platform_compat::check_platform_compatibility(&code, &platform_version)
+}
+
+/// Static security audit of BSL code: query concatenation, dynamic Выполнить,
+/// external processings, writes without rights checks, user management calls
+#[tauri::command]
+pub fn audit_bsl_security(code: String) -> Vec<crate::bsl_analysis::SecurityFinding> {
+    crate::bsl_analysis::audit_bsl_security(&code)
+}
+
+/// Ask the model to explain audit findings in detail (outside the chat)
+#[tauri::command]
+pub async fn explain_security_findings(
+    code: String,
+    findings: Vec<crate::bsl_analysis::SecurityFinding>,
+) -> Result<String, AiClientError> {
+    crate::ai::security_review::explain_security_findings(code, findings).await
+}
+
+/// Metadata objects referenced via `Справочники.`, `Документы.`, `Catalogs.`... in the code
+#[tauri::command]
+pub fn extract_metadata_refs(code: String) -> Vec<crate::bsl_analysis::MetadataRef> {
+    crate::bsl_analysis::extract_metadata_references(&code)
+}
+
+/// Latin spelling of the Cyrillic names the module declares, with the list of renames
+/// for review before applying
+#[tauri::command]
+pub fn transliterate_identifiers(code: String) -> crate::bsl_analysis::TransliterationReport {
+    crate::bsl_analysis::transliterate_bsl_identifiers(&code)
+}
+
+/// Routines never called, variables never read and code after `Возврат`
+#[tauri::command]
+pub fn detect_dead_code(code: String) -> Vec<crate::bsl_analysis::DeadCodeFinding> {
+    crate::bsl_analysis::detect_dead_code(&code)
+}
+
+/// Ask the model to remove the findings; returns the cleaned module
+#[tauri::command]
+pub async fn remove_dead_code(
+    code: String,
+    findings: Vec<crate::bsl_analysis::DeadCodeFinding>,
+) -> Result<String, AiClientError> {
+    crate::ai::dead_code::remove_dead_code(code, findings).await
+}
+
+/// YaxUnit test module with a stub for each procedure and function of `code`
+#[tauri::command]
+pub async fn generate_test_stubs(
+    code: String,
+) -> Result<crate::ai::code_blocks::CodeBlock, AiClientError> {
+    crate::ai::test_stubs::generate_test_stubs(code).await
+}
+
+/// Documentation comments for undocumented procedures and functions, as a unified diff
+/// of added lines for `apply_bsl_diff`
+#[tauri::command]
+pub async fn generate_docs(code: String) -> Result<String, AiClientError> {
+    crate::ai::doc_generation::generate_bsl_docs(code).await
+}
+
+/// Code without the documentation comments above procedures and functions
+#[tauri::command]
+pub fn strip_bsl_docs(code: String) -> String {
+    crate::bsl_analysis::strip_bsl_docs(&code)
+}
+
+/// Line annotations from an AI answer about `code`; empty when the answer names no lines
+#[tauri::command]
+pub fn extract_annotations(
+    response: String,
+    code: String,
+) -> Vec<crate::ai::annotations::InlineAnnotation> {
+    crate::ai::annotations::parse_annotations_from_response(&response, &code)
+}
+
 /// Analyze BSL code
 #[tauri::command]
 pub async fn analyze_bsl(
@@ -1,10 +1,13 @@
 use crate::llm::cli_providers::{self as cli, codex::CodexCliProvider, qwen::QwenCliProvider};
 
 #[tauri::command]
-pub async fn cli_auth_start(provider: String) -> Result<cli::CliAuthInitResponse, String> {
+pub async fn cli_auth_start(
+    provider: String,
+    profile_id: Option<String>,
+) -> Result<cli::CliAuthInitResponse, String> {
     crate::app_log!(force: true, "[DEBUG] cli_auth_start called for: {}", provider);
     match provider.as_str() {
-        "qwen" => QwenCliProvider::auth_start().await,
+        "qwen" => QwenCliProvider::auth_start(profile_id.as_deref()).await,
         "codex" => CodexCliProvider::auth_start().await,
         _ => Err(format!("Unsupported provider: {}", provider)),
     }
@@ -15,10 +18,18 @@ pub async fn cli_auth_poll(
     provider: String,
     device_code: String,
     code_verifier: Option<String>,
+    profile_id: Option<String>,
 ) -> Result<cli::CliAuthStatus, String> {
     crate::app_log!(force: true, "[DEBUG] cli_auth_poll called for: {}", provider);
     match provider.as_str() {
-        "qwen" => QwenCliProvider::auth_poll(&device_code, code_verifier.as_deref()).await,
+        "qwen" => {
+            QwenCliProvider::auth_poll(
+                &device_code,
+                code_verifier.as_deref(),
+                profile_id.as_deref(),
+            )
+            .await
+        }
         "codex" => CodexCliProvider::auth_poll(&device_code, code_verifier.as_deref()).await,
         _ => Err(format!("Unsupported provider: {}", provider)),
     }
@@ -236,6 +236,20 @@ pub async fn save_debug_logs(app_handle: tauri::AppHandle) -> Result<(), String>
     Ok(())
 }
 
+/// Path of the current structured (tracing) log file
+#[tauri::command]
+pub fn get_log_path() -> String {
+    crate::logger::tracing_log_path()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Last `n` lines of the structured log file, for the log panel
+#[tauri::command]
+pub fn get_recent_logs(n: usize) -> Vec<String> {
+    crate::logger::recent_log_lines(n)
+}
+
 /// Call an MCP tool on a specific server
 #[tauri::command]
 pub async fn call_mcp_tool(
@@ -472,6 +472,18 @@ pub async fn quick_chat_invoke(prompt: String) -> Result<String, String> {
         return Err("1С:Напарник не поддерживается для быстрых действий".to_string());
     }
 
+    // Quick-action templates are written in Russian; only other languages need an instruction
+    let language = crate::ai::prompts::answer_language(Some(&profile));
+    let prompt = if language == crate::settings::AnswerLanguage::Ru {
+        prompt
+    } else {
+        format!(
+            "{}\n\n{}",
+            prompt,
+            crate::ai::prompts::answer_language_instruction(language)
+        )
+    };
+
     if matches!(profile.provider, LLMProvider::CodexCli) {
         return crate::ai::codex_client::quick_codex_invoke(prompt).await;
     }
@@ -512,30 +524,17 @@ pub async fn quick_chat_invoke(prompt: String) -> Result<String, String> {
         (profile.get_api_key(), profile.get_base_url())
     };
 
-    let raw_url = raw_url;
-
-    // Normalize URL for Ollama/LMStudio
-    let base_url = {
-        let trimmed = raw_url.trim_end_matches('/');
-        if matches!(
-            profile.provider,
-            LLMProvider::Ollama | LLMProvider::LMStudio
-        ) && !trimmed.ends_with("/v1")
-        {
-            format!("{}/v1", trimmed)
-        } else {
-            trimmed.to_string()
-        }
-    };
+    // Already normalised by get_base_url
+    let base_url = raw_url;
 
-    let client = crate::http_client::build_http_client()?;
+    let client = crate::http_client::build_profile_client(&profile)?;
 
     // Anthropic uses a different API format
     if matches!(profile.provider, LLMProvider::Anthropic) {
         let url = format!("{}/messages", base_url);
         let body = serde_json::json!({
             "model": profile.model,
-            "max_tokens": profile.max_tokens.min(4096),
+            "max_tokens": profile.max_tokens.unwrap_or(4096).min(4096),
             "messages": [{"role": "user", "content": prompt}]
         });
 
@@ -564,13 +563,17 @@ pub async fn quick_chat_invoke(prompt: String) -> Result<String, String> {
 
     // OpenAI-compatible endpoint
     let url = format!("{}/chat/completions", base_url);
-    let body = serde_json::json!({
+    let mut body = serde_json::json!({
         "model": profile.model,
-        "max_tokens": profile.max_tokens.min(4096),
-        "temperature": profile.temperature,
         "stream": false,
         "messages": [{"role": "user", "content": prompt}]
     });
+    if let Some(max_tokens) = profile.max_tokens {
+        body["max_tokens"] = max_tokens.min(4096).into();
+    }
+    if let Some(temperature) = profile.temperature {
+        body["temperature"] = temperature.into();
+    }
 
     let mut req = client.post(&url).header("content-type", "application/json");
 
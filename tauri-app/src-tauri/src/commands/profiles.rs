@@ -1,6 +1,11 @@
+use crate::generation_presets::{self, GenerationPreset};
 use crate::llm::cli_providers::codex::CodexCliProvider;
 use crate::llm::cli_providers::qwen::QwenCliProvider;
-use crate::llm_profiles::{self, LLMProfile, ProfileStore};
+use crate::llm::model_cache::{self, ProfileModels};
+use crate::llm_profiles::{
+    self, LLMProfile, MergeStrategy, ProfileError, ProfileErrorKind, ProfileImportResult,
+    ProfileSaveResult, ProfileStore, ProfilesExport,
+};
 use tauri::{AppHandle, Emitter};
 
 fn sync_legacy_active_profile(active_profile_id: &str) {
@@ -25,8 +30,12 @@ fn sync_legacy_active_profile(active_profile_id: &str) {
 
 fn persist_profile_store(store: &ProfileStore, app_handle: &AppHandle) -> Result<(), String> {
     llm_profiles::save_profiles(store)?;
+    crate::http_client::forget_cached_clients();
     sync_legacy_active_profile(&store.active_profile_id);
     let _ = app_handle.emit("profiles-changed", &store.active_profile_id);
+    crate::metrics::sync_with_active_profile();
+    model_cache::invalidate_outdated(&store.profiles);
+    crate::llm::profile_health::retain_profiles(&store.profiles);
     Ok(())
 }
 
@@ -36,25 +45,39 @@ pub fn get_profiles() -> ProfileStore {
     llm_profiles::load_profiles()
 }
 
-/// Save profile
-#[tauri::command]
-pub fn save_profile(
-    mut profile: LLMProfile,
+/// Keeps the stored key when the frontend sends an empty or "set" placeholder.
+/// A new key is checked with `check_api_key`; its warning goes back to the frontend.
+/// Call after the store checks: the keychain is written here.
+fn apply_api_key(
+    profile: &mut LLMProfile,
     api_key: Option<String>,
-    app_handle: AppHandle,
-) -> Result<(), String> {
-    let mut store = llm_profiles::load_profiles();
-    let existing_encrypted = store
-        .profiles
-        .iter()
-        .find(|p| p.id == profile.id)
-        .map(|p| p.api_key_encrypted.clone());
-
-    match api_key {
-        Some(key) if !key.trim().is_empty() => {
-            profile.set_api_key(&key);
+    store: &ProfileStore,
+) -> Result<ProfileSaveResult, ProfileError> {
+    let checked_key = match api_key.as_deref() {
+        Some(key) if !key.trim().is_empty() => Some(
+            llm_profiles::check_api_key(&profile.provider, key)
+                .map_err(|e| ProfileError::new(ProfileErrorKind::Invalid, e))?,
+        ),
+        _ => None,
+    };
+
+    let existing = store.profiles.iter().find(|p| p.id == profile.id);
+    let saved_proxy_password = existing
+        .and_then(|p| p.proxy.as_ref())
+        .map(|proxy| proxy.password.clone());
+    profile
+        .store_proxy_password(saved_proxy_password.as_deref())
+        .map_err(|e| ProfileError::new(ProfileErrorKind::Storage, e))?;
+    let existing_encrypted = existing.map(|p| p.api_key_encrypted.clone());
+
+    match checked_key {
+        Some(check) => {
+            profile.set_api_key(&check.key);
+            return Ok(ProfileSaveResult {
+                api_key_warning: check.warning,
+            });
         }
-        _ => {
+        None => {
             let incoming = profile.api_key_encrypted.trim();
             let incoming_is_placeholder =
                 incoming.is_empty() || incoming.eq_ignore_ascii_case("set");
@@ -72,117 +95,284 @@ pub fn save_profile(
             }
         }
     }
+    Ok(ProfileSaveResult::default())
+}
 
-    // Update or add profile
-    if let Some(pos) = store.profiles.iter().position(|p| p.id == profile.id) {
-        store.profiles[pos] = profile;
-    } else {
-        store.profiles.push(profile);
-    }
+fn persist_or_storage_error(
+    store: &ProfileStore,
+    app_handle: &AppHandle,
+) -> Result<(), ProfileError> {
+    persist_profile_store(store, app_handle)
+        .map_err(|e| ProfileError::new(ProfileErrorKind::Storage, e))
+}
 
-    persist_profile_store(&store, &app_handle)
+/// All profiles without the active id
+#[tauri::command]
+pub fn list_profiles() -> Vec<LLMProfile> {
+    llm_profiles::load_profiles().sorted()
 }
 
-/// Delete a profile
+/// Rewrite the profile order; `ids_in_order` must list every profile exactly once
 #[tauri::command]
-pub fn delete_profile(profile_id: String, app_handle: AppHandle) -> Result<(), String> {
+pub fn reorder_profiles(
+    ids_in_order: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), ProfileError> {
     let mut store = llm_profiles::load_profiles();
+    store.reorder(&ids_in_order)?;
+    persist_or_storage_error(&store, &app_handle)
+}
 
-    // Check if profile exists
-    let profile = store.profiles.iter().find(|p| p.id == profile_id).cloned();
-    if profile.is_none() {
-        return Err("Профиль не найден".to_string());
-    }
+/// Add a new profile; fails if the id or name is taken
+#[tauri::command]
+pub fn create_profile(
+    mut profile: LLMProfile,
+    api_key: Option<String>,
+    app_handle: AppHandle,
+) -> Result<ProfileSaveResult, ProfileError> {
+    let mut store = llm_profiles::load_profiles();
+    store.check_create(&profile)?;
+    let result = apply_api_key(&mut profile, api_key, &store)?;
+    store.create(profile)?;
+    persist_or_storage_error(&store, &app_handle)?;
+    Ok(result)
+}
 
-    // Don't allow deleting the last profile
-    if store.profiles.len() <= 1 {
-        return Err("Нельзя удалить последний профиль".to_string());
+/// Replace an existing profile
+#[tauri::command]
+pub fn update_profile(
+    mut profile: LLMProfile,
+    api_key: Option<String>,
+    app_handle: AppHandle,
+) -> Result<ProfileSaveResult, ProfileError> {
+    let mut store = llm_profiles::load_profiles();
+    store.check_update(&profile)?;
+    let result = apply_api_key(&mut profile, api_key, &store)?;
+    store.update(profile)?;
+    persist_or_storage_error(&store, &app_handle)?;
+    Ok(result)
+}
+
+/// Save profile: update when the id exists, create otherwise
+#[tauri::command]
+pub fn save_profile(
+    mut profile: LLMProfile,
+    api_key: Option<String>,
+    app_handle: AppHandle,
+) -> Result<ProfileSaveResult, String> {
+    let mut store = llm_profiles::load_profiles();
+    let exists = store.profiles.iter().any(|p| p.id == profile.id);
+    if exists {
+        store.check_update(&profile)?;
+    } else {
+        store.check_create(&profile)?;
+    }
+    let result = apply_api_key(&mut profile, api_key, &store)?;
+    if exists {
+        store.update(profile)?;
+    } else {
+        store.create(profile)?;
     }
+    persist_profile_store(&store, &app_handle)?;
+    Ok(result)
+}
 
-    // If it's a CLI provider — clear the stored token from keychain
-    if let Some(p) = &profile {
-        if matches!(p.provider, crate::llm_profiles::LLMProvider::QwenCli) {
-            let _ = QwenCliProvider::logout(&profile_id); // ignore error if no token exists
-        } else if matches!(p.provider, crate::llm_profiles::LLMProvider::CodexCli) {
-            let _ = CodexCliProvider::logout(&profile_id); // ignore error if no token exists
+/// Delete a profile; the active one needs `replacement_id` to become active instead
+#[tauri::command]
+pub fn delete_profile(
+    profile_id: String,
+    replacement_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<(), ProfileError> {
+    let mut store = llm_profiles::load_profiles();
+    let removed = store.remove(&profile_id, replacement_id.as_deref())?;
+    // Duplicated profiles share the keychain entry; it goes with the last reference
+    if let Some(account) = crate::keychain::parse_reference(&removed.api_key_encrypted) {
+        if !store
+            .profiles
+            .iter()
+            .any(|p| p.api_key_encrypted == removed.api_key_encrypted)
+        {
+            crate::keychain::delete_api_key(account);
         }
     }
-
-    // Remove the profile
-    store.profiles.retain(|p| p.id != profile_id);
-
-    // If we deleted the active profile, pick the first available one
-    if store.active_profile_id == profile_id {
-        if let Some(first) = store.profiles.first() {
-            store.active_profile_id = first.id.clone();
+    if let Some(password) = removed.proxy.as_ref().map(|proxy| &proxy.password) {
+        if let Some(account) = crate::keychain::parse_reference(password) {
+            if !store.profiles.iter().any(|p| {
+                p.proxy
+                    .as_ref()
+                    .is_some_and(|proxy| &proxy.password == password)
+            }) {
+                crate::keychain::delete_api_key(account);
+            }
         }
     }
 
-    persist_profile_store(&store, &app_handle)
+    // If it's a CLI provider — clear the stored token from keychain
+    if matches!(removed.provider, crate::llm_profiles::LLMProvider::QwenCli) {
+        let _ = QwenCliProvider::logout(&profile_id); // ignore error if no token exists
+    } else if matches!(removed.provider, crate::llm_profiles::LLMProvider::CodexCli) {
+        let _ = CodexCliProvider::logout(&profile_id); // ignore error if no token exists
+    }
+
+    persist_or_storage_error(&store, &app_handle)
+}
+
+/// Copy a profile under a new id; it shares the API key and does not become active
+#[tauri::command]
+pub fn duplicate_profile(
+    profile_id: String,
+    new_name: Option<String>,
+    app_handle: AppHandle,
+) -> Result<LLMProfile, ProfileError> {
+    let mut store = llm_profiles::load_profiles();
+    let copy = store.duplicate(&profile_id, new_name.as_deref())?;
+    persist_or_storage_error(&store, &app_handle)?;
+    Ok(copy)
 }
 
 /// Set active profile
 #[tauri::command]
-pub fn set_active_profile(profile_id: String, app_handle: AppHandle) -> Result<(), String> {
+pub fn set_active_profile(profile_id: String, app_handle: AppHandle) -> Result<(), ProfileError> {
     let mut store = llm_profiles::load_profiles();
+    store.set_active(&profile_id)?;
+    persist_or_storage_error(&store, &app_handle)
+}
 
-    if !store.profiles.iter().any(|p| p.id == profile_id) {
-        return Err("Profile not found".to_string());
+/// Fetch models for a profile (using stored profile settings); the active one when `profile_id` is omitted.
+/// Their metadata is cached for `get_model_entry`.
+#[tauri::command]
+pub async fn fetch_models_cmd(
+    profile_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, String> {
+    let profile = llm_profiles::resolve_profile(profile_id.as_deref())?;
+    let models = fetch_models_reporting_auth(&profile, &app_handle).await?;
+    model_cache::store_model_entries(&profile, &models);
+    Ok(models.into_iter().map(|m| m.id).collect())
+}
+
+/// Metadata (context window, output limit) of a model from the profile's `/models`
+/// response; fetched once when the profile has none cached yet
+#[tauri::command]
+pub async fn get_model_entry(
+    profile_id: String,
+    model_id: String,
+    app_handle: AppHandle,
+) -> Result<Option<crate::ai::ModelEntry>, String> {
+    let profile = llm_profiles::resolve_profile(Some(&profile_id))?;
+    let cache = model_cache::load_model_cache();
+    if cache.model_entries_of(&profile).is_some() {
+        return Ok(cache.model_entry(&profile, &model_id).cloned());
     }
+    let models = fetch_models_reporting_auth(&profile, &app_handle).await?;
+    model_cache::store_model_entries(&profile, &models);
+    Ok(models.into_iter().find(|m| m.id == model_id))
+}
 
-    store.active_profile_id = profile_id;
-    persist_profile_store(&store, &app_handle)
+/// Rate limits the provider reported with the profile's last response; `None` before the
+/// first request or when the provider sends no `x-ratelimit-*` headers
+#[tauri::command]
+pub fn get_rate_limit_status(profile_id: String) -> Option<crate::ai::rate_limit::RateLimitStatus> {
+    crate::ai::rate_limit::status(&profile_id)
 }
 
-/// Fetch models for a profile (using stored profile settings)
+/// `fetch_models` that flags a rejected key in the profile health and sends `auth-error`
+async fn fetch_models_reporting_auth(
+    profile: &LLMProfile,
+    app_handle: &AppHandle,
+) -> Result<Vec<crate::ai::ModelEntry>, String> {
+    crate::ai::fetch_models(profile).await.map_err(|e| {
+        if e.is_auth_failure() {
+            crate::llm::profile_health::mark_auth_failed(&profile.id);
+            e.emit_auth_error(app_handle);
+        }
+        e.to_string()
+    })
+}
+
+/// Test connection for a profile (the active one by default) with step-by-step diagnostics
 #[tauri::command]
-pub async fn fetch_models_cmd(profile_id: String) -> Result<Vec<String>, String> {
-    let store = llm_profiles::load_profiles();
-    let profile = store
-        .profiles
-        .iter()
-        .find(|p| p.id == profile_id)
-        .ok_or("Profile not found")?;
+pub async fn test_llm_connection_cmd(
+    profile_id: Option<String>,
+    app_handle: AppHandle,
+) -> Result<crate::ai::ConnectionDiagnostics, String> {
+    let profile = llm_profiles::resolve_profile(profile_id.as_deref())?;
+    let diagnostics = crate::ai::test_connection_detailed(&profile, &app_handle).await;
+    crate::llm::profile_health::record_test(
+        &profile.id,
+        diagnostics.error.as_deref().map_or(Ok(()), Err),
+        diagnostics.latency_ms,
+    );
+    if diagnostics.tls_ok && !diagnostics.auth_ok {
+        crate::llm::profile_health::mark_auth_failed(&profile.id);
+    }
+    Ok(diagnostics)
+}
 
-    crate::ai::fetch_models(profile).await
+/// Last test/chat outcome and average time to first token of every profile
+#[tauri::command]
+pub fn get_profile_health() -> Vec<crate::llm::profile_health::ProfileHealthRow> {
+    crate::llm::profile_health::health_table(
+        &crate::llm::profile_health::load_health(),
+        &llm_profiles::load_profiles().profiles,
+    )
 }
 
-/// Test connection for a profile
+/// Closes the profile's circuit breaker so the next chat goes to the provider at once
 #[tauri::command]
-pub async fn test_llm_connection_cmd(profile_id: String) -> Result<String, String> {
-    let store = llm_profiles::load_profiles();
-    let profile = store
-        .profiles
-        .iter()
-        .find(|p| p.id == profile_id)
-        .ok_or("Profile not found")?;
+pub fn reset_circuit(profile_id: String) {
+    crate::llm::circuit_breaker::reset(&profile_id);
+}
 
-    crate::ai::test_connection(profile).await
+/// Tests all enabled profiles concurrently and returns the updated health table
+#[tauri::command]
+pub async fn run_health_checks() -> Vec<crate::llm::profile_health::ProfileHealthRow> {
+    crate::llm::profile_health::run_health_checks().await
 }
 
-/// Fetch models from a specific provider using API and Registry
+/// Fetch models from a specific provider using API and Registry; through the proxy of
+/// `profile_id` when that profile is saved, otherwise the app-level proxy
 #[tauri::command]
 pub async fn fetch_models_from_provider(
     provider_id: String,
     base_url: String,
     api_key: String,
+    profile_id: Option<String>,
 ) -> Result<Vec<crate::llm::providers::Model>, String> {
     use crate::llm::providers;
 
+    let profile = profile_id.and_then(|id| {
+        llm_profiles::load_profiles()
+            .profiles
+            .into_iter()
+            .find(|p| p.id == id)
+    });
+    let client = match &profile {
+        Some(profile) => crate::http_client::cached_profile_client(
+            profile,
+            crate::http_client::ClientPurpose::Models,
+        )?,
+        None => crate::http_client::build_http_client()?,
+    };
+
     // 1. Fetch from API
-    let api_models = providers::fetch_models_from_api(&provider_id, &base_url, &api_key).await?;
+    let api_models =
+        providers::fetch_models_with_client(&client, &provider_id, &base_url, &api_key).await?;
 
     if api_models.is_empty() {
         return Err("Provider returned empty model list".to_string());
     }
 
     // 2. Fetch Registry
-    let registry = providers::fetch_registry().await.unwrap_or_else(|e| {
-        crate::app_log!(force: true, "Failed to fetch registry: {}", e);
-        providers::RegistryData {
-            providers: std::collections::HashMap::new(),
-        }
-    });
+    let registry = providers::fetch_registry(&client)
+        .await
+        .unwrap_or_else(|e| {
+            crate::app_log!(force: true, "Failed to fetch registry: {}", e);
+            providers::RegistryData {
+                providers: std::collections::HashMap::new(),
+            }
+        });
 
     // 3. Merge
     let merged = providers::merge_models(api_models, &registry, &provider_id);
@@ -190,47 +380,226 @@ pub async fn fetch_models_from_provider(
     Ok(merged)
 }
 
-/// Fetch models for an existing profile (using stored key)
+/// Models of an existing profile: the cached list while it is younger than
+/// `model_cache_ttl_secs`, otherwise fetched (the cached list marked `stale` if that fails)
 #[tauri::command]
-pub async fn fetch_models_for_profile(
-    profile_id: String,
-) -> Result<Vec<crate::llm::providers::Model>, String> {
-    use crate::llm::providers;
+pub async fn fetch_models_for_profile(profile_id: String) -> Result<ProfileModels, String> {
+    profile_models(&profile_id, false).await
+}
+
+/// Fetch models of a profile bypassing the cache
+#[tauri::command]
+pub async fn refresh_models(profile_id: String) -> Result<ProfileModels, String> {
+    profile_models(&profile_id, true).await
+}
 
+async fn profile_models(profile_id: &str, force_refresh: bool) -> Result<ProfileModels, String> {
     let store = llm_profiles::load_profiles();
     let profile = store
         .profiles
         .iter()
         .find(|p| p.id == profile_id)
         .ok_or("Profile not found")?;
+    let ttl = crate::settings::load_settings()
+        .model_cache_ttl_secs
+        .unwrap_or(model_cache::DEFAULT_MODEL_CACHE_TTL_SECS);
+    model_cache::models_with_cache(profile, ttl, force_refresh, || {
+        fetch_profile_models_uncached(profile)
+    })
+    .await
+}
+
+async fn fetch_profile_models_uncached(
+    profile: &LLMProfile,
+) -> Result<Vec<crate::llm::providers::Model>, String> {
+    use crate::llm::providers;
 
     // CLI providers use OAuth token, not API key — route to their own fetch_models
     if profile.provider.to_string() == "CodexCli" {
-        return CodexCliProvider::fetch_models(&profile_id).await;
+        return CodexCliProvider::fetch_models(&profile.id).await;
     }
 
     let api_key = profile.try_get_api_key()?;
     let base_url = profile.get_base_url();
 
     // 1. Fetch from API
-    let api_models =
-        providers::fetch_models_from_api(&profile.provider.to_string(), &base_url, &api_key)
-            .await?;
+    let client = crate::http_client::cached_profile_client(
+        profile,
+        crate::http_client::ClientPurpose::Models,
+    )?;
+    let api_models = providers::fetch_models_with_client(
+        &client,
+        &profile.provider.to_string(),
+        &base_url,
+        &api_key,
+    )
+    .await?;
 
     if api_models.is_empty() {
         return Err("Provider returned empty model list".to_string());
     }
 
     // 2. Fetch Registry
-    let registry = providers::fetch_registry().await.unwrap_or_else(|e| {
-        crate::app_log!(force: true, "Failed to fetch registry: {}", e);
-        providers::RegistryData {
-            providers: std::collections::HashMap::new(),
-        }
-    });
+    let registry = providers::fetch_registry(&client)
+        .await
+        .unwrap_or_else(|e| {
+            crate::app_log!(force: true, "Failed to fetch registry: {}", e);
+            providers::RegistryData {
+                providers: std::collections::HashMap::new(),
+            }
+        });
 
     // 3. Merge
     let merged = providers::merge_models(api_models, &registry, &profile.provider.to_string());
 
     Ok(merged)
 }
+
+/// Usage totals (requests, tokens, estimated cost) since app start
+#[tauri::command]
+pub fn get_total_usage() -> crate::ai::usage::UsageTotals {
+    crate::ai::usage::get_total_usage()
+}
+
+/// Usage of one profile (all when omitted) over a period, with per-day buckets
+#[tauri::command]
+pub fn get_usage_stats(
+    profile_id: Option<String>,
+    period: crate::ai::usage::UsagePeriod,
+) -> crate::ai::usage::UsageStats {
+    crate::ai::usage::get_usage_stats(profile_id.as_deref(), period)
+}
+
+/// Clears the usage history of one profile, or of all of them
+#[tauri::command]
+pub fn reset_usage_stats(profile_id: Option<String>) -> Result<(), String> {
+    crate::ai::usage::reset_usage_stats(profile_id.as_deref())
+}
+
+/// Project the monthly (30 days) cost in USD for a profile's model
+#[tauri::command]
+pub fn project_monthly_cost(
+    profile_id: String,
+    avg_daily_requests: u32,
+    avg_prompt_tokens: u32,
+    avg_completion_tokens: u32,
+) -> Result<f64, String> {
+    let store = llm_profiles::load_profiles();
+    let profile = store
+        .profiles
+        .iter()
+        .find(|p| p.id == profile_id)
+        .ok_or("Profile not found")?;
+
+    let per_request =
+        crate::ai::pricing::estimate_cost(avg_prompt_tokens, avg_completion_tokens, &profile.model)
+            .ok_or_else(|| format!("Нет данных о стоимости модели '{}'", profile.model))?;
+
+    Ok(per_request * avg_daily_requests as f64 * 30.0)
+}
+
+/// Change the port of the Prometheus `/metrics` endpoint
+#[tauri::command]
+pub fn set_metrics_port(port: u16) -> Result<(), String> {
+    if port == 0 {
+        return Err("Некорректный порт".to_string());
+    }
+    crate::metrics::set_port(port);
+    Ok(())
+}
+
+/// URL of the Prometheus `/metrics` endpoint
+#[tauri::command]
+pub fn get_metrics_url() -> String {
+    crate::metrics::metrics_url()
+}
+
+/// User-Agent sent with requests of the profile
+#[tauri::command]
+pub fn get_effective_user_agent(profile_id: String) -> Result<String, String> {
+    let store = llm_profiles::load_profiles();
+    let profile = store
+        .profiles
+        .iter()
+        .find(|p| p.id == profile_id)
+        .ok_or("Profile not found")?;
+    Ok(crate::http_client::effective_user_agent(profile))
+}
+
+/// Provider matching a pasted endpoint URL; `None` suggests a Custom profile
+#[tauri::command]
+pub fn detect_provider_from_url(url: String) -> Option<crate::llm_profiles::LLMProvider> {
+    crate::llm_profiles::LLMProvider::detect_from_url(&url)
+}
+
+/// JSON Schema of a profile, for editing profile files with validation and auto-complete
+#[tauri::command]
+pub fn get_profile_schema() -> serde_json::Value {
+    crate::llm_profiles::profile_schema()
+}
+
+/// Why the profiles file could not be loaded (corrupt or undecryptable); `None` when it is fine.
+/// The UI then offers `reset_profiles`.
+#[tauri::command]
+pub fn get_profiles_load_error() -> Option<String> {
+    llm_profiles::load_profiles();
+    llm_profiles::profiles_load_error()
+}
+
+/// Replace an unreadable profiles file with the default profile
+#[tauri::command]
+pub fn reset_profiles(app_handle: AppHandle) -> Result<ProfileStore, String> {
+    let store = llm_profiles::reset_profile_store()?;
+    crate::http_client::forget_cached_clients();
+    sync_legacy_active_profile(&store.active_profile_id);
+    let _ = app_handle.emit("profiles-changed", &store.active_profile_id);
+    Ok(store)
+}
+
+/// Why API keys are kept in the settings file instead of the OS keychain, if they are
+#[tauri::command]
+pub fn get_keychain_warning() -> Option<String> {
+    crate::keychain::fallback_warning()
+}
+
+/// Write all profiles to a shareable JSON file; API keys only with `include_keys`
+#[tauri::command]
+pub fn export_profiles(path: String, include_keys: bool) -> Result<(), String> {
+    let document = llm_profiles::export_profiles(&llm_profiles::load_profiles(), include_keys);
+    let content = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Не удалось записать {}: {}", path, e))
+}
+
+/// Merge profiles from an exported file, resolving name collisions with `merge_strategy`
+#[tauri::command]
+pub fn import_profiles(
+    path: String,
+    merge_strategy: MergeStrategy,
+    app_handle: AppHandle,
+) -> Result<Vec<ProfileImportResult>, String> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Не удалось прочитать {}: {}", path, e))?;
+    let document: ProfilesExport =
+        serde_json::from_str(&content).map_err(|e| format!("Некорректный файл профилей: {}", e))?;
+    let mut store = llm_profiles::load_profiles();
+    let results = llm_profiles::import_profiles(&mut store, document, merge_strategy)?;
+    persist_profile_store(&store, &app_handle)?;
+    Ok(results)
+}
+
+/// Built-in and user generation presets
+#[tauri::command]
+pub fn get_generation_presets() -> Vec<GenerationPreset> {
+    generation_presets::load_presets()
+}
+
+/// Add or update a user generation preset
+#[tauri::command]
+pub fn save_generation_preset(preset: GenerationPreset) -> Result<(), String> {
+    generation_presets::save_preset(preset)
+}
+
+#[tauri::command]
+pub fn delete_generation_preset(name: String) -> Result<(), String> {
+    generation_presets::delete_preset(&name)
+}
@@ -1,11 +1,12 @@
 use crate::{
+    ai::few_shot::{self, FewShotExample},
     llm_profiles::{self, LLMProfile, ProfileStore},
     settings::{self, AppSettings},
 };
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter};
 use tauri_plugin_dialog::DialogExt;
 
 const SETTINGS_EXPORT_FORMAT_VERSION: u32 = 2;
@@ -71,6 +72,9 @@ fn sanitize_settings_for_export(mut safe_settings: AppSettings) -> AppSettings {
 fn sanitize_profiles_for_export(mut safe_profiles: ProfileStore) -> ProfileStore {
     for profile in &mut safe_profiles.profiles {
         profile.api_key_encrypted.clear();
+        if let Some(proxy) = profile.proxy.as_mut() {
+            proxy.password.clear();
+        }
     }
 
     safe_profiles
@@ -262,6 +266,8 @@ pub fn get_settings() -> AppSettings {
 #[tauri::command]
 pub fn save_settings(new_settings: AppSettings) -> Result<(), String> {
     settings::save_settings(&new_settings)?;
+    // The app proxy settings are part of every cached client without its own proxy
+    crate::http_client::forget_cached_clients();
 
     #[cfg(windows)]
     {
@@ -274,6 +280,76 @@ pub fn save_settings(new_settings: AppSettings) -> Result<(), String> {
     Ok(())
 }
 
+/// App-level default system prompt (`None` = built-in prompt)
+#[tauri::command]
+pub fn get_system_prompt() -> Option<String> {
+    settings::load_settings()
+        .custom_prompts
+        .default_system_prompt
+}
+
+/// Set the app-level default system prompt; empty text restores the built-in one
+#[tauri::command]
+pub fn set_system_prompt(prompt: Option<String>) -> Result<(), String> {
+    let mut settings = settings::load_settings();
+    settings.custom_prompts.default_system_prompt = prompt.filter(|p| !p.trim().is_empty());
+    settings::save_settings(&settings)
+}
+
+/// Level of the tracing log file (`error`..`trace`), applied at once and kept for
+/// the next start; `debug` adds the redacted request bodies of the AI client
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<String, String> {
+    let level = crate::logger::set_log_level(&level)?;
+    let mut settings = settings::load_settings();
+    settings.log_level = Some(level.to_string());
+    settings::save_settings(&settings)?;
+    Ok(level.to_string())
+}
+
+/// Value sent as `user` with chat requests (configured `user_id` or the machine hash)
+#[tauri::command]
+pub fn get_effective_user_id() -> String {
+    settings::effective_user_id(&settings::load_settings())
+}
+
+/// Few-shot examples injected before the conversation
+#[tauri::command]
+pub fn list_few_shot_examples() -> Vec<FewShotExample> {
+    few_shot::load_few_shot_store().examples
+}
+
+#[tauri::command]
+pub fn add_few_shot_example(mut example: FewShotExample) -> Result<FewShotExample, String> {
+    if example.user_text.trim().is_empty() || example.assistant_text.trim().is_empty() {
+        return Err("Пример должен содержать текст запроса и ответа".to_string());
+    }
+    let mut store = few_shot::load_few_shot_store();
+    example.id = uuid::Uuid::new_v4().to_string();
+    store.examples.push(example.clone());
+    few_shot::save_few_shot_store(&store)?;
+    Ok(example)
+}
+
+#[tauri::command]
+pub fn delete_few_shot_example(id: String) -> Result<(), String> {
+    let mut store = few_shot::load_few_shot_store();
+    let before = store.examples.len();
+    store.examples.retain(|e| e.id != id);
+    if store.examples.len() == before {
+        return Err(format!("Пример {} не найден", id));
+    }
+    few_shot::save_few_shot_store(&store)
+}
+
+/// Number of examples injected per request; 0 disables few-shot
+#[tauri::command]
+pub fn set_few_shot_k(k: u32) -> Result<(), String> {
+    let mut store = few_shot::load_few_shot_store();
+    store.k = k;
+    few_shot::save_few_shot_store(&store)
+}
+
 /// Mark onboarding as completed
 #[tauri::command]
 pub fn complete_onboarding() -> Result<(), String> {
@@ -433,12 +509,41 @@ pub fn export_chat(
     Ok(ExportSettingsResult::saved(path.display().to_string()))
 }
 
+/// Write everything stored about the user (profiles with masked keys, usage, apply
+/// history) to one JSON file; nothing stored is modified.
+#[tauri::command]
+pub async fn export_user_data(path: String) -> Result<(), String> {
+    crate::user_data::export_user_data(&path).await
+}
+
+/// Delete all stored keys, apply history, logs and settings files. `confirm` must be
+/// set explicitly; the running chat is cancelled first. Emits `wipe-complete`.
+#[tauri::command]
+pub async fn secure_wipe(
+    confirm: bool,
+    app_handle: AppHandle,
+    chat_state: tauri::State<'_, crate::commands::ai::ChatState>,
+) -> Result<(), String> {
+    if !confirm {
+        return Err("Удаление данных не подтверждено".to_string());
+    }
+    chat_state.cancel_active().await;
+    chat_state.pending_images.lock().await.clear();
+    chat_state.pending_files.lock().await.clear();
+
+    crate::user_data::secure_wipe().await?;
+    let _ = app_handle.emit("wipe-complete", ());
+    Ok(())
+}
+
 /// Import settings from JSON string, preserving credentials from current settings
 #[tauri::command]
 pub fn import_settings(json_data: String) -> Result<(), String> {
     let (imported_settings, imported_profiles) = parse_imported_settings(&json_data)?;
     settings::save_settings(&imported_settings)?;
-    llm_profiles::save_profiles(&imported_profiles)
+    llm_profiles::save_profiles(&imported_profiles)?;
+    crate::http_client::forget_cached_clients();
+    Ok(())
 }
 
 /// Validate a settings file before import.
@@ -455,7 +560,9 @@ pub fn import_settings_from_file(file_path: String) -> Result<(), String> {
     let json_data = read_import_settings_file(&file_path)?;
     let (imported_settings, imported_profiles) = parse_imported_settings(&json_data)?;
     settings::save_settings(&imported_settings)?;
-    llm_profiles::save_profiles(&imported_profiles)
+    llm_profiles::save_profiles(&imported_profiles)?;
+    crate::http_client::forget_cached_clients();
+    Ok(())
 }
 
 /// Check if Java is installed and available in PATH
@@ -558,15 +665,43 @@ mod tests {
                     model: "gpt-5".to_string(),
                     api_key_encrypted: "encrypted-secret".to_string(),
                     base_url: Some("https://api.example.com/v1".to_string()),
-                    max_tokens: 8000,
-                    temperature: 0.2,
+                    max_tokens: Some(8000),
+                    temperature: Some(0.2),
+                    top_p: Some(0.9),
+                    frequency_penalty: Some(0.1),
+                    presence_penalty: None,
+                    stop: vec!["КонецПроцедуры".to_string()],
+                    seed: Some(42),
+                    logit_bias: Default::default(),
                     context_window_override: Some(128_000),
+                    reasoning_model: None,
                     reasoning_effort: None,
                     enable_thinking: Some(true),
                     disable_streaming: Some(false),
                     stream_timeout_secs: Some(60),
+                    connect_timeout_secs: None,
+                    request_timeout_secs: None,
+                    max_reconnect_attempts: None,
+                    min_reconnect_delay_ms: None,
+                    max_response_bytes: None,
+                    smoothing_mode: crate::llm_profiles::SmoothingMode::None,
+                    enable_metrics: false,
+                    system_prompt: None,
+                    user_agent_override: None,
+                    answer_language: None,
+                    locale: crate::llm_profiles::SystemPromptLocale::Russian,
+                    system_prompt_placement: None,
+                    prompt_caching: false,
                     context_compress_strategy: "summarize".to_string(),
                     max_context_messages: Some(50),
+                    fallback_profiles: Vec::new(),
+                    sort_order: 0,
+                    favorite: false,
+                    target_platform_version: None,
+                    enabled: true,
+                    auto_language_match: true,
+                    proxy: None,
+                    tls: None,
                 },
                 LLMProfile {
                     id: "profile-2".to_string(),
@@ -575,15 +710,43 @@ mod tests {
                     model: "qwen3-coder".to_string(),
                     api_key_encrypted: "encrypted-qwen-secret".to_string(),
                     base_url: Some("https://chat.qwen.ai/api/v1".to_string()),
-                    max_tokens: 4096,
-                    temperature: 0.1,
+                    max_tokens: Some(4096),
+                    temperature: Some(0.1),
+                    top_p: None,
+                    frequency_penalty: None,
+                    presence_penalty: None,
+                    stop: Vec::new(),
+                    seed: None,
+                    logit_bias: Default::default(),
                     context_window_override: None,
+                    reasoning_model: None,
                     reasoning_effort: None,
                     enable_thinking: Some(false),
                     disable_streaming: Some(true),
                     stream_timeout_secs: Some(30),
+                    connect_timeout_secs: None,
+                    request_timeout_secs: None,
+                    max_reconnect_attempts: Some(1),
+                    min_reconnect_delay_ms: None,
+                    max_response_bytes: None,
+                    smoothing_mode: crate::llm_profiles::SmoothingMode::None,
+                    enable_metrics: false,
+                    system_prompt: None,
+                    user_agent_override: None,
+                    answer_language: None,
+                    locale: crate::llm_profiles::SystemPromptLocale::Russian,
+                    system_prompt_placement: None,
+                    prompt_caching: false,
                     context_compress_strategy: "disabled".to_string(),
                     max_context_messages: None,
+                    fallback_profiles: Vec::new(),
+                    sort_order: 0,
+                    favorite: false,
+                    target_platform_version: None,
+                    enabled: true,
+                    auto_language_match: true,
+                    proxy: None,
+                    tls: None,
                 },
             ],
             active_profile_id: "profile-2".to_string(),
@@ -659,7 +822,7 @@ mod tests {
             profile.base_url.as_deref(),
             Some("https://api.example.com/v1")
         );
-        assert_eq!(profile.temperature, 0.2);
+        assert_eq!(profile.temperature, Some(0.2));
         assert_eq!(profile.context_window_override, Some(128_000));
         assert_eq!(active_profile.id, "profile-2");
         assert_eq!(sanitized.active_profile_id, "profile-2");
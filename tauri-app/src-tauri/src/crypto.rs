@@ -12,9 +12,12 @@ use crate::settings::get_settings_dir;
 
 const NONCE_SIZE: usize = 12;
 
+/// Master key file in the settings directory
+pub(crate) const MASTER_KEY_FILE: &str = ".key";
+
 /// Get or create master key
 fn get_master_key() -> [u8; 32] {
-    let key_file = get_settings_dir().join(".key");
+    let key_file = get_settings_dir().join(MASTER_KEY_FILE);
 
     if key_file.exists() {
         if let Ok(data) = fs::read(&key_file) {
@@ -38,10 +41,109 @@ fn get_master_key() -> [u8; 32] {
     key
 }
 
+/// Salt mixed into the machine-bound key, so it differs from other apps hashing the same id
+const MACHINE_KEY_SALT: &[u8] = b"mini-ai-1c:profiles-at-rest:v1";
+
+/// Machine GUID kept by the OS: `MachineGuid` from the registry on Windows
+#[cfg(target_os = "windows")]
+fn read_machine_identifier() -> Result<String, String> {
+    use std::os::windows::process::CommandExt;
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+            "/reg:64",
+        ])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map_err(|e| format!("Не удалось прочитать MachineGuid: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["MachineGuid", _, guid] => Some(guid.to_string()),
+                _ => None,
+            },
+        )
+        .ok_or_else(|| "В реестре нет MachineGuid".to_string())
+}
+
+/// `IOPlatformUUID` of the platform expert device on macOS
+#[cfg(target_os = "macos")]
+fn read_machine_identifier() -> Result<String, String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .map_err(|e| format!("Не удалось прочитать IOPlatformUUID: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            (name.trim() == "\"IOPlatformUUID\"")
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+        .ok_or_else(|| "ioreg не вернул IOPlatformUUID".to_string())
+}
+
+/// `/etc/machine-id` (or the D-Bus copy of it) on Linux
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn read_machine_identifier() -> Result<String, String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .ok_or_else(|| "Не найден /etc/machine-id".to_string())
+}
+
+/// The OS machine id; read once per session. Without it there is no machine-bound key:
+/// a host name is neither secret nor stable enough to derive one from.
+fn machine_identifier() -> Result<String, String> {
+    static MACHINE_ID: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    if let Some(id) = MACHINE_ID.get() {
+        return Ok(id.clone());
+    }
+    let id = read_machine_identifier()?;
+    if id.is_empty() {
+        return Err("Пустой идентификатор компьютера".to_string());
+    }
+    Ok(MACHINE_ID.get_or_init(|| id).clone())
+}
+
+/// Key that needs no file next to the data: SHA-256 of the app salt and the machine id
+fn machine_key() -> Result<[u8; 32], String> {
+    use sha2::{Digest, Sha256};
+
+    let id = machine_identifier()
+        .map_err(|e| format!("Не удалось получить идентификатор компьютера: {}", e))?;
+    let mut hasher = Sha256::new();
+    hasher.update(MACHINE_KEY_SALT);
+    hasher.update(id.as_bytes());
+    Ok(hasher.finalize().into())
+}
+
 /// Encrypt a string
 pub fn encrypt_string(plaintext: &str) -> Result<String, String> {
-    let key = get_master_key();
-    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    encrypt_with_key(&get_master_key(), plaintext)
+}
+
+/// Decrypt a string
+pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
+    decrypt_with_key(&get_master_key(), encrypted)
+}
+
+/// Encrypt with the machine-bound key (data at rest that must survive a lost `.key` file)
+pub fn encrypt_for_machine(plaintext: &str) -> Result<String, String> {
+    encrypt_with_key(&machine_key()?, plaintext)
+}
+
+pub fn decrypt_for_machine(encrypted: &str) -> Result<String, String> {
+    decrypt_with_key(&machine_key()?, encrypted)
+}
+
+fn encrypt_with_key(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
 
     let mut nonce_bytes = [0u8; NONCE_SIZE];
     rand::thread_rng().fill(&mut nonce_bytes);
@@ -58,10 +160,8 @@ pub fn encrypt_string(plaintext: &str) -> Result<String, String> {
     Ok(STANDARD.encode(&combined))
 }
 
-/// Decrypt a string
-pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
-    let key = get_master_key();
-    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+fn decrypt_with_key(key: &[u8; 32], encrypted: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
 
     let combined = STANDARD.decode(encrypted).map_err(|e| e.to_string())?;
 
@@ -78,3 +178,30 @@ pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
 
     String::from_utf8(plaintext).map_err(|e| e.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_identifier_comes_from_the_os() {
+        let id = machine_identifier().unwrap();
+        assert!(!id.is_empty());
+        assert_eq!(machine_identifier().unwrap(), id);
+    }
+
+    #[test]
+    fn machine_key_round_trip_and_tamper_detection() {
+        let encrypted = encrypt_for_machine("{\"profiles\":[]}").unwrap();
+        assert_eq!(
+            decrypt_for_machine(&encrypted).unwrap(),
+            "{\"profiles\":[]}"
+        );
+
+        let mut bytes = STANDARD.decode(&encrypted).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        assert!(decrypt_for_machine(&STANDARD.encode(&bytes)).is_err());
+        assert!(decrypt_for_machine("not base64 at all").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Named sets of sampling parameters applied over the profile for a single request
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::ai::models::RequestOverrides;
+use crate::settings::get_settings_dir;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    pub name: String,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// `None` keeps the profile stop sequences
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    /// Shipped with the app; cannot be edited or deleted
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+impl GenerationPreset {
+    /// Fills the parameters the request did not set explicitly; request overrides win.
+    pub fn apply_to(&self, overrides: &mut RequestOverrides) {
+        if overrides.temperature.is_none() {
+            overrides.temperature = self.temperature;
+        }
+        if overrides.top_p.is_none() {
+            overrides.top_p = self.top_p;
+        }
+        if overrides.max_tokens.is_none() {
+            overrides.max_tokens = self.max_tokens;
+        }
+        if overrides.stop.is_none() {
+            overrides.stop = self.stop.clone();
+        }
+        if overrides.reasoning_effort.is_none() {
+            overrides.reasoning_effort = self.reasoning_effort.clone();
+        }
+        overrides.preset = Some(self.name.clone());
+    }
+}
+
+pub fn builtin_presets() -> Vec<GenerationPreset> {
+    let preset = |name: &str, temperature: f32, top_p: f32, max_tokens: u32| GenerationPreset {
+        name: name.to_string(),
+        temperature: Some(temperature),
+        top_p: Some(top_p),
+        max_tokens: Some(max_tokens),
+        stop: None,
+        reasoning_effort: None,
+        builtin: true,
+    };
+    vec![
+        preset("Точный рефакторинг", 0.0, 0.1, 8192),
+        preset("Сбалансированный", 0.4, 0.9, 4096),
+        preset("Мозговой штурм", 0.9, 1.0, 4096),
+    ]
+}
+
+fn get_presets_file() -> std::path::PathBuf {
+    get_settings_dir().join("generation_presets.json")
+}
+
+fn load_user_presets() -> Vec<GenerationPreset> {
+    fs::read_to_string(get_presets_file())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_user_presets(presets: &[GenerationPreset]) -> Result<(), String> {
+    let dir = get_settings_dir();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?;
+    fs::write(get_presets_file(), content).map_err(|e| e.to_string())
+}
+
+/// Built-in presets followed by the user ones
+pub fn load_presets() -> Vec<GenerationPreset> {
+    let mut presets = builtin_presets();
+    presets.extend(load_user_presets());
+    presets
+}
+
+pub fn find_preset(name: &str) -> Option<GenerationPreset> {
+    load_presets().into_iter().find(|p| p.name == name)
+}
+
+fn validate_preset(preset: &GenerationPreset) -> Result<(), String> {
+    if preset.name.trim().is_empty() {
+        return Err("Название пресета не может быть пустым".to_string());
+    }
+    if builtin_presets().iter().any(|p| p.name == preset.name) {
+        return Err(format!(
+            "Встроенный пресет «{}» нельзя изменить",
+            preset.name
+        ));
+    }
+    // Same limits as the profile itself
+    let mut overrides = RequestOverrides::default();
+    preset.apply_to(&mut overrides);
+    overrides
+        .apply_to(&crate::llm_profiles::LLMProfile::default_profile())
+        .map(|_| ())
+}
+
+/// Adds the preset or replaces the user preset with the same name
+pub fn save_preset(mut preset: GenerationPreset) -> Result<(), String> {
+    validate_preset(&preset)?;
+    preset.builtin = false;
+    let mut presets = load_user_presets();
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    save_user_presets(&presets)
+}
+
+pub fn delete_preset(name: &str) -> Result<(), String> {
+    if builtin_presets().iter().any(|p| p.name == name) {
+        return Err(format!("Встроенный пресет «{}» нельзя удалить", name));
+    }
+    let mut presets = load_user_presets();
+    let before = presets.len();
+    presets.retain(|p| p.name != name);
+    if presets.len() == before {
+        return Err(format!("Пресет не найден: {}", name));
+    }
+    save_user_presets(&presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_fills_only_unset_overrides() {
+        let preset = &builtin_presets()[0];
+        let mut overrides = RequestOverrides {
+            max_tokens: Some(512),
+            ..Default::default()
+        };
+        preset.apply_to(&mut overrides);
+
+        assert_eq!(overrides.temperature, Some(0.0));
+        assert_eq!(overrides.top_p, Some(0.1));
+        assert_eq!(overrides.max_tokens, Some(512));
+        assert_eq!(overrides.stop, None);
+        assert_eq!(overrides.preset.as_deref(), Some("Точный рефакторинг"));
+    }
+
+    #[test]
+    fn builtin_presets_are_valid_and_protected() {
+        for preset in builtin_presets() {
+            let mut overrides = RequestOverrides::default();
+            preset.apply_to(&mut overrides);
+            assert!(overrides
+                .apply_to(&crate::llm_profiles::LLMProfile::default_profile())
+                .is_ok());
+        }
+        assert!(validate_preset(&builtin_presets()[2]).is_err());
+
+        let invalid = GenerationPreset {
+            name: "Горячий".to_string(),
+            temperature: Some(3.0),
+            top_p: None,
+            max_tokens: None,
+            stop: None,
+            reasoning_effort: None,
+            builtin: false,
+        };
+        assert!(validate_preset(&invalid).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use tokio::sync::Mutex;
 
 #[derive(Debug, Clone)]
@@ -36,3 +36,159 @@ pub async fn pop_snapshot(hwnd: isize) -> Option<CodeSnapshot> {
         None
     }
 }
+
+/// How many applied suggestions can be rolled back
+const MAX_APPLY_RECORDS: usize = 20;
+
+/// AI-suggested code written to a file, with the content it replaced
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ApplyRecord {
+    pub file_path: String,
+    pub original_content: String,
+    pub applied_content: String,
+    pub timestamp_ms: u64,
+    /// Chat message the code came from
+    pub message_id: String,
+}
+
+lazy_static! {
+    static ref APPLY_RECORDS: std::sync::Mutex<VecDeque<ApplyRecord>> =
+        std::sync::Mutex::new(VecDeque::new());
+}
+
+/// Writes `code` over the file and remembers its previous content in `records`
+fn apply_to_file(
+    records: &std::sync::Mutex<VecDeque<ApplyRecord>>,
+    file_path: &str,
+    code: &str,
+    message_id: &str,
+) -> Result<ApplyRecord, String> {
+    let bytes = std::fs::read(file_path)
+        .map_err(|e| format!("Не удалось прочитать файл {}: {}", file_path, e))?;
+    // Rollback must restore the exact bytes, so non-UTF-8 exports are not touched
+    let original_content = String::from_utf8(bytes).map_err(|_| {
+        format!(
+            "Файл {} не в кодировке UTF-8 — автоматическое применение недоступно",
+            file_path
+        )
+    })?;
+    std::fs::write(file_path, code)
+        .map_err(|e| format!("Не удалось записать файл {}: {}", file_path, e))?;
+
+    let record = ApplyRecord {
+        file_path: file_path.to_string(),
+        original_content,
+        applied_content: code.to_string(),
+        timestamp_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        message_id: message_id.to_string(),
+    };
+    let mut guard = records.lock().unwrap_or_else(|e| e.into_inner());
+    guard.push_back(record.clone());
+    if guard.len() > MAX_APPLY_RECORDS {
+        guard.pop_front();
+    }
+    Ok(record)
+}
+
+/// Restores the file of the most recent record; returns its path. The record is
+/// dropped only once the file is written, so a failed rollback can be retried.
+fn rollback_last(records: &std::sync::Mutex<VecDeque<ApplyRecord>>) -> Result<String, String> {
+    let mut guard = records.lock().unwrap_or_else(|e| e.into_inner());
+    let record = guard.back().ok_or("Нет применённых изменений для отката")?;
+    std::fs::write(&record.file_path, record.original_content.as_bytes())
+        .map_err(|e| format!("Не удалось восстановить файл {}: {}", record.file_path, e))?;
+    let file_path = record.file_path.clone();
+    guard.pop_back();
+    Ok(file_path)
+}
+
+pub fn apply_code_to_file(
+    file_path: &str,
+    code: &str,
+    message_id: &str,
+) -> Result<ApplyRecord, String> {
+    apply_to_file(&APPLY_RECORDS, file_path, code, message_id)
+}
+
+/// Forgets the in-memory snapshots and apply records
+pub async fn clear_history() {
+    SNAPSHOTS.lock().await.clear();
+    APPLY_RECORDS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+pub fn rollback_last_apply() -> Result<String, String> {
+    rollback_last(&APPLY_RECORDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_then_rollback_restores_exact_bytes() {
+        let dir = std::env::temp_dir().join(format!("apply-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Module.bsl");
+        // BOM and CRLF line endings must survive the round trip
+        let original = "\u{feff}Процедура А()\r\n\tБ = 1;\r\nКонецПроцедуры\r\n";
+        std::fs::write(&path, original).unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let records = std::sync::Mutex::new(VecDeque::new());
+        let record =
+            apply_to_file(&records, path_str, "Процедура А()\nКонецПроцедуры\n", "m1").unwrap();
+        assert_eq!(record.message_id, "m1");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Процедура А()\nКонецПроцедуры\n"
+        );
+
+        assert_eq!(rollback_last(&records).unwrap(), path_str);
+        assert_eq!(std::fs::read(&path).unwrap(), original.as_bytes());
+        assert!(rollback_last(&records).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_rollback_keeps_the_record() {
+        let records = std::sync::Mutex::new(VecDeque::from([ApplyRecord {
+            file_path: std::env::temp_dir()
+                .join(format!("apply-missing-{}", std::process::id()))
+                .join("Module.bsl")
+                .to_string_lossy()
+                .into_owned(),
+            original_content: "v0".to_string(),
+            applied_content: "v1".to_string(),
+            timestamp_ms: 1,
+            message_id: "m1".to_string(),
+        }]));
+
+        assert!(rollback_last(&records).is_err());
+        assert_eq!(records.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_only_the_latest_records() {
+        let dir = std::env::temp_dir().join(format!("apply-cap-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Module.bsl");
+        std::fs::write(&path, "v0").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let records = std::sync::Mutex::new(VecDeque::new());
+        for i in 1..=MAX_APPLY_RECORDS + 5 {
+            apply_to_file(&records, path_str, &format!("v{}", i), "m").unwrap();
+        }
+        let guard = records.lock().unwrap();
+        assert_eq!(guard.len(), MAX_APPLY_RECORDS);
+        assert_eq!(guard.front().unwrap().original_content, "v5");
+        drop(guard);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,24 @@
+use crate::llm_profiles::{LLMProfile, LLMProvider, ProxyConfig, TlsConfig};
 use crate::settings::{load_settings, ProxyMode, ProxyProtocol, ProxySettings};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// `mini-ai-1c/<app version>`
+pub fn default_user_agent() -> String {
+    format!("mini-ai-1c/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// User-Agent sent for the profile: its override (corporate proxies) or the default
+pub fn effective_user_agent(profile: &LLMProfile) -> String {
+    profile
+        .user_agent_override
+        .as_deref()
+        .map(str::trim)
+        .filter(|ua| !ua.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(default_user_agent)
+}
 
 pub fn proxy_url_from_settings(settings: &ProxySettings) -> Result<Option<String>, String> {
     if settings.mode != ProxyMode::Custom {
@@ -57,7 +77,7 @@ pub fn custom_proxy_bypass_list() -> &'static str {
 pub fn client_builder_with_proxy_settings(
     settings: &ProxySettings,
 ) -> Result<reqwest::ClientBuilder, String> {
-    let builder = reqwest::Client::builder();
+    let builder = reqwest::Client::builder().user_agent(default_user_agent());
     match settings.mode {
         ProxyMode::System => Ok(builder),
         ProxyMode::Disabled => Ok(builder.no_proxy()),
@@ -78,6 +98,196 @@ pub fn client_builder_with_proxy_settings(
     }
 }
 
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// Overall limit of the models list request and the connection test
+pub const DEFAULT_MODELS_TIMEOUT_SECS: u64 = 30;
+
+/// Builder honouring the profile's own proxy (profiles without one use the app settings),
+/// TLS options and connect timeout
+pub fn client_builder_for_profile(profile: &LLMProfile) -> Result<reqwest::ClientBuilder, String> {
+    let connect_timeout = profile
+        .connect_timeout_secs
+        .map_or(DEFAULT_CONNECT_TIMEOUT_SECS, u64::from);
+    let builder = match profile.proxy.as_ref() {
+        Some(config) => {
+            let password = profile.try_get_proxy_password()?;
+            client_builder_with_proxy_config(config, &password)?
+        }
+        None => http_client_builder()?,
+    }
+    .connect_timeout(Duration::from_secs(connect_timeout));
+    match profile.tls.as_ref() {
+        Some(tls) => apply_tls_config(builder, tls),
+        None => Ok(builder),
+    }
+}
+
+/// Root certificates from a PEM file (one or a bundle)
+pub fn load_ca_certificates(path: &str) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = std::fs::read(path)
+        .map_err(|e| format!("Не удалось прочитать сертификат CA '{}': {}", path, e))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| format!("Некорректный PEM-сертификат CA '{}': {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("В файле '{}' нет PEM-сертификатов", path));
+    }
+    Ok(certificates)
+}
+
+pub fn apply_tls_config(
+    mut builder: reqwest::ClientBuilder,
+    tls: &TlsConfig,
+) -> Result<reqwest::ClientBuilder, String> {
+    if let Some(path) = tls.ca_cert_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        for certificate in load_ca_certificates(path.trim())? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if tls.danger_accept_invalid_certs {
+        crate::app_log!("[TLS] Certificate verification is disabled for this profile");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// The profile sends requests through its own proxy server (not the OS or app settings)
+pub fn profile_uses_explicit_proxy(profile: &LLMProfile) -> bool {
+    profile.proxy.as_ref().is_some_and(|config| {
+        let url = config.url.trim();
+        !url.is_empty() && !url.eq_ignore_ascii_case("system") && !url.eq_ignore_ascii_case("none")
+    })
+}
+
+pub fn client_builder_with_proxy_config(
+    config: &ProxyConfig,
+    password: &str,
+) -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder().user_agent(default_user_agent());
+    let url = config.url.trim();
+    if url.eq_ignore_ascii_case("system") {
+        return Ok(builder);
+    }
+    if url.is_empty() || url.eq_ignore_ascii_case("none") {
+        return Ok(builder.no_proxy());
+    }
+
+    let mut proxy = reqwest::Proxy::all(url).map_err(|error| {
+        redact_proxy_password(format!("Invalid proxy settings: {}", error), password)
+    })?;
+    if !config.username.is_empty() {
+        proxy = proxy.basic_auth(&config.username, password);
+    }
+    let bypass = if config.no_proxy.trim().is_empty() {
+        custom_proxy_bypass_list()
+    } else {
+        config.no_proxy.as_str()
+    };
+
+    Ok(builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(bypass))))
+}
+
+/// Client for non-streaming requests; limited overall only by `request_timeout_secs`
+pub fn build_profile_client(profile: &LLMProfile) -> Result<reqwest::Client, String> {
+    let mut builder = client_builder_for_profile(profile)?;
+    if let Some(secs) = profile.request_timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs.into()));
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Client for the models list and the connection test, with an overall timeout
+pub fn build_models_client(profile: &LLMProfile) -> Result<reqwest::Client, String> {
+    let timeout = profile
+        .request_timeout_secs
+        .map_or(DEFAULT_MODELS_TIMEOUT_SECS, u64::from);
+    client_builder_for_profile(profile)?
+        .timeout(Duration::from_secs(timeout))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Client for streaming chat: no overall timeout (thinking streams run for minutes),
+/// only connect and, for remote providers, a read-idle limit against dead connections
+pub fn build_stream_client(profile: &LLMProfile) -> Result<reqwest::Client, String> {
+    let mut builder = client_builder_for_profile(profile)?;
+    if !matches!(
+        profile.provider,
+        LLMProvider::Ollama | LLMProvider::LMStudio
+    ) {
+        builder = builder.read_timeout(Duration::from_secs(180));
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))
+}
+
+/// What a cached client is built for; each has its own timeouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientPurpose {
+    Stream,
+    Models,
+}
+
+/// Clients by profile id: a reused client keeps its connection pool, so repeated
+/// requests skip the TCP/TLS (and proxy CONNECT) handshakes
+static CLIENT_CACHE: OnceLock<Mutex<HashMap<(String, ClientPurpose), reqwest::Client>>> =
+    OnceLock::new();
+
+fn client_cache() -> &'static Mutex<HashMap<(String, ClientPurpose), reqwest::Client>> {
+    CLIENT_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Client for the profile from the cache, built on first use and after
+/// `forget_cached_clients`
+pub fn cached_profile_client(
+    profile: &LLMProfile,
+    purpose: ClientPurpose,
+) -> Result<reqwest::Client, String> {
+    let key = (profile.id.clone(), purpose);
+    if let Some(client) = client_cache()
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&key).cloned())
+    {
+        return Ok(client);
+    }
+
+    let client = match purpose {
+        ClientPurpose::Stream => build_stream_client(profile)?,
+        ClientPurpose::Models => build_models_client(profile)?,
+    };
+    if let Ok(mut cache) = client_cache().lock() {
+        cache.insert(key, client.clone());
+    }
+    Ok(client)
+}
+
+/// Drops all cached clients. Called when profiles or app settings are saved: a client
+/// is built from the profile's proxy (with its keychain password), TLS and timeouts,
+/// or from the app proxy settings
+pub fn forget_cached_clients() {
+    if let Ok(mut cache) = client_cache().lock() {
+        cache.clear();
+    }
+}
+
+/// Request error text that tells timeouts apart from other network failures
+pub fn describe_request_error(error: &reqwest::Error) -> String {
+    if !error.is_timeout() {
+        return error.to_string();
+    }
+    if error.is_connect() {
+        format!(
+            "Таймаут подключения (проверьте адрес и порт сервера): {}",
+            error
+        )
+    } else {
+        format!("Таймаут запроса: сервер не ответил вовремя: {}", error)
+    }
+}
+
 pub fn http_client_builder() -> Result<reqwest::ClientBuilder, String> {
     let settings = load_settings();
     client_builder_with_proxy_settings(&settings.proxy)
@@ -101,9 +311,14 @@ pub fn build_client_with_proxy_settings(
 #[cfg(test)]
 mod tests {
     use crate::http_client::{
-        build_client_with_proxy_settings, custom_proxy_bypass_list, proxy_url_from_settings,
+        apply_tls_config, build_client_with_proxy_settings, build_models_client,
+        cached_profile_client, client_builder_with_proxy_config, custom_proxy_bypass_list,
+        default_user_agent, describe_request_error, effective_user_agent, forget_cached_clients,
+        load_ca_certificates, profile_uses_explicit_proxy, proxy_url_from_settings, ClientPurpose,
     };
+    use crate::llm_profiles::{LLMProfile, ProxyConfig, TlsConfig};
     use crate::settings::{ProxyMode, ProxyProtocol, ProxySettings};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[test]
     fn custom_http_proxy_url_is_normalized_from_host_and_port() {
@@ -196,4 +411,246 @@ mod tests {
         assert!(bypass.contains("::1"));
         assert!(reqwest::NoProxy::from_string(bypass).is_some());
     }
+
+    #[test]
+    fn user_agent_override_wins_over_default() {
+        let mut profile = LLMProfile::default_profile();
+        assert_eq!(effective_user_agent(&profile), default_user_agent());
+        assert!(default_user_agent().starts_with("mini-ai-1c/"));
+
+        profile.user_agent_override = Some("CorpProxy/1.0".to_string());
+        assert_eq!(effective_user_agent(&profile), "CorpProxy/1.0");
+
+        profile.user_agent_override = Some("  ".to_string());
+        assert_eq!(effective_user_agent(&profile), default_user_agent());
+    }
+
+    #[tokio::test]
+    async fn client_sends_default_user_agent() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let _ = socket
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let settings = ProxySettings {
+            mode: ProxyMode::Disabled,
+            ..ProxySettings::default()
+        };
+        let client = build_client_with_proxy_settings(&settings).unwrap();
+        let _ = client.get(format!("http://{}/", addr)).send().await;
+
+        let request = server.await.unwrap();
+        assert!(request.contains(&format!(
+            "user-agent: {}",
+            default_user_agent().to_lowercase()
+        )));
+    }
+
+    fn proxy(url: &str) -> ProxyConfig {
+        ProxyConfig {
+            url: url.to_string(),
+            ..ProxyConfig::default()
+        }
+    }
+
+    #[test]
+    fn only_proxy_server_urls_count_as_explicit_proxy() {
+        let mut profile = LLMProfile::default_profile();
+        assert!(!profile_uses_explicit_proxy(&profile));
+
+        for url in ["system", "none", " "] {
+            profile.proxy = Some(proxy(url));
+            assert!(!profile_uses_explicit_proxy(&profile), "{url}");
+        }
+
+        profile.proxy = Some(proxy("socks5://127.0.0.1:1080"));
+        assert!(profile_uses_explicit_proxy(&profile));
+    }
+
+    #[test]
+    fn system_and_direct_profile_proxies_build_clients() {
+        for url in ["system", "none", ""] {
+            let client = client_builder_with_proxy_config(&proxy(url), "")
+                .and_then(|b| b.build().map_err(|e| e.to_string()));
+            assert!(client.is_ok(), "{url}: {:?}", client.err());
+        }
+    }
+
+    #[test]
+    fn invalid_profile_proxy_error_hides_password() {
+        let config = ProxyConfig {
+            username: "user".to_string(),
+            ..proxy("http://user:s3cret@[bad")
+        };
+        let err = client_builder_with_proxy_config(&config, "s3cret")
+            .err()
+            .expect("invalid url should fail");
+        assert!(!err.contains("s3cret"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn profile_proxy_receives_requests_with_credentials() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let _ = socket
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let config = ProxyConfig {
+            username: "user".to_string(),
+            ..proxy(&format!("http://{}", addr))
+        };
+        let client = client_builder_with_proxy_config(&config, "pass")
+            .unwrap()
+            .build()
+            .unwrap();
+        let _ = client.get("http://llm.example.test/v1/models").send().await;
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("get http://llm.example.test/v1/models"));
+        // base64("user:pass")
+        assert!(request.contains("proxy-authorization: basic dxnlcjpwyxnz"));
+    }
+
+    #[test]
+    fn ca_certificates_load_from_pem_and_reject_garbage() {
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/test_ca.pem");
+        assert_eq!(load_ca_certificates(fixture).unwrap().len(), 1);
+
+        let path = std::env::temp_dir().join(format!("bad-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, "not a certificate").unwrap();
+        let err = load_ca_certificates(path.to_str().unwrap()).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(err.contains("bad-ca-"), "{err}");
+
+        let tls = TlsConfig {
+            ca_cert_path: Some(fixture.to_string()),
+            danger_accept_invalid_certs: true,
+        };
+        let builder = apply_tls_config(reqwest::Client::builder(), &tls).unwrap();
+        assert!(builder.build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn silent_server_yields_request_timeout_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            drop(socket);
+        });
+
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(std::time::Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let error = client
+            .get(format!("http://{}/v1/models", addr))
+            .send()
+            .await
+            .unwrap_err();
+        server.abort();
+
+        assert!(describe_request_error(&error).starts_with("Таймаут запроса"));
+    }
+
+    fn direct_profile(id: &str) -> LLMProfile {
+        LLMProfile {
+            id: id.to_string(),
+            proxy: Some(proxy("none")),
+            ..LLMProfile::default_profile()
+        }
+    }
+
+    /// Serves keep-alive responses and counts accepted TCP connections
+    async fn counting_server() -> (
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    while matches!(socket.read(&mut buf).await, Ok(n) if n > 0) {
+                        let response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n[]";
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (addr, accepted)
+    }
+
+    #[tokio::test]
+    async fn cached_client_reuses_connections() {
+        const REQUESTS: usize = 20;
+        let (addr, accepted) = counting_server().await;
+        let url = format!("http://{}/v1/models", addr);
+        let profile = direct_profile("client-cache-test");
+
+        for _ in 0..REQUESTS {
+            let client = build_models_client(&profile).unwrap();
+            client
+                .get(&url)
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+        }
+        let fresh_connections = accepted.swap(0, std::sync::atomic::Ordering::SeqCst);
+
+        for _ in 0..REQUESTS {
+            let client = cached_profile_client(&profile, ClientPurpose::Models).unwrap();
+            client
+                .get(&url)
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+        }
+        let cached_connections = accepted.swap(0, std::sync::atomic::Ordering::SeqCst);
+
+        assert_eq!(fresh_connections, REQUESTS);
+        assert_eq!(cached_connections, 1);
+
+        // A saved profile or settings change drops the cache: a new connection pool
+        forget_cached_clients();
+        let client = cached_profile_client(&profile, ClientPurpose::Models).unwrap();
+        client
+            .get(&url)
+            .send()
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 1);
+        forget_cached_clients();
+    }
 }
@@ -0,0 +1,101 @@
+//! Small JSON documents in the settings directory (usage stats, profile health,
+//! model cache) with load-modify-save serialized per file.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::settings::get_settings_dir;
+
+pub struct JsonFile {
+    name: &'static str,
+    lock: Mutex<()>,
+}
+
+impl JsonFile {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        get_settings_dir().join(self.name)
+    }
+
+    /// A missing or broken file yields the default value
+    pub fn load<T: DeserializeOwned + Default>(&self) -> T {
+        match fs::read_to_string(self.path()) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                crate::app_log!("[Settings] Failed to parse {}: {}", self.name, e);
+                T::default()
+            }),
+            Err(_) => T::default(),
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<(), String> {
+        fs::create_dir_all(get_settings_dir()).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string(value).map_err(|e| e.to_string())?;
+        fs::write(self.path(), content).map_err(|e| e.to_string())
+    }
+
+    /// Load-modify-save under the file's lock
+    pub fn update<T>(&self, apply: impl FnOnce(&mut T)) -> Result<(), String>
+    where
+        T: Serialize + DeserializeOwned + Default,
+    {
+        self.update_if(|value| {
+            apply(value);
+            true
+        })
+    }
+
+    /// Same as `update`, but the file is written only when `apply` returns `true`
+    pub fn update_if<T>(&self, apply: impl FnOnce(&mut T) -> bool) -> Result<(), String>
+    where
+        T: Serialize + DeserializeOwned + Default,
+    {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut value = self.load();
+        if apply(&mut value) {
+            self.save(&value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_round_trips_and_update_if_can_skip_the_write() {
+        let dir = std::env::temp_dir().join(format!("json-file-test-{}", std::process::id()));
+        crate::settings::set_test_settings_dir(Some(dir.clone()));
+        let file = JsonFile::new("counters.json");
+
+        assert_eq!(file.load::<Vec<u32>>(), Vec::<u32>::new());
+        file.update(|v: &mut Vec<u32>| v.push(1)).unwrap();
+        file.update(|v: &mut Vec<u32>| v.push(2)).unwrap();
+        assert_eq!(file.load::<Vec<u32>>(), vec![1, 2]);
+
+        std::fs::remove_file(file.path()).unwrap();
+        file.update_if(|v: &mut Vec<u32>| {
+            v.push(3);
+            false
+        })
+        .unwrap();
+        assert!(!file.path().exists());
+
+        std::fs::write(file.path(), "not json").unwrap();
+        assert_eq!(file.load::<Vec<u32>>(), Vec::<u32>::new());
+
+        crate::settings::set_test_settings_dir(None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
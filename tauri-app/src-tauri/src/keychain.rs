@@ -0,0 +1,117 @@
+//! API keys in the OS keychain (Credential Manager, Keychain, Secret Service).
+//!
+//! Profiles store only a `keyring:<account>` reference. When the keychain is not
+//! available (e.g. Linux without a secret service) keys stay AES-encrypted in the
+//! profiles file and `fallback_warning` explains why.
+
+use std::sync::Mutex;
+
+use keyring::Entry;
+
+const SERVICE: &str = "mini-ai-1c";
+const REFERENCE_PREFIX: &str = "keyring:";
+
+static FALLBACK_WARNING: Mutex<Option<String>> = Mutex::new(None);
+
+fn account_for(profile_id: &str) -> String {
+    format!("api-key-{}", profile_id)
+}
+
+/// Value stored in the profile instead of the secret of `account`
+fn reference_for(account: &str) -> String {
+    format!("{}{}", REFERENCE_PREFIX, account)
+}
+
+/// Keychain account of a stored reference, `None` for an encrypted key
+pub fn parse_reference(stored: &str) -> Option<&str> {
+    stored
+        .strip_prefix(REFERENCE_PREFIX)
+        .filter(|account| !account.is_empty())
+}
+
+fn remember_failure(error: &str) {
+    let warning = format!(
+        "Системное хранилище ключей недоступно ({}). API-ключи сохраняются в зашифрованном файле настроек.",
+        error
+    );
+    crate::app_log!(force: true, "[Keychain] {}", warning);
+    *FALLBACK_WARNING.lock().unwrap_or_else(|e| e.into_inner()) = Some(warning);
+}
+
+fn store_secret(account: &str, secret: &str) -> Result<String, String> {
+    let result = Entry::new(SERVICE, account)
+        .and_then(|entry| entry.set_password(secret))
+        .map_err(|e| e.to_string());
+    match result {
+        Ok(()) => Ok(reference_for(account)),
+        Err(e) => {
+            remember_failure(&e);
+            Err(e)
+        }
+    }
+}
+
+/// Puts the key into the keychain and returns the reference to store in the profile
+pub fn store_api_key(profile_id: &str, api_key: &str) -> Result<String, String> {
+    store_secret(&account_for(profile_id), api_key)
+}
+
+fn proxy_account_for(profile_id: &str) -> String {
+    format!("proxy-password-{}", profile_id)
+}
+
+/// Same as `store_api_key` for the password of the profile's proxy
+pub fn store_proxy_password(profile_id: &str, password: &str) -> Result<String, String> {
+    store_secret(&proxy_account_for(profile_id), password)
+}
+
+/// Removes the API key and proxy password entries of a profile, whether or not they exist
+pub fn delete_profile_secrets(profile_id: &str) {
+    delete_api_key(&account_for(profile_id));
+    delete_api_key(&proxy_account_for(profile_id));
+}
+
+pub fn read_api_key(account: &str) -> Result<String, String> {
+    match Entry::new(SERVICE, account).and_then(|entry| entry.get_password()) {
+        Ok(key) => Ok(key),
+        Err(keyring::Error::NoEntry) => Ok(String::new()),
+        Err(e) => Err(format!(
+            "Не удалось прочитать API key из системного хранилища: {}",
+            e
+        )),
+    }
+}
+
+/// Removes a key no profile references any more; a missing entry is not an error
+pub fn delete_api_key(account: &str) {
+    if let Ok(entry) = Entry::new(SERVICE, account) {
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => crate::app_log!(force: true, "[Keychain] Failed to delete key: {}", e),
+        }
+    }
+}
+
+/// Why keys are kept in the settings file instead of the keychain, if they are
+pub fn fallback_warning() -> Option<String> {
+    FALLBACK_WARNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_round_trips_to_account() {
+        let reference = reference_for(&account_for("openai-main"));
+        assert_eq!(reference, "keyring:api-key-openai-main");
+        assert_eq!(parse_reference(&reference), Some("api-key-openai-main"));
+        // AES-encrypted keys are base64 and never carry the prefix
+        assert_eq!(parse_reference("q83vEjRWeJA="), None);
+        assert_eq!(parse_reference("keyring:"), None);
+        assert_eq!(parse_reference(""), None);
+    }
+}
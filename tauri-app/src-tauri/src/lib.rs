@@ -3,8 +3,10 @@
 //! AI-ассистент для разработки на платформе 1С:Предприятие
 
 mod ai;
+mod bsl_analysis;
 mod bsl_client;
 mod bsl_installer;
+mod chat_store;
 mod commands;
 #[cfg(windows)]
 mod configurator;
@@ -13,19 +15,26 @@ mod crypto;
 mod editor_bridge;
 #[cfg(windows)]
 mod editor_bridge_installer;
+mod generation_presets;
 mod history_manager;
 mod http_client;
 mod job_guard;
+mod json_file;
+mod keychain;
 mod llm;
+mod llm_profile_types;
 mod llm_profiles;
 mod logger;
 mod mcp_client;
+mod metrics;
 #[cfg(windows)]
 mod mouse_hook;
+mod platform_compat;
 #[cfg(windows)]
 mod scintilla;
 mod semantic_bridge;
 mod settings;
+mod user_data;
 
 use std::sync::Arc;
 
@@ -54,20 +63,80 @@ pub fn run() {
         .manage(crate::commands::ChatState::default())
         .invoke_handler(tauri::generate_handler![
             get_settings,
+            get_system_prompt,
+            set_system_prompt,
+            get_effective_user_id,
+            list_few_shot_examples,
+            add_few_shot_example,
+            delete_few_shot_example,
+            set_few_shot_k,
             save_settings,
             get_profiles,
+            list_profiles,
+            reorder_profiles,
+            create_profile,
+            update_profile,
+            get_keychain_warning,
+            get_profiles_load_error,
+            reset_profiles,
+            duplicate_profile,
+            export_profiles,
+            import_profiles,
             save_profile,
             delete_profile,
             set_active_profile,
+            get_generation_presets,
+            save_generation_preset,
+            delete_generation_preset,
             stream_chat,
             stop_chat,
+            attach_image,
+            clear_image_attachments,
+            attach_files,
+            read_and_attach_files,
+            attach_git_diff,
+            chat_structured,
+            suggest_commit_message,
+            apply_response_transforms,
+            clear_file_attachments,
             interrupt_chat,
             compact_context,
+            estimate_tokens,
+            tokenize_words,
+            get_last_request_id,
+            validate_prompt_template,
+            render_template,
+            render_saved_template,
+            one_shot,
+            compare_completions,
+            extract_code_from_completion,
+            explain_error,
+            analyse_stack_trace,
+            parse_error,
             approve_tool,
             reject_tool,
             undo_last_change,
             analyze_bsl,
             format_bsl,
+            diff_bsl_code,
+            apply_bsl_diff,
+            apply_bsl_code,
+            rollback_last_apply,
+            get_apply_history,
+            rollback_to,
+            list_modified_files,
+            analyze_bsl_complexity,
+            extract_annotations,
+            audit_bsl_security,
+            check_compatibility,
+            explain_security_findings,
+            extract_metadata_refs,
+            transliterate_identifiers,
+            detect_dead_code,
+            remove_dead_code,
+            generate_test_stubs,
+            generate_docs,
+            strip_bsl_docs,
             find_configurator_windows_cmd,
             set_configurator_rdp_mode,
             set_configurator_editor_bridge_enabled,
@@ -87,9 +156,24 @@ pub fn run() {
             // Hotkeys removed
             // LLM Utilities
             fetch_models_cmd,
+            get_model_entry,
+            get_rate_limit_status,
             fetch_models_from_provider,
             fetch_models_for_profile,
+            refresh_models,
             test_llm_connection_cmd,
+            get_profile_health,
+            reset_circuit,
+            run_health_checks,
+            get_total_usage,
+            get_usage_stats,
+            reset_usage_stats,
+            project_monthly_cost,
+            set_metrics_port,
+            get_metrics_url,
+            get_effective_user_agent,
+            detect_provider_from_url,
+            get_profile_schema,
             // BSL Utilities
             check_bsl_status_cmd,
             install_bsl_ls_cmd,
@@ -110,6 +194,9 @@ pub fn run() {
             get_mcp_server_statuses,
             get_mcp_server_logs,
             save_debug_logs,
+            get_log_path,
+            get_recent_logs,
+            set_log_level,
             write_frontend_log,
             delete_search_index,
             open_search_index_dir,
@@ -128,6 +215,8 @@ pub fn run() {
             // Settings export/import
             commands::settings::export_chat,
             commands::settings::export_settings,
+            commands::settings::export_user_data,
+            commands::settings::secure_wipe,
             commands::settings::import_settings,
             commands::settings::validate_import_settings_file,
             commands::settings::import_settings_from_file,
@@ -150,6 +239,11 @@ pub fn run() {
             quick_chat_invoke,
         ])
         .setup(|app| {
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                crate::logger::init_tracing(&app_data_dir);
+            }
+            crate::metrics::sync_with_active_profile();
+
             // Setup Tray Icon with context menu
             let quit_item = MenuItem::with_id(app, "quit", "Выход", true, None::<&str>)?;
             let tray_menu = Menu::with_items(app, &[&quit_item])?;
@@ -194,14 +288,28 @@ pub fn run() {
             // Handle window close: hide tray icon then exit cleanly
             if let Some(main_window) = app.get_webview_window("main") {
                 let app_handle = app.handle().clone();
-                main_window.on_window_event(move |event| {
-                    if let WindowEvent::CloseRequested { .. } = event {
+                main_window.on_window_event(move |event| match event {
+                    WindowEvent::CloseRequested { .. } => {
                         // Hide tray icon before exit to prevent ghost icon in Windows tray
                         if let Some(tray) = app_handle.tray_by_id("main-tray") {
                             let _ = tray.set_visible(false);
                         }
                         app_handle.exit(0);
                     }
+                    WindowEvent::Destroyed => {
+                        // Stop any in-flight stream owned by this window; dropping the task
+                        // also drops its HTTP client and closes pooled connections.
+                        // Spawned: blocking the event loop on the chat state locks could
+                        // deadlock with a task that is waiting for that loop.
+                        let app_handle = app_handle.clone();
+                        tauri::async_runtime::spawn(async move {
+                            app_handle
+                                .state::<crate::commands::ChatState>()
+                                .cancel_for_window("main")
+                                .await;
+                        });
+                    }
+                    _ => {}
                 });
             }
 
@@ -237,6 +345,7 @@ pub fn run() {
             }
             // Start settings watcher for reactive MCP
             crate::mcp_client::start_settings_watcher(app.handle().clone());
+            crate::llm_profiles::start_profiles_watcher(app.handle().clone());
 
             // Install global mouse hook to detect right-click on 1C Configurator
             #[cfg(windows)]
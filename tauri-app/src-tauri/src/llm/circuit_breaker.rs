@@ -0,0 +1,277 @@
+//! Circuit breaker per profile: after `AppSettings::circuit_breaker_threshold` outage
+//! failures in a row the profile is "open" for the cool-down and chats fail fast with
+//! `CircuitOpen` (the fallback chain takes over at once). The first request after the
+//! cool-down is a probe: success closes the circuit, a failure opens it again.
+//!
+//! Kept in memory only: a restart starts with every circuit closed.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::ai::error::AiClientError;
+
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+pub const DEFAULT_COOLDOWN_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    Open,
+    /// The probe request is in flight
+    HalfOpen,
+}
+
+/// Circuit of a profile, as shown in the health table
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// End of the cool-down (`Open`) or of the probe's grace time (`HalfOpen`)
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BreakerConfig {
+    /// 0 disables the breaker
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl BreakerConfig {
+    fn from_settings() -> Self {
+        let settings = crate::settings::load_settings();
+        Self {
+            threshold: settings
+                .circuit_breaker_threshold
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            cooldown: Duration::seconds(
+                settings
+                    .circuit_breaker_cooldown_secs
+                    .unwrap_or(DEFAULT_COOLDOWN_SECS) as i64,
+            ),
+        }
+    }
+}
+
+static CIRCUITS: OnceLock<Mutex<HashMap<String, CircuitStatus>>> = OnceLock::new();
+
+fn circuits() -> &'static Mutex<HashMap<String, CircuitStatus>> {
+    CIRCUITS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Provider-side trouble that the breaker counts: retryable errors except the breaker's
+/// own rejections; rejected keys, bad requests and cancellations say nothing about an outage
+fn is_outage(error: &AiClientError) -> bool {
+    !matches!(error, AiClientError::CircuitOpen { .. }) && error.is_retryable()
+}
+
+/// Errors raised before the provider was asked; they leave the circuit as it is
+fn is_local(error: &AiClientError) -> bool {
+    matches!(
+        error,
+        AiClientError::NoActiveProfile
+            | AiClientError::InvalidProfile { .. }
+            | AiClientError::Cancelled
+            | AiClientError::CircuitOpen { .. }
+    )
+}
+
+fn admit(
+    circuit: &mut CircuitStatus,
+    config: BreakerConfig,
+    now: DateTime<Utc>,
+) -> Result<(), DateTime<Utc>> {
+    match (circuit.state, circuit.retry_at) {
+        (CircuitState::Closed, _) => Ok(()),
+        (_, Some(retry_at)) if now < retry_at => Err(retry_at),
+        // Cool-down over, or the previous probe never reported back
+        _ => {
+            circuit.state = CircuitState::HalfOpen;
+            circuit.retry_at = Some(now + config.cooldown);
+            Ok(())
+        }
+    }
+}
+
+fn apply_result(
+    circuit: &mut CircuitStatus,
+    failed: bool,
+    config: BreakerConfig,
+    now: DateTime<Utc>,
+) {
+    if !failed {
+        *circuit = CircuitStatus::default();
+        return;
+    }
+    circuit.consecutive_failures += 1;
+    let trips = circuit.state == CircuitState::HalfOpen
+        || (config.threshold > 0 && circuit.consecutive_failures >= config.threshold);
+    if trips {
+        circuit.state = CircuitState::Open;
+        circuit.retry_at = Some(now + config.cooldown);
+    }
+}
+
+/// `CircuitOpen` while the profile is cooling down; otherwise lets the request through
+/// (after the cool-down as the half-open probe)
+pub fn check(profile_id: &str, profile_name: &str) -> Result<(), AiClientError> {
+    let config = BreakerConfig::from_settings();
+    let mut circuits = circuits().lock().unwrap_or_else(|e| e.into_inner());
+    let circuit = circuits.entry(profile_id.to_string()).or_default();
+    admit(circuit, config, Utc::now()).map_err(|retry_at| AiClientError::CircuitOpen {
+        profile_id: profile_id.to_string(),
+        retry_at,
+        message: format!(
+            "Профиль «{}» временно отключён после серии сбоев провайдера; \
+            повторная попытка после {}",
+            profile_name,
+            retry_at.with_timezone(&chrono::Local).format("%H:%M:%S")
+        ),
+    })
+}
+
+/// Counts the outcome of a request that passed `check`
+pub fn record<T>(profile_id: &str, result: &Result<T, AiClientError>) {
+    let failed = match result {
+        Ok(_) => false,
+        Err(error) if is_local(error) => return,
+        Err(error) => is_outage(error),
+    };
+    let config = BreakerConfig::from_settings();
+    let mut circuits = circuits().lock().unwrap_or_else(|e| e.into_inner());
+    let circuit = circuits.entry(profile_id.to_string()).or_default();
+    let before = circuit.state;
+    apply_result(circuit, failed, config, Utc::now());
+    if circuit.state != before {
+        crate::app_log!(
+            force: true,
+            "[AI][CIRCUIT] profile {}: {:?} -> {:?} after {} failure(s)",
+            profile_id,
+            before,
+            circuit.state,
+            circuit.consecutive_failures
+        );
+    }
+}
+
+pub fn status(profile_id: &str) -> CircuitStatus {
+    circuits()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(profile_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Closes the circuit by hand
+pub fn reset(profile_id: &str) {
+    circuits()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(profile_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig {
+            threshold: 3,
+            cooldown: Duration::seconds(30),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-05-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures_and_fails_fast() {
+        let mut circuit = CircuitStatus::default();
+        for _ in 0..2 {
+            assert!(admit(&mut circuit, config(), now()).is_ok());
+            apply_result(&mut circuit, true, config(), now());
+        }
+        assert_eq!(circuit.state, CircuitState::Closed);
+        // A success in between starts the count over
+        apply_result(&mut circuit, false, config(), now());
+        assert_eq!(circuit.consecutive_failures, 0);
+
+        for _ in 0..3 {
+            apply_result(&mut circuit, true, config(), now());
+        }
+        assert_eq!(circuit.state, CircuitState::Open);
+        let retry_at = now() + Duration::seconds(30);
+        assert_eq!(
+            admit(&mut circuit, config(), now() + Duration::seconds(10)),
+            Err(retry_at)
+        );
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens() {
+        let mut circuit = CircuitStatus {
+            state: CircuitState::Open,
+            consecutive_failures: 3,
+            retry_at: Some(now()),
+        };
+        let later = now() + Duration::seconds(1);
+        assert!(admit(&mut circuit, config(), later).is_ok());
+        assert_eq!(circuit.state, CircuitState::HalfOpen);
+        // Only one probe at a time
+        assert!(admit(&mut circuit, config(), later).is_err());
+
+        apply_result(&mut circuit, true, config(), later);
+        assert_eq!(circuit.state, CircuitState::Open);
+        assert_eq!(circuit.retry_at, Some(later + Duration::seconds(30)));
+
+        let after_cooldown = later + Duration::seconds(31);
+        assert!(admit(&mut circuit, config(), after_cooldown).is_ok());
+        apply_result(&mut circuit, false, config(), after_cooldown);
+        assert_eq!(circuit, CircuitStatus::default());
+    }
+
+    #[test]
+    fn only_provider_outages_count() {
+        assert!(is_outage(&AiClientError::Network {
+            message: String::new()
+        }));
+        assert!(!is_outage(&AiClientError::parse("bad json")));
+        assert!(!is_outage(&AiClientError::CircuitOpen {
+            profile_id: String::new(),
+            retry_at: Utc::now(),
+            message: String::new(),
+        }));
+        assert!(is_local(&AiClientError::Cancelled));
+
+        let id = "circuit-test-profile";
+        let disabled = BreakerConfig {
+            threshold: 0,
+            ..config()
+        };
+        let mut circuit = CircuitStatus::default();
+        for _ in 0..10 {
+            apply_result(&mut circuit, true, disabled, now());
+        }
+        assert_eq!(circuit.state, CircuitState::Closed);
+
+        circuits().lock().unwrap().insert(
+            id.to_string(),
+            CircuitStatus {
+                state: CircuitState::Open,
+                ..circuit
+            },
+        );
+        assert_eq!(status(id).state, CircuitState::Open);
+        reset(id);
+        assert_eq!(status(id), CircuitStatus::default());
+    }
+}
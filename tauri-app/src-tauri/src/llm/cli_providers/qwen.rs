@@ -43,13 +43,28 @@ fn generate_code_challenge(code_verifier: &str) -> String {
     URL_SAFE_NO_PAD.encode(hash)
 }
 
+/// Client through the profile's proxy and TLS settings; the app-level proxy when the
+/// profile is not saved yet
+fn client_for_profile(profile_id: Option<&str>) -> Result<reqwest::Client, String> {
+    let profile = profile_id.and_then(|id| {
+        crate::llm_profiles::load_profiles()
+            .profiles
+            .into_iter()
+            .find(|p| p.id == id)
+    });
+    match profile {
+        Some(profile) => crate::http_client::build_profile_client(&profile),
+        None => crate::http_client::build_http_client(),
+    }
+}
+
 pub struct QwenCliProvider;
 
 impl QwenCliProvider {
     // ── Auth ─────────────────────────────────────────────────────────────────
 
-    pub async fn auth_start() -> Result<CliAuthInitResponse, String> {
-        let client = crate::http_client::build_http_client()?;
+    pub async fn auth_start(profile_id: Option<&str>) -> Result<CliAuthInitResponse, String> {
+        let client = client_for_profile(profile_id)?;
 
         let code_verifier = generate_code_verifier();
         let code_challenge = generate_code_challenge(&code_verifier);
@@ -102,8 +117,9 @@ impl QwenCliProvider {
     pub async fn auth_poll(
         device_code: &str,
         code_verifier: Option<&str>,
+        profile_id: Option<&str>,
     ) -> Result<CliAuthStatus, String> {
-        let client = crate::http_client::build_http_client()?;
+        let client = client_for_profile(profile_id)?;
 
         let mut params = std::collections::HashMap::new();
         params.insert("client_id", CLIENT_ID);
@@ -216,7 +232,7 @@ impl QwenCliProvider {
     }
 
     pub async fn refresh_access_token(profile_id: &str, refresh_token: &str) -> Result<(), String> {
-        let client = crate::http_client::build_http_client()?;
+        let client = client_for_profile(Some(profile_id))?;
 
         let mut params = std::collections::HashMap::new();
         params.insert("client_id", CLIENT_ID);
@@ -1,2 +1,5 @@
+pub mod circuit_breaker;
 pub mod cli_providers;
+pub mod model_cache;
+pub mod profile_health;
 pub mod providers;
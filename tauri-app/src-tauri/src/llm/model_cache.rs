@@ -0,0 +1,282 @@
+//! Last successful model list per profile (`model_cache.json` in the settings
+//! directory), so the settings dialog does not refetch hundreds of models on
+//! every open and still shows something offline.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::providers::Model;
+use crate::ai::ModelEntry;
+use crate::json_file::JsonFile;
+use crate::llm_profiles::LLMProfile;
+
+/// Used when `model_cache_ttl_secs` is not set: one day
+pub const DEFAULT_MODEL_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCacheEntry<T = Model> {
+    /// Provider and base URL the list was fetched from; a profile that no longer
+    /// matches them does not use the entry
+    pub provider: String,
+    pub base_url: String,
+    /// Unix time in seconds
+    pub fetched_at: u64,
+    pub models: Vec<T>,
+}
+
+impl<T> ModelCacheEntry<T> {
+    fn new(profile: &LLMProfile, models: Vec<T>, now: u64) -> Self {
+        Self {
+            provider: profile.provider.to_string(),
+            base_url: profile.get_base_url(),
+            fetched_at: now,
+            models,
+        }
+    }
+
+    fn matches(&self, profile: &LLMProfile) -> bool {
+        self.provider == profile.provider.to_string() && self.base_url == profile.get_base_url()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelCache {
+    /// By profile id
+    pub entries: HashMap<String, ModelCacheEntry>,
+    /// Raw `/models` entries with their metadata, by profile id
+    #[serde(default)]
+    pub model_entries: HashMap<String, ModelCacheEntry<ModelEntry>>,
+}
+
+/// Model list for a profile as returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileModels {
+    pub models: Vec<Model>,
+    /// Served from an expired cache entry because the request failed
+    pub stale: bool,
+    /// When the list was fetched (unix seconds)
+    pub fetched_at: u64,
+}
+
+impl ModelCache {
+    /// Entry for the profile if it was fetched with the profile's current provider and base URL
+    pub fn get(&self, profile: &LLMProfile) -> Option<&ModelCacheEntry> {
+        self.entries
+            .get(&profile.id)
+            .filter(|entry| entry.matches(profile))
+    }
+
+    /// Entry younger than `ttl_secs` at `now`
+    pub fn fresh(&self, profile: &LLMProfile, now: u64, ttl_secs: u64) -> Option<&ModelCacheEntry> {
+        self.get(profile)
+            .filter(|entry| now.saturating_sub(entry.fetched_at) < ttl_secs)
+    }
+
+    pub fn insert(&mut self, profile: &LLMProfile, models: Vec<Model>, now: u64) {
+        self.entries.insert(
+            profile.id.clone(),
+            ModelCacheEntry::new(profile, models, now),
+        );
+    }
+
+    pub fn model_entries_of(&self, profile: &LLMProfile) -> Option<&ModelCacheEntry<ModelEntry>> {
+        self.model_entries
+            .get(&profile.id)
+            .filter(|entry| entry.matches(profile))
+    }
+
+    pub fn model_entry(&self, profile: &LLMProfile, model_id: &str) -> Option<&ModelEntry> {
+        self.model_entries_of(profile)?
+            .models
+            .iter()
+            .find(|m| m.id == model_id)
+    }
+
+    pub fn insert_model_entries(
+        &mut self,
+        profile: &LLMProfile,
+        models: Vec<ModelEntry>,
+        now: u64,
+    ) {
+        self.model_entries.insert(
+            profile.id.clone(),
+            ModelCacheEntry::new(profile, models, now),
+        );
+    }
+
+    /// Drops entries of deleted profiles and of profiles whose provider or base URL changed
+    pub fn retain_valid(&mut self, profiles: &[LLMProfile]) -> bool {
+        let before = self.entries.len() + self.model_entries.len();
+        let profile_of = |id: &String| profiles.iter().find(|p| &p.id == id);
+        self.entries
+            .retain(|id, entry| profile_of(id).is_some_and(|p| entry.matches(p)));
+        self.model_entries
+            .retain(|id, entry| profile_of(id).is_some_and(|p| entry.matches(p)));
+        self.entries.len() + self.model_entries.len() != before
+    }
+}
+
+static CACHE_FILE: JsonFile = JsonFile::new("model_cache.json");
+
+/// Load the cache; a missing or broken file yields an empty one
+pub fn load_model_cache() -> ModelCache {
+    CACHE_FILE.load()
+}
+
+/// Removes entries that no longer match the stored profiles
+pub fn invalidate_outdated(profiles: &[LLMProfile]) {
+    let result = CACHE_FILE.update_if(|cache: &mut ModelCache| cache.retain_valid(profiles));
+    if let Err(e) = result {
+        crate::app_log!("[ModelCache] Failed to save cache: {}", e);
+    }
+}
+
+/// Replaces the cached `/models` entries of the profile
+pub fn store_model_entries(profile: &LLMProfile, models: &[ModelEntry]) {
+    let now = now_secs();
+    let result = CACHE_FILE
+        .update(|cache: &mut ModelCache| cache.insert_model_entries(profile, models.to_vec(), now));
+    if let Err(e) = result {
+        crate::app_log!("[ModelCache] Failed to save cache: {}", e);
+    }
+}
+
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Cached list when it is fresh (unless `force_refresh`), otherwise `fetch`; when the fetch
+/// fails an expired entry is served with `stale: true`.
+pub async fn models_with_cache<F, Fut>(
+    profile: &LLMProfile,
+    ttl_secs: u64,
+    force_refresh: bool,
+    fetch: F,
+) -> Result<ProfileModels, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<Model>, String>>,
+{
+    let cache = load_model_cache();
+    let now = now_secs();
+    if !force_refresh {
+        if let Some(entry) = cache.fresh(profile, now, ttl_secs) {
+            return Ok(ProfileModels {
+                models: entry.models.clone(),
+                stale: false,
+                fetched_at: entry.fetched_at,
+            });
+        }
+    }
+
+    match fetch().await {
+        Ok(models) => {
+            let result = CACHE_FILE
+                .update(|cache: &mut ModelCache| cache.insert(profile, models.clone(), now));
+            if let Err(e) = result {
+                crate::app_log!("[ModelCache] Failed to save cache: {}", e);
+            }
+            Ok(ProfileModels {
+                models,
+                stale: false,
+                fetched_at: now,
+            })
+        }
+        Err(e) => match cache.get(profile) {
+            Some(entry) => {
+                crate::app_log!(
+                    "[ModelCache] Fetch failed for {}, serving cached list: {}",
+                    profile.id,
+                    e
+                );
+                Ok(ProfileModels {
+                    models: entry.models.clone(),
+                    stale: true,
+                    fetched_at: entry.fetched_at,
+                })
+            }
+            None => Err(e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_profiles::LLMProvider;
+
+    fn model(id: &str) -> Model {
+        Model {
+            id: id.to_string(),
+            name: id.to_string(),
+            context_window: 128_000,
+            description: None,
+            cost_in: None,
+            cost_out: None,
+        }
+    }
+
+    #[test]
+    fn entry_expires_after_ttl() {
+        let profile = LLMProfile::default_profile();
+        let mut cache = ModelCache::default();
+        cache.insert(&profile, vec![model("gpt-5")], 1_000);
+
+        assert!(cache.fresh(&profile, 1_500, 600).is_some());
+        assert!(cache.fresh(&profile, 1_600, 600).is_none());
+        assert!(cache.get(&profile).is_some());
+    }
+
+    #[test]
+    fn changing_provider_or_base_url_invalidates_entry() {
+        let profile = LLMProfile::default_profile();
+        let mut cache = ModelCache::default();
+        cache.insert(&profile, vec![model("gpt-5")], 1_000);
+
+        let mut moved = profile.clone();
+        moved.base_url = Some("https://proxy.example.com/v1".to_string());
+        assert!(cache.get(&moved).is_none());
+
+        let mut switched = profile.clone();
+        switched.provider = LLMProvider::OpenRouter;
+        assert!(cache.get(&switched).is_none());
+
+        assert!(!cache.retain_valid(std::slice::from_ref(&profile)));
+        assert!(cache.retain_valid(&[moved]));
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn model_entries_follow_the_profile() {
+        let profile = LLMProfile::default_profile();
+        let mut cache = ModelCache::default();
+        cache.insert_model_entries(
+            &profile,
+            vec![ModelEntry {
+                id: "gpt-5".to_string(),
+                context_window: Some(400_000),
+                max_completion_tokens: Some(128_000),
+                created: None,
+            }],
+            1_000,
+        );
+
+        assert_eq!(
+            cache
+                .model_entry(&profile, "gpt-5")
+                .and_then(|m| m.context_window),
+            Some(400_000)
+        );
+        assert!(cache.model_entry(&profile, "gpt-4o").is_none());
+
+        let mut moved = profile.clone();
+        moved.base_url = Some("https://proxy.example.com/v1".to_string());
+        assert!(cache.model_entry(&moved, "gpt-5").is_none());
+        assert!(cache.retain_valid(&[moved]));
+        assert!(cache.model_entries.is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+//! Last connection test / chat outcome and time to first token per profile
+//! (`profile_health.json` next to the profiles), so the settings show which
+//! profiles work without testing each one by hand.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::json_file::JsonFile;
+use crate::llm_profiles::{LLMProfile, LLMProvider};
+
+/// Time-to-first-token samples in the rolling average
+pub const TTFT_WINDOW: usize = 20;
+
+/// Bound of a single check in `run_health_checks`
+pub const HEALTH_CHECK_TIMEOUT_SECS: u64 = 15;
+
+static HEALTH_FILE: JsonFile = JsonFile::new("profile_health.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthSource {
+    Test,
+    Chat,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileHealth {
+    /// Outcome of the last test or chat attempt; `None` before the first one
+    pub ok: Option<bool>,
+    pub error: Option<String>,
+    pub source: Option<HealthSource>,
+    /// Unix time in seconds
+    pub checked_at: Option<u64>,
+    /// Duration of the last connection test
+    pub latency_ms: Option<u64>,
+    /// Last `TTFT_WINDOW` streamed chats, oldest first
+    #[serde(default)]
+    pub ttft_samples_ms: Vec<u64>,
+    /// The last failure was a rejected key (401/403)
+    #[serde(default)]
+    pub auth_failed: bool,
+}
+
+impl ProfileHealth {
+    pub fn avg_ttft_ms(&self) -> Option<u64> {
+        if self.ttft_samples_ms.is_empty() {
+            return None;
+        }
+        Some(self.ttft_samples_ms.iter().sum::<u64>() / self.ttft_samples_ms.len() as u64)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthStore {
+    /// By profile id
+    pub entries: HashMap<String, ProfileHealth>,
+}
+
+impl HealthStore {
+    pub fn record(
+        &mut self,
+        profile_id: &str,
+        source: HealthSource,
+        result: Result<(), &str>,
+        now: u64,
+    ) -> &mut ProfileHealth {
+        let entry = self.entries.entry(profile_id.to_string()).or_default();
+        entry.ok = Some(result.is_ok());
+        entry.error = result.err().map(str::to_string);
+        entry.source = Some(source);
+        entry.checked_at = Some(now);
+        entry.auth_failed = false;
+        entry
+    }
+
+    pub fn record_ttft(&mut self, profile_id: &str, ttft_ms: u64) {
+        let samples = &mut self
+            .entries
+            .entry(profile_id.to_string())
+            .or_default()
+            .ttft_samples_ms;
+        samples.push(ttft_ms);
+        if samples.len() > TTFT_WINDOW {
+            samples.drain(..samples.len() - TTFT_WINDOW);
+        }
+    }
+}
+
+/// Row of `get_profile_health`, one per profile in list order
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileHealthRow {
+    pub profile_id: String,
+    pub profile_name: String,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub health: ProfileHealth,
+    pub avg_ttft_ms: Option<u64>,
+    /// In-memory circuit breaker state
+    pub circuit: super::circuit_breaker::CircuitStatus,
+}
+
+pub fn health_table(store: &HealthStore, profiles: &[LLMProfile]) -> Vec<ProfileHealthRow> {
+    profiles
+        .iter()
+        .map(|p| {
+            let health = store.entries.get(&p.id).cloned().unwrap_or_default();
+            ProfileHealthRow {
+                profile_id: p.id.clone(),
+                profile_name: p.name.clone(),
+                enabled: p.enabled,
+                avg_ttft_ms: health.avg_ttft_ms(),
+                circuit: super::circuit_breaker::status(&p.id),
+                health,
+            }
+        })
+        .collect()
+}
+
+/// Load the records; a missing or broken file yields an empty store
+pub fn load_health() -> HealthStore {
+    HEALTH_FILE.load()
+}
+
+fn update_health(apply: impl FnOnce(&mut HealthStore, u64)) {
+    let now = unix_now();
+    if let Err(e) = HEALTH_FILE.update(|store| apply(store, now)) {
+        crate::app_log!("[ProfileHealth] Failed to save health file: {}", e);
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Outcome of a chat attempt with the profile; `ttft_ms` for streamed answers
+pub fn record_chat(profile_id: &str, result: Result<(), &str>, ttft_ms: Option<u64>) {
+    update_health(|store, now| {
+        store.record(profile_id, HealthSource::Chat, result, now);
+        if let Some(ttft_ms) = ttft_ms {
+            store.record_ttft(profile_id, ttft_ms);
+        }
+    });
+}
+
+/// Outcome of a connection test
+pub fn record_test(profile_id: &str, result: Result<(), &str>, latency_ms: u64) {
+    update_health(|store, now| {
+        store
+            .record(profile_id, HealthSource::Test, result, now)
+            .latency_ms = Some(latency_ms);
+    });
+}
+
+/// Flags the outcome just recorded for the profile as a rejected key
+pub fn mark_auth_failed(profile_id: &str) {
+    update_health(|store, _| {
+        store
+            .entries
+            .entry(profile_id.to_string())
+            .or_default()
+            .auth_failed = true;
+    });
+}
+
+/// Drops records of deleted profiles
+pub fn retain_profiles(profiles: &[LLMProfile]) {
+    let result = HEALTH_FILE.update_if(|store: &mut HealthStore| {
+        let before = store.entries.len();
+        store
+            .entries
+            .retain(|id, _| profiles.iter().any(|p| &p.id == id));
+        store.entries.len() != before
+    });
+    if let Err(e) = result {
+        crate::app_log!("[ProfileHealth] Failed to save health file: {}", e);
+    }
+}
+
+/// CLI providers have no `/models` endpoint to probe; their health comes from chats only
+pub fn is_checkable(profile: &LLMProfile) -> bool {
+    profile.enabled
+        && !matches!(
+            profile.provider,
+            LLMProvider::CodexCli | LLMProvider::QwenCli | LLMProvider::OneCNaparnik
+        )
+}
+
+/// Tests every enabled HTTP profile concurrently (`/models`, bounded by
+/// `HEALTH_CHECK_TIMEOUT_SECS` each) and returns the updated table
+pub async fn run_health_checks() -> Vec<ProfileHealthRow> {
+    let profiles = crate::llm_profiles::load_profiles().profiles;
+    let checks = profiles
+        .iter()
+        .filter(|p| is_checkable(p))
+        .map(|profile| async move {
+            let started = std::time::Instant::now();
+            let result = tokio::time::timeout(
+                std::time::Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS),
+                crate::ai::fetch_models(profile),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(crate::ai::error::AiClientError::Timeout {
+                    message: format!("Таймаут проверки ({} с)", HEALTH_CHECK_TIMEOUT_SECS),
+                    secs: Some(HEALTH_CHECK_TIMEOUT_SECS),
+                })
+            });
+            let latency_ms = started.elapsed().as_millis() as u64;
+            let error_text = result.as_ref().err().map(ToString::to_string);
+            record_test(
+                &profile.id,
+                error_text.as_deref().map_or(Ok(()), Err),
+                latency_ms,
+            );
+            if result.as_ref().is_err_and(|e| e.is_auth_failure()) {
+                mark_auth_failed(&profile.id);
+            }
+        });
+    futures::future::join_all(checks).await;
+    health_table(&load_health(), &profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_outcome_and_rolling_ttft() {
+        let mut store = HealthStore::default();
+        store.record("p1", HealthSource::Test, Err("HTTP 401"), 100);
+        assert_eq!(store.entries["p1"].ok, Some(false));
+        assert_eq!(store.entries["p1"].error.as_deref(), Some("HTTP 401"));
+
+        store.record("p1", HealthSource::Chat, Ok(()), 200);
+        for ms in 1..=(TTFT_WINDOW as u64 + 5) {
+            store.record_ttft("p1", ms * 100);
+        }
+        let health = &store.entries["p1"];
+        assert_eq!(health.ok, Some(true));
+        assert_eq!(health.error, None);
+        assert_eq!(health.source, Some(HealthSource::Chat));
+        assert_eq!(health.checked_at, Some(200));
+        assert_eq!(health.ttft_samples_ms.len(), TTFT_WINDOW);
+        // Samples 6..=25 remain: average of 600..=2500
+        assert_eq!(health.avg_ttft_ms(), Some(1550));
+    }
+
+    #[test]
+    fn table_lists_every_profile_in_order() {
+        let profile = |id: &str| LLMProfile {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            ..LLMProfile::default_profile()
+        };
+        let profiles = vec![profile("b"), profile("a")];
+        let mut store = HealthStore::default();
+        store.record("a", HealthSource::Test, Ok(()), 1);
+        store.record("deleted", HealthSource::Test, Ok(()), 1);
+
+        let table = health_table(&store, &profiles);
+        let ids: Vec<&str> = table.iter().map(|r| r.profile_id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+        assert_eq!(table[0].health.ok, None);
+        assert_eq!(table[1].health.ok, Some(true));
+    }
+}
@@ -78,12 +78,15 @@ pub fn static_minimax_models() -> Vec<Model> {
 }
 
 /// Fetch MiniMax models: try live /v1/models API, fallback to static list.
-async fn fetch_minimax_models(base_url: &str, api_key: &str) -> Result<Vec<Model>, String> {
+async fn fetch_minimax_models(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+) -> Result<Vec<Model>, String> {
     if api_key.trim().is_empty() {
         return Ok(static_minimax_models());
     }
 
-    let client = crate::http_client::build_http_client()?;
     let trimmed = base_url.trim_end_matches('/');
     let url = if trimmed.ends_with("/v1") {
         format!("{}/models", trimmed)
@@ -212,7 +215,9 @@ pub fn static_codex_models() -> Vec<Model> {
     ]
 }
 
-pub async fn fetch_models_from_api(
+/// Models of a provider over a prepared client (the profile's proxy and TLS settings)
+pub async fn fetch_models_with_client(
+    client: &reqwest::Client,
     provider_id: &str,
     base_url: &str,
     api_key: &str,
@@ -263,7 +268,7 @@ pub async fn fetch_models_from_api(
 
     // MiniMax: try live API first, fallback to static list on error
     if provider_id == "MiniMax" {
-        return fetch_minimax_models(base_url, api_key).await;
+        return fetch_minimax_models(client, base_url, api_key).await;
     }
 
     let requires_api_key = matches!(
@@ -289,7 +294,6 @@ pub async fn fetch_models_from_api(
         ));
     }
 
-    let client = crate::http_client::build_http_client()?;
     let trimmed_base = base_url.trim_end_matches('/');
 
     let url = if trimmed_base.ends_with("/v1") {
@@ -305,7 +309,10 @@ pub async fn fetch_models_from_api(
         builder = builder.header("Authorization", format!("Bearer {}", api_key));
     }
 
-    let resp = builder.send().await.map_err(|e| e.to_string())?;
+    let resp = builder
+        .send()
+        .await
+        .map_err(|e| crate::http_client::describe_request_error(&e))?;
 
     if !resp.status().is_success() {
         return Err(format!("API request failed: {}", resp.status()));
@@ -550,8 +557,7 @@ async fn enrich_lmstudio_context_windows(client: &Client, lms_base: &str, models
     }
 }
 
-pub async fn fetch_registry() -> Result<RegistryData, String> {
-    let client = crate::http_client::build_http_client()?;
+pub async fn fetch_registry(client: &reqwest::Client) -> Result<RegistryData, String> {
     let resp = client
         .get(REGISTRY_URL)
         .send()
@@ -711,8 +717,9 @@ mod tests {
             std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string());
         let base_url = format!("{}/v1", host.trim_end_matches('/'));
 
-        let result = fetch_models_from_api("Ollama", &base_url, "").await;
-        let models = result.expect("fetch_models_from_api should succeed for Ollama");
+        let client = crate::http_client::build_http_client().unwrap();
+        let result = fetch_models_with_client(&client, "Ollama", &base_url, "").await;
+        let models = result.expect("fetch_models_with_client should succeed for Ollama");
 
         assert!(
             !models.is_empty(),
@@ -749,7 +756,8 @@ mod tests {
             std::env::var("LMSTUDIO_HOST").unwrap_or_else(|_| "http://localhost:1234".to_string());
         let base_url = format!("{}/v1", host.trim_end_matches('/'));
 
-        let result = fetch_models_from_api("LMStudio", &base_url, "").await;
+        let client = crate::http_client::build_http_client().unwrap();
+        let result = fetch_models_with_client(&client, "LMStudio", &base_url, "").await;
 
         // If server is not running — gracefully skip
         let models = match result {
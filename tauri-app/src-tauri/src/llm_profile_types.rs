@@ -0,0 +1,291 @@
+//! Data types of LLM profiles, kept free of app dependencies so that `build.rs` can
+//! include this file and write `target/profile-schema.json`. Field docs are the schema
+//! descriptions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Supported LLM providers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum LLMProvider {
+    OpenAI,
+    Anthropic,
+    OpenRouter,
+    Google,
+    DeepSeek,
+    Groq,
+    Mistral,
+    XAI,
+    Perplexity,
+    Ollama,
+    OllamaCloud,
+    LMStudio,
+    ZAI,
+    MiniMax,
+    Custom,
+    QwenCli,
+    CodexCli,
+    OneCNaparnik,
+}
+
+/// Where the system prompt goes in the request
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SystemPromptPlacement {
+    /// `role: "system"` message
+    System,
+    /// `role: "developer"` message (newer OpenAI models)
+    Developer,
+    /// Prepended to the first user message (models without a system role)
+    PrependToUser,
+    /// Top-level `system` field (Anthropic-style APIs)
+    TopLevel,
+}
+
+/// Pacing of the streamed answer
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SmoothingMode {
+    /// `chat-chunk` as received, the lowest latency
+    #[default]
+    None,
+    /// `chat-chunk` by whole words
+    Word,
+    /// `chat-chunk` as received plus whole sentences in `chat-sentence`
+    Sentence,
+}
+
+/// Also reads the former `word_smoothing: bool`
+fn deserialize_smoothing_mode<'de, D>(deserializer: D) -> Result<SmoothingMode, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Mode(SmoothingMode),
+        WordSmoothing(bool),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Mode(mode) => mode,
+        Stored::WordSmoothing(true) => SmoothingMode::Word,
+        Stored::WordSmoothing(false) => SmoothingMode::None,
+    })
+}
+
+impl Default for LLMProvider {
+    fn default() -> Self {
+        LLMProvider::OpenAI
+    }
+}
+
+impl std::fmt::Display for LLMProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Язык ответов ассистента; в профиле переопределяется полем `answer_language`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerLanguage {
+    #[default]
+    Ru,
+    En,
+    /// Украинский
+    Uk,
+    /// Язык последнего сообщения пользователя
+    Auto,
+}
+
+/// Language of the built-in system prompt text; the answer language is set separately
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemPromptLocale {
+    #[default]
+    Russian,
+    Ukrainian,
+    English,
+}
+
+/// Proxy of a single profile, overrides the app-level proxy settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProxyConfig {
+    /// `http://host:port`, `socks5://host:port`, `system` for the OS proxy,
+    /// `none` (or empty) for a direct connection
+    pub url: String,
+    /// Proxy user name
+    #[serde(default)]
+    pub username: String,
+    /// Keychain reference or encrypted value, like `api_key_encrypted`; the UI sends
+    /// the plain password, which is stored on save
+    #[serde(default)]
+    pub password: String,
+    /// Comma-separated hosts that bypass the proxy; empty — localhost only
+    #[serde(default)]
+    pub no_proxy: String,
+}
+
+/// TLS options for gateways behind a private CA
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TlsConfig {
+    /// PEM file with extra root certificates; read on every client build, so a replaced
+    /// file takes effect without restarting
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// DANGER: skips certificate and host name checks. For lab environments only
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+}
+
+/// LLM Profile
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LLMProfile {
+    /// Unique profile id
+    pub id: String,
+    /// Name shown in the profile list
+    pub name: String,
+    /// API provider
+    pub provider: LLMProvider,
+    /// Model id at the provider
+    pub model: String,
+    /// Keychain reference or encrypted API key; the plain key is never stored here
+    pub api_key_encrypted: String,
+    /// API endpoint; `None` or empty — the provider default
+    pub base_url: Option<String>,
+    /// Response token limit. `None` leaves it to the provider default (key omitted from the request).
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature. `None` leaves it to the provider default.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling (0.0 < top_p <= 1.0). Omitted from the request when unset.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Penalty for repeated tokens (-2.0..=2.0). Omitted from the request when unset.
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Penalty for tokens already present in the text (-2.0..=2.0). Omitted when unset.
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    /// Stop sequences sent as the OpenAI `stop` parameter. Empty list omits the field.
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Sampling seed for reproducible generations where the provider supports it
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// `logit_bias` token ids (as strings, per the OpenAI spec) -> bias in -100..100;
+    /// ids are tokenizer-specific, see `tokenize_words`
+    #[serde(default)]
+    pub logit_bias: HashMap<String, f32>,
+    /// Context window of the model instead of the one known to the app
+    pub context_window_override: Option<u32>,
+    /// Force reasoning-model request rules (max_completion_tokens, no temperature).
+    /// `None` — detect by model id.
+    #[serde(default)]
+    pub reasoning_model: Option<bool>,
+    /// Reasoning depth: none, low, medium, high, xhigh
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    /// Turns on the model's thinking mode
+    #[serde(default)]
+    pub enable_thinking: Option<bool>,
+    /// Plain `stream: false` request; the whole answer arrives as one `chat-chunk`
+    #[serde(default)]
+    pub disable_streaming: Option<bool>,
+    /// Wait for the next stream chunk in seconds
+    #[serde(default)]
+    pub stream_timeout_secs: Option<u32>,
+    /// TCP/TLS connect limit (default 10 s)
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u32>,
+    /// Overall limit of non-streaming requests: models list and connection test default
+    /// to 30 s, background completions are unlimited unless set. Streaming is bounded by
+    /// `stream_timeout_secs` between chunks instead
+    #[serde(default)]
+    pub request_timeout_secs: Option<u32>,
+    /// How many times a broken SSE stream is resumed via `Last-Event-ID` (default 3)
+    #[serde(default)]
+    pub max_reconnect_attempts: Option<u32>,
+    /// Pause before a resume attempt in ms (default 1000); the server's `retry:` is
+    /// used only when it is longer
+    #[serde(default)]
+    pub min_reconnect_delay_ms: Option<u64>,
+    /// Limit of accumulated response text in bytes (default 4 MB)
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+    /// Pacing of the streamed answer; `none` keeps raw chunks for the lowest latency
+    #[serde(
+        default,
+        alias = "word_smoothing",
+        deserialize_with = "deserialize_smoothing_mode"
+    )]
+    #[schemars(with = "SmoothingMode")]
+    pub smoothing_mode: SmoothingMode,
+    /// Serve Prometheus metrics on `localhost:<port>/metrics` while this profile is active
+    #[serde(default)]
+    pub enable_metrics: bool,
+    /// Replaces the app-level/built-in system prompt for this profile
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Replaces the default `mini-ai-1c/<version>` User-Agent (some corporate proxies require it)
+    #[serde(default)]
+    pub user_agent_override: Option<String>,
+    /// Overrides the app-level answer language
+    #[serde(default)]
+    pub answer_language: Option<AnswerLanguage>,
+    /// Language of the built-in system prompt; with the `auto` answer language the
+    /// prompt follows the user's last message instead
+    #[serde(default)]
+    pub locale: SystemPromptLocale,
+    /// `None` — chosen by provider and model
+    #[serde(default)]
+    pub system_prompt_placement: Option<SystemPromptPlacement>,
+    /// Prompt caching: `cache_control` breakpoints for Anthropic-style providers,
+    /// a byte-stable prompt prefix (no timestamps) for automatic caching elsewhere
+    #[serde(default)]
+    pub prompt_caching: bool,
+    /// Context compression strategy: "disabled" | "sliding_window" | "summarize"
+    #[serde(default)]
+    pub context_compress_strategy: String,
+    /// Threshold: compress when dialog messages exceed this count (default 40)
+    #[serde(default)]
+    pub max_context_messages: Option<u32>,
+    /// Profile ids tried in order when this profile fails before answering
+    /// (connection error, 429 after retries, 5xx)
+    #[serde(default)]
+    pub fallback_profiles: Vec<String>,
+    /// Position in the profile list; new profiles go to the end
+    #[serde(default)]
+    pub sort_order: u32,
+    /// Favourites are listed first
+    #[serde(default)]
+    pub favorite: bool,
+    /// 1С platform the code is written for (e.g. `8.3.10`); BSL in answers is checked
+    /// against it and `compatibility-issues` is emitted
+    #[serde(default)]
+    pub target_platform_version: Option<String>,
+    /// Disabled profiles (first-run templates until a key is entered) are not used for chat
+    /// or as fallbacks
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Ask for a reply in the language of the user's message when it is not Russian
+    #[serde(default = "default_true")]
+    pub auto_language_match: bool,
+    /// `None` — the app-level proxy settings
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// TLS options for gateways behind a private CA
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// JSON Schema of a profile, for validation and auto-complete of profile files in editors
+pub fn profile_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(LLMProfile)).unwrap_or_default()
+}
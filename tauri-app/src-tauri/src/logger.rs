@@ -1,16 +1,144 @@
 use chrono::Local;
 use lazy_static::lazy_static;
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::{prelude::*, reload, EnvFilter, Registry};
 
 const MAX_LOG_LINES: usize = 2000;
 
+/// Prefix of the daily-rotated tracing file (`mini-ai-1c.log.YYYY-MM-DD`)
+const TRACING_LOG_FILE: &str = "mini-ai-1c.log";
+
+/// Levels accepted by `set_log_level`, most quiet first
+pub const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+const DEFAULT_LOG_LEVEL: &str = "info";
+
 lazy_static! {
     static ref LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(MAX_LOG_LINES));
     static ref DEBUG_MODE: AtomicBool = AtomicBool::new(false);
 }
 
+static TRACING_LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Keeps the non-blocking writer alive; dropping it would stop flushing the file.
+static TRACING_GUARD: OnceLock<tracing_appender::non_blocking::WorkerGuard> = OnceLock::new();
+/// Swaps the level filter of the installed subscriber at runtime
+static TRACING_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Filter directives for `level`: HTTP internals stay at `info` at most, their debug
+/// output would drown the client's own
+fn filter_directives(level: &str) -> String {
+    let deps_level = if matches!(level, "debug" | "trace") {
+        DEFAULT_LOG_LEVEL
+    } else {
+        level
+    };
+    format!(
+        "{0},hyper={1},hyper_util={1},h2={1},reqwest={1},rustls={1},tungstenite={1}",
+        level, deps_level
+    )
+}
+
+fn parse_log_level(level: &str) -> Result<&'static str, String> {
+    let level = level.trim().to_ascii_lowercase();
+    LOG_LEVELS
+        .iter()
+        .find(|l| **l == level)
+        .copied()
+        .ok_or_else(|| {
+            format!(
+                "Неизвестный уровень логирования «{}»: допустимы {}",
+                level,
+                LOG_LEVELS.join(", ")
+            )
+        })
+}
+
+/// Install the global `tracing` subscriber writing to a daily-rotated file in `log_dir`.
+/// Level is taken from `RUST_LOG`, then `AppSettings::log_level` (default `info`).
+/// Subsequent calls are no-ops.
+pub fn init_tracing(log_dir: &Path) {
+    if TRACING_LOG_DIR.get().is_some() {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(log_dir) {
+        log(
+            &format!("[LOG] Failed to create log dir {:?}: {}", log_dir, e),
+            true,
+        );
+        return;
+    }
+
+    let appender = tracing_appender::rolling::daily(log_dir, TRACING_LOG_FILE);
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        let level = crate::settings::load_settings()
+            .log_level
+            .and_then(|level| parse_log_level(&level).ok())
+            .unwrap_or(DEFAULT_LOG_LEVEL);
+        EnvFilter::new(filter_directives(level))
+    });
+    let (filter, filter_handle) = reload::Layer::new(filter);
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false),
+        )
+        .try_init()
+        .is_ok();
+    if installed {
+        let _ = TRACING_GUARD.set(guard);
+        let _ = TRACING_FILTER.set(filter_handle);
+        let _ = TRACING_LOG_DIR.set(log_dir.to_path_buf());
+    }
+}
+
+/// Changes the level of the tracing log file without a restart; returns the level applied
+pub fn set_log_level(level: &str) -> Result<&'static str, String> {
+    let level = parse_log_level(level)?;
+    if let Some(handle) = TRACING_FILTER.get() {
+        handle
+            .reload(EnvFilter::new(filter_directives(level)))
+            .map_err(|e| format!("Не удалось сменить уровень логирования: {}", e))?;
+    }
+    Ok(level)
+}
+
+/// Last `n` lines of today's tracing log file, oldest first
+pub fn recent_log_lines(n: usize) -> Vec<String> {
+    tracing_log_path()
+        .map(|path| read_last_lines(&path, n.min(MAX_LOG_LINES)))
+        .unwrap_or_default()
+}
+
+fn read_last_lines(path: &Path, n: usize) -> Vec<String> {
+    let Ok(bytes) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Path of today's tracing log file, `None` until `init_tracing` succeeded
+pub fn tracing_log_path() -> Option<PathBuf> {
+    // The rolling appender names files by UTC date
+    TRACING_LOG_DIR.get().map(|dir| {
+        dir.join(format!(
+            "{}.{}",
+            TRACING_LOG_FILE,
+            chrono::Utc::now().format("%Y-%m-%d")
+        ))
+    })
+}
+
 pub fn set_debug_mode(enabled: bool) {
     DEBUG_MODE.store(enabled, Ordering::Relaxed);
 }
@@ -38,6 +166,36 @@ pub fn log(message: &str, force: bool) {
     logs.push_back(formatted_message);
 }
 
+/// Empties the in-memory log and removes the tracing log files (today's file stays
+/// open by the writer, so it is truncated instead). Returns paths that could not be removed.
+pub fn clear_logs() -> Vec<PathBuf> {
+    LOGS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    let Some(dir) = TRACING_LOG_DIR.get() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let current = tracing_log_path();
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(TRACING_LOG_FILE))
+        })
+        .filter(|path| {
+            let result = if Some(path) == current.as_ref() {
+                std::fs::File::create(path).map(|_| ())
+            } else {
+                std::fs::remove_file(path)
+            };
+            result.is_err()
+        })
+        .collect()
+}
+
 pub fn get_all_logs() -> String {
     let logs = LOGS.lock().unwrap();
     logs.iter().cloned().collect::<Vec<String>>().join("\n")
@@ -52,3 +210,26 @@ macro_rules! app_log {
         $crate::logger::log(&format!($($arg)*), false)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_levels_are_validated_and_keep_dependencies_quiet() {
+        assert_eq!(parse_log_level(" DEBUG "), Ok("debug"));
+        assert!(parse_log_level("verbose").unwrap_err().contains("verbose"));
+        assert!(filter_directives("debug").starts_with("debug,hyper=info,"));
+        assert!(filter_directives("warn").contains("reqwest=warn"));
+    }
+
+    #[test]
+    fn recent_lines_are_the_tail_of_the_file() {
+        let path = std::env::temp_dir().join(format!("logger-tail-{}.log", std::process::id()));
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(read_last_lines(&path, 2), vec!["two", "three"]);
+        assert_eq!(read_last_lines(&path, 10).len(), 3);
+        assert!(read_last_lines(&path.with_extension("missing"), 5).is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
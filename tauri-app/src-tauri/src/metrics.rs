@@ -0,0 +1,188 @@
+//! Prometheus metrics exposition (`GET /metrics`) for scraping on shared servers.
+//!
+//! Counters are always collected; the HTTP server only runs while the active
+//! profile has `enable_metrics` set.
+
+use lazy_static::lazy_static;
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_METRICS_PORT: u16 = 9090;
+
+static METRICS_PORT: AtomicU16 = AtomicU16::new(DEFAULT_METRICS_PORT);
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+    static ref CHAT_REQUESTS: IntCounterVec = registered(
+        IntCounterVec::new(
+            Opts::new("chat_requests_total", "Chat completion requests"),
+            &["profile", "status"],
+        )
+        .expect("valid chat_requests_total")
+    );
+    static ref CHAT_TOKENS: IntCounterVec = registered(
+        IntCounterVec::new(
+            Opts::new("chat_tokens_total", "Tokens reported by the provider"),
+            &["profile", "direction"],
+        )
+        .expect("valid chat_tokens_total")
+    );
+    static ref CHAT_LATENCY: HistogramVec = registered(
+        HistogramVec::new(
+            HistogramOpts::new("chat_latency_seconds", "Chat completion duration")
+                .buckets(vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+            &["profile"],
+        )
+        .expect("valid chat_latency_seconds")
+    );
+    static ref SERVER_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> = Mutex::new(None);
+}
+
+fn registered<C: Collector + Clone + 'static>(collector: C) -> C {
+    if let Err(e) = REGISTRY.register(Box::new(collector.clone())) {
+        crate::app_log!(force: true, "[METRICS] Failed to register collector: {}", e);
+    }
+    collector
+}
+
+/// Record one finished chat completion
+pub fn record_chat(
+    profile: &str,
+    success: bool,
+    elapsed: Duration,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+) {
+    let status = if success { "ok" } else { "error" };
+    CHAT_REQUESTS.with_label_values(&[profile, status]).inc();
+    CHAT_LATENCY
+        .with_label_values(&[profile])
+        .observe(elapsed.as_secs_f64());
+    if let Some(tokens) = prompt_tokens {
+        CHAT_TOKENS
+            .with_label_values(&[profile, "prompt"])
+            .inc_by(tokens as u64);
+    }
+    if let Some(tokens) = completion_tokens {
+        CHAT_TOKENS
+            .with_label_values(&[profile, "completion"])
+            .inc_by(tokens as u64);
+    }
+}
+
+/// Metrics in the Prometheus text exposition format
+pub fn encode_metrics() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        crate::app_log!(force: true, "[METRICS] Encode error: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+pub fn metrics_port() -> u16 {
+    METRICS_PORT.load(Ordering::Relaxed)
+}
+
+pub fn metrics_url() -> String {
+    format!("http://localhost:{}/metrics", metrics_port())
+}
+
+fn stop_server() {
+    if let Some(task) = SERVER_TASK.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        task.abort();
+        crate::app_log!("[METRICS] Server stopped");
+    }
+}
+
+fn start_server() {
+    let mut task = SERVER_TASK.lock().unwrap_or_else(|e| e.into_inner());
+    if task.is_some() {
+        return;
+    }
+
+    let port = metrics_port();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        let app = axum::Router::new().route(
+            "/metrics",
+            axum::routing::get(|| async {
+                (
+                    [(axum::http::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+                    encode_metrics(),
+                )
+            }),
+        );
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                crate::app_log!(force: true, "[METRICS] Failed to bind port {}: {}", port, e);
+                return;
+            }
+        };
+        crate::app_log!(force: true, "[METRICS] Serving {}", metrics_url());
+        if let Err(e) = axum::serve(listener, app).await {
+            crate::app_log!(force: true, "[METRICS] Server error: {}", e);
+        }
+    }));
+}
+
+/// Start or stop the server according to the active profile's `enable_metrics`
+pub fn sync_with_active_profile() {
+    let enabled = crate::llm_profiles::get_active_profile()
+        .map(|p| p.enable_metrics)
+        .unwrap_or(false);
+    if enabled {
+        start_server();
+    } else {
+        stop_server();
+    }
+}
+
+/// Change the listen port; a running server is restarted on the new port
+pub fn set_port(port: u16) {
+    if METRICS_PORT.swap(port, Ordering::Relaxed) != port {
+        stop_server();
+        sync_with_active_profile();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_chats_show_up_in_the_exposition() {
+        let profile = "metrics-test-profile";
+        record_chat(
+            profile,
+            true,
+            Duration::from_millis(1500),
+            Some(120),
+            Some(30),
+        );
+        record_chat(profile, false, Duration::from_millis(200), None, None);
+
+        assert_eq!(CHAT_REQUESTS.with_label_values(&[profile, "ok"]).get(), 1);
+        assert_eq!(
+            CHAT_REQUESTS.with_label_values(&[profile, "error"]).get(),
+            1
+        );
+        assert_eq!(
+            CHAT_TOKENS.with_label_values(&[profile, "prompt"]).get(),
+            120
+        );
+
+        let text = encode_metrics();
+        assert!(
+            text.contains(r#"chat_requests_total{profile="metrics-test-profile",status="ok"} 1"#)
+        );
+        assert!(text.contains(
+            r#"chat_tokens_total{direction="completion",profile="metrics-test-profile"} 30"#
+        ));
+        assert!(text.contains(r#"chat_latency_seconds_count{profile="metrics-test-profile"} 2"#));
+    }
+}
@@ -0,0 +1,129 @@
+//! Minimum 1С platform versions of global context functions and types, used to
+//! warn when suggested code would not compile on the target platform.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+lazy_static::lazy_static! {
+    /// API name (Russian and English spelling) → first platform version that has it
+    pub static ref PLATFORM_API_VERSIONS: HashMap<&'static str, &'static str> =
+        serde_json::from_str(include_str!("data/platform_api_versions.json"))
+            .expect("platform_api_versions.json is valid");
+    /// Lowercase name → canonical name, for case-insensitive lookup
+    static ref API_BY_LOWERCASE: HashMap<String, &'static str> = PLATFORM_API_VERSIONS
+        .keys()
+        .map(|name| (name.to_lowercase(), *name))
+        .collect();
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompatibilityIssue {
+    pub api_name: String,
+    pub required_version: String,
+    /// First code line that uses the API
+    pub found_in_code: String,
+}
+
+/// `8.3.10.2580` → `[8, 3, 10, 2580]`; non-numeric parts are ignored
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .trim()
+        .split('.')
+        .map_while(|part| part.trim().parse().ok())
+        .collect()
+}
+
+/// `a < b`, missing trailing components count as zero (`8.3` == `8.3.0`)
+fn version_less(a: &[u32], b: &[u32]) -> bool {
+    let len = a.len().max(b.len());
+    let at = |v: &[u32], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| at(a, i).cmp(&at(b, i)))
+        .find(|o| o.is_ne())
+        .is_some_and(|o| o.is_lt())
+}
+
+/// APIs used in `code` (outside comments and strings) that appeared after `min_version`.
+/// Each API is reported once, at its first use.
+pub fn check_platform_compatibility(code: &str, min_version: &str) -> Vec<CompatibilityIssue> {
+    let target = parse_version(min_version);
+    if target.is_empty() {
+        return Vec::new();
+    }
+    let stripped = crate::bsl_analysis::strip_non_code(code);
+    let mut issues: Vec<CompatibilityIssue> = Vec::new();
+
+    for (raw, code_line) in code.lines().zip(stripped.lines()) {
+        for word in code_line
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+        {
+            let Some(name) = API_BY_LOWERCASE.get(&word.to_lowercase()) else {
+                continue;
+            };
+            let required = PLATFORM_API_VERSIONS[name];
+            if !version_less(&target, &parse_version(required))
+                || issues.iter().any(|issue| issue.api_name == *name)
+            {
+                continue;
+            }
+            issues.push(CompatibilityIssue {
+                api_name: name.to_string(),
+                required_version: required.to_string(),
+                found_in_code: raw.trim().to_string(),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_file_versions_are_parseable() {
+        assert!(!PLATFORM_API_VERSIONS.is_empty());
+        for (name, version) in PLATFORM_API_VERSIONS.iter() {
+            assert!(parse_version(version).len() >= 2, "{} → {}", name, version);
+        }
+    }
+
+    #[test]
+    fn reports_apis_newer_than_target_once() {
+        let code = "Процедура Тест()\n\
+                    \tЧасти = СтрРазделить(Строка, \",\");\n\
+                    \tЧтение = Новый ЧтениеJSON;\n\
+                    \tИмя = стрразделить(Путь, \"/\")[0];\n\
+                    \t// Ждать ОбещаниеДиалога;\n\
+                    \tТекст = \"Асинх\";\n\
+                    КонецПроцедуры";
+
+        let issues = check_platform_compatibility(code, "8.2.19");
+        let names: Vec<&str> = issues.iter().map(|i| i.api_name.as_str()).collect();
+        assert_eq!(names, vec!["СтрРазделить", "ЧтениеJSON"]);
+        assert_eq!(issues[0].required_version, "8.3.6");
+        assert_eq!(
+            issues[0].found_in_code,
+            "Части = СтрРазделить(Строка, \",\");"
+        );
+
+        assert!(check_platform_compatibility(code, "8.3.6").is_empty());
+        assert!(check_platform_compatibility(code, "8.3.10.2580").is_empty());
+        assert!(check_platform_compatibility(code, "").is_empty());
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert!(version_less(
+            &parse_version("8.3.9"),
+            &parse_version("8.3.18")
+        ));
+        assert!(!version_less(
+            &parse_version("8.3"),
+            &parse_version("8.3.0")
+        ));
+        assert!(version_less(&parse_version("8.2"), &parse_version("8.3.6")));
+    }
+}
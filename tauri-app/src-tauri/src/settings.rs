@@ -6,6 +6,8 @@ use serde_json::Value;
 use std::fs;
 use std::path::PathBuf;
 
+pub use crate::llm_profile_types::AnswerLanguage;
+
 // Helper functions for defaults
 fn default_true() -> bool {
     true
@@ -444,6 +446,46 @@ pub struct AppSettings {
     /// Устаревшее поле — сохранено для миграции старых конфигов.
     #[serde(default)]
     pub max_context_messages: Option<u32>,
+
+    /// Лимиты на прикрепляемые файлы
+    #[serde(default)]
+    pub attachments: AttachmentSettings,
+
+    /// Значение поля `user` в запросах к провайдеру (по умолчанию — хэш машины)
+    #[serde(default)]
+    pub user_id: Option<String>,
+
+    /// Хранить профили LLM зашифрованными ключом, привязанным к компьютеру (llm_profiles.enc)
+    #[serde(default)]
+    pub encrypt_profiles: bool,
+
+    /// Время жизни кэша списка моделей профиля, сек (по умолчанию сутки)
+    #[serde(default)]
+    pub model_cache_ttl_secs: Option<u64>,
+
+    /// Повторы запроса к провайдеру при 429/5xx и ошибках соединения (по умолчанию 3)
+    #[serde(default)]
+    pub max_request_retries: Option<u32>,
+
+    /// Не проверять доступность сервера провайдера перед запросом (нестандартные файрволы)
+    #[serde(default)]
+    pub skip_reachability_check: bool,
+
+    /// Сбоев провайдера подряд, после которых профиль временно отключается (по умолчанию 5, 0 — никогда)
+    #[serde(default)]
+    pub circuit_breaker_threshold: Option<u32>,
+
+    /// Сколько секунд отключённый после сбоев профиль не используется (по умолчанию 60)
+    #[serde(default)]
+    pub circuit_breaker_cooldown_secs: Option<u64>,
+
+    /// Уровень журнала запросов к провайдерам: error, warn, info, debug, trace (по умолчанию info)
+    #[serde(default)]
+    pub log_level: Option<String>,
+
+    /// Шаблоны профилей LLM уже созданы при первом запуске
+    #[serde(default)]
+    pub profiles_seeded: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -528,6 +570,10 @@ pub struct CodeGenerationSettings {
     /// Шаблон маркера для удаления (Maintenance)
     #[serde(default = "default_deletion_marker")]
     pub deletion_marker_template: String,
+
+    /// Язык ответов (профиль может переопределить)
+    #[serde(default)]
+    pub answer_language: AnswerLanguage,
 }
 
 impl Default for CodeGenerationSettings {
@@ -539,6 +585,7 @@ impl Default for CodeGenerationSettings {
             addition_marker_template: default_addition_marker(),
             modification_marker_template: default_modification_marker(),
             deletion_marker_template: default_deletion_marker(),
+            answer_language: AnswerLanguage::Ru,
         }
     }
 }
@@ -572,6 +619,10 @@ pub struct CustomPromptsSettings {
     /// Пользовательские шаблоны промптов
     #[serde(default)]
     pub templates: Vec<PromptTemplate>,
+
+    /// Системный промпт по умолчанию вместо встроенного (профиль может переопределить)
+    #[serde(default)]
+    pub default_system_prompt: Option<String>,
 }
 
 impl Default for CustomPromptsSettings {
@@ -589,6 +640,59 @@ impl Default for CustomPromptsSettings {
                         .to_string(),
                 enabled: false,
             }],
+            default_system_prompt: None,
+        }
+    }
+}
+
+/// Лимиты на файлы, прикрепляемые к сообщению
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredAttachmentSettings")]
+pub struct AttachmentSettings {
+    /// Максимальный размер одного файла, КБ
+    pub max_file_kb: u32,
+
+    /// Максимальный суммарный размер файлов, отправляемых с одним запросом, байт
+    pub max_attachment_bytes: u64,
+}
+
+/// Лимиты в файле настроек: прежние версии хранили общий лимит в КБ (`max_total_kb`)
+#[derive(Deserialize)]
+struct StoredAttachmentSettings {
+    #[serde(default = "default_max_attachment_file_kb")]
+    max_file_kb: u32,
+    #[serde(default)]
+    max_attachment_bytes: Option<u64>,
+    #[serde(default)]
+    max_total_kb: Option<u64>,
+}
+
+impl From<StoredAttachmentSettings> for AttachmentSettings {
+    fn from(stored: StoredAttachmentSettings) -> Self {
+        Self {
+            max_file_kb: stored.max_file_kb,
+            max_attachment_bytes: stored
+                .max_attachment_bytes
+                .or(stored.max_total_kb.map(|kb| kb.saturating_mul(1024)))
+                .unwrap_or_else(default_max_attachment_bytes),
+        }
+    }
+}
+
+fn default_max_attachment_file_kb() -> u32 {
+    256
+}
+
+/// Не меньше лимита одного файла, иначе файл допустимого размера не отправить
+fn default_max_attachment_bytes() -> u64 {
+    1024 * 1024
+}
+
+impl Default for AttachmentSettings {
+    fn default() -> Self {
+        Self {
+            max_file_kb: default_max_attachment_file_kb(),
+            max_attachment_bytes: default_max_attachment_bytes(),
         }
     }
 }
@@ -686,8 +790,57 @@ fn migrate_builtin_mcp_launchers(settings: &mut AppSettings) -> bool {
     modified
 }
 
+/// Stable anonymous id of this machine and user profile: a SHA-256 of the computer name
+/// and the settings directory, so the OS user name never appears in requests.
+pub fn machine_user_hash() -> String {
+    use sha2::{Digest, Sha256};
+
+    let machine = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(b"mini-ai-1c:");
+    hasher.update(machine.as_bytes());
+    hasher.update(get_settings_dir().to_string_lossy().as_bytes());
+    let digest = hasher.finalize();
+    let hex: String = digest
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("mai-{}", hex)
+}
+
+/// `user_id` from the settings, or [`machine_user_hash`] when it is not set.
+pub fn effective_user_id(settings: &AppSettings) -> String {
+    settings
+        .user_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(machine_user_hash)
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Settings directory seen by `get_settings_dir` on this thread
+    static TEST_SETTINGS_DIR: std::cell::RefCell<Option<PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Makes `get_settings_dir` on the current thread return `dir` (the real one when `None`)
+#[cfg(test)]
+pub fn set_test_settings_dir(dir: Option<PathBuf>) {
+    TEST_SETTINGS_DIR.with(|cell| *cell.borrow_mut() = dir);
+}
+
 /// Get the settings directory path
 pub fn get_settings_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_SETTINGS_DIR.with(|cell| cell.borrow().clone()) {
+        return dir;
+    }
     // Use data_local_dir instead of config_dir to avoid UNC paths on terminal servers
     // data_local_dir points to %LOCALAPPDATA% which is always local, not roaming
     let config_dir = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -699,17 +852,25 @@ pub fn get_settings_file() -> PathBuf {
     get_settings_dir().join("settings.json")
 }
 
-/// Load settings from file
-pub fn load_settings() -> AppSettings {
+/// Settings as stored, without migrations and without syncing the active profile.
+/// For the profile store, which `load_settings` itself loads.
+pub fn read_settings_file() -> AppSettings {
     let path = get_settings_file();
-    let mut settings = if path.exists() {
+    if path.exists() {
         match fs::read_to_string(&path) {
             Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
             Err(_) => AppSettings::default(),
         }
     } else {
         AppSettings::default()
-    };
+    }
+}
+
+/// Load settings from file
+pub fn load_settings() -> AppSettings {
+    // Profiles first: seeding them on first run writes a marker into the settings file
+    let profile_store = crate::llm_profiles::load_profiles();
+    let mut settings = read_settings_file();
 
     let mut modified = false;
 
@@ -771,7 +932,6 @@ pub fn load_settings() -> AppSettings {
         modified = true;
     }
 
-    let profile_store = crate::llm_profiles::load_profiles();
     if !profile_store.active_profile_id.is_empty()
         && settings.active_llm_profile != profile_store.active_profile_id
     {
@@ -810,6 +970,27 @@ pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn user_id_defaults_to_machine_hash_without_os_user_name() {
+        let mut settings = AppSettings::default();
+        let id = effective_user_id(&settings);
+        assert_eq!(id, machine_user_hash());
+        assert!(id.starts_with("mai-"));
+        assert_eq!(id.len(), 4 + 32);
+        for var in ["USERNAME", "USER"] {
+            if let Ok(name) = std::env::var(var) {
+                if name.len() > 4 {
+                    assert!(!id.contains(&name.to_lowercase()));
+                }
+            }
+        }
+
+        settings.user_id = Some("  ".to_string());
+        assert_eq!(effective_user_id(&settings), id);
+        settings.user_id = Some("ivanov".to_string());
+        assert_eq!(effective_user_id(&settings), "ivanov");
+    }
+
     #[test]
     fn legacy_configurator_settings_deserialize_without_binding_fields() {
         let mut json = serde_json::to_value(AppSettings::default())
@@ -941,6 +1122,25 @@ mod tests {
         assert_eq!(settings.proxy.protocol, ProxyProtocol::Http);
     }
 
+    #[test]
+    fn legacy_attachment_total_in_kb_is_converted_to_bytes() {
+        let settings: AttachmentSettings =
+            serde_json::from_value(serde_json::json!({"max_file_kb": 128, "max_total_kb": 512}))
+                .unwrap();
+        assert_eq!(settings.max_file_kb, 128);
+        assert_eq!(settings.max_attachment_bytes, 512 * 1024);
+
+        let defaults: AttachmentSettings = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(defaults.max_attachment_bytes >= defaults.max_file_kb as u64 * 1024);
+        let saved = serde_json::to_value(&defaults).unwrap();
+        assert_eq!(
+            serde_json::from_value::<AttachmentSettings>(saved)
+                .unwrap()
+                .max_attachment_bytes,
+            defaults.max_attachment_bytes
+        );
+    }
+
     #[test]
     fn proxy_settings_debug_does_not_expose_password() {
         let proxy = ProxySettings {
@@ -0,0 +1,390 @@
+//! Export of everything the app stores about the user into one JSON document,
+//! for data-protection requests. Read-only: nothing on disk is created or rewritten.
+//!
+//! Chat conversations live in the frontend storage and are exported from there
+//! (`export_chat`); the backend keeps no conversations, bookmarks or audit log.
+//!
+//! `secure_wipe` is the opposite: it removes all of it, keys included.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::ai::few_shot::FewShotExample;
+use crate::ai::usage::UsageStatsStore;
+use crate::chat_store::ApplyHistoryRow;
+use crate::llm::profile_health::HealthStore;
+use crate::llm_profiles::{LLMProfile, ProfileStore, ProfilesExport};
+
+/// Bumped when a section changes shape, so an import can tell old documents apart
+pub const USER_DATA_SCHEMA_VERSION: u32 = 1;
+
+/// Characters of a key kept on each side of the mask
+const KEY_VISIBLE_CHARS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MaskedApiKey {
+    pub profile_id: String,
+    /// e.g. `sk-p…a1b2`; only `…` for short keys
+    pub masked: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserDataExport {
+    pub schema_version: u32,
+    pub app_version: String,
+    /// RFC 3339
+    pub exported_at: String,
+    /// Same format as `export_profiles`, without keys
+    pub profiles: ProfilesExport,
+    pub api_keys: Vec<MaskedApiKey>,
+    pub usage_stats: UsageStatsStore,
+    pub profile_health: HealthStore,
+    pub few_shot_examples: Vec<FewShotExample>,
+    /// Oldest first
+    pub apply_history: Vec<ApplyHistoryRow>,
+}
+
+pub fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= KEY_VISIBLE_CHARS * 3 {
+        return "…".to_string();
+    }
+    let head: String = chars[..KEY_VISIBLE_CHARS].iter().collect();
+    let tail: String = chars[chars.len() - KEY_VISIBLE_CHARS..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+fn masked_api_keys(store: &ProfileStore) -> Vec<MaskedApiKey> {
+    store
+        .profiles
+        .iter()
+        .filter_map(|p| {
+            let key = p.get_api_key();
+            (!key.is_empty()).then(|| MaskedApiKey {
+                profile_id: p.id.clone(),
+                masked: mask_api_key(&key),
+            })
+        })
+        .collect()
+}
+
+pub fn collect_user_data() -> Result<UserDataExport, String> {
+    let profiles = crate::llm_profiles::read_profiles_snapshot()?;
+    let apply_history = crate::chat_store::read_apply_history()?;
+    Ok(UserDataExport {
+        schema_version: USER_DATA_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        profiles: crate::llm_profiles::export_profiles(&profiles, false),
+        api_keys: masked_api_keys(&profiles),
+        usage_stats: crate::ai::usage::load_usage_stats(),
+        profile_health: crate::llm::profile_health::load_health(),
+        few_shot_examples: crate::ai::few_shot::load_few_shot_store().examples,
+        apply_history,
+    })
+}
+
+/// Writes `collect_user_data` to `output_path`; refuses paths inside the settings
+/// directory so the export cannot replace one of the stores it reads
+pub async fn export_user_data(output_path: &str) -> Result<(), String> {
+    let path = std::path::Path::new(output_path);
+    let settings_dir = crate::settings::get_settings_dir();
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+    if let (Ok(parent), Ok(settings_dir)) = (parent.canonicalize(), settings_dir.canonicalize()) {
+        if parent.starts_with(&settings_dir) {
+            return Err("Выберите для экспорта папку вне каталога настроек приложения".to_string());
+        }
+    }
+
+    let document = tokio::task::spawn_blocking(collect_user_data)
+        .await
+        .map_err(|e| e.to_string())??;
+    let content = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|e| format!("Не удалось записать {}: {}", output_path, e))
+}
+
+/// Files of the settings directory removed by the wipe: profiles (with their
+/// backups and temp files), settings with the prompt templates, examples, presets,
+/// usage, health and model cache
+fn is_user_data_file(name: &str) -> bool {
+    name.starts_with("llm_profiles.")
+        || name.starts_with("settings.json")
+        || matches!(
+            name,
+            "examples.json"
+                | "generation_presets.json"
+                | "usage_stats.json"
+                | "profile_health.json"
+                | "model_cache.json"
+        )
+}
+
+/// Overwrites the file with zeros before it is deleted, so the freed blocks do not
+/// keep the conversation text
+fn overwrite_with_zeros(path: &Path) -> std::io::Result<()> {
+    use std::io::Write;
+    let len = std::fs::metadata(path)?.len();
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let chunk = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        left -= chunk as u64;
+    }
+    file.sync_all()
+}
+
+/// Removes the SQLite database with its journal files, the master key and the user
+/// data files of `dir`. Returns the paths that could not be removed.
+fn wipe_settings_dir(dir: &Path) -> Vec<PathBuf> {
+    let mut failed = Vec::new();
+    let overwritten = ["", "-wal", "-shm", "-journal"]
+        .iter()
+        .map(|suffix| dir.join(format!("chat.db{}", suffix)))
+        .chain(std::iter::once(dir.join(crate::crypto::MASTER_KEY_FILE)));
+    for path in overwritten {
+        if !path.exists() {
+            continue;
+        }
+        if let Err(e) = overwrite_with_zeros(&path) {
+            crate::app_log!("[Wipe] Failed to overwrite {}: {}", path.display(), e);
+        }
+        if std::fs::remove_file(&path).is_err() {
+            failed.push(path);
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return failed;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let is_data = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(is_user_data_file);
+        if is_data && path.is_file() && std::fs::remove_file(&path).is_err() {
+            failed.push(path);
+        }
+    }
+    failed
+}
+
+/// Deletes every stored API key, proxy password and CLI token, the apply history
+/// database, the log files and the settings files, and resets the in-memory state.
+/// Secrets go first: a wipe that fails half-way must not leave keys behind.
+pub async fn secure_wipe() -> Result<(), String> {
+    let profiles = crate::llm_profiles::read_profiles_snapshot()
+        .map(|store| store.profiles)
+        .unwrap_or_else(|e| {
+            crate::app_log!("[Wipe] Profiles unreadable, removing files only: {}", e);
+            Vec::new()
+        });
+    secure_wipe_at(crate::settings::get_settings_dir(), &profiles).await
+}
+
+/// `secure_wipe` of the secrets of `profiles` and the files under `settings_dir`
+async fn secure_wipe_at(settings_dir: PathBuf, profiles: &[LLMProfile]) -> Result<(), String> {
+    for profile in profiles {
+        crate::keychain::delete_profile_secrets(&profile.id);
+        if let Some(account) = crate::keychain::parse_reference(&profile.api_key_encrypted) {
+            crate::keychain::delete_api_key(account);
+        }
+        let _ = crate::llm::cli_providers::qwen::QwenCliProvider::logout(&profile.id);
+        let _ = crate::llm::cli_providers::codex::CodexCliProvider::logout(&profile.id);
+    }
+
+    crate::chat_store::close_store();
+    let mut failed = tokio::task::spawn_blocking(move || wipe_settings_dir(&settings_dir))
+        .await
+        .map_err(|e| e.to_string())?;
+    failed.extend(crate::logger::clear_logs());
+
+    crate::llm_profiles::forget_cached_store();
+    crate::ai::usage::reset_total_usage();
+    crate::history_manager::clear_history().await;
+
+    if failed.is_empty() {
+        crate::app_log!("[Wipe] All user data removed ({} profiles)", profiles.len());
+        Ok(())
+    } else {
+        let list: Vec<String> = failed.iter().map(|p| p.display().to_string()).collect();
+        Err(format!("Не удалось удалить файлы: {}", list.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_masked_to_their_ends() {
+        assert_eq!(mask_api_key("sk-proj-0123456789abcdef"), "sk-p…cdef");
+        assert_eq!(mask_api_key("short-key"), "…");
+    }
+
+    /// Names and contents of the files directly in `dir`
+    fn snapshot_dir(dir: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                (name, std::fs::read(entry.path()).unwrap_or_default())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn export_reads_the_settings_dir_without_writing_to_it() {
+        let dir = std::env::temp_dir().join(format!("mini-ai-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        crate::settings::set_test_settings_dir(Some(dir.clone()));
+
+        std::fs::write(dir.join(crate::crypto::MASTER_KEY_FILE), [7u8; 32]).unwrap();
+        let store = ProfileStore {
+            profiles: vec![crate::llm_profiles::LLMProfile {
+                api_key_encrypted: crate::crypto::encrypt_string("sk-proj-0123456789abcdef")
+                    .unwrap(),
+                ..crate::llm_profiles::LLMProfile::default_profile()
+            }],
+            active_profile_id: "default".to_string(),
+        };
+        // A v1 document without `schema_version`: migrated in memory only
+        std::fs::write(
+            dir.join("llm_profiles.json"),
+            serde_json::to_string(&store).unwrap(),
+        )
+        .unwrap();
+        {
+            let db = crate::chat_store::ChatStore::open(&dir.join("chat.db")).unwrap();
+            db.record_apply(&crate::history_manager::ApplyRecord {
+                file_path: "Module.bsl".to_string(),
+                original_content: "А = 1;".to_string(),
+                applied_content: "А = 2;".to_string(),
+                timestamp_ms: 1,
+                message_id: "m1".to_string(),
+            })
+            .unwrap();
+        }
+        let before = snapshot_dir(&dir);
+
+        let document = collect_user_data();
+
+        let after = snapshot_dir(&dir);
+        crate::settings::set_test_settings_dir(None);
+        let _ = std::fs::remove_dir_all(&dir);
+        let document = document.unwrap();
+        assert_eq!(before, after);
+
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["schema_version"], USER_DATA_SCHEMA_VERSION);
+        assert_eq!(json["profiles"]["profiles"][0]["api_key_encrypted"], "");
+        assert_eq!(document.api_keys[0].masked, "sk-p…cdef");
+        assert!(!json.to_string().contains("0123456789"));
+        assert_eq!(document.apply_history.len(), 1);
+    }
+
+    #[test]
+    fn export_of_an_empty_settings_dir_creates_nothing() {
+        let dir = std::env::temp_dir().join(format!("mini-ai-export-empty-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        crate::settings::set_test_settings_dir(Some(dir.clone()));
+
+        let document = collect_user_data();
+
+        let after = snapshot_dir(&dir);
+        crate::settings::set_test_settings_dir(None);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(document.unwrap().apply_history.is_empty());
+        assert!(after.is_empty());
+    }
+
+    #[test]
+    fn wipe_removes_database_and_data_files() {
+        let dir = std::env::temp_dir().join(format!("mini-ai-wipe-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let db = dir.join("chat.db");
+        {
+            let store = crate::chat_store::ChatStore::open(&db).unwrap();
+            store
+                .record_apply(&crate::history_manager::ApplyRecord {
+                    file_path: "Module.bsl".to_string(),
+                    original_content: "А = 1;".to_string(),
+                    applied_content: "А = 2;".to_string(),
+                    timestamp_ms: 1,
+                    message_id: "m1".to_string(),
+                })
+                .unwrap();
+        }
+        for name in [
+            "llm_profiles.json",
+            "llm_profiles.json.v1.bak",
+            "settings.json",
+            "examples.json",
+            "usage_stats.json",
+            "unrelated.txt",
+        ] {
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        assert!(wipe_settings_dir(&dir).is_empty());
+
+        assert!(!db.exists());
+        assert!(!dir.join("llm_profiles.json").exists());
+        assert!(!dir.join("llm_profiles.json.v1.bak").exists());
+        assert!(!dir.join("settings.json").exists());
+        assert!(!dir.join("examples.json").exists());
+        assert!(dir.join("unrelated.txt").exists());
+
+        // A fresh database in its place has no history
+        let reopened = crate::chat_store::ChatStore::open(&db).unwrap();
+        assert!(reopened.all_apply_history().unwrap().is_empty());
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn secure_wipe_clears_a_settings_root() {
+        let root = std::env::temp_dir().join(format!("mini-ai-secure-wipe-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        drop(crate::chat_store::ChatStore::open(&root.join("chat.db")).unwrap());
+        std::fs::write(root.join(crate::crypto::MASTER_KEY_FILE), [7u8; 32]).unwrap();
+        for name in ["llm_profiles.json", "settings.json", "unrelated.txt"] {
+            std::fs::write(root.join(name), "{}").unwrap();
+        }
+
+        secure_wipe_at(root.clone(), &[]).await.unwrap();
+
+        assert!(!root.join("chat.db").exists());
+        assert!(!root.join(crate::crypto::MASTER_KEY_FILE).exists());
+        assert!(!root.join("llm_profiles.json").exists());
+        assert!(!root.join("settings.json").exists());
+        assert!(root.join("unrelated.txt").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn overwrite_zeroes_the_whole_file() {
+        let path = std::env::temp_dir().join(format!("mini-ai-zero-{}", std::process::id()));
+        std::fs::write(&path, vec![0xAB; 70_000]).unwrap();
+        overwrite_with_zeros(&path).unwrap();
+        let content = std::fs::read(&path).unwrap();
+        assert_eq!(content.len(), 70_000);
+        assert!(content.iter().all(|b| *b == 0));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { CodeBlock } from './chat';
 
 export interface BslStatus {
     installed: boolean;
@@ -48,6 +49,181 @@ export async function formatBsl(code: string): Promise<string> {
     return await invoke<string>('format_bsl', { code });
 }
 
+/**
+ * Unified diff between the original and the suggested code (empty when equal)
+ */
+export async function diffBslCode(original: string, suggested: string): Promise<string> {
+    return await invoke<string>('diff_bsl_code', { original, suggested });
+}
+
+/**
+ * Apply a unified diff to the original code
+ */
+export async function applyBslDiff(original: string, diff: string): Promise<string> {
+    return await invoke<string>('apply_bsl_diff', { original, diff });
+}
+
+/**
+ * Writes AI-suggested code to a file (emits 'code-applied'); up to 20 applies can be rolled back.
+ */
+export async function applyBslCode(filePath: string, code: string, messageId?: string): Promise<void> {
+    return await invoke('apply_bsl_code', { filePath, code, messageId: messageId ?? null });
+}
+
+/**
+ * Restores the file changed by the last applyBslCode (emits 'code-rolled-back'); resolves to its path.
+ */
+export async function rollbackLastApply(): Promise<string> {
+    return await invoke<string>('rollback_last_apply');
+}
+
+export interface ApplyHistoryRow {
+    id: string;
+    file_path: string;
+    original_content: string;
+    applied_content: string;
+    message_id: string;
+    /** Unix time in milliseconds. */
+    applied_at: number;
+    rolled_back: boolean;
+}
+
+/** Persistent apply history of the file, newest first. */
+export async function getApplyHistory(filePath: string, limit = 50): Promise<ApplyHistoryRow[]> {
+    return await invoke<ApplyHistoryRow[]>('get_apply_history', { filePath, limit });
+}
+
+/**
+ * Restores the file to its content before the given apply; it and later applies are marked rolled back.
+ */
+export async function rollbackTo(applyId: string): Promise<void> {
+    await invoke('rollback_to', { applyId });
+}
+
+export async function listModifiedFiles(): Promise<string[]> {
+    return await invoke<string[]>('list_modified_files');
+}
+
+export type AnnotationSeverity = 'error' | 'warning' | 'info' | 'hint';
+
+export interface InlineAnnotation {
+    /** 1-based line of the code. */
+    line: number;
+    column: number | null;
+    severity: AnnotationSeverity;
+    message: string;
+}
+
+/** Lines the AI answer refers to ("строка 12", "line 12"); empty when there are none. */
+export async function extractAnnotations(response: string, code: string): Promise<InlineAnnotation[]> {
+    return await invoke<InlineAnnotation[]>('extract_annotations', { response, code });
+}
+
+export interface CompatibilityIssue {
+    api_name: string;
+    required_version: string;
+    /** First code line that uses the API. */
+    found_in_code: string;
+}
+
+/**
+ * APIs that need a newer platform than platformVersion. Also emitted after answers as
+ * 'compatibility-issues' ({ platform_version, issues }) when the profile sets target_platform_version.
+ */
+export async function checkCompatibility(code: string, platformVersion: string): Promise<CompatibilityIssue[]> {
+    return await invoke<CompatibilityIssue[]>('check_compatibility', { code, platformVersion });
+}
+
+export interface SecurityFinding {
+    /** Rule id: 'sql_concatenation' | 'dynamic_execute' | 'external_processing' | 'missing_rights_check' | 'user_management'. */
+    pattern: string;
+    line: number;
+    severity: AnnotationSeverity;
+    description: string;
+    suggestion: string;
+}
+
+export async function auditBslSecurity(code: string): Promise<SecurityFinding[]> {
+    return await invoke<SecurityFinding[]>('audit_bsl_security', { code });
+}
+
+/** Detailed AI explanation of the findings, answered outside the chat. */
+export async function explainSecurityFindings(code: string, findings: SecurityFinding[]): Promise<string> {
+    return await invoke<string>('explain_security_findings', { code, findings });
+}
+
+export interface MetadataRef {
+    /** Collection as written: 'Справочники', 'Catalogs'... */
+    kind: string;
+    name: string;
+    /** Member accessed on the manager, e.g. '.НайтиПоКоду'; empty when none. */
+    access: string;
+}
+
+/** Metadata objects referenced in the code (comments and strings ignored), each once. */
+export async function extractMetadataRefs(code: string): Promise<MetadataRef[]> {
+    return await invoke<MetadataRef[]>('extract_metadata_refs', { code });
+}
+
+export interface TransliterationReport {
+    original: string;
+    transliterated: string;
+    /** [old, new] pairs; review them before applying the transliterated code. */
+    names_changed: [string, string][];
+}
+
+/** Latin CamelCase names for Cyrillic identifiers the module declares; keywords and strings untouched. */
+export async function transliterateIdentifiers(code: string): Promise<TransliterationReport> {
+    return await invoke<TransliterationReport>('transliterate_identifiers', { code });
+}
+
+export interface DeadCodeFinding {
+    kind: 'UnusedProcedure' | 'UnusedVariable' | 'UnreachableCode';
+    /** Routine or variable name; the first statement for unreachable code. */
+    name: string;
+    line: number;
+}
+
+export async function detectDeadCode(code: string): Promise<DeadCodeFinding[]> {
+    return await invoke<DeadCodeFinding[]>('detect_dead_code', { code });
+}
+
+/** Asks the AI to remove the findings and resolves to the cleaned module. */
+export async function removeDeadCode(code: string, findings: DeadCodeFinding[]): Promise<string> {
+    return await invoke<string>('remove_dead_code', { code, findings });
+}
+
+/** YaxUnit test module (language 'bsl') with a stub per method, generated outside the chat. */
+export async function generateTestStubs(code: string): Promise<CodeBlock> {
+    return await invoke<CodeBlock>('generate_test_stubs', { code });
+}
+
+/**
+ * Documents procedures and functions that have no description yet; resolves to a unified diff
+ * that only adds comment lines (apply with applyBslDiff). Empty when there is nothing to add.
+ */
+export async function generateDocs(code: string): Promise<string> {
+    return await invoke<string>('generate_docs', { code });
+}
+
+/** Removes documentation comments above procedures and functions so they can be regenerated. */
+export async function stripBslDocs(code: string): Promise<string> {
+    return await invoke<string>('strip_bsl_docs', { code });
+}
+
+export interface ComplexityReport {
+    score: number;
+    rating: 'Simple' | 'Moderate' | 'Complex' | 'Very Complex';
+    decision_points: number;
+}
+
+/**
+ * Cyclomatic complexity estimate of BSL code
+ */
+export async function analyzeBslComplexity(code: string): Promise<ComplexityReport> {
+    return await invoke<ComplexityReport>('analyze_bsl_complexity', { code });
+}
+
 /**
  * Diagnose BSL LS launch issues
  */
@@ -15,6 +15,8 @@ export interface ChatMessage {
     tool_calls?: ChatToolCall[];
     tool_call_id?: string;
     name?: string;
+    /** Stable context sent first and marked for prompt caching. */
+    pinned?: boolean;
 }
 
 export interface ChatSession {
@@ -24,13 +26,375 @@ export interface ChatSession {
     messages: ChatMessage[];
 }
 
+/**
+ * Payload of the 'chat-end' event emitted after each model response.
+ */
+export interface CompletionMeta {
+    processing_ms: number | null;
+    remaining_tokens: number | null;
+    prompt_tokens: number | null;
+    completion_tokens: number | null;
+    /** Prompt tokens served from the provider cache. */
+    cached_tokens: number | null;
+    system_fingerprint: string | null;
+    estimated_cost_usd: number | null;
+    /** X-Request-ID sent with the request. */
+    request_id: string | null;
+    /** Request id echoed by the provider (x-request-id or cf-ray). */
+    server_request_id: string | null;
+    /** From the response headers to the first streamed chunk. */
+    time_to_first_token_ms: number | null;
+    /** Token counts are a chars/4 estimate: the provider sent no usage. */
+    usage_estimated: boolean;
+}
+
+/**
+ * Payload of the 'chat-usage' event emitted after each model response.
+ */
+export interface ChatUsage {
+    prompt_tokens: number | null;
+    completion_tokens: number | null;
+    cached_tokens: number | null;
+    /** True when the provider sent no usage and the counts are estimated. */
+    estimated: boolean;
+}
+
+/**
+ * Per-request overrides of the active profile generation settings.
+ */
+export interface RequestOverrides {
+    temperature?: number;
+    max_tokens?: number;
+    top_p?: number;
+    /** Model of the same provider to use instead of the profile model. */
+    model?: string;
+    /** Replaces profile stop sequences; an empty array disables them for this request. */
+    stop?: string[];
+    /** Replaces the profile seed for reproducible generations. */
+    seed?: number;
+    /**
+     * Replaces the profile reasoning effort: sent as reasoning_effort to o-series/gpt-5,
+     * mapped to thinking.budget_tokens for Claude, dropped for other models.
+     */
+    reasoning_effort?: 'low' | 'medium' | 'high';
+    /**
+     * Number of alternative completions. Alternative 0 streams as chat-chunk, the others as
+     * chat-alternative-chunk { index, chunk }; chat-alternatives carries all texts at the end.
+     */
+    n?: number;
+    /** Replaces the profile/app system prompt for this request only. */
+    system_prompt?: string;
+    /** Appended to the system prompt for this request only. */
+    system_prompt_suffix?: string;
+    /** Values for {{variable}} placeholders in the configured system prompt. */
+    prompt_context?: PromptContext;
+    /** Answer length: 'short' asks for code only, up to 20 lines. */
+    verbosity?: 'short' | 'medium' | 'long';
+    /**
+     * Post-processing of the final answer text, in order. Applied before chat-alternatives and
+     * chat-end; the streamed text is replaced via chat-answer-replaced. Tool-call turns are left as is.
+     */
+    transforms?: TransformSpec[];
+}
+
+export type TransformSpec =
+    | { type: 'strip_markdown' }
+    | { type: 'extract_first_code_block' }
+    | { type: 'truncate_to_n_lines'; lines: number }
+    | { type: 'prepend_text'; text: string }
+    | { type: 'append_text'; text: string };
+
+/** Applies transforms to a text the same way stream_chat does with `transforms`. */
+export async function applyResponseTransforms(text: string, transforms: TransformSpec[]): Promise<string> {
+    return await invoke<string>('apply_response_transforms', { text, transforms });
+}
+
+export interface PromptContext {
+    platform_version?: string;
+    project_name?: string;
+    /** Defaults to the current date (YYYY-MM-DD). */
+    today?: string;
+    /** Custom variables, e.g. forbidden_apis. */
+    variables?: Record<string, string>;
+}
+
+/**
+ * Warnings about unknown {{variable}} placeholders in a prompt template.
+ */
+export async function validatePromptTemplate(template: string, context?: PromptContext): Promise<string[]> {
+    return await invoke<string[]>('validate_prompt_template', { template, context });
+}
+
+/**
+ * Fills {{variable}} / {{variable|default}} placeholders; rejects with the list of missing variables.
+ */
+export async function renderTemplate(template: string, variables: Record<string, string>): Promise<string> {
+    return await invoke<string>('render_template', { template, variables });
+}
+
+/**
+ * Renders a saved prompt template by id; the result is sent as a regular chat message.
+ */
+export async function renderSavedTemplate(templateId: string, variables: Record<string, string>): Promise<string> {
+    return await invoke<string>('render_saved_template', { templateId, variables });
+}
+
+/**
+ * Rejection of the AI commands (streamChat, oneShot, explainError, chatStructured...):
+ * what failed, not just the text.
+ */
+export type AiClientError =
+    | { kind: 'no_active_profile' }
+    | { kind: 'invalid_profile'; message: string }
+    | { kind: 'network'; message: string }
+    | { kind: 'unreachable'; host: string; message: string }
+    | { kind: 'circuit_open'; profile_id: string; retry_at: string; message: string }
+    | {
+          kind: 'http';
+          status: number;
+          code: string | null;
+          /** Readable summary of the provider's error */
+          message: string;
+          provider_type: string;
+          /** Body as received, for debugging */
+          raw_body: string;
+      }
+    | {
+          kind: 'auth_failed';
+          status: number;
+          profile_id: string;
+          profile_name: string;
+          provider_type: string;
+          /** What to check, specific to the provider and the key */
+          hint: string;
+          message: string;
+          raw_body: string;
+      }
+    | { kind: 'stream'; message: string }
+    | {
+          kind: 'max_reconnects_exceeded';
+          attempts: number;
+          message: string;
+          /** Answer text received before the stream gave up */
+          partial_response: string;
+      }
+    | { kind: 'timeout'; message: string; secs: number | null }
+    | { kind: 'cancelled' }
+    | { kind: 'parse'; message: string }
+    /** Nothing to send, e.g. an empty diff for suggestCommitMessage */
+    | { kind: 'empty_input'; message: string }
+    | { kind: 'other'; message: string };
+
+/**
+ * Terminal events of one completion request; exactly one of them is sent per
+ * `request_id` of 'chat-start'. The chat turn as a whole (tool calls included)
+ * ends with a separate 'chat-turn-done' without payload.
+ */
+export interface ChatDoneEvent {
+    request_id: string;
+}
+
+export interface ChatErrorEvent {
+    request_id: string;
+    kind: AiClientError['kind'];
+    message: string;
+    http_status: number | null;
+    /** Worth retrying later or with another profile */
+    retryable: boolean;
+    /** Characters of the answer received before the failure */
+    partial_text_len: number;
+}
+
+export interface ChatCancelledEvent {
+    request_id: string;
+}
+
+/**
+ * Text of a chat error for display; accepts plain strings from other commands too.
+ */
+export function describeAiError(err: unknown): string {
+    if (typeof err === 'object' && err !== null && 'kind' in err) {
+        const e = err as AiClientError;
+        if (e.kind === 'no_active_profile') return 'Нет активного LLM профиля';
+        if (e.kind === 'cancelled') return 'Cancelled';
+        return e.message;
+    }
+    return String(err);
+}
+
+/**
+ * Asks a single question without the conversation history; no chat events are sent and
+ * the answer is not stored in the chat.
+ */
+export async function oneShot(prompt: string): Promise<string> {
+    return await invoke<string>('one_shot', { prompt });
+}
+
+/**
+ * Answers of two profiles to the same messages; diff covers the BSL blocks (or whole answers without code).
+ */
+export interface ModelComparison {
+    model_a: string;
+    model_b: string;
+    response_a: string;
+    response_b: string;
+    latency_a_ms: number;
+    latency_b_ms: number;
+    tokens_a: number;
+    tokens_b: number;
+    diff: string;
+}
+
+/**
+ * Sends the messages to both profiles in parallel, without chat events.
+ */
+export async function compareCompletions(
+    messages: Pick<ChatMessage, 'role' | 'content'>[],
+    profileIdA: string,
+    profileIdB: string,
+): Promise<ModelComparison> {
+    return await invoke<ModelComparison>('compare_completions', { messages, profileIdA, profileIdB });
+}
+
+export interface CodeBlock {
+    /** Fence language ('bsl', 'sql', ...); empty for a bare fence. */
+    language: string;
+    code: string;
+}
+
+/**
+ * Runs a chat completion and returns only the code blocks of the answer; rejects when there are none.
+ * codeOnlyPrompt (default true) also tells the model to skip explanations.
+ */
+export async function extractCodeFromCompletion(
+    messages: Pick<ChatMessage, 'role' | 'content'>[],
+    codeOnlyPrompt?: boolean,
+): Promise<CodeBlock[]> {
+    return await invoke<CodeBlock[]>('extract_code_from_completion', { messages, codeOnlyPrompt: codeOnlyPrompt ?? null });
+}
+
+export interface ParsedError {
+    module: string;
+    line: number;
+    message: string;
+    /** e.g. 'not_object_type', 'method_not_found'; 'runtime' when not recognised. */
+    error_type: string;
+}
+
+/** Location of a 1С runtime error like "{Модуль(35)}: текст"; null when there is none. */
+export async function parseError(errorStr: string): Promise<ParsedError | null> {
+    return await invoke<ParsedError | null>('parse_error', { errorStr });
+}
+
+/** Explanation of a 1С runtime error, answered outside the chat (no chat events). */
+export async function explainError(errorStr: string, contextCode?: string): Promise<string> {
+    return await invoke<string>('explain_error', { errorStr, contextCode: contextCode ?? null });
+}
+
+/**
+ * Root-cause analysis of a 1С stack trace ("{Модуль(строка)}: текст" per frame,
+ * 8.2 and 8.3 formats), answered outside the chat; rejects with an AiClientError.
+ */
+export async function analyseStackTrace(trace: string): Promise<string> {
+    return await invoke<string>('analyse_stack_trace', { trace });
+}
+
+/**
+ * Payload of the 'chat-fallback' event: the request failed before answering and is
+ * retried with the next profile of the fallback chain.
+ */
+export interface ChatFallbackInfo {
+    from_profile_id: string;
+    profile_id: string;
+    profile_name: string;
+    error: string;
+}
+
+/**
+ * Payload of the 'auth-error' event: the provider rejected the profile's key (chat,
+ * model list or connection test); the UI can open the profile's key field.
+ */
+export interface AuthErrorInfo {
+    profile_id: string;
+    profile_name: string;
+    status: number;
+    hint: string;
+}
+
+/**
+ * Payload of the 'chat-retrying' event: the request hit a 429/5xx or connect error
+ * before answering and is repeated with the same profile after `delay_ms`.
+ */
+export interface ChatRetryingInfo {
+    /** 1-based number of the retry */
+    attempt: number;
+    delay_ms: number;
+    /** e.g. "HTTP 502" */
+    reason: string;
+}
+
+/**
+ * Payload of the 'chat-start' event: parameters actually sent for the request.
+ */
+export interface ChatStartInfo {
+    request_id: string;
+    /** Profile actually used: the one passed to streamChat or the active one. */
+    profile_id: string;
+    profile_name: string;
+    model: string;
+    temperature: number | null;
+    max_tokens: number | null;
+    top_p: number | null;
+    stop: string[] | null;
+    seed: number | null;
+    /** Where the base system prompt came from: request override, profile, app setting or built-in. */
+    system_prompt_source: 'request' | 'profile' | 'app' | 'built_in' | 'built_in_lightweight';
+    system_prompt_suffix: boolean;
+    /** Generation preset applied to this request. */
+    preset: string | null;
+}
+
+/**
+ * Kind of task; adds a task-specific focus to the system prompt ('free_form' leaves it as-is).
+ */
+export type TaskType = 'refactor' | 'explain' | 'debug' | 'generate' | 'review' | 'document' | 'free_form';
+
+/**
+ * File the user is editing; added to the system prompt.
+ */
+export interface FileContext {
+    file_path: string;
+    /** e.g. 'МодульФормы'; inferred from the path when omitted. */
+    module_type?: string;
+    selected_text?: string;
+}
+
 /**
  * Stream chat response
- * Note: This command emits events ('chat-chunk', 'chat-status', 'chat-done'), 
- * so the frontend needs to listen for them separately.
+ * Note: This command emits events ('chat-chunk', 'chat-status', 'chat-turn-done', and per
+ * completion 'chat-done' / 'chat-error' / 'chat-cancelled'), so the frontend needs to listen for them separately.
  */
-export async function streamChat(messages: ChatMessage[]): Promise<void> {
-    return await invoke('stream_chat', { messages });
+export async function streamChat(
+    messages: ChatMessage[],
+    overrides?: RequestOverrides,
+    taskType?: TaskType,
+    fileContext?: FileContext,
+    attachments?: FileAttachment[],
+    gitDiff?: GitDiffAttachment,
+    preset?: string,
+    /** Profile for this request only; the active profile when omitted. */
+    profileId?: string,
+): Promise<void> {
+    return await invoke('stream_chat', {
+        messages,
+        overrides,
+        taskType: taskType ?? null,
+        fileContext: fileContext ?? null,
+        attachments: attachments ?? null,
+        gitDiff: gitDiff ?? null,
+        preset: preset ?? null,
+        profileId: profileId ?? null,
+    });
 }
 
 /**
@@ -40,6 +404,107 @@ export async function stopChat(): Promise<void> {
     return await invoke('stop_chat');
 }
 
+export interface ImageAttachment {
+    /** data:image/png;base64,... (downscaled to 1568px on the longest side) */
+    data_url: string;
+    width: number;
+    height: number;
+}
+
+/**
+ * Attach an image to the next message: from a file path, or from the clipboard when omitted.
+ * Fails for non-image clipboard content; vision support is checked when the message is sent.
+ */
+export async function attachImage(path?: string): Promise<ImageAttachment> {
+    return await invoke('attach_image', { path: path ?? null });
+}
+
+/**
+ * Drop images attached to the next message
+ */
+export async function clearImageAttachments(): Promise<void> {
+    return await invoke('clear_image_attachments');
+}
+
+export interface FileAttachment {
+    path: string;
+    content: string;
+    /** Code fence language: bsl, xml, json or empty */
+    language: string;
+}
+
+/**
+ * Payload of the 'attachment-added' event emitted for every attached file.
+ */
+export interface AttachmentAddedEvent {
+    name: string;
+    path: string;
+    bytes: number;
+    estimated_tokens: number;
+    /** Size of all files attached to the next message so far */
+    total_bytes: number;
+}
+
+/**
+ * Attach source files (UTF-8 or windows-1251) to the next message as fenced blocks.
+ * Fails with a message naming the file when the per-file or total size limit is exceeded.
+ */
+export async function attachFiles(paths: string[]): Promise<FileAttachment[]> {
+    return await invoke('attach_files', { paths });
+}
+
+/**
+ * Read source files to pass as `attachments` of streamChat (total limited by max_attachment_bytes)
+ */
+export async function readAndAttachFiles(paths: string[]): Promise<FileAttachment[]> {
+    return await invoke('read_and_attach_files', { paths });
+}
+
+export interface GitDiffAttachment {
+    diff: string;
+    staged: boolean;
+}
+
+/**
+ * git diff (--cached when staged) of the repository, truncated to 32 KB, to pass to streamChat.
+ * Fails when git is missing, the folder is not a repository or there are no changes.
+ */
+export async function attachGitDiff(repoPath: string, staged: boolean): Promise<string> {
+    return await invoke<string>('attach_git_diff', { repoPath, staged });
+}
+
+export interface CommitMessageSuggestion {
+    subject: string;
+    /** '- ' bullets, one per line. */
+    body: string | null;
+}
+
+/**
+ * Non-streaming request that must answer with JSON (validated against `schema` when given);
+ * messages are API messages ({ role, content }). The answer is retried once if it is not valid JSON.
+ */
+export async function chatStructured<T = unknown>(
+    messages: { role: string; content: string }[],
+    schema?: Record<string, unknown>,
+): Promise<T> {
+    return await invoke<T>('chat_structured', {
+        messagesJson: JSON.stringify(messages),
+        schema: schema ?? null,
+    });
+}
+
+/** Commit message for a diff (e.g. from attachGitDiff); fails on an empty diff. */
+export async function suggestCommitMessage(diff: string): Promise<CommitMessageSuggestion> {
+    return await invoke<CommitMessageSuggestion>('suggest_commit_message', { diff });
+}
+
+/**
+ * Drop files attached to the next message
+ */
+export async function clearFileAttachments(): Promise<void> {
+    return await invoke('clear_file_attachments');
+}
+
 /**
  * Inject a user message into the active agentic loop (mid-loop interrupt).
  * Returns true if accepted by an active loop, false if no loop is running.
@@ -74,3 +539,23 @@ export async function clearNaparnikSession(): Promise<void> {
 export async function compactContext(messagesJson: string): Promise<string> {
     return await invoke<string>('compact_context', { messagesJson });
 }
+
+export interface TokenEstimate {
+    prompt_tokens: number;
+    with_system_prompt: number;
+    remaining_in_context: number | null;
+}
+
+/**
+ * Estimate how many tokens the request will consume before sending it
+ */
+export async function estimateTokens(messages: ChatMessage[]): Promise<TokenEstimate> {
+    return await invoke<TokenEstimate>('estimate_tokens', { messages });
+}
+
+/**
+ * Most recent X-Request-ID of the active profile (for support tickets).
+ */
+export async function getLastRequestId(): Promise<string | null> {
+    return await invoke<string | null>('get_last_request_id');
+}
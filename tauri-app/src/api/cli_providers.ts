@@ -4,16 +4,17 @@ import { CliAuthInitResponse, CliAuthStatus, CliStatus, CliUsage } from '../type
 export const cliProvidersApi = {
     /**
      * Запустить процесс OAuth Device Flow для указанного провайдера
+     * (через прокси профиля, если он уже сохранён)
      */
-    async authStart(provider: string): Promise<CliAuthInitResponse> {
-        return await invoke('cli_auth_start', { provider });
+    async authStart(provider: string, profileId?: string): Promise<CliAuthInitResponse> {
+        return await invoke('cli_auth_start', { provider, profileId });
     },
 
     /**
      * Опросить статус авторизации по коду устройства
      */
-    async authPoll(provider: string, deviceCode: string, codeVerifier?: string): Promise<CliAuthStatus> {
-        return await invoke('cli_auth_poll', { provider, deviceCode, codeVerifier });
+    async authPoll(provider: string, deviceCode: string, codeVerifier?: string, profileId?: string): Promise<CliAuthStatus> {
+        return await invoke('cli_auth_poll', { provider, deviceCode, codeVerifier, profileId });
     },
 
     /**
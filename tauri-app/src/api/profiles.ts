@@ -1,5 +1,27 @@
 import { invoke } from '@tauri-apps/api/core';
-import { CliProviderInfo } from '../types/settings';
+import { AnswerLanguage, CliProviderInfo } from '../types/settings';
+
+export interface ProxyConfig {
+    /** 'http://host:port', 'socks5://host:port', 'system' or 'none'. */
+    url: string;
+    username?: string;
+    /** Plain password to store; comes back as a keychain reference. */
+    password?: string;
+    /** Comma-separated hosts that bypass the proxy. */
+    no_proxy?: string;
+}
+
+export interface TlsConfig {
+    /** PEM file with extra root CA certificates (corporate CA). */
+    ca_cert_path?: string;
+    /** DANGER: disables certificate checks; lab environments only. */
+    danger_accept_invalid_certs?: boolean;
+}
+
+export type SmoothingMode = 'none' | 'word' | 'sentence';
+
+/** Language of the built-in system prompt text. */
+export type SystemPromptLocale = 'russian' | 'ukrainian' | 'english';
 
 export interface LLMProfile {
     id: string;
@@ -8,15 +30,66 @@ export interface LLMProfile {
     model: string;
     api_key_encrypted: string;
     base_url: string | null;
-    max_tokens: number;
-    temperature: number;
+    /** Omitted (provider default) when unset. */
+    max_tokens?: number;
+    temperature?: number;
+    top_p?: number;
+    frequency_penalty?: number;
+    presence_penalty?: number;
+    stop?: string[];
+    seed?: number;
+    /** Token id (string) -> bias in -100..100 */
+    logit_bias?: Record<string, number>;
     context_window_override?: number;
+    reasoning_model?: boolean;
+    /** Codex CLI; o-series/gpt-5 reasoning_effort; Claude extended thinking budget. */
     reasoning_effort?: 'none' | 'low' | 'medium' | 'high' | 'xhigh';
     enable_thinking?: boolean;
     disable_streaming?: boolean;
     stream_timeout_secs?: number;
+    /** Connect limit in seconds (default 10). */
+    connect_timeout_secs?: number;
+    /** Overall limit of non-streaming requests; models list and connection test default to 30. */
+    request_timeout_secs?: number;
+    max_reconnect_attempts?: number;
+    /** Pause before resuming a broken stream in ms (default 1000); a longer server `retry:` wins. */
+    min_reconnect_delay_ms?: number;
+    /** Streamed response text limit in bytes (default 4 MB); 'chat-truncated' is emitted when hit. */
+    max_response_bytes?: number;
+    /**
+     * Pacing of the streamed answer: 'word' — chat-chunk by whole words (no mid-word flicker),
+     * 'sentence' — chat-chunk as received plus whole sentences in 'chat-sentence' (screen readers).
+     */
+    smoothing_mode?: SmoothingMode;
+    enable_metrics?: boolean;
+    /** Replaces the app-level/built-in system prompt for requests through this profile; multi-line. */
+    system_prompt?: string;
+    user_agent_override?: string;
+    /** Overrides the app-level answer language. */
+    answer_language?: AnswerLanguage;
+    /** Built-in system prompt language; with the 'auto' answer language it follows the user's last message. */
+    locale?: SystemPromptLocale;
+    /** Where the system prompt goes; unset — chosen by provider and model. */
+    system_prompt_placement?: 'system' | 'developer' | 'prepend-to-user' | 'top-level';
+    /** Prompt caching: cache_control for Anthropic, byte-stable prefix for automatic caching elsewhere. */
+    prompt_caching?: boolean;
     context_compress_strategy?: 'disabled' | 'sliding_window' | 'summarize';
     max_context_messages?: number;
+    /** Profile ids tried in order when this one fails before answering (network, 429, 5xx). */
+    fallback_profiles?: string[];
+    /** Position in the list; new profiles are appended. */
+    sort_order?: number;
+    /** Favourites are listed first. */
+    favorite?: boolean;
+    /** 1С platform version (e.g. '8.3.10'); answers are checked and 'compatibility-issues' is emitted. */
+    target_platform_version?: string;
+    /** False for first-run templates: not used for chat or fallback until enabled. */
+    enabled?: boolean;
+    /** Ask for a reply in the language of a non-Russian user message (default true). */
+    auto_language_match?: boolean;
+    /** Own proxy; unset — the app-level proxy settings. */
+    proxy?: ProxyConfig;
+    tls?: TlsConfig;
     provider_subtype?: 'cli';
     cli_info?: CliProviderInfo;
 }
@@ -38,15 +111,94 @@ export async function getProfiles(): Promise<ProfileStore> {
  * @param profile The profile data
  * @param apiKey Optional API key to update
  */
-export async function saveProfile(profile: LLMProfile, apiKey?: string): Promise<void> {
+export async function saveProfile(profile: LLMProfile, apiKey?: string): Promise<ProfileSaveResult> {
     return await invoke('save_profile', { profile, apiKey });
 }
 
+/**
+ * Response of save/create/update profile commands.
+ * `api_key_warning` is set when the new key does not look like a key of the provider.
+ */
+export interface ProfileSaveResult {
+    api_key_warning: string | null;
+}
+
+/**
+ * Rejection of create/update/delete/set-active profile commands.
+ */
+export interface ProfileError {
+    kind: 'not_found' | 'duplicate_name' | 'invalid' | 'active_profile' | 'last_profile' | 'storage';
+    message: string;
+}
+
+/**
+ * Set when the profiles file is corrupt or cannot be decrypted; offer resetProfiles() then.
+ */
+export async function getProfilesLoadError(): Promise<string | null> {
+    return await invoke<string | null>('get_profiles_load_error');
+}
+
+/** Replaces an unreadable profiles file with the default profile. */
+export async function resetProfiles(): Promise<ProfileStore> {
+    return await invoke<ProfileStore>('reset_profiles');
+}
+
+/**
+ * Set when the OS keychain is unavailable and API keys stay encrypted in the settings file.
+ */
+export async function getKeychainWarning(): Promise<string | null> {
+    return await invoke<string | null>('get_keychain_warning');
+}
+
+/** Copies the profile under a new id; the copy shares the API key and is not activated. */
+export async function duplicateProfile(profileId: string, newName?: string): Promise<LLMProfile> {
+    return await invoke<LLMProfile>('duplicate_profile', { profileId, newName: newName ?? null });
+}
+
+export type MergeStrategy = 'rename' | 'overwrite' | 'skip';
+
+export interface ProfileImportResult {
+    name: string;
+    status: 'added' | 'renamed' | 'overwritten' | 'skipped' | 'failed';
+    /** New name for 'renamed', the reason for 'failed'. */
+    message: string | null;
+}
+
+/** Keys are written in plain text only when includeKeys is set. */
+export async function exportProfiles(path: string, includeKeys = false): Promise<void> {
+    await invoke('export_profiles', { path, includeKeys });
+}
+
+export async function importProfiles(
+    path: string,
+    mergeStrategy: MergeStrategy,
+): Promise<ProfileImportResult[]> {
+    return await invoke<ProfileImportResult[]>('import_profiles', { path, mergeStrategy });
+}
+
+/** Fails with a ProfileError unless every profile id is listed exactly once. */
+export async function reorderProfiles(idsInOrder: string[]): Promise<void> {
+    await invoke('reorder_profiles', { idsInOrder });
+}
+
+export async function listProfiles(): Promise<LLMProfile[]> {
+    return await invoke<LLMProfile[]>('list_profiles');
+}
+
+/** Fails with a ProfileError when the id or name is already taken or a field is invalid. */
+export async function createProfile(profile: LLMProfile, apiKey?: string): Promise<ProfileSaveResult> {
+    return await invoke('create_profile', { profile, apiKey });
+}
+
+export async function updateProfile(profile: LLMProfile, apiKey?: string): Promise<ProfileSaveResult> {
+    return await invoke('update_profile', { profile, apiKey });
+}
+
 /**
  * Delete a profile
  */
-export async function deleteProfile(profileId: string): Promise<void> {
-    return await invoke('delete_profile', { profileId });
+export async function deleteProfile(profileId: string, replacementId?: string): Promise<void> {
+    return await invoke('delete_profile', { profileId, replacementId: replacementId ?? null });
 }
 
 /**
@@ -59,7 +211,7 @@ export async function setActiveProfile(profileId: string): Promise<void> {
 /**
  * Fetch available models for a specific profile
  */
-export async function fetchModelsForProfile(profileId: string): Promise<string[]> {
+export async function fetchModelsForProfile(profileId?: string): Promise<string[]> {
     // Note: The backend command returns Vec<Model> struct, but frontend mapping might differ. 
     // Let's check commands.rs again. 
     // commands.rs: fetch_models_for_profile -> Vec<crate::llm::providers::Model>
@@ -69,12 +221,265 @@ export async function fetchModelsForProfile(profileId: string): Promise<string[]
     // In App.tsx: invoke('fetch_models_cmd', { profileId }) -> returns Strings? 
     // Checking App.tsx (it wasn't fully visible but I saw `fetch_models_cmd` in `commands.rs`)
     // commands.rs: pub async fn fetch_models_cmd(profile_id: String) -> Result<Vec<String>, String>
-    return await invoke<string[]>('fetch_models_cmd', { profileId });
+    return await invoke<string[]>('fetch_models_cmd', { profileId: profileId ?? null });
+}
+
+/** Model of the provider's `/models` response with its metadata, when the provider sends it. */
+export interface ModelEntry {
+    id: string;
+    context_window: number | null;
+    max_completion_tokens: number | null;
+    /** Unix seconds */
+    created: number | null;
+}
+
+/**
+ * Metadata of a model from the profile's `/models` list (cached after the first fetch);
+ * null when the provider does not list the model.
+ */
+export async function getModelEntry(profileId: string, modelId: string): Promise<ModelEntry | null> {
+    return await invoke<ModelEntry | null>('get_model_entry', { profileId, modelId });
+}
+
+export interface RateLimitStatus {
+    limit_requests: number | null;
+    remaining_requests: number | null;
+    /** Unix time in ms when the request budget is restored. */
+    reset_requests_at_ms: number | null;
+    limit_tokens: number | null;
+    remaining_tokens: number | null;
+    /** Unix time in ms when the token budget is restored. */
+    reset_tokens_at_ms: number | null;
+    updated_at_ms: number;
+}
+
+/**
+ * Payload of 'rate-limit-wait': a request of the profile waits for its rate limit reset.
+ */
+export interface RateLimitWaitInfo {
+    profile_id: string;
+    wait_ms: number;
+    reason: 'requests' | 'tokens';
+    remaining_tokens: number | null;
+    remaining_requests: number | null;
+}
+
+/**
+ * Rate limits from the profile's last response; null until a request was made or when
+ * the provider does not report them.
+ */
+export async function getRateLimitStatus(profileId: string): Promise<RateLimitStatus | null> {
+    return await invoke<RateLimitStatus | null>('get_rate_limit_status', { profileId });
+}
+
+export interface ProfileModels {
+    /** Same shape as fetch_models_from_provider results. */
+    models: any[];
+    /** The request failed and an expired cached list is shown. */
+    stale: boolean;
+    /** Unix seconds. */
+    fetched_at: number;
 }
 
 /**
- * Test connection for a profile
+ * Models of a stored profile; served from the per-profile cache while it is younger than
+ * model_cache_ttl_secs (one day by default).
  */
-export async function testConnection(profileId: string): Promise<string> {
-    return await invoke<string>('test_llm_connection_cmd', { profileId });
+export async function getProfileModels(profileId: string): Promise<ProfileModels> {
+    return await invoke<ProfileModels>('fetch_models_for_profile', { profileId });
+}
+
+/** Fetch models of a stored profile bypassing the cache. */
+export async function refreshModels(profileId: string): Promise<ProfileModels> {
+    return await invoke<ProfileModels>('refresh_models', { profileId });
+}
+
+export interface ConnectionDiagnostics {
+    dns_resolved: boolean;
+    tcp_connected: boolean;
+    tls_ok: boolean;
+    auth_ok: boolean;
+    models_fetched: number;
+    latency_ms: number;
+    error: string | null;
+    /** The /models request went through the profile's proxy; DNS/TCP steps probe the host directly. */
+    via_proxy: boolean;
+}
+
+/**
+ * Test connection for a profile (the active one when profileId is omitted).
+ * Partial results are emitted as 'connection-probe' events ({ step, diagnostics }).
+ */
+export async function testConnection(profileId?: string): Promise<ConnectionDiagnostics> {
+    return await invoke<ConnectionDiagnostics>('test_llm_connection_cmd', {
+        profileId: profileId ?? null,
+    });
+}
+
+export interface CircuitStatus {
+    state: 'closed' | 'open' | 'half_open';
+    consecutive_failures: number;
+    /** ISO time: end of the cool-down, or of the probe's grace time when half-open. */
+    retry_at: string | null;
+}
+
+export interface ProfileHealthRow {
+    profile_id: string;
+    profile_name: string;
+    enabled: boolean;
+    /** Outcome of the last connection test or chat; null before the first one. */
+    ok: boolean | null;
+    error: string | null;
+    source: 'test' | 'chat' | null;
+    /** Unix seconds. */
+    checked_at: number | null;
+    latency_ms: number | null;
+    ttft_samples_ms: number[];
+    /** The last failure was a rejected API key (401/403). */
+    auth_failed: boolean;
+    /** Circuit breaker: 'open' — the profile fails fast until retry_at after repeated outages. */
+    circuit: CircuitStatus;
+    /** Rolling average over the last 20 streamed chats. */
+    avg_ttft_ms: number | null;
+}
+
+/** Health of every profile, updated by connection tests and chats. */
+export async function getProfileHealth(): Promise<ProfileHealthRow[]> {
+    return await invoke<ProfileHealthRow[]>('get_profile_health');
+}
+
+/** Closes the profile's circuit breaker so the next chat is sent to the provider at once. */
+export async function resetCircuit(profileId: string): Promise<void> {
+    await invoke('reset_circuit', { profileId });
+}
+
+/** Tests all enabled HTTP profiles concurrently (15 s each) and returns the updated table. */
+export async function runHealthChecks(): Promise<ProfileHealthRow[]> {
+    return await invoke<ProfileHealthRow[]>('run_health_checks');
+}
+
+export interface UsageTotals {
+    requests: number;
+    prompt_tokens: number;
+    completion_tokens: number;
+    estimated_cost_usd: number;
+}
+
+/**
+ * Usage totals since app start.
+ */
+export async function getTotalUsage(): Promise<UsageTotals> {
+    return await invoke<UsageTotals>('get_total_usage');
+}
+
+export interface UsageCounters {
+    requests: number;
+    /** Requests that failed before the provider reported usage. */
+    errors: number;
+    prompt_tokens: number;
+    completion_tokens: number;
+    /** Only models with known pricing contribute. */
+    estimated_cost_usd: number;
+}
+
+export type UsagePeriod = 'today' | 'week' | 'month' | 'all';
+
+export interface UsageStats {
+    totals: UsageCounters;
+    /** Days with activity, oldest first (date is YYYY-MM-DD). */
+    days: (UsageCounters & { date: string })[];
+    by_profile: Record<string, UsageCounters>;
+}
+
+/** Persisted usage of a profile (all profiles when omitted) with per-day buckets. */
+export async function getUsageStats(period: UsagePeriod, profileId?: string): Promise<UsageStats> {
+    return await invoke<UsageStats>('get_usage_stats', { profileId: profileId ?? null, period });
+}
+
+/** Clears the usage history of a profile, or of all profiles when omitted. */
+export async function resetUsageStats(profileId?: string): Promise<void> {
+    await invoke('reset_usage_stats', { profileId: profileId ?? null });
+}
+
+/**
+ * Projected 30-day cost in USD for the profile's model.
+ */
+export async function projectMonthlyCost(
+    profileId: string,
+    avgDailyRequests: number,
+    avgPromptTokens: number,
+    avgCompletionTokens: number,
+): Promise<number> {
+    return await invoke<number>('project_monthly_cost', {
+        profileId,
+        avgDailyRequests,
+        avgPromptTokens,
+        avgCompletionTokens,
+    });
+}
+
+export async function setMetricsPort(port: number): Promise<void> {
+    return await invoke('set_metrics_port', { port });
+}
+
+export async function getMetricsUrl(): Promise<string> {
+    return await invoke<string>('get_metrics_url');
+}
+
+export async function getEffectiveUserAgent(profileId: string): Promise<string> {
+    return await invoke<string>('get_effective_user_agent', { profileId });
+}
+
+/**
+ * Provider of a pasted endpoint URL; `null` for unknown gateways (Custom)
+ */
+export async function detectProviderFromUrl(url: string): Promise<string | null> {
+    return await invoke<string | null>('detect_provider_from_url', { url });
+}
+
+/**
+ * JSON Schema of a profile (with field descriptions), for editors that validate
+ * and auto-complete profile files
+ */
+export async function getProfileSchema(): Promise<Record<string, unknown>> {
+    return await invoke<Record<string, unknown>>('get_profile_schema');
+}
+
+export interface WordTokens {
+    text: string;
+    token_ids: number[];
+    /** The model's tokenizer is unknown: ids are from o200k_base and may not match */
+    approximate: boolean;
+}
+
+/**
+ * Token ids of the words (as written and with a leading space) for the active model, to fill logit_bias
+ */
+export async function tokenizeWords(words: string[]): Promise<WordTokens[]> {
+    return await invoke<WordTokens[]>('tokenize_words', { words });
+}
+
+/** Named sampling parameters applied over the profile for one request; see streamChat `preset`. */
+export interface GenerationPreset {
+    name: string;
+    temperature?: number;
+    top_p?: number;
+    max_tokens?: number;
+    /** Unset keeps the profile stop sequences. */
+    stop?: string[];
+    reasoning_effort?: string;
+    /** Shipped with the app; read-only. */
+    builtin?: boolean;
+}
+
+export async function getGenerationPresets(): Promise<GenerationPreset[]> {
+    return await invoke<GenerationPreset[]>('get_generation_presets');
+}
+
+export async function saveGenerationPreset(preset: GenerationPreset): Promise<void> {
+    return await invoke('save_generation_preset', { preset });
+}
+
+export async function deleteGenerationPreset(name: string): Promise<void> {
+    return await invoke('delete_generation_preset', { name });
 }
@@ -1,7 +1,8 @@
 import { invoke } from '@tauri-apps/api/core';
-import { AppSettings, McpServerConfig } from '../types/settings';
+import { AppSettings, LogLevel, McpServerConfig } from '../types/settings';
+import type { TaskType } from './chat';
 
-export type { McpServerConfig, AppSettings };
+export type { McpServerConfig, AppSettings, LogLevel };
 
 export interface ExportSettingsResult {
     status: 'saved' | 'cancelled';
@@ -22,10 +23,83 @@ export async function saveSettings(newSettings: AppSettings): Promise<void> {
     return await invoke('save_settings', { newSettings });
 }
 
+/**
+ * App-level default system prompt (null = built-in prompt)
+ */
+export async function getSystemPrompt(): Promise<string | null> {
+    return await invoke<string | null>('get_system_prompt');
+}
+
+export async function setSystemPrompt(prompt: string | null): Promise<void> {
+    await invoke<void>('set_system_prompt', { prompt });
+}
+
+/**
+ * Level of the structured log file, applied without a restart;
+ * `debug` also logs request bodies with keys redacted
+ */
+export async function setLogLevel(level: LogLevel): Promise<LogLevel> {
+    return await invoke<LogLevel>('set_log_level', { level });
+}
+
+/** Last `n` lines of the structured log file, for the log panel */
+export async function getRecentLogs(n: number): Promise<string[]> {
+    return await invoke<string[]>('get_recent_logs', { n });
+}
+
+/**
+ * Value sent as `user` with chat requests (configured user_id or the machine hash)
+ */
+export async function getEffectiveUserId(): Promise<string> {
+    return await invoke<string>('get_effective_user_id');
+}
+
+export interface FewShotExample {
+    id: string;
+    task_type: TaskType;
+    user_text: string;
+    assistant_text: string;
+}
+
+/** Few-shot examples (examples.json in the app data directory). */
+export async function listFewShotExamples(): Promise<FewShotExample[]> {
+    return await invoke<FewShotExample[]>('list_few_shot_examples');
+}
+
+/** Adds an example; the id is assigned by the backend. */
+export async function addFewShotExample(example: Omit<FewShotExample, 'id'>): Promise<FewShotExample> {
+    return await invoke<FewShotExample>('add_few_shot_example', { example });
+}
+
+export async function deleteFewShotExample(id: string): Promise<void> {
+    await invoke<void>('delete_few_shot_example', { id });
+}
+
+/** Number of examples injected per request (default 2); 0 disables few-shot. */
+export async function setFewShotK(k: number): Promise<void> {
+    await invoke<void>('set_few_shot_k', { k });
+}
+
 export async function exportSettings(): Promise<ExportSettingsResult> {
     return await invoke<ExportSettingsResult>('export_settings');
 }
 
+/**
+ * Everything stored about the user (profiles with masked keys, usage statistics,
+ * apply history) as one JSON file with `schema_version`. Read-only.
+ */
+export async function exportUserData(path: string): Promise<void> {
+    await invoke<void>('export_user_data', { path });
+}
+
+/**
+ * Deletes all API keys, apply history, logs and settings files. Requires `confirm`;
+ * emits `wipe-complete` when done.
+ */
+export async function secureWipe(confirm: boolean): Promise<void> {
+    await invoke<void>('secure_wipe', { confirm });
+}
+
 export async function importSettings(jsonData: string): Promise<void> {
     await invoke<void>('import_settings', { jsonData });
 }
@@ -1699,7 +1699,7 @@ export const ChatArea = memo(function ChatArea({
                 />
                 <ContextUsageBar
                     onNewChat={clearChat}
-                    profileId={activeProfileId ?? undefined}
+                    profileId={activeProfileId}
                     chatId={activeSessionId}
                 />
                 <div className="relative bg-[#18181b] border border-[#27272a] rounded-xl focus-within:ring-1 focus-within:ring-blue-500/50 transition-all min-h-[120px] flex flex-col max-w-4xl mx-auto">
@@ -2080,6 +2080,7 @@ export const ChatArea = memo(function ChatArea({
             {authModalProvider === 'qwen' && (
                 <QwenAuthModal
                     isOpen={true}
+                    profileId={activeProfileId}
                     onClose={() => {
                         setAuthModalProvider(null);
                         fetchCliStatuses();
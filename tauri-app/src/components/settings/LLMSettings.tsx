@@ -12,6 +12,7 @@ import { LLMProfile, ProfileStore } from '../../contexts/ProfileContext';
 import { applyFetchedModelMetadata, applySelectedModelMetadata } from '../../utils/llmProfileModelMetadata';
 import { isOllamaCloudProfile } from '../../utils/profileHelpers';
 import { shouldResetApiKeyDraft } from '../../utils/profileSecretDraft';
+import type { ProfileModels, ProfileSaveResult, SmoothingMode, SystemPromptLocale } from '../../api/profiles';
 
 interface LLMSettingsProps {
     profiles: ProfileStore;
@@ -84,6 +85,7 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
     const [connectionTest, setConnectionTest] = useState<string | null>(null);
     const [isSaving, setIsSaving] = useState(false);
     const [showSaved, setShowSaved] = useState(false);
+    const [apiKeyWarning, setApiKeyWarning] = useState<string | null>(null);
     const [isAuthModalOpen, setIsAuthModalOpen] = useState(false);
     const [isCodexAuthModalOpen, setIsCodexAuthModalOpen] = useState(false);
     const [cliStatus, setCliStatus] = useState<CliStatus | null>(null);
@@ -104,6 +106,7 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                 if (isNewProfile) {
                     setNewApiKey('');
                     setConnectionTest(null);
+                    setApiKeyWarning(null);
                 }
 
                 // Only reset model list when switching to a different profile
@@ -125,11 +128,12 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                 if (PROVIDERS.find(prov => prov.value === p.provider)?.type === 'cli') {
                     setLoadingModels(true);
                     const fetchPromise = p.provider === 'CodexCli'
-                        ? invoke<any[]>('fetch_models_for_profile', { profileId: p.id })
+                        ? invoke<ProfileModels>('fetch_models_for_profile', { profileId: p.id }).then(res => res.models)
                         : invoke<any[]>('fetch_models_from_provider', {
                             providerId: p.provider,
                             baseUrl: p.base_url || PROVIDERS.find(prov => prov.value === p.provider)?.defaultUrl || '',
-                            apiKey: ''
+                            apiKey: '',
+                            profileId: p.id
                         });
 
                     fetchPromise.then(res => {
@@ -145,7 +149,8 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                     invoke<any[]>('fetch_models_from_provider', {
                         providerId: p.provider,
                         baseUrl: p.base_url || 'https://api.minimax.io/v1',
-                        apiKey: ''
+                        apiKey: '',
+                        profileId: p.id
                     }).then(res => {
                         const sorted = sortModels(res);
                         setModelList(sorted);
@@ -165,7 +170,8 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                     invoke<any[]>('fetch_models_from_provider', {
                         providerId: p.provider,
                         baseUrl: p.base_url || PROVIDERS.find(prov => prov.value === p.provider)?.defaultUrl || '',
-                        apiKey: ''
+                        apiKey: '',
+                        profileId: p.id
                     }).then(res => {
                         const sorted = sortModels(res);
                         setModelList(sorted);
@@ -182,8 +188,8 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                     // Cloud Ollama: use profile-based fetch so backend decrypts the API key.
                     // /api/show needs auth on ollama.com, so apiKey: '' would fail.
                     setLoadingModels(true);
-                    invoke<any[]>('fetch_models_for_profile', { profileId: p.id }).then(res => {
-                        const sorted = sortModels(res);
+                    invoke<ProfileModels>('fetch_models_for_profile', { profileId: p.id }).then(res => {
+                        const sorted = sortModels(res.models);
                         setModelList(sorted);
                         const currentModel = sorted.find((m: any) => m.id === p.model);
                         if (currentModel?.context_window) {
@@ -221,13 +227,15 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
 
         setIsSaving(true);
         setShowSaved(false);
+        setApiKeyWarning(null);
         try {
             const apiKeyFromInput = apiKeyInputRef.current?.value ?? '';
             const apiKeyToSave = apiKeyFromInput || newApiKey;
-            await invoke('save_profile', {
+            const result = await invoke<ProfileSaveResult>('save_profile', {
                 profile: editForm,
                 apiKey: apiKeyToSave || null
             });
+            setApiKeyWarning(result.api_key_warning);
             if (apiKeyToSave) {
                 setNewApiKey('');
                 if (apiKeyInputRef.current) {
@@ -291,21 +299,23 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
         try {
             let res: any[] = [];
             if (editForm.provider === 'CodexCli') {
-                res = await invoke<any[]>('fetch_models_for_profile', { profileId: editForm.id });
+                res = (await invoke<ProfileModels>('refresh_models', { profileId: editForm.id })).models;
             } else if (newApiKey) {
                 res = await invoke<any[]>('fetch_models_from_provider', {
                     providerId: editForm.provider,
                     baseUrl: editForm.base_url || PROVIDERS.find(p => p.value === editForm.provider)?.defaultUrl || '',
-                    apiKey: newApiKey
+                    apiKey: newApiKey,
+                    profileId: editForm.id
                 });
             } else if (editForm.api_key_encrypted) {
                 await invoke('save_profile', { profile: editForm, apiKey: null });
-                res = await invoke<any[]>('fetch_models_for_profile', { profileId: editForm.id });
+                res = (await invoke<ProfileModels>('refresh_models', { profileId: editForm.id })).models;
             } else {
                 res = await invoke<any[]>('fetch_models_from_provider', {
                     providerId: editForm.provider,
                     baseUrl: editForm.base_url || PROVIDERS.find(p => p.value === editForm.provider)?.defaultUrl || '',
-                    apiKey: ''
+                    apiKey: '',
+                    profileId: editForm.id
                 });
             }
 
@@ -958,8 +968,12 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                                         <input
                                             type="number"
                                             className="w-full bg-zinc-900 border border-zinc-700 rounded-md pl-3 pr-16 h-9 text-sm text-zinc-200"
-                                            value={editForm.max_tokens}
-                                            onChange={e => setEditForm({ ...editForm, max_tokens: parseInt(e.target.value) || 0 })}
+                                            placeholder="по умолч."
+                                            value={editForm.max_tokens ?? ''}
+                                            onChange={e => {
+                                                const v = parseInt(e.target.value);
+                                                setEditForm({ ...editForm, max_tokens: isNaN(v) || v <= 0 ? undefined : v });
+                                            }}
                                         />
                                         {(() => {
                                             const currentModel = modelList.find(m => m.id === editForm.model);
@@ -1014,19 +1028,66 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                                         <input
                                             type="number" step="0.1" min="0" max="2"
                                             className="w-full mt-1 bg-zinc-900 border border-zinc-700 rounded-md px-3 h-9 text-sm text-zinc-200"
-                                            value={editForm.temperature}
-                                            onChange={e => setEditForm({ ...editForm, temperature: parseFloat(e.target.value) || 0.7 })}
+                                            placeholder="по умолч."
+                                            value={editForm.temperature ?? ''}
+                                            onChange={e => {
+                                                const v = parseFloat(e.target.value);
+                                                setEditForm({ ...editForm, temperature: isNaN(v) ? undefined : v });
+                                            }}
                                         />
                                     </div>
                                 )}
                             </div>
 
+                            {editForm.provider !== 'CodexCli' && editForm.provider !== 'OneCNaparnik' && (
+                                <div className="flex flex-wrap gap-4 pt-2">
+                                    {([
+                                        ['top_p', 'Top P', '0.05', '0', '1'],
+                                        ['frequency_penalty', 'Frequency penalty', '0.1', '-2', '2'],
+                                        ['presence_penalty', 'Presence penalty', '0.1', '-2', '2'],
+                                    ] as const).map(([key, label, step, min, max]) => (
+                                        <div key={key} className="flex-1 min-w-[120px]">
+                                            <label className="text-xs text-zinc-500 uppercase font-bold px-1 whitespace-nowrap overflow-hidden text-ellipsis">
+                                                {label}
+                                            </label>
+                                            <input
+                                                type="number" step={step} min={min} max={max}
+                                                placeholder="по умолч."
+                                                className="w-full mt-1 bg-zinc-900 border border-zinc-700 rounded-md px-3 h-9 text-sm text-zinc-200"
+                                                value={editForm[key] ?? ''}
+                                                onChange={e => {
+                                                    const v = parseFloat(e.target.value);
+                                                    setEditForm({ ...editForm, [key]: isNaN(v) ? undefined : v });
+                                                }}
+                                            />
+                                        </div>
+                                    ))}
+                                </div>
+                            )}
+
                             {editForm.provider === 'CodexCli' && (
                                 <p className="text-[10px] text-zinc-600 px-1 pt-2">
                                     Чем выше значение, тем глубже рассуждение и тем быстрее расходуются лимиты ChatGPT Plus/Pro.
                                 </p>
                             )}
 
+                            {/* Enabled toggle — first-run templates start disabled */}
+                            <div className="flex items-center justify-between pt-3 px-1">
+                                <div>
+                                    <span className="text-xs text-zinc-400 font-medium">Профиль включён</span>
+                                    <p className="text-[10px] text-zinc-600 mt-0.5">
+                                        Отключённый профиль не используется в чате и как резервный
+                                    </p>
+                                </div>
+                                <button
+                                    type="button"
+                                    onClick={() => setEditForm({ ...editForm, enabled: editForm.enabled === false })}
+                                    className={`relative w-9 h-5 rounded-full transition-colors focus:outline-none ${editForm.enabled !== false ? 'bg-blue-500' : 'bg-zinc-700'}`}
+                                >
+                                    <span className={`absolute top-0.5 left-0.5 w-4 h-4 rounded-full bg-white transition-transform ${editForm.enabled !== false ? 'translate-x-4' : 'translate-x-0'}`} />
+                                </button>
+                            </div>
+
                             {/* Disable streaming toggle — Ollama/LMStudio */}
                             {(editForm.provider === 'Ollama' || editForm.provider === 'LMStudio') && (
                                 <div className="flex items-center justify-between pt-3 px-1">
@@ -1046,6 +1107,52 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                                 </div>
                             )}
 
+                            {/* Answer smoothing mode */}
+                            <div className="flex items-center justify-between pt-3 px-1">
+                                <div>
+                                    <span className="text-xs text-zinc-400 font-medium">Вывод ответа</span>
+                                    <p className="text-[10px] text-zinc-600 mt-0.5">
+                                        Целыми словами — без мерцания; предложениями — для экранных дикторов
+                                    </p>
+                                </div>
+                                <Select
+                                    value={editForm.smoothing_mode ?? 'none'}
+                                    onValueChange={v => setEditForm({ ...editForm, smoothing_mode: v as SmoothingMode })}
+                                >
+                                    <SelectTrigger className="w-40 bg-zinc-900 border-zinc-700 h-8 px-2 text-xs">
+                                        <SelectValue />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        <SelectItem value="none">Как приходит</SelectItem>
+                                        <SelectItem value="word">Целыми словами</SelectItem>
+                                        <SelectItem value="sentence">Предложениями</SelectItem>
+                                    </SelectContent>
+                                </Select>
+                            </div>
+
+                            {/* Built-in system prompt language */}
+                            <div className="flex items-center justify-between pt-3 px-1">
+                                <div>
+                                    <span className="text-xs text-zinc-400 font-medium">Язык системного промпта</span>
+                                    <p className="text-[10px] text-zinc-600 mt-0.5">
+                                        Язык встроенных инструкций; язык ответа задаётся отдельно
+                                    </p>
+                                </div>
+                                <Select
+                                    value={editForm.locale ?? 'russian'}
+                                    onValueChange={v => setEditForm({ ...editForm, locale: v as SystemPromptLocale })}
+                                >
+                                    <SelectTrigger className="w-40 bg-zinc-900 border-zinc-700 h-8 px-2 text-xs">
+                                        <SelectValue />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        <SelectItem value="russian">Русский</SelectItem>
+                                        <SelectItem value="ukrainian">Українська</SelectItem>
+                                        <SelectItem value="english">English</SelectItem>
+                                    </SelectContent>
+                                </Select>
+                            </div>
+
                             {/* Stream timeout — Ollama/LMStudio */}
                             {(editForm.provider === 'Ollama' || editForm.provider === 'LMStudio') && (
                                 <div className="flex items-center justify-between pt-3 px-1">
@@ -1115,6 +1222,11 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
                                     </>
                                 )}
                             </button>
+                            {apiKeyWarning && (
+                                <p className="mt-2 text-xs text-amber-400 font-medium leading-relaxed">
+                                    {apiKeyWarning}
+                                </p>
+                            )}
                         </div>
                     </div>
                 ) : (
@@ -1129,6 +1241,7 @@ export function LLMSettings({ profiles, onUpdate }: LLMSettingsProps) {
 
             <QwenAuthModal
                 isOpen={isAuthModalOpen}
+                profileId={editForm?.id}
                 onClose={() => setIsAuthModalOpen(false)}
                 onSuccess={async (access_token, refresh_token, expires_at, resource_url) => {
                     console.log('[DEBUG] LLMSettings: Qwen Auth Success, saving token...');
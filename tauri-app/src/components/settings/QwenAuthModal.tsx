@@ -7,9 +7,11 @@ interface QwenAuthModalProps {
     isOpen: boolean;
     onClose: () => void;
     onSuccess: (accessToken: string, refreshToken: string | null, expiresAt: number, resourceUrl: string | null) => void;
+    /** Saved profile whose proxy is used for authorization */
+    profileId?: string;
 }
 
-export function QwenAuthModal({ isOpen, onClose, onSuccess }: QwenAuthModalProps) {
+export function QwenAuthModal({ isOpen, onClose, onSuccess, profileId }: QwenAuthModalProps) {
     const [step, setStep] = useState<'init' | 'waiting' | 'error'>('init');
     const [authData, setAuthData] = useState<CliAuthInitResponse | null>(null);
     const [timeLeft, setTimeLeft] = useState(0);
@@ -21,7 +23,7 @@ export function QwenAuthModal({ isOpen, onClose, onSuccess }: QwenAuthModalProps
         setStep('init');
         setError(null);
         try {
-            const data = await cliProvidersApi.authStart('qwen');
+            const data = await cliProvidersApi.authStart('qwen', profileId);
             setAuthData(data);
             setTimeLeft(data.expires_in);
             setStep('waiting');
@@ -43,7 +45,7 @@ export function QwenAuthModal({ isOpen, onClose, onSuccess }: QwenAuthModalProps
         const poll = async () => {
             if (!isPolling) return;
             try {
-                const status = await cliProvidersApi.authPoll('qwen', deviceCode, codeVerifier);
+                const status = await cliProvidersApi.authPoll('qwen', deviceCode, codeVerifier, profileId);
                 console.log('[DEBUG] Qwen Auth Poll Status:', status);
 
                 if (status.status === 'Authorized' && status.data) {
@@ -417,6 +417,31 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                         thinkingBuffer.current += event.payload;
                         scheduleFlush();
                     }),
+                    // chat-answer-replaced: текст ответа после transforms — заменяет уже показанный
+                    listen<string>('chat-answer-replaced', (event) => {
+                        flushNow();
+                        const text = event.payload;
+                        setMessages(prev => {
+                            for (let i = prev.length - 1; i >= 0; i--) {
+                                if (prev[i].role === 'user') break;
+                                if (prev[i].role !== 'assistant') continue;
+                                const last = prev[i];
+                                const parts = [...(last.parts || [])];
+                                const textIdx = parts.length > 0 && parts[parts.length - 1].type === 'text' ? parts.length - 1 : -1;
+                                const streamed = textIdx !== -1 ? parts[textIdx].content || '' : '';
+                                if (textIdx !== -1) {
+                                    parts[textIdx] = { ...parts[textIdx], content: text };
+                                } else {
+                                    parts.push({ type: 'text', content: text });
+                                }
+                                const content = last.content.endsWith(streamed)
+                                    ? last.content.slice(0, last.content.length - streamed.length) + text
+                                    : text;
+                                return [...prev.slice(0, i), { ...last, content, parts }, ...prev.slice(i + 1)];
+                            }
+                            return prev;
+                        });
+                    }),
                     listen<{ index: number, id: string, name: string }>('tool-call-started', (event) => {
                         flushNow();
                         setMessages(prev => {
@@ -573,7 +598,7 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                         setCurrentIteration(event.payload);
                     }),
 
-                    listen('chat-done', () => {
+                    listen('chat-turn-done', () => {
                         flushNow();
                         const elapsed = streamStartTimeRef.current ? Date.now() - streamStartTimeRef.current : null;
                         streamStartTimeRef.current = null;
@@ -808,7 +833,7 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                 const last = withFixedTools[withFixedTools.length - 1];
                 if (last && last.role === 'assistant') {
                     // Append error to the existing assistant message
-                    const errorStr = `\n\n❌ **Ошибка:** ${err}`;
+                    const errorStr = `\n\n❌ **Ошибка:** ${api.describeAiError(err)}`;
                     const newParts = [...(last.parts || [])];
                     const lastPart = newParts[newParts.length - 1];
                     if (lastPart && lastPart.type === 'text') {
@@ -822,7 +847,7 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                     ];
                 }
                 // Fallback: create a new message
-                const errorStr = `❌ Ошибка: ${err}`;
+                const errorStr = `❌ Ошибка: ${api.describeAiError(err)}`;
                 return [...withFixedTools, { id: generateId(), role: 'assistant', content: errorStr, parts: [{ type: 'text', content: errorStr }], timestamp: Date.now() }];
             });
             setIsLoading(false);
@@ -977,7 +1002,7 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                 const last = withFixedTools[withFixedTools.length - 1];
                 if (last && last.role === 'assistant') {
                     // Append error to the existing assistant message
-                    const errorStr = `\n\n❌ **Ошибка:** ${err}`;
+                    const errorStr = `\n\n❌ **Ошибка:** ${api.describeAiError(err)}`;
                     const newParts = [...(last.parts || [])];
                     const lastPart = newParts[newParts.length - 1];
                     if (lastPart && lastPart.type === 'text') {
@@ -990,7 +1015,7 @@ export function ChatProvider({ children }: { children: React.ReactNode }) {
                         { ...last, content: last.content + errorStr, parts: newParts }
                     ];
                 }
-                const errorMsg = `❌ Ошибка: ${err} `;
+                const errorMsg = `❌ Ошибка: ${api.describeAiError(err)} `;
                 return [...withFixedTools, { id: generateId(), role: 'assistant', content: errorMsg, parts: [{ type: 'text', content: errorMsg }], timestamp: Date.now() }];
             });
             setIsLoading(false);
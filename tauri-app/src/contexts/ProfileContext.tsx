@@ -2,7 +2,7 @@ import React, { createContext, useContext, useEffect, useState } from 'react';
 import { listen } from '@tauri-apps/api/event';
 import * as api from '../api';
 
-import { LLMProfile, ProfileStore } from '../api';
+import { LLMProfile, ProfileSaveResult, ProfileStore } from '../api';
 
 export type { LLMProfile, ProfileStore };
 
@@ -12,7 +12,7 @@ interface ProfileContextType {
     activeProfile: LLMProfile | undefined;
     loadProfiles: () => Promise<void>;
     setActiveProfile: (id: string) => Promise<void>;
-    saveProfile: (profile: LLMProfile, apiKey?: string) => Promise<void>;
+    saveProfile: (profile: LLMProfile, apiKey?: string) => Promise<ProfileSaveResult>;
     deleteProfile: (id: string) => Promise<void>;
 }
 
@@ -50,14 +50,19 @@ export function ProfileProvider({ children }: { children: React.ReactNode }) {
     }, [loadProfiles]);
 
     const handleSaveProfile = React.useCallback(async (profile: LLMProfile, apiKey?: string) => {
-        await api.saveProfile(profile, apiKey);
+        const result = await api.saveProfile(profile, apiKey);
         await loadProfiles();
+        return result;
     }, [loadProfiles]);
 
     const handleDeleteProfile = React.useCallback(async (id: string) => {
-        await api.deleteProfile(id);
+        // The active profile can only be deleted together with a replacement
+        const replacement = store?.active_profile_id === id
+            ? store.profiles.find(p => p.id !== id)?.id
+            : undefined;
+        await api.deleteProfile(id, replacement);
         await loadProfiles();
-    }, [loadProfiles]);
+    }, [loadProfiles, store]);
 
     const activeProfile = React.useMemo(() =>
         store?.profiles.find(p => p.id === store.active_profile_id),
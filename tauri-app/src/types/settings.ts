@@ -73,6 +73,9 @@ export interface CliProviderInfo {
 
 // Стиль маркировки больше не нужен как отдельный тип, он зашит в пресет
 
+// Язык ответов: auto — язык последнего сообщения пользователя
+export type AnswerLanguage = 'ru' | 'en' | 'uk' | 'auto';
+
 // Настройки генерации кода
 export interface CodeGenerationSettings {
     mode: CodeGenerationMode;
@@ -81,6 +84,7 @@ export interface CodeGenerationSettings {
     addition_marker_template: string;
     modification_marker_template: string;
     deletion_marker_template: string;
+    answer_language?: AnswerLanguage;
 }
 
 
@@ -99,6 +103,8 @@ export interface CustomPromptsSettings {
     on_code_change: string;
     on_code_generate: string;
     templates: PromptTemplate[];
+    /** Replaces the built-in system prompt; a profile may override it. */
+    default_system_prompt?: string | null;
 }
 
 export interface McpServerConfig {
@@ -173,6 +179,31 @@ export interface AppSettings {
     max_context_tokens?: number;
     /** @deprecated Устарело */
     max_context_messages?: number;
+    /** Лимиты на прикрепляемые файлы */
+    attachments?: AttachmentSettings;
+    /** Поле `user` запросов к провайдеру; пусто — хэш машины */
+    user_id?: string | null;
+    /** Хранить профили LLM зашифрованными ключом компьютера (llm_profiles.enc) */
+    encrypt_profiles?: boolean;
+    /** Повторы запроса к провайдеру при 429/5xx и ошибках соединения; пусто — 3 */
+    max_request_retries?: number | null;
+    /** Не проверять доступность сервера провайдера перед запросом */
+    skip_reachability_check?: boolean;
+    /** Сбоев провайдера подряд до временного отключения профиля; пусто — 5, 0 — никогда */
+    circuit_breaker_threshold?: number | null;
+    /** Длительность отключения профиля после сбоев, сек; пусто — 60 */
+    circuit_breaker_cooldown_secs?: number | null;
+    /** Уровень журнала запросов к провайдерам; пусто — info */
+    log_level?: LogLevel | null;
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface AttachmentSettings {
+    /** Максимальный размер одного файла, КБ */
+    max_file_kb: number;
+    /** Максимальный суммарный размер файлов одного запроса, байт (по умолчанию 1 МБ) */
+    max_attachment_bytes: number;
 }
 
 export interface BslDiagnosticItem {
@@ -319,5 +350,6 @@ export const DEFAULT_CODE_GENERATION: CodeGenerationSettings = {
     mark_changes: true,
     addition_marker_template: DEFAULT_ADDITION_MARKER_TEMPLATE,
     modification_marker_template: DEFAULT_MODIFICATION_MARKER_TEMPLATE,
-    deletion_marker_template: DEFAULT_DELETION_MARKER_TEMPLATE
+    deletion_marker_template: DEFAULT_DELETION_MARKER_TEMPLATE,
+    answer_language: "ru"
 };